@@ -74,6 +74,9 @@ impl AppModel {
         let removed_username = removed.config.username.clone();
         let removed_jmap_url = removed.config.jmap_url.clone();
 
+        self.settings.forget_account(&removed_id);
+        let save_settings = self.save_settings();
+
         // Keep compose account index valid.
         if self.accounts.is_empty() {
             self.compose_account = 0;
@@ -104,7 +107,7 @@ impl AppModel {
         self.status_message = "Account removed".into();
 
         // Clean up cached data for removed account
-        let mut tasks = vec![follow_up];
+        let mut tasks = vec![follow_up, save_settings];
         if let Some(cache) = &self.cache {
             let cache = cache.clone();
             tasks.push(cosmic::task::future(async move {
@@ -127,10 +130,11 @@ impl AppModel {
         self.messages.clear();
         self.selected_message = None;
         self.messages_offset = 0;
+        self.pending_messages_offset = 0;
         self.has_more_messages = false;
         self.pending_body = None;
         self.preview_body.clear();
-        self.preview_markdown.clear();
+        self.preview_blocks.clear();
         self.preview_editor = cosmic::widget::text_editor::Content::new();
         self.preview_attachments.clear();
         self.preview_image_handles.clear();
@@ -198,13 +202,6 @@ impl AppModel {
             .unwrap_or_default()
     }
 
-    /// Get the active account's client.
-    pub(super) fn active_client(&self) -> Option<JmapClient> {
-        self.active_account
-            .and_then(|i| self.accounts.get(i))
-            .and_then(|a| a.client.clone())
-    }
-
     /// Find account index by ID.
     pub(super) fn account_index(&self, account_id: &str) -> Option<usize> {
         self.accounts.iter().position(|a| a.config.id == account_id)
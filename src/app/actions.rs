@@ -1,10 +1,12 @@
+use std::collections::VecDeque;
+
 use cosmic::app::Task;
 use neverlight_mail_core::store;
 use neverlight_mail_core::FlagOp;
 
 use super::{
-    ActionKind, AppModel, FlagIntentKind, MailboxIdentity, Message, MessageIdentity,
-    PendingFlagIntent, PendingMoveIntent, Phase, RecoverableActionError, RetryAction,
+    ActionKind, AppModel, FlagIntentKind, IntentOrigin, MailboxIdentity, Message, MessageIdentity,
+    MoveKind, PendingFlagIntent, PendingMoveIntent, Phase, RecoverableActionError, RetryAction,
 };
 
 fn error_indicates_dead_session(e: &str) -> bool {
@@ -40,6 +42,7 @@ impl AppModel {
                             email_id: msg.email_id.clone(),
                         },
                         kind: FlagIntentKind::ToggleRead,
+                        origin: IntentOrigin::User,
                     });
                 }
             }
@@ -52,6 +55,7 @@ impl AppModel {
                             email_id: msg.email_id.clone(),
                         },
                         kind: FlagIntentKind::ToggleStar,
+                        origin: IntentOrigin::User,
                     });
                 }
             }
@@ -116,6 +120,8 @@ impl AppModel {
                     message,
                     source,
                     dest,
+                    kind: MoveKind::Plain,
+                    origin: IntentOrigin::User,
                 });
             }
             Message::FolderDragEnter(i) => {
@@ -126,6 +132,7 @@ impl AppModel {
             }
             Message::FlagOpComplete {
                 message,
+                origin,
                 epoch,
                 prev_flags,
                 result,
@@ -157,15 +164,20 @@ impl AppModel {
                         }
                     }
                     Err(e) => {
-                        log::error!("Flag operation failed: {}", e);
-                        self.set_recoverable_action_error(RecoverableActionError {
-                            action: ActionKind::Flag,
-                            message: format!("Flag update failed: {}", e),
-                            retry: RetryAction::Refresh,
-                            email_id: Some(message.email_id.clone()),
-                            mailbox_id: Some(message.mailbox_id.clone()),
-                        });
-                        self.phase = Phase::Error;
+                        if let IntentOrigin::Background { .. } = origin {
+                            log::warn!("Background flag update failed: {}", e);
+                            self.status_message = format!("Background flag update failed: {}", e);
+                        } else {
+                            log::error!("Flag operation failed: {}", e);
+                            self.set_recoverable_action_error(RecoverableActionError {
+                                action: ActionKind::Flag,
+                                message: format!("Flag update failed: {}", e),
+                                retry: RetryAction::Refresh,
+                                email_id: Some(message.email_id.clone()),
+                                mailbox_id: Some(message.mailbox_id.clone()),
+                            });
+                            self.phase = Phase::Error;
+                        }
 
                         // Revert optimistic UI to exact pre-op flags.
                         if let Some(msg) = self
@@ -210,9 +222,7 @@ impl AppModel {
                         }
                     }
                 }
-                if let Some(next) = self.pending_flag_intents.remove(&message.account_id) {
-                    tasks.push(self.dispatch(Message::RunFlagIntent(next)));
-                }
+                tasks.push(self.try_run_next_flag_intent_for(&message.account_id));
                 if tasks.is_empty() {
                     return Task::none();
                 }
@@ -221,6 +231,8 @@ impl AppModel {
             Message::MoveOpComplete {
                 message,
                 source,
+                kind,
+                origin,
                 epoch,
                 result,
             } => {
@@ -248,7 +260,8 @@ impl AppModel {
                         let email_id = message.email_id.clone();
                         self.pending_move_restore.remove(&message);
                         let mut tasks: Vec<Task<Message>> = Vec::new();
-                        if let Some(cache) = &self.cache {
+                        if let Some(cache) = self.cache.as_ref().filter(|_| kind != MoveKind::Label)
+                        {
                             let cache = cache.clone();
                             let account_id_for_cache = account_id.clone();
                             let email_id_for_cache = email_id.clone();
@@ -262,6 +275,7 @@ impl AppModel {
                                 Message::Noop
                             }));
                         }
+                        tasks.push(self.finish_snooze_move(kind, true));
 
                         // JMAP moves are atomic (Email/set with mailboxIds patch).
                         // No postcondition verification needed — the server either
@@ -289,15 +303,20 @@ impl AppModel {
                             self.selected_message = Some(insert_at);
                             self.recompute_visible();
                         }
-                        log::error!("Move operation failed: {}", e);
-                        self.set_recoverable_action_error(RecoverableActionError {
-                            action: ActionKind::Move,
-                            message: format!("Move failed: {}", e),
-                            retry: RetryAction::Refresh,
-                            email_id: Some(message.email_id.clone()),
-                            mailbox_id: Some(source.mailbox_id.clone()),
-                        });
-                        self.phase = Phase::Error;
+                        if let IntentOrigin::Background { .. } = origin {
+                            log::warn!("Background move failed: {}", e);
+                            self.status_message = format!("Background move failed: {}", e);
+                        } else {
+                            log::error!("Move operation failed: {}", e);
+                            self.set_recoverable_action_error(RecoverableActionError {
+                                action: ActionKind::Move,
+                                message: format!("Move failed: {}", e),
+                                retry: RetryAction::Refresh,
+                                email_id: Some(message.email_id.clone()),
+                                mailbox_id: Some(source.mailbox_id.clone()),
+                            });
+                            self.phase = Phase::Error;
+                        }
                         self.mutation_in_flight_accounts.remove(&source.account_id);
 
                         // Dead session likely caused the failure — drop and reconnect
@@ -312,6 +331,7 @@ impl AppModel {
                                 ));
                             }
                         }
+                        tasks.push(self.finish_snooze_move(kind, false));
                         tasks.push(self.try_run_next_move_intent_for(&source.account_id));
                         return cosmic::task::batch(tasks);
                     }
@@ -323,24 +343,44 @@ impl AppModel {
     }
 
     fn try_run_next_flag_intent_for(&mut self, account_id: &str) -> Task<Message> {
-        if let Some(next) = self.pending_flag_intents.remove(account_id) {
+        let next = self.pending_flag_intents.remove(account_id).or_else(|| {
+            self.background_flag_intents
+                .get_mut(account_id)
+                .and_then(VecDeque::pop_front)
+        });
+        if let Some(next) = next {
             return self.dispatch(Message::RunFlagIntent(next));
         }
         Task::none()
     }
 
     fn try_run_next_move_intent_for(&mut self, account_id: &str) -> Task<Message> {
-        if let Some(next) = self.pending_move_intents.remove(account_id) {
+        let next = self.pending_move_intents.remove(account_id).or_else(|| {
+            self.background_move_intents
+                .get_mut(account_id)
+                .and_then(VecDeque::pop_front)
+        });
+        if let Some(next) = next {
             return self.dispatch(Message::RunMoveIntent(next));
         }
         Task::none()
     }
 
-    fn queue_or_start_flag(&mut self, intent: PendingFlagIntent) -> Task<Message> {
+    pub(super) fn queue_or_start_flag(&mut self, intent: PendingFlagIntent) -> Task<Message> {
         let account_id = intent.message.account_id.clone();
         if self.flag_in_flight_accounts.contains(&account_id) {
-            self.pending_flag_intents.insert(account_id, intent);
-            self.status_message = "Flag update queued...".into();
+            match intent.origin {
+                IntentOrigin::User => {
+                    self.pending_flag_intents.insert(account_id, intent);
+                    self.status_message = "Flag update queued...".into();
+                }
+                IntentOrigin::Background { .. } => {
+                    self.background_flag_intents
+                        .entry(account_id)
+                        .or_default()
+                        .push_back(intent);
+                }
+            }
             return Task::none();
         }
         self.dispatch(Message::RunFlagIntent(intent))
@@ -348,60 +388,52 @@ impl AppModel {
 
     fn run_flag_intent(&mut self, intent: PendingFlagIntent) -> Task<Message> {
         let message_id = intent.message.clone();
-        let Some(index) = self
-            .messages
-            .iter()
-            .position(|m| {
-                m.email_id == message_id.email_id
-                    && m.context_mailbox_id == message_id.mailbox_id
-            })
-        else {
-            return self.try_run_next_flag_intent_for(&message_id.account_id);
+        let index = self.messages.iter().position(|m| {
+            m.email_id == message_id.email_id && m.context_mailbox_id == message_id.mailbox_id
+        });
+        let listed = index.and_then(|i| self.messages.get(i));
+        let (is_read, is_starred) = match (listed, intent.origin) {
+            (Some(msg), _) => (msg.is_read, msg.is_starred),
+            (None, IntentOrigin::Background { flags }) => store::flags_from_u8(flags),
+            (None, IntentOrigin::User) => {
+                return self.try_run_next_flag_intent_for(&message_id.account_id)
+            }
         };
 
-        let Some(msg) = self.messages.get_mut(index) else {
-            return self.try_run_next_flag_intent_for(&message_id.account_id);
+        let prev_flags = store::flags_to_u8(is_read, is_starred);
+        let (new_read, new_starred) = match intent.kind {
+            FlagIntentKind::ToggleRead => (!is_read, is_starred),
+            FlagIntentKind::ToggleStar => (is_read, !is_starred),
+            FlagIntentKind::MarkRead => (true, is_starred),
+            FlagIntentKind::Star => (is_read, true),
+            FlagIntentKind::MarkUnread => (false, is_starred),
         };
-
-        let prev_flags = store::flags_to_u8(msg.is_read, msg.is_starred);
-        let (new_read, new_starred, pending_op, flag_op) = match intent.kind {
-            FlagIntentKind::ToggleRead => {
-                let new_read = !msg.is_read;
-                if !new_read {
-                    self.auto_read_suppressed = true;
+        if (new_read, new_starred) == (is_read, is_starred) {
+            return self.try_run_next_flag_intent_for(&message_id.account_id);
+        }
+        if intent.kind == FlagIntentKind::ToggleRead && !new_read {
+            self.auto_read_suppressed = true;
+        }
+        let (pending_op, flag_op) = if new_read != is_read {
+            (
+                if new_read { "set_seen" } else { "unset_seen" }.to_string(),
+                FlagOp::SetSeen(new_read),
+            )
+        } else {
+            (
+                if new_starred {
+                    "set_flagged"
+                } else {
+                    "unset_flagged"
                 }
-                (
-                    new_read,
-                    msg.is_starred,
-                    if new_read { "set_seen" } else { "unset_seen" }.to_string(),
-                    if new_read {
-                        FlagOp::SetSeen(true)
-                    } else {
-                        FlagOp::SetSeen(false)
-                    },
-                )
-            }
-            FlagIntentKind::ToggleStar => {
-                let new_starred = !msg.is_starred;
-                (
-                    msg.is_read,
-                    new_starred,
-                    if new_starred {
-                        "set_flagged"
-                    } else {
-                        "unset_flagged"
-                    }
-                    .to_string(),
-                    if new_starred {
-                        FlagOp::SetFlagged(true)
-                    } else {
-                        FlagOp::SetFlagged(false)
-                    },
-                )
-            }
+                .to_string(),
+                FlagOp::SetFlagged(new_starred),
+            )
         };
-        msg.is_read = new_read;
-        msg.is_starred = new_starred;
+        if let Some(msg) = index.and_then(|i| self.messages.get_mut(i)) {
+            msg.is_read = new_read;
+            msg.is_starred = new_starred;
+        }
 
         let new_flags = store::flags_to_u8(new_read, new_starred);
         let mut tasks: Vec<Task<Message>> = Vec::new();
@@ -432,6 +464,7 @@ impl AppModel {
             op_epoch = Some(epoch);
             let message_for_completion = message_id.clone();
             let email_id = message_for_completion.email_id.clone();
+            let origin = intent.origin;
             tasks.push(cosmic::task::future(async move {
                 let result = neverlight_mail_core::email::set_flag(
                     &client,
@@ -441,6 +474,7 @@ impl AppModel {
                 .await;
                 Message::FlagOpComplete {
                     message: message_for_completion,
+                    origin,
                     epoch,
                     prev_flags,
                     result: result.map(|_| new_flags).map_err(|e| e.to_string()),
@@ -459,7 +493,7 @@ impl AppModel {
         }
     }
 
-    fn queue_or_start_move(&mut self, intent: PendingMoveIntent) -> Task<Message> {
+    pub(super) fn queue_or_start_move(&mut self, intent: PendingMoveIntent) -> Task<Message> {
         let account_id = intent.source.account_id.clone();
         if self.mutation_in_flight_accounts.contains(&account_id) {
             log::debug!(
@@ -467,8 +501,18 @@ impl AppModel {
                 intent.message.email_id,
                 account_id,
             );
-            self.pending_move_intents.insert(account_id, intent);
-            self.status_message = "Move queued...".into();
+            match intent.origin {
+                IntentOrigin::User => {
+                    self.pending_move_intents.insert(account_id, intent);
+                    self.status_message = "Move queued...".into();
+                }
+                IntentOrigin::Background { .. } => {
+                    self.background_move_intents
+                        .entry(account_id)
+                        .or_default()
+                        .push_back(intent);
+                }
+            }
             return Task::none();
         }
         self.dispatch(Message::RunMoveIntent(intent))
@@ -494,20 +538,42 @@ impl AppModel {
                 intent.source.account_id,
             );
             self.status_message = "Move failed: account is offline".into();
-            return self.try_run_next_move_intent_for(&source_account_id);
+            let settled = self.finish_snooze_move(intent.kind, false);
+            let next = self.try_run_next_move_intent_for(&source_account_id);
+            return cosmic::task::batch(vec![settled, next]);
         }
-        let Some(index) = self.messages.iter().position(|m| {
+        let listed = self.messages.iter().position(|m| {
             m.email_id == intent.message.email_id
                 && m.context_mailbox_id == intent.source.mailbox_id
-        }) else {
-            log::debug!(
-                "Move skipped (message not in list): email_id={} source_mailbox={} messages_count={} mailbox_ids={:?}",
-                intent.message.email_id,
-                intent.source.mailbox_id,
-                self.messages.len(),
-                self.messages.iter().take(5).map(|m| (m.email_id.as_str(), m.context_mailbox_id.as_str())).collect::<Vec<_>>(),
-            );
-            return self.try_run_next_move_intent_for(&source_account_id);
+        });
+        let index = match listed {
+            Some(index) if intent.kind != MoveKind::Label => index,
+            _ => {
+                // A label keeps the message listed; a mail rule may act on
+                // one that was never listed. Neither is removed from view.
+                let flags = match (listed.and_then(|i| self.messages.get(i)), intent.origin) {
+                    (Some(msg), _) => store::flags_to_u8(msg.is_read, msg.is_starred),
+                    (None, IntentOrigin::Background { flags }) => flags,
+                    (None, IntentOrigin::User) => {
+                        log::debug!(
+                            "Move skipped (message not in list): email_id={} source_mailbox={} messages_count={} mailbox_ids={:?}",
+                            intent.message.email_id,
+                            intent.source.mailbox_id,
+                            self.messages.len(),
+                            self.messages.iter().take(5).map(|m| (m.email_id.as_str(), m.context_mailbox_id.as_str())).collect::<Vec<_>>(),
+                        );
+                        return self.try_run_next_move_intent_for(&source_account_id);
+                    }
+                };
+                return self.dispatch_move(
+                    intent.message,
+                    intent.source,
+                    intent.dest,
+                    intent.kind,
+                    intent.origin,
+                    flags,
+                );
+            }
         };
         if let Some(removed) = self.remove_message_optimistic(index) {
             log::debug!(
@@ -520,7 +586,14 @@ impl AppModel {
             let pre_move_flags = store::flags_to_u8(removed.is_read, removed.is_starred);
             self.pending_move_restore
                 .insert(intent.message.clone(), (removed, index));
-            return self.dispatch_move(intent.message, intent.source, intent.dest, pre_move_flags);
+            return self.dispatch_move(
+                intent.message,
+                intent.source,
+                intent.dest,
+                intent.kind,
+                intent.origin,
+                pre_move_flags,
+            );
         }
         log::debug!(
             "Move skipped (remove_message_optimistic returned None): email_id={} index={}",
@@ -566,6 +639,8 @@ impl AppModel {
                 account_id,
                 mailbox_id: trash_id,
             },
+            kind: MoveKind::Plain,
+            origin: IntentOrigin::User,
         })
     }
 
@@ -597,6 +672,8 @@ impl AppModel {
                 account_id,
                 mailbox_id: archive_id,
             },
+            kind: MoveKind::Plain,
+            origin: IntentOrigin::User,
         })
     }
 
@@ -618,7 +695,7 @@ impl AppModel {
                     Some(index.min(self.messages.len() - 1))
                 };
                 self.preview_body.clear();
-                self.preview_markdown.clear();
+                self.preview_blocks.clear();
                 self.preview_editor = cosmic::widget::text_editor::Content::new();
                 self.preview_attachments.clear();
                 self.preview_image_handles.clear();
@@ -638,11 +715,15 @@ impl AppModel {
         message: MessageIdentity,
         source: MailboxIdentity,
         dest: MailboxIdentity,
+        kind: MoveKind,
+        origin: IntentOrigin,
         pre_move_flags: u8,
     ) -> Task<Message> {
         let mut tasks: Vec<Task<Message>> = Vec::new();
 
-        if let Some(cache) = &self.cache {
+        // A label leaves the cached row where it is; the next sync adds it
+        // to the other mailbox.
+        if let Some(cache) = self.cache.as_ref().filter(|_| kind != MoveKind::Label) {
             let cache = cache.clone();
             let account_id = source.account_id.clone();
             let email_id = message.email_id.clone();
@@ -680,18 +761,44 @@ impl AppModel {
             let email_id = message.email_id.clone();
             let source_mailbox_id = source.mailbox_id.clone();
             let dest_mailbox_id = dest.mailbox_id.clone();
+            let kind_for_completion = kind.clone();
             tasks.push(cosmic::task::future(async move {
-                let result = neverlight_mail_core::email::move_to(
-                    &client,
-                    &email_id,
-                    &source_mailbox_id,
-                    &dest_mailbox_id,
-                )
-                .await
+                let result = match kind {
+                    MoveKind::Snooze(entry) if entry.server_managed => {
+                        neverlight_mail_core::email::snooze(
+                            &client,
+                            &email_id,
+                            &source_mailbox_id,
+                            &dest_mailbox_id,
+                            &entry.wake_mailbox_id,
+                            entry.until,
+                        )
+                        .await
+                    }
+                    MoveKind::Plain | MoveKind::Snooze(_) | MoveKind::Wake(_) => {
+                        neverlight_mail_core::email::move_to(
+                            &client,
+                            &email_id,
+                            &source_mailbox_id,
+                            &dest_mailbox_id,
+                        )
+                        .await
+                    }
+                    MoveKind::Label => {
+                        neverlight_mail_core::email::add_to_mailbox(
+                            &client,
+                            &email_id,
+                            &dest_mailbox_id,
+                        )
+                        .await
+                    }
+                }
                 .map_err(|e| e.to_string());
                 Message::MoveOpComplete {
                     message: message_for_completion,
                     source: source_for_completion,
+                    kind: kind_for_completion,
+                    origin,
                     epoch,
                     result,
                 }
@@ -704,6 +811,4 @@ impl AppModel {
             cosmic::task::batch(tasks)
         }
     }
-
 }
-
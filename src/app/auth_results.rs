@@ -0,0 +1,333 @@
+//! Sender verification from `Authentication-Results` (RFC 8601).
+//!
+//! Only the topmost header counts: our provider's receiving server adds it,
+//! while lower ones arrive with the message and can be forged. The verdict
+//! is about the visible From domain, since that is what the reader trusts:
+//! DMARC decides when present, otherwise a DKIM signature or SPF pass whose
+//! domain aligns with it. A DMARC failure on a message whose ARC chain
+//! passed (typically a mailing list) is downgraded to unverified.
+//!
+//! A display name showing one of our own addresses or domains while the
+//! real sender is outside them is flagged whatever the results say.
+
+use neverlight_mail_core::models::MessageSummary;
+
+use super::remote_images::sender_address;
+use super::AuthVerdict;
+
+/// One `method=result prop=value …` entry.
+#[derive(Debug, PartialEq, Eq)]
+struct MethodResult {
+    method: String,
+    result: String,
+    props: Vec<(String, String)>,
+}
+
+impl MethodResult {
+    fn prop(&self, name: &str) -> Option<&str> {
+        self.props
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn passed(&self) -> bool {
+        self.result == "pass"
+    }
+}
+
+/// The header value without `(comments)`, which may nest and contain
+/// quoted strings.
+fn strip_comments(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut depth = 0usize;
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for c in value.chars() {
+        if escaped {
+            escaped = false;
+            if depth == 0 {
+                out.push(c);
+            }
+            continue;
+        }
+        match c {
+            '\\' => escaped = true,
+            '"' if depth == 0 => in_quotes = !in_quotes,
+            '(' if !in_quotes => depth += 1,
+            ')' if !in_quotes && depth > 0 => {
+                depth -= 1;
+                continue;
+            }
+            _ => {}
+        }
+        if depth == 0 && c != '\\' {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Method results after the authserv-id, methods and values lowercased.
+fn parse_results(value: &str) -> Vec<MethodResult> {
+    strip_comments(value)
+        .split(';')
+        .skip(1)
+        .filter_map(|part| {
+            let mut tokens = part.split_whitespace();
+            let (method, result) = tokens.next()?.split_once('=')?;
+            let method = method.split('/').next().unwrap_or(method);
+            let props = tokens
+                .filter_map(|token| token.split_once('='))
+                .map(|(key, value)| {
+                    let value = value.trim_matches('"');
+                    (key.to_ascii_lowercase(), value.to_ascii_lowercase())
+                })
+                .collect();
+            Some(MethodResult {
+                method: method.to_ascii_lowercase(),
+                result: result.to_ascii_lowercase(),
+                props,
+            })
+        })
+        .collect()
+}
+
+/// Domain part of an address, or the value itself when it is a domain.
+fn domain_of(value: &str) -> &str {
+    value.rsplit('@').next().unwrap_or(value)
+}
+
+/// Relaxed alignment (RFC 7489 §3.1), approximated without the public
+/// suffix list as one domain being the other or a subdomain of it.
+pub(super) fn aligned(a: &str, b: &str) -> bool {
+    a == b || a.ends_with(&format!(".{b}")) || b.ends_with(&format!(".{a}"))
+}
+
+fn verdict_for(results: &[MethodResult], from_domain: &str) -> AuthVerdict {
+    if results.is_empty() {
+        return AuthVerdict::Unknown;
+    }
+    let dmarc = results.iter().find(|r| {
+        r.method == "dmarc"
+            && r.prop("header.from")
+                .is_none_or(|d| aligned(d, from_domain))
+    });
+    let arc_passed = results.iter().any(|r| r.method == "arc" && r.passed());
+    match dmarc.map(|r| r.result.as_str()) {
+        Some("pass") => return AuthVerdict::Verified(format!("DMARC pass for {from_domain}")),
+        Some("fail") if arc_passed => {
+            return AuthVerdict::Unverified(format!(
+                "{from_domain} fails DMARC after forwarding (ARC pass)"
+            ));
+        }
+        Some("fail") => {
+            return AuthVerdict::Danger(format!(
+                "{from_domain} fails DMARC — this sender may be forged"
+            ));
+        }
+        _ => {}
+    }
+
+    let dkim = results.iter().any(|r| {
+        r.method == "dkim"
+            && r.passed()
+            && r.prop("header.d").is_some_and(|d| aligned(d, from_domain))
+    });
+    let spf = results.iter().any(|r| {
+        r.method == "spf"
+            && r.passed()
+            && r.prop("smtp.mailfrom")
+                .is_some_and(|m| aligned(domain_of(m), from_domain))
+    });
+    let passed: Vec<&str> = [("DKIM", dkim), ("SPF", spf)]
+        .into_iter()
+        .filter_map(|(name, ok)| ok.then_some(name))
+        .collect();
+    if !passed.is_empty() {
+        return AuthVerdict::Verified(format!("{} pass for {from_domain}", passed.join(", ")));
+    }
+
+    let failed: Vec<String> = results
+        .iter()
+        .filter(|r| matches!(r.result.as_str(), "fail" | "softfail" | "permerror"))
+        .map(|r| format!("{} {}", r.method.to_uppercase(), r.result))
+        .collect();
+    if failed.is_empty() {
+        AuthVerdict::Unverified(format!("nothing vouches for {from_domain}"))
+    } else {
+        AuthVerdict::Unverified(failed.join(", "))
+    }
+}
+
+/// Display name of a `From` value, unquoted.
+fn display_name(from: &str) -> Option<&str> {
+    let name = from[..from.rfind('<')?].trim().trim_matches('"').trim();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Why the display name poses as one of our own addresses, if it does.
+fn impersonation(from: &str, sender: &str, own_addresses: &[String]) -> Option<String> {
+    if own_addresses.iter().any(|o| o.eq_ignore_ascii_case(sender)) {
+        return None;
+    }
+    let name = display_name(from)?.to_lowercase();
+    if let Some(own) = own_addresses
+        .iter()
+        .find(|o| name.contains(&o.to_lowercase()))
+    {
+        return Some(format!("The name shows {own}, but this came from {sender}"));
+    }
+    let sender_domain = domain_of(sender);
+    own_addresses
+        .iter()
+        .map(|o| domain_of(o).to_lowercase())
+        .find(|domain| name.contains(domain.as_str()) && !aligned(sender_domain, domain))
+        .map(|domain| format!("The name mentions {domain}, but this came from {sender}"))
+}
+
+/// Verification badge for a message's sender. `own_addresses` are the
+/// addresses of every configured account.
+pub(crate) fn auth_verdict(msg: &MessageSummary, own_addresses: &[String]) -> AuthVerdict {
+    let Some(sender) = sender_address(&msg.from) else {
+        return AuthVerdict::Unknown;
+    };
+    if let Some(reason) = impersonation(&msg.from, &sender, own_addresses) {
+        return AuthVerdict::Danger(reason);
+    }
+    let Some(header) = msg.authentication_results.as_deref() else {
+        return AuthVerdict::Unknown;
+    };
+    verdict_for(&parse_results(header), domain_of(&sender))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verdict(header: &str, from_domain: &str) -> AuthVerdict {
+        verdict_for(&parse_results(header), from_domain)
+    }
+
+    #[test]
+    fn results_parse_without_comments() {
+        let results = parse_results(
+            "mx.example.net; dkim=pass (2048-bit key; unprotected) header.d=Example.com \
+             header.s=sel1; spf=softfail (domain of \"x (y)\" is weird) smtp.mailfrom=a@b.example",
+        );
+        assert_eq!(
+            results,
+            vec![
+                MethodResult {
+                    method: "dkim".into(),
+                    result: "pass".into(),
+                    props: vec![
+                        ("header.d".into(), "example.com".into()),
+                        ("header.s".into(), "sel1".into()),
+                    ],
+                },
+                MethodResult {
+                    method: "spf".into(),
+                    result: "softfail".into(),
+                    props: vec![("smtp.mailfrom".into(), "a@b.example".into())],
+                },
+            ]
+        );
+        assert!(parse_results("mx.example.net; none").is_empty());
+    }
+
+    #[test]
+    fn dmarc_decides_when_present() {
+        assert_eq!(
+            verdict(
+                "mx.example.net; dkim=fail; dmarc=pass header.from=example.com",
+                "example.com"
+            ),
+            AuthVerdict::Verified("DMARC pass for example.com".into())
+        );
+        assert_eq!(
+            verdict(
+                "mx.example.net; dkim=pass header.d=mailer.test; dmarc=fail (p=REJECT) header.from=example.com",
+                "example.com"
+            ),
+            AuthVerdict::Danger("example.com fails DMARC — this sender may be forged".into())
+        );
+    }
+
+    #[test]
+    fn arc_softens_a_forwarded_dmarc_failure() {
+        assert_eq!(
+            verdict(
+                "mx.example.net; arc=pass (i=1); dmarc=fail header.from=example.com",
+                "example.com"
+            ),
+            AuthVerdict::Unverified("example.com fails DMARC after forwarding (ARC pass)".into())
+        );
+    }
+
+    #[test]
+    fn aligned_dkim_or_spf_verify_without_dmarc() {
+        assert_eq!(
+            verdict(
+                "mx.example.net; dkim=pass header.d=mail.example.com; spf=pass smtp.mailfrom=bounce@example.com",
+                "example.com"
+            ),
+            AuthVerdict::Verified("DKIM, SPF pass for example.com".into())
+        );
+        assert_eq!(
+            verdict(
+                "mx.example.net; dkim=pass header.d=esp.test; spf=pass smtp.mailfrom=x@esp.test",
+                "example.com"
+            ),
+            AuthVerdict::Unverified("nothing vouches for example.com".into())
+        );
+        assert_eq!(
+            verdict(
+                "mx.example.net; spf=fail smtp.mailfrom=x@example.com",
+                "example.com"
+            ),
+            AuthVerdict::Unverified("SPF fail".into())
+        );
+        assert_eq!(
+            verdict("mx.example.net; none", "example.com"),
+            AuthVerdict::Unknown
+        );
+    }
+
+    #[test]
+    fn display_names_posing_as_us_are_flagged() {
+        let own = vec!["me@corp.example".to_string()];
+        assert_eq!(
+            impersonation(
+                "\"me@corp.example\" <attacker@evil.test>",
+                "attacker@evil.test",
+                &own
+            ),
+            Some("The name shows me@corp.example, but this came from attacker@evil.test".into())
+        );
+        assert_eq!(
+            impersonation(
+                "IT Desk corp.example <help@evil.test>",
+                "help@evil.test",
+                &own
+            ),
+            Some("The name mentions corp.example, but this came from help@evil.test".into())
+        );
+        assert_eq!(
+            impersonation(
+                "IT Desk corp.example <help@it.corp.example>",
+                "help@it.corp.example",
+                &own
+            ),
+            None
+        );
+        assert_eq!(
+            impersonation("Me <me@corp.example>", "me@corp.example", &own),
+            None
+        );
+        assert_eq!(
+            impersonation("alice@evil.test", "alice@evil.test", &own),
+            None
+        );
+    }
+}
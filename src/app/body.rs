@@ -1,10 +1,14 @@
 use std::path::PathBuf;
 
 use cosmic::app::Task;
-use cosmic::widget::{image, markdown, text_editor};
+use cosmic::widget::{image, text_editor};
 use futures::future::{AbortHandle, Abortable};
 
-use super::{AppModel, ConversationEntry, Message};
+use super::calendar::find_invite;
+use super::highlight::highlight_markdown;
+use super::inline_images::{body_blocks, text_blocks};
+use super::link_safety::link_texts;
+use super::{AppModel, BodyBlock, ConversationEntry, InviteCard, Message, RsvpState};
 use neverlight_mail_core::models::AttachmentData;
 use neverlight_mail_core::store::CacheHandle;
 
@@ -25,22 +29,6 @@ fn should_apply_body_result(
         && current_selected_email_id == Some(incoming_email_id)
 }
 
-const MAX_MD_ITEMS: usize = 200;
-
-fn parse_markdown_capped(markdown_body: &str, plain_body: &str) -> Vec<markdown::Item> {
-    let items: Vec<markdown::Item> = markdown::parse(markdown_body).collect();
-    if items.len() <= MAX_MD_ITEMS {
-        items
-    } else {
-        log::warn!(
-            "Markdown items ({}) exceed cap ({}), falling back to plain text",
-            items.len(),
-            MAX_MD_ITEMS
-        );
-        markdown::parse(plain_body).collect()
-    }
-}
-
 fn build_image_handles(attachments: &[AttachmentData]) -> Vec<Option<image::Handle>> {
     attachments
         .iter()
@@ -54,6 +42,21 @@ fn build_image_handles(attachments: &[AttachmentData]) -> Vec<Option<image::Hand
         .collect()
 }
 
+/// Rich body blocks and attachment image handles. Images placed inline lose
+/// their preview in the attachments section but stay listed there.
+fn render_body(
+    markdown_body: &str,
+    plain_body: &str,
+    attachments: &[AttachmentData],
+) -> (Vec<BodyBlock>, Vec<Option<image::Handle>>) {
+    let mut handles = build_image_handles(attachments);
+    let (blocks, inlined) = body_blocks(markdown_body, plain_body, attachments, &handles);
+    for index in inlined {
+        handles[index] = None;
+    }
+    (blocks, handles)
+}
+
 /// Build a body fetch task for a single email, returning either BodyLoaded or
 /// ConversationBodyLoaded depending on whether `thread_id` is Some.
 fn body_fetch_task(
@@ -169,7 +172,7 @@ fn body_fetch_task(
 
 impl AppModel {
     /// Check if a from address matches any of the account's email addresses.
-    fn is_sent_message(&self, from: &str, account_id: &str) -> bool {
+    pub(super) fn is_sent_message(&self, from: &str, account_id: &str) -> bool {
         let from_lower = from.to_lowercase();
         self.account_index(account_id)
             .and_then(|idx| self.accounts.get(idx))
@@ -209,6 +212,7 @@ impl AppModel {
         match message {
             Message::ViewBody(index) => {
                 self.selected_message = Some(index);
+                self.reset_source_view();
                 self.pending_body = None;
                 self.body_defer_retries = 0;
                 self.auto_read_suppressed = false;
@@ -376,9 +380,15 @@ impl AppModel {
                 if let Some(index) = self.selected_message {
                     const MAX_DEFER_RETRIES: u8 = 6;
 
+                    // Route by the message's own account (virtual views mix accounts)
+                    let has_client = self
+                        .messages
+                        .get(index)
+                        .and_then(|m| self.client_for_account(&m.account_id))
+                        .is_some();
                     if self.body_defer_retries < MAX_DEFER_RETRIES
                         && !self.is_busy()
-                        && self.active_client().is_some()
+                        && has_client
                     {
                         self.body_defer_retries += 1;
                         self.status_message = "Loading message...".into();
@@ -390,7 +400,7 @@ impl AppModel {
 
                     if self.body_defer_retries >= MAX_DEFER_RETRIES {
                         let msg = "Message body unavailable — try refreshing the folder";
-                        self.preview_markdown = markdown::parse(msg).collect();
+                        self.preview_blocks = text_blocks(msg);
                         self.preview_body = msg.into();
                         self.preview_editor = text_editor::Content::with_text(msg);
                         self.status_message = msg.into();
@@ -417,12 +427,25 @@ impl AppModel {
                 }
                 self.body_abort = None;
 
-                self.preview_markdown = parse_markdown_capped(&markdown_body, &plain_body);
+                self.link_texts = link_texts(&markdown_body);
+                let markdown_body = highlight_markdown(&markdown_body, &self.search_terms);
+                let (blocks, handles) = render_body(&markdown_body, &plain_body, &attachments);
+                self.preview_blocks = blocks;
                 self.preview_editor = text_editor::Content::with_text(&plain_body);
+                self.search_match = None;
                 self.preview_body = plain_body;
-                self.preview_image_handles = build_image_handles(&attachments);
+                self.preview_image_handles = handles;
+                self.invites.clear();
+                if let Some(invite) = find_invite(&attachments) {
+                    let rsvp = RsvpState::Unsent;
+                    self.invites.insert(email_id, InviteCard { invite, rsvp });
+                }
                 self.preview_attachments = attachments;
                 self.status_message = "Ready".into();
+                self.read_receipts.clear();
+                let selected = self.selected_message.and_then(|i| self.messages.get(i));
+                let receipts = self.load_read_receipts(selected);
+                return cosmic::task::batch(vec![self.auto_load_remote_images(), receipts]);
             }
             Message::BodyLoaded {
                 email_id,
@@ -485,7 +508,7 @@ impl AppModel {
                     }
                     self.selected_message = None;
                     self.preview_body.clear();
-                    self.preview_markdown.clear();
+                    self.preview_blocks.clear();
                     self.preview_editor = text_editor::Content::new();
                     self.preview_attachments.clear();
                     self.preview_image_handles.clear();
@@ -494,7 +517,7 @@ impl AppModel {
                 }
 
                 let msg = format!("Failed to load message body: {}", e);
-                self.preview_markdown = markdown::parse(&msg).collect();
+                self.preview_blocks = text_blocks(&msg);
                 self.preview_editor = text_editor::Content::with_text(&msg);
                 self.preview_body = msg;
                 self.status_message = "Error loading message".into();
@@ -550,13 +573,17 @@ impl AppModel {
 
                 // --- Multi-message conversation ---
                 self.active_conversation_id = Some(email_id.clone());
+                self.search_match = None;
+                self.invites.clear();
+                self.link_texts.clear();
+                self.read_receipts.clear();
 
                 self.conversation = thread_msgs
                     .iter()
                     .map(|m| ConversationEntry {
                         email_id: m.email_id.clone(),
                         summary: m.clone(),
-                        markdown_items: Vec::new(),
+                        body_blocks: Vec::new(),
                         plain_body: String::new(),
                         attachments: Vec::new(),
                         image_handles: Vec::new(),
@@ -570,7 +597,7 @@ impl AppModel {
                     .collect();
 
                 self.preview_body.clear();
-                self.preview_markdown.clear();
+                self.preview_blocks.clear();
                 self.preview_editor = text_editor::Content::new();
                 self.preview_attachments.clear();
                 self.preview_image_handles.clear();
@@ -581,7 +608,7 @@ impl AppModel {
                     .map(|m| m.account_id.clone())
                     .unwrap_or_default();
                 let client = self.client_for_account(&account_id);
-                let tasks: Vec<Task<Message>> = thread_msgs
+                let mut tasks: Vec<Task<Message>> = thread_msgs
                     .iter()
                     .map(|m| {
                         body_fetch_task(
@@ -594,6 +621,7 @@ impl AppModel {
                         )
                     })
                     .collect();
+                tasks.push(self.load_read_receipts(&thread_msgs));
 
                 self.status_message = format!(
                     "Loading conversation ({} messages)...",
@@ -630,22 +658,31 @@ impl AppModel {
                     return Task::none();
                 };
                 let entry = &mut self.conversation[entry_idx];
+                let body_ok = result.is_ok();
 
                 match result {
                     Ok((markdown_body, plain_body, attachments)) => {
-                        entry.markdown_items =
-                            parse_markdown_capped(&markdown_body, &plain_body);
-                        entry.image_handles = build_image_handles(&attachments);
+                        self.link_texts.extend(link_texts(&markdown_body));
+                        let markdown_body = highlight_markdown(&markdown_body, &self.search_terms);
+                        let (blocks, handles) =
+                            render_body(&markdown_body, &plain_body, &attachments);
+                        entry.body_blocks = blocks;
+                        entry.image_handles = handles;
                         if let Some(editor) = self.conversation_editors.get_mut(entry_idx) {
                             *editor = text_editor::Content::with_text(&plain_body);
                         }
                         entry.plain_body = plain_body;
+                        if let Some(invite) = find_invite(&attachments) {
+                            let rsvp = RsvpState::Unsent;
+                            self.invites
+                                .insert(email_id.clone(), InviteCard { invite, rsvp });
+                        }
                         entry.attachments = attachments;
                         entry.loaded = true;
                     }
                     Err(e) => {
                         let msg = format!("Failed to load: {e}");
-                        entry.markdown_items = markdown::parse(&msg).collect();
+                        entry.body_blocks = text_blocks(&msg);
                         if let Some(editor) = self.conversation_editors.get_mut(entry_idx) {
                             *editor = text_editor::Content::with_text(&msg);
                         }
@@ -664,10 +701,14 @@ impl AppModel {
                         loaded_count, total
                     );
                 }
+                if body_ok && self.active_conversation_id.as_deref() == Some(email_id.as_str()) {
+                    return self.auto_load_remote_images();
+                }
             }
 
             Message::SetActiveConversation(ref email_id) => {
                 self.active_conversation_id = Some(email_id.clone());
+                self.reset_source_view();
                 // Update selected_message to match so toolbar actions target the right message
                 if let Some(idx) = self
                     .messages
@@ -706,10 +747,6 @@ impl AppModel {
                 }
             }
 
-            Message::LinkClicked(url) => {
-                neverlight_mail_core::mime::open_link(url.as_str());
-            }
-
             Message::ToggleSelectableView => {
                 self.preview_selectable = !self.preview_selectable;
             }
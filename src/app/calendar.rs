@@ -0,0 +1,959 @@
+//! Calendar invitations (iCalendar, RFC 5545, delivered over iMIP, RFC 6047).
+//!
+//! The first `text/calendar` part of a loaded message becomes an invite card:
+//! title, time in the local zone, organizer, attendees and location. For a
+//! `REQUEST`, Accept / Tentative / Decline send an iTIP `REPLY` (RFC 5546) to
+//! the organizer through `submit::send`.
+//!
+//! Times with a `TZID` are resolved through the invite's own `VTIMEZONE`
+//! definitions, which RFC 5545 requires for every zone an invite references.
+//! That covers Windows zone names from Exchange as well as IANA names.
+
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, Utc, Weekday,
+};
+use cosmic::app::Task;
+
+use neverlight_mail_core::models::AttachmentData;
+use neverlight_mail_core::submit::{self, SendRequest};
+
+use super::compose::build_references;
+use super::{AppModel, CalendarAddress, EventTime, Invite, Message, Rsvp, RsvpState};
+
+/// Content lines a REPLY echoes from the request (RFC 5546 §3.2.3).
+const REPLY_ECHO: [&str; 5] = ["UID", "SEQUENCE", "RECURRENCE-ID", "ORGANIZER", "SUMMARY"];
+
+/// One content line: `NAME;PARAM=value:value`.
+#[derive(Debug, Default)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The property as a content line, quoting parameter values that need it.
+    fn to_line(&self) -> String {
+        let mut line = self.name.clone();
+        for (key, value) in &self.params {
+            if value.contains([':', ';', ',']) {
+                line.push_str(&format!(";{key}=\"{value}\""));
+            } else {
+                line.push_str(&format!(";{key}={value}"));
+            }
+        }
+        line.push(':');
+        line.push_str(&self.value);
+        line
+    }
+}
+
+#[derive(Debug, Default)]
+struct Component {
+    name: String,
+    properties: Vec<Property>,
+    children: Vec<Component>,
+}
+
+impl Component {
+    fn property(&self, name: &str) -> Option<&Property> {
+        self.properties.iter().find(|p| p.name == name)
+    }
+
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Component> {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+}
+
+/// Physical lines joined back into content lines (RFC 5545 §3.1).
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if let (Some(rest), Some(last)) = (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            last.push_str(rest);
+        } else if !line.is_empty() {
+            lines.push(line.to_string());
+        }
+    }
+    lines
+}
+
+/// Fold a content line at 75 octets without splitting a character.
+fn fold_line(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + line.len() / 74 * 3);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out
+}
+
+fn parse_line(line: &str) -> Option<Property> {
+    let mut segments = vec![String::new()];
+    let mut in_quotes = false;
+    let mut value_start = None;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => segments.push(String::new()),
+            ':' if !in_quotes => {
+                value_start = Some(i + 1);
+                break;
+            }
+            _ => segments.last_mut()?.push(c),
+        }
+    }
+    let value = line[value_start?..].to_string();
+    let mut segments = segments.into_iter();
+    let name = segments.next()?.trim().to_ascii_uppercase();
+    if name.is_empty() {
+        return None;
+    }
+    let params = segments
+        .filter_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            Some((key.trim().to_ascii_uppercase(), value.to_string()))
+        })
+        .collect();
+    Some(Property {
+        name,
+        params,
+        value,
+    })
+}
+
+/// The `VCALENDAR` object in `text`, if it is well formed.
+fn parse_calendar(text: &str) -> Option<Component> {
+    let mut stack: Vec<Component> = Vec::new();
+    for line in unfold(text) {
+        let Some(property) = parse_line(&line) else {
+            continue;
+        };
+        match property.name.as_str() {
+            "BEGIN" => stack.push(Component {
+                name: property.value.trim().to_ascii_uppercase(),
+                ..Default::default()
+            }),
+            "END" => {
+                let done = stack.pop()?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(done),
+                    None => return (done.name == "VCALENDAR").then_some(done),
+                }
+            }
+            _ => {
+                if let Some(current) = stack.last_mut() {
+                    current.properties.push(property);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// TEXT value with its backslash escapes resolved.
+fn unescape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+fn calendar_address(property: &Property) -> Option<CalendarAddress> {
+    let value = property.value.trim();
+    let email = match value.get(..7) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("mailto:") => &value[7..],
+        _ => value,
+    };
+    if email.is_empty() {
+        return None;
+    }
+    Some(CalendarAddress {
+        email: email.to_string(),
+        name: property
+            .param("CN")
+            .filter(|name| !name.is_empty())
+            .map(str::to_string),
+        partstat: property.param("PARTSTAT").map(str::to_ascii_uppercase),
+    })
+}
+
+fn parse_date_time(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()
+}
+
+/// `+hhmm` or `+hhmmss`.
+fn parse_utc_offset(value: &str) -> Option<FixedOffset> {
+    let value = value.trim();
+    let sign = match value.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits = &value[1..];
+    if !matches!(digits.len(), 4 | 6) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let field = |range: std::ops::Range<usize>| {
+        digits.get(range).map_or(Some(0), |d| d.parse::<i32>().ok())
+    };
+    let seconds = field(0..2)? * 3600 + field(2..4)? * 60 + field(4..6)?;
+    FixedOffset::east_opt(sign * seconds)
+}
+
+/// `FREQ=YEARLY;BYMONTH=m;BYDAY=nDD`, the shape every VTIMEZONE rule takes.
+struct YearlyRule {
+    month: u32,
+    /// Week of the month; negative counts from the end.
+    week: i32,
+    weekday: Weekday,
+    until: Option<NaiveDateTime>,
+}
+
+fn yearly_rule(value: &str) -> Option<YearlyRule> {
+    let (mut yearly, mut month, mut byday, mut until) = (false, None, None, None);
+    for part in value.split(';') {
+        let Some((key, value)) = part.split_once('=') else {
+            continue;
+        };
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" => yearly = value.eq_ignore_ascii_case("YEARLY"),
+            "BYMONTH" => month = value.parse().ok(),
+            "BYDAY" => byday = Some(value.to_ascii_uppercase()),
+            "UNTIL" => until = parse_date_time(value.trim_end_matches('Z')),
+            _ => {}
+        }
+    }
+    let byday = byday.filter(|_| yearly)?;
+    let split = byday.len().checked_sub(2)?;
+    let (week, day) = (byday.get(..split)?, byday.get(split..)?);
+    let week = match week.trim_start_matches('+') {
+        "" => 1,
+        n => n.parse().ok()?,
+    };
+    let weekday = match day {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(YearlyRule {
+        month: month?,
+        week,
+        weekday,
+        until,
+    })
+}
+
+fn nth_weekday(year: i32, month: u32, week: i32, weekday: Weekday) -> Option<NaiveDate> {
+    if week > 0 {
+        return NaiveDate::from_weekday_of_month_opt(
+            year,
+            month,
+            weekday,
+            u8::try_from(week).ok()?,
+        );
+    }
+    let next_month = match month {
+        12 => NaiveDate::from_ymd_opt(year + 1, 1, 1),
+        _ => NaiveDate::from_ymd_opt(year, month + 1, 1),
+    }?;
+    let last = next_month.pred_opt()?;
+    let back = (last.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7;
+    Some(last - Duration::days(i64::from(back) + 7 * i64::from(-week - 1)))
+}
+
+/// Latest start of a STANDARD/DAYLIGHT observance at or before `time`.
+fn observance_onset(observance: &Component, time: NaiveDateTime) -> Option<NaiveDateTime> {
+    let start = parse_date_time(observance.property("DTSTART")?.value.trim())?;
+    if start > time {
+        return None;
+    }
+    let Some(rule) = observance
+        .property("RRULE")
+        .and_then(|p| yearly_rule(&p.value))
+    else {
+        return Some(start);
+    };
+    let onset_in = |year| {
+        nth_weekday(year, rule.month, rule.week, rule.weekday).map(|d| d.and_time(start.time()))
+    };
+    let year = rule
+        .until
+        .map_or(time.year(), |until| until.year().min(time.year()));
+    let onset = onset_in(year)
+        .filter(|onset| *onset <= time)
+        .or_else(|| onset_in(year - 1))?;
+    Some(onset.max(start))
+}
+
+/// UTC offset of wall-clock `time` in the invite's zone `tzid`.
+fn zone_offset(zones: &[&Component], tzid: &str, time: NaiveDateTime) -> Option<FixedOffset> {
+    let zone = zones
+        .iter()
+        .find(|z| z.property("TZID").is_some_and(|p| p.value.trim() == tzid))?;
+    zone.children
+        .iter()
+        .filter(|c| c.name == "STANDARD" || c.name == "DAYLIGHT")
+        .filter_map(|observance| {
+            let onset = observance_onset(observance, time)?;
+            let offset = parse_utc_offset(&observance.property("TZOFFSETTO")?.value)?;
+            Some((onset, offset))
+        })
+        .max_by_key(|(onset, _)| *onset)
+        .map(|(_, offset)| offset)
+}
+
+fn event_time(property: &Property, zones: &[&Component]) -> Option<EventTime> {
+    let value = property.value.trim();
+    let is_date = property
+        .param("VALUE")
+        .is_some_and(|v| v.eq_ignore_ascii_case("DATE"));
+    if is_date || value.len() == 8 {
+        return NaiveDate::parse_from_str(value, "%Y%m%d")
+            .ok()
+            .map(EventTime::Date);
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let time = parse_date_time(utc)?;
+        return Some(EventTime::Instant(time.and_utc().fixed_offset()));
+    }
+    let time = parse_date_time(value)?;
+    let tzid = property.param("TZID").map(str::to_string);
+    let offset = tzid
+        .as_deref()
+        .and_then(|tzid| zone_offset(zones, tzid, time));
+    match offset.and_then(|offset| time.and_local_timezone(offset).single()) {
+        Some(instant) => Some(EventTime::Instant(instant)),
+        None => Some(EventTime::Local { time, tzid }),
+    }
+}
+
+fn invite_from(calendar: &Component) -> Option<Invite> {
+    let event = calendar.children_named("VEVENT").next()?;
+    event.property("UID")?;
+    let zones: Vec<&Component> = calendar.children_named("VTIMEZONE").collect();
+    let text = |name| event.property(name).map(|p| unescape_text(p.value.trim()));
+    Some(Invite {
+        method: calendar
+            .property("METHOD")
+            .map(|p| p.value.trim().to_ascii_uppercase()),
+        summary: text("SUMMARY").unwrap_or_default(),
+        start: event
+            .property("DTSTART")
+            .and_then(|p| event_time(p, &zones)),
+        end: event.property("DTEND").and_then(|p| event_time(p, &zones)),
+        location: text("LOCATION").filter(|l| !l.is_empty()),
+        organizer: event.property("ORGANIZER").and_then(calendar_address),
+        attendees: event
+            .properties
+            .iter()
+            .filter(|p| p.name == "ATTENDEE")
+            .filter_map(calendar_address)
+            .collect(),
+        reply_lines: event
+            .properties
+            .iter()
+            .filter(|p| REPLY_ECHO.contains(&p.name.as_str()))
+            .map(Property::to_line)
+            .collect(),
+    })
+}
+
+fn is_calendar(attachment: &AttachmentData) -> bool {
+    let mime = attachment.mime_type.to_ascii_lowercase();
+    mime.starts_with("text/calendar")
+        || mime.starts_with("application/ics")
+        || attachment.filename.to_ascii_lowercase().ends_with(".ics")
+}
+
+/// The invite in a message's calendar attachments, if any.
+pub(super) fn find_invite(attachments: &[AttachmentData]) -> Option<Invite> {
+    attachments
+        .iter()
+        .filter(|a| is_calendar(a))
+        .find_map(|a| invite_from(&parse_calendar(&String::from_utf8_lossy(&a.data))?))
+}
+
+fn day_label(date: NaiveDate) -> String {
+    date.format("%a %-d %b %Y").to_string()
+}
+
+/// Wall-clock range, the end shortened to a time when it is the same day.
+fn time_range_label(
+    start: NaiveDateTime,
+    end: Option<NaiveDateTime>,
+    zone: Option<&str>,
+) -> String {
+    let mut label = format!("{}, {}", day_label(start.date()), start.format("%H:%M"));
+    match end {
+        Some(end) if end.date() == start.date() => {
+            label.push_str(&format!(" – {}", end.format("%H:%M")));
+        }
+        Some(end) => {
+            label.push_str(&format!(
+                " – {}, {}",
+                day_label(end.date()),
+                end.format("%H:%M")
+            ));
+        }
+        None => {}
+    }
+    if let Some(zone) = zone {
+        label.push_str(&format!(" ({zone})"));
+    }
+    label
+}
+
+/// Event time as shown on the invite card, in the local zone when the
+/// invite pins it to an instant.
+pub(crate) fn event_time_label(start: &EventTime, end: Option<&EventTime>) -> String {
+    let wall_clock = |time: &EventTime| match time {
+        EventTime::Date(date) => date.and_time(Default::default()),
+        EventTime::Instant(instant) => instant.with_timezone(&Local).naive_local(),
+        EventTime::Local { time, .. } => *time,
+    };
+    match start {
+        EventTime::Date(first) => {
+            let last = match end {
+                Some(EventTime::Date(end)) => end.pred_opt().filter(|last| last > first),
+                _ => None,
+            };
+            match last {
+                Some(last) => format!("{} – {} (all day)", day_label(*first), day_label(last)),
+                None => format!("{} (all day)", day_label(*first)),
+            }
+        }
+        EventTime::Instant(_) => time_range_label(wall_clock(start), end.map(wall_clock), None),
+        EventTime::Local { time, tzid } => {
+            time_range_label(*time, end.map(wall_clock), tzid.as_deref())
+        }
+    }
+}
+
+fn partstat(response: Rsvp) -> &'static str {
+    match response {
+        Rsvp::Accepted => "ACCEPTED",
+        Rsvp::Tentative => "TENTATIVE",
+        Rsvp::Declined => "DECLINED",
+    }
+}
+
+fn response_label(response: Rsvp) -> &'static str {
+    match response {
+        Rsvp::Accepted => "Accepted",
+        Rsvp::Tentative => "Tentative",
+        Rsvp::Declined => "Declined",
+    }
+}
+
+fn response_verb(response: Rsvp) -> &'static str {
+    match response {
+        Rsvp::Accepted => "accepted",
+        Rsvp::Tentative => "tentatively accepted",
+        Rsvp::Declined => "declined",
+    }
+}
+
+/// iTIP REPLY carrying `attendee`'s answer, CRLF-terminated and folded.
+fn reply_ics(
+    invite: &Invite,
+    attendee: &CalendarAddress,
+    response: Rsvp,
+    now: DateTime<Utc>,
+) -> String {
+    let mut lines: Vec<String> = [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//Neverlight Mail//EN",
+        "METHOD:REPLY",
+        "BEGIN:VEVENT",
+    ]
+    .map(String::from)
+    .to_vec();
+    lines.extend(invite.reply_lines.iter().cloned());
+    lines.push(format!("DTSTAMP:{}", now.format("%Y%m%dT%H%M%SZ")));
+    let cn = attendee
+        .name
+        .as_deref()
+        .map(|name| format!(";CN=\"{}\"", name.replace('"', "")))
+        .unwrap_or_default();
+    lines.push(format!(
+        "ATTENDEE;PARTSTAT={}{cn}:mailto:{}",
+        partstat(response),
+        attendee.email
+    ));
+    lines.push("END:VEVENT".into());
+    lines.push("END:VCALENDAR".into());
+    lines.iter().map(|line| fold_line(line) + "\r\n").collect()
+}
+
+/// Which of our addresses answers: the one the invite lists, else the
+/// account's first address.
+fn attendee_for(attendees: &[CalendarAddress], own: &[String]) -> Option<CalendarAddress> {
+    attendees
+        .iter()
+        .find(|a| own.iter().any(|o| o.eq_ignore_ascii_case(&a.email)))
+        .cloned()
+        .or_else(|| {
+            Some(CalendarAddress {
+                email: own.first()?.clone(),
+                name: None,
+                partstat: None,
+            })
+        })
+}
+
+impl AppModel {
+    pub(super) fn handle_calendar(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::InviteRespond { email_id, response } => {
+                return self.send_invite_response(email_id, response);
+            }
+            Message::InviteResponded {
+                email_id,
+                response,
+                result,
+            } => {
+                let Some(card) = self.invites.get_mut(&email_id) else {
+                    return Task::none();
+                };
+                match result {
+                    Ok(()) => {
+                        card.rsvp = RsvpState::Sent(response);
+                        self.status_message =
+                            format!("Sent \"{}\" to the organizer", response_label(response));
+                    }
+                    Err(e) => {
+                        log::warn!("Invite response for {} failed: {}", email_id, e);
+                        card.rsvp = RsvpState::Unsent;
+                        self.status_message = format!("Could not send response: {e}");
+                    }
+                }
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+
+    fn send_invite_response(&mut self, email_id: String, response: Rsvp) -> Task<Message> {
+        let msg = self
+            .conversation
+            .iter()
+            .map(|e| &e.summary)
+            .chain(&self.messages)
+            .find(|m| m.email_id == email_id);
+        let (Some(msg), Some(card)) = (msg, self.invites.get(&email_id)) else {
+            return Task::none();
+        };
+        if matches!(card.rsvp, RsvpState::Sending(_)) {
+            return Task::none();
+        }
+        let Some(organizer) = card.invite.organizer.clone() else {
+            self.status_message = "The invite has no organizer to reply to".into();
+            return Task::none();
+        };
+        let Some(acct) = self
+            .account_index(&msg.account_id)
+            .and_then(|i| self.accounts.get(i))
+        else {
+            return Task::none();
+        };
+        let Some(client) = acct.client.clone() else {
+            self.status_message = "Not connected — cannot send a response".into();
+            return Task::none();
+        };
+        let Some(attendee) = attendee_for(&card.invite.attendees, &acct.config.email_addresses)
+        else {
+            self.status_message = "No email address configured. Re-run setup to add one.".into();
+            return Task::none();
+        };
+        let drafts = neverlight_mail_core::mailbox::find_by_role(&acct.folders, "drafts");
+        let sent = neverlight_mail_core::mailbox::find_by_role(&acct.folders, "sent");
+        let (Some(drafts_mailbox_id), Some(sent_mailbox_id)) = (drafts, sent) else {
+            self.status_message = "Drafts or Sent folder not found".into();
+            return Task::none();
+        };
+
+        let ics = reply_ics(&card.invite, &attendee, response, Utc::now());
+        let subject = format!("{}: {}", response_label(response), card.invite.summary);
+        let body = format!(
+            "{} has {} the invitation \"{}\".\n",
+            attendee.name.as_deref().unwrap_or(&attendee.email),
+            response_verb(response),
+            card.invite.summary
+        );
+        let in_reply_to = msg.message_id.clone();
+        let references = build_references(msg.in_reply_to.as_deref(), &msg.message_id);
+        if let Some(card) = self.invites.get_mut(&email_id) {
+            card.rsvp = RsvpState::Sending(response);
+        }
+        self.status_message = "Sending response...".into();
+
+        cosmic::task::future(async move {
+            let result = async {
+                let identities = submit::get_identities(&client)
+                    .await
+                    .map_err(|e| format!("Failed to fetch identities: {e}"))?;
+                let identity = submit::find_identity_for_address(&identities, &attendee.email)
+                    .ok_or_else(|| "No sender identity found".to_string())?;
+                let req = SendRequest {
+                    identity_id: &identity.id,
+                    from: &attendee.email,
+                    to: &[organizer.email],
+                    cc: &[],
+                    subject: &subject,
+                    text_body: &body,
+                    html_body: None,
+                    drafts_mailbox_id: &drafts_mailbox_id,
+                    sent_mailbox_id: &sent_mailbox_id,
+                    in_reply_to: Some(&in_reply_to),
+                    references: Some(&references),
+                    attachments: &[],
+                    calendar_reply: Some(&ics),
+                    request_mdn: false,
+                };
+                submit::send(&client, &req)
+                    .await
+                    .map(|_email_id| ())
+                    .map_err(|e| format!("Send failed: {e}"))
+            }
+            .await;
+            Message::InviteResponded {
+                email_id,
+                response,
+                result,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Google Calendar: IANA zone with a VTIMEZONE, folded attendee lines.
+    const GOOGLE: &str = "BEGIN:VCALENDAR\r
+PRODID:-//Google Inc//Google Calendar 70.9054//EN\r
+VERSION:2.0\r
+CALSCALE:GREGORIAN\r
+METHOD:REQUEST\r
+BEGIN:VTIMEZONE\r
+TZID:America/New_York\r
+X-LIC-LOCATION:America/New_York\r
+BEGIN:DAYLIGHT\r
+TZOFFSETFROM:-0500\r
+TZOFFSETTO:-0400\r
+TZNAME:EDT\r
+DTSTART:19700308T020000\r
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU\r
+END:DAYLIGHT\r
+BEGIN:STANDARD\r
+TZOFFSETFROM:-0400\r
+TZOFFSETTO:-0500\r
+TZNAME:EST\r
+DTSTART:19701101T020000\r
+RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU\r
+END:STANDARD\r
+END:VTIMEZONE\r
+BEGIN:VEVENT\r
+DTSTART;TZID=America/New_York:20240315T140000\r
+DTEND;TZID=America/New_York:20240315T150000\r
+DTSTAMP:20240301T120000Z\r
+ORGANIZER;CN=Alice Example:mailto:alice@example.com\r
+UID:4f3k2j1h0g9f8e7d6c5b4a3@google.com\r
+ATTENDEE;CUTYPE=INDIVIDUAL;ROLE=REQ-PARTICIPANT;PARTSTAT=ACCEPTED;RSVP=TRUE\r
+ ;CN=Alice Example;X-NUM-GUESTS=0:mailto:alice@example.com\r
+ATTENDEE;CUTYPE=INDIVIDUAL;ROLE=REQ-PARTICIPANT;PARTSTAT=NEEDS-ACTION;RSVP=\r
+ TRUE;CN=bob@example.org;X-NUM-GUESTS=0:mailto:bob@example.org\r
+CREATED:20240301T115900Z\r
+DESCRIPTION:Quarterly planning\\, agenda to follow.\\n\\nJoin with Google Meet\r
+ : https://meet.google.com/abc-defg-hij\r
+LAST-MODIFIED:20240301T120000Z\r
+LOCATION:Room 4\\, Building B\r
+SEQUENCE:0\r
+STATUS:CONFIRMED\r
+SUMMARY:Q2 planning\r
+TRANSP:OPAQUE\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    /// Exchange: Windows zone name, last-Sunday rules, quoted CN with a comma.
+    const EXCHANGE: &str = "BEGIN:VCALENDAR\r
+METHOD:REQUEST\r
+PRODID:Microsoft Exchange Server 2010\r
+VERSION:2.0\r
+BEGIN:VTIMEZONE\r
+TZID:W. Europe Standard Time\r
+BEGIN:STANDARD\r
+DTSTART:16010101T030000\r
+TZOFFSETFROM:+0200\r
+TZOFFSETTO:+0100\r
+RRULE:FREQ=YEARLY;INTERVAL=1;BYDAY=-1SU;BYMONTH=10\r
+END:STANDARD\r
+BEGIN:DAYLIGHT\r
+DTSTART:16010101T020000\r
+TZOFFSETFROM:+0100\r
+TZOFFSETTO:+0200\r
+RRULE:FREQ=YEARLY;INTERVAL=1;BYDAY=-1SU;BYMONTH=3\r
+END:DAYLIGHT\r
+END:VTIMEZONE\r
+BEGIN:VEVENT\r
+ORGANIZER;CN=\"Müller, Jörg\":mailto:joerg.mueller@example.de\r
+ATTENDEE;ROLE=REQ-PARTICIPANT;PARTSTAT=NEEDS-ACTION;RSVP=TRUE;CN=Bob Smith:mailto:bob@example.org\r
+DESCRIPTION;LANGUAGE=de-DE:Bitte bestätigen.\\n\r
+UID:040000008200E00074C5B7101A82E00800000000B0C4E2F6A0B4DA010000000000000000\r
+ 10000000F1A3E1B4C1E0B94F8E1E0D1C3B2A1908\r
+SUMMARY;LANGUAGE=de-DE:Projektbesprechung\r
+DTSTART;TZID=W. Europe Standard Time:20241028T100000\r
+DTEND;TZID=W. Europe Standard Time:20241028T113000\r
+CLASS:PUBLIC\r
+PRIORITY:5\r
+DTSTAMP:20241020T081500Z\r
+TRANSP:OPAQUE\r
+STATUS:CONFIRMED\r
+SEQUENCE:2\r
+LOCATION;LANGUAGE=de-DE:Konferenzraum 3\r
+X-MICROSOFT-CDO-APPT-SEQUENCE:2\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    /// Fastmail: all-day event, bare LF line endings.
+    const FASTMAIL: &str = "BEGIN:VCALENDAR
+VERSION:2.0
+PRODID:-//Fastmail/2020.5/EN
+METHOD:REQUEST
+BEGIN:VEVENT
+DTEND;VALUE=DATE:20240603
+DTSTAMP:20240520T093000Z
+DTSTART;VALUE=DATE:20240601
+ORGANIZER;CN=Carol:mailto:carol@fastmail.example
+ATTENDEE;CN=Bob;PARTSTAT=NEEDS-ACTION;RSVP=TRUE:mailto:bob@example.org
+ATTENDEE;CN=Carol;PARTSTAT=ACCEPTED:mailto:carol@fastmail.example
+SEQUENCE:0
+SUMMARY:Offsite weekend
+UID:7c3e9f2a-1b4d-4e8f-9a6b-2c5d8e1f0a3b
+END:VEVENT
+END:VCALENDAR
+";
+
+    fn invite(text: &str) -> Invite {
+        invite_from(&parse_calendar(text).expect("parses")).expect("has an event")
+    }
+
+    fn utc(s: &str) -> EventTime {
+        EventTime::Instant(
+            DateTime::parse_from_rfc3339(s)
+                .expect("valid instant")
+                .with_timezone(&Utc)
+                .fixed_offset(),
+        )
+    }
+
+    fn address(email: &str, name: Option<&str>, partstat: Option<&str>) -> CalendarAddress {
+        CalendarAddress {
+            email: email.into(),
+            name: name.map(Into::into),
+            partstat: partstat.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn google_invite_parses() {
+        let invite = invite(GOOGLE);
+        assert_eq!(invite.method.as_deref(), Some("REQUEST"));
+        assert_eq!(invite.summary, "Q2 planning");
+        assert_eq!(invite.location.as_deref(), Some("Room 4, Building B"));
+        assert_eq!(
+            invite.organizer,
+            Some(address("alice@example.com", Some("Alice Example"), None))
+        );
+        assert_eq!(
+            invite.attendees,
+            vec![
+                address("alice@example.com", Some("Alice Example"), Some("ACCEPTED")),
+                address(
+                    "bob@example.org",
+                    Some("bob@example.org"),
+                    Some("NEEDS-ACTION")
+                ),
+            ]
+        );
+        // EDT began on 10 March 2024
+        assert_eq!(invite.start, Some(utc("2024-03-15T18:00:00Z")));
+        assert_eq!(invite.end, Some(utc("2024-03-15T19:00:00Z")));
+    }
+
+    #[test]
+    fn exchange_windows_zone_resolves_through_vtimezone() {
+        let invite = invite(EXCHANGE);
+        assert_eq!(invite.summary, "Projektbesprechung");
+        assert_eq!(
+            invite.organizer,
+            Some(address(
+                "joerg.mueller@example.de",
+                Some("Müller, Jörg"),
+                None
+            ))
+        );
+        // CET again after the last Sunday of October (27th)
+        assert_eq!(invite.start, Some(utc("2024-10-28T09:00:00Z")));
+        assert_eq!(invite.end, Some(utc("2024-10-28T10:30:00Z")));
+    }
+
+    #[test]
+    fn zone_switches_at_the_transition() {
+        let event = GOOGLE.replace("20240315T140000", "20240309T140000");
+        assert_eq!(invite(&event).start, Some(utc("2024-03-09T19:00:00Z")));
+    }
+
+    #[test]
+    fn undefined_zone_stays_wall_clock() {
+        let event = GOOGLE.replace(
+            "DTSTART;TZID=America/New_York",
+            "DTSTART;TZID=Mars/Olympus_Mons",
+        );
+        let start = NaiveDate::from_ymd_opt(2024, 3, 15)
+            .and_then(|d| d.and_hms_opt(14, 0, 0))
+            .expect("valid time");
+        assert_eq!(
+            invite(&event).start,
+            Some(EventTime::Local {
+                time: start,
+                tzid: Some("Mars/Olympus_Mons".into())
+            })
+        );
+    }
+
+    #[test]
+    fn all_day_event_ends_the_day_before_dtend() {
+        let invite = invite(FASTMAIL);
+        let start = invite.start.expect("has a start");
+        assert_eq!(
+            start,
+            EventTime::Date(NaiveDate::from_ymd_opt(2024, 6, 1).expect("valid date"))
+        );
+        assert_eq!(
+            event_time_label(&start, invite.end.as_ref()),
+            "Sat 1 Jun 2024 – Sun 2 Jun 2024 (all day)"
+        );
+    }
+
+    #[test]
+    fn same_day_ranges_show_the_end_time_only() {
+        let at = |d: u32, h: u32| {
+            NaiveDate::from_ymd_opt(2024, 3, d)
+                .and_then(|d| d.and_hms_opt(h, 30, 0))
+                .expect("valid time")
+        };
+        assert_eq!(
+            time_range_label(at(15, 9), Some(at(15, 10)), None),
+            "Fri 15 Mar 2024, 09:30 – 10:30"
+        );
+        assert_eq!(
+            time_range_label(at(15, 23), Some(at(16, 1)), Some("Pacific Standard Time")),
+            "Fri 15 Mar 2024, 23:30 – Sat 16 Mar 2024, 01:30 (Pacific Standard Time)"
+        );
+    }
+
+    #[test]
+    fn events_without_uid_are_not_invites() {
+        let event = FASTMAIL.replace("UID:7c3e9f2a-1b4d-4e8f-9a6b-2c5d8e1f0a3b\n", "");
+        assert!(invite_from(&parse_calendar(&event).expect("parses")).is_none());
+        assert!(parse_calendar("BEGIN:VEVENT\nEND:VEVENT\n").is_none());
+    }
+
+    #[test]
+    fn reply_echoes_the_request_and_sets_partstat() {
+        let invite = invite(EXCHANGE);
+        let me = attendee_for(&invite.attendees, &["BOB@example.org".into()]).expect("listed");
+        let now = DateTime::parse_from_rfc3339("2024-10-21T07:00:00Z")
+            .expect("valid instant")
+            .with_timezone(&Utc);
+        let ics = reply_ics(&invite, &me, Rsvp::Tentative, now);
+        assert!(ics.split("\r\n").all(|line| line.len() <= 75), "{ics}");
+
+        let reply = parse_calendar(&ics).expect("reply parses");
+        assert_eq!(
+            reply.property("METHOD").map(|p| p.value.as_str()),
+            Some("REPLY")
+        );
+        let event = reply.children_named("VEVENT").next().expect("has an event");
+        let value = |name| event.property(name).map(|p| p.value.clone());
+        assert_eq!(
+            value("UID").as_deref(),
+            Some("040000008200E00074C5B7101A82E00800000000B0C4E2F6A0B4DA01000000000000000010000000F1A3E1B4C1E0B94F8E1E0D1C3B2A1908")
+        );
+        assert_eq!(value("SEQUENCE").as_deref(), Some("2"));
+        assert_eq!(value("DTSTAMP").as_deref(), Some("20241021T070000Z"));
+        assert!(event.property("DTSTART").is_none());
+        let organizer = event
+            .property("ORGANIZER")
+            .and_then(calendar_address)
+            .expect("organizer echoed");
+        assert_eq!(organizer.name.as_deref(), Some("Müller, Jörg"));
+        assert_eq!(
+            event.property("ATTENDEE").and_then(calendar_address),
+            Some(address(
+                "bob@example.org",
+                Some("Bob Smith"),
+                Some("TENTATIVE")
+            ))
+        );
+    }
+
+    #[test]
+    fn unlisted_attendee_answers_from_the_first_address() {
+        let invite = invite(FASTMAIL);
+        assert_eq!(
+            attendee_for(&invite.attendees, &["me@example.net".into()]),
+            Some(address("me@example.net", None, None))
+        );
+        assert_eq!(attendee_for(&invite.attendees, &[]), None);
+    }
+
+    #[test]
+    fn folding_never_splits_a_character() {
+        let line = format!("SUMMARY:{}", "ä".repeat(60));
+        let folded = fold_line(&line);
+        assert!(folded.split("\r\n").all(|l| l.len() <= 75));
+        assert_eq!(unfold(&folded), vec![line]);
+    }
+
+    #[test]
+    fn text_escapes_resolve() {
+        assert_eq!(
+            unescape_text("a\\, b\\; c\\\\d\\nnext\\N"),
+            "a, b; c\\d\nnext\n"
+        );
+    }
+}
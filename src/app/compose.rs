@@ -2,6 +2,7 @@ use cosmic::app::Task;
 use cosmic::dialog::file_chooser;
 use cosmic::widget::text_editor;
 
+use super::lists::list_post_address;
 use super::{AppModel, ComposePhase, Message};
 use neverlight_mail_core::models::AttachmentData;
 use neverlight_mail_core::submit::{self, SendRequest};
@@ -65,12 +66,13 @@ impl AppModel {
                 self.compose_in_reply_to = None;
                 self.compose_references = None;
                 self.compose_attachments.clear();
+                self.compose_request_receipt = false;
                 self.compose_error = None;
                 self.compose_phase = ComposePhase::Open;
                 self.refresh_compose_cache();
             }
 
-            Message::ComposeReply => {
+            reply @ (Message::ComposeReply | Message::ComposeReplyList) => {
                 if self.setup_model.is_some() || self.compose_phase.is_open() {
                     return Task::none();
                 }
@@ -97,7 +99,11 @@ impl AppModel {
                 self.compose_account = self
                     .account_index(&msg.account_id)
                     .unwrap_or(self.active_account.unwrap_or(0));
-                self.compose_to = msg.from.clone();
+                let list_address = msg.list_post.as_deref().and_then(list_post_address);
+                self.compose_to = match list_address {
+                    Some(address) if matches!(reply, Message::ComposeReplyList) => address,
+                    _ => msg.from.clone(),
+                };
 
                 let subj = &msg.subject;
                 self.compose_subject = if subj.starts_with("Re: ") {
@@ -116,6 +122,7 @@ impl AppModel {
                     &msg.message_id,
                 ));
                 self.compose_attachments.clear();
+                self.compose_request_receipt = false;
                 self.compose_error = None;
                 self.compose_phase = ComposePhase::Open;
                 self.refresh_compose_cache();
@@ -177,6 +184,7 @@ impl AppModel {
                 self.compose_in_reply_to = None;
                 self.compose_references = None;
                 self.compose_attachments = fwd_attachments;
+                self.compose_request_receipt = false;
                 self.compose_error = None;
                 self.compose_phase = ComposePhase::Open;
                 self.refresh_compose_cache();
@@ -229,6 +237,7 @@ impl AppModel {
                                     filename,
                                     mime_type,
                                     data,
+                                    content_id: None,
                                 });
                             }
                             Message::ComposeAttachLoaded(Ok(attachments))
@@ -260,6 +269,9 @@ impl AppModel {
             Message::ComposeDragLeave => {
                 self.compose_drag_hover = false;
             }
+            Message::ComposeRequestReceiptToggled(request) => {
+                self.compose_request_receipt = request;
+            }
             Message::ComposeFileTransfer(key) => {
                 self.compose_drag_hover = false;
                 return cosmic::task::future(async move {
@@ -360,6 +372,7 @@ impl AppModel {
                 let in_reply_to = self.compose_in_reply_to.clone();
                 let references = self.compose_references.clone();
                 let attachments = self.compose_attachments.clone();
+                let request_mdn = self.compose_request_receipt;
 
                 return cosmic::task::future(async move {
                     // Fetch identities to find the right one
@@ -405,6 +418,8 @@ impl AppModel {
                         in_reply_to: in_reply_to.as_deref(),
                         references: references.as_deref(),
                         attachments: &uploaded,
+                        calendar_reply: None,
+                        request_mdn,
                     };
 
                     match submit::send(&client, &req).await {
@@ -464,7 +479,7 @@ fn forward_body(body: &str, from: &str, date: &str, subject: &str) -> String {
     out
 }
 
-fn build_references(in_reply_to: Option<&str>, message_id: &str) -> String {
+pub(super) fn build_references(in_reply_to: Option<&str>, message_id: &str) -> String {
     match in_reply_to {
         Some(irt) => format!("{irt} {message_id}"),
         None => message_id.to_string(),
@@ -472,7 +487,7 @@ fn build_references(in_reply_to: Option<&str>, message_id: &str) -> String {
 }
 
 /// Parse a text/uri-list string into local file paths.
-fn parse_uri_list(uri_list: &str) -> Vec<String> {
+pub(super) fn parse_uri_list(uri_list: &str) -> Vec<String> {
     uri_list
         .lines()
         .filter_map(|line| {
@@ -510,6 +525,7 @@ async fn read_paths_as_attachments(paths: Vec<String>) -> Message {
             filename,
             mime_type,
             data,
+            content_id: None,
         });
     }
     Message::ComposeAttachLoaded(Ok(attachments))
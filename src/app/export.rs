@@ -0,0 +1,658 @@
+//! Getting mail out: single messages as `.eml`, conversations as a directory
+//! of `.eml` files, folders as an mbox file or a Maildir, and messages
+//! dragged to other applications as `.eml` files in a private directory.
+//!
+//! Bulk exports run as a subscription while `AppModel::export` is set, so
+//! cancelling is just clearing the job. Raw blobs come from the cache when it
+//! holds them and from JMAP otherwise; offline, messages without a cached
+//! blob are skipped and counted.
+
+use std::path::{Path, PathBuf};
+
+use chrono::DateTime;
+use cosmic::app::Task;
+use cosmic::dialog::file_chooser;
+use futures::SinkExt;
+use serde_json::json;
+use tokio::io::AsyncWriteExt;
+
+use neverlight_mail_core::client::JmapClient;
+use neverlight_mail_core::config::AccountId;
+use neverlight_mail_core::models::MessageSummary;
+use neverlight_mail_core::store::{CacheHandle, ListOrder, ListQuery, SortKey, DEFAULT_PAGE_SIZE};
+
+use super::remote_images::sender_address;
+use super::{AppModel, ExportFormat, ExportJob, ExportSource, Message};
+
+/// Longest file name stem taken from a subject or folder name.
+const MAX_NAME_CHARS: usize = 80;
+
+#[derive(Debug, thiserror::Error)]
+enum ExportError {
+    #[error("{0}")]
+    Source(String),
+    #[error("write failed: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A subject or folder name made safe as a file name.
+pub(super) fn safe_file_name(name: &str, fallback: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(MAX_NAME_CHARS)
+        .collect();
+    let cleaned = cleaned.trim().trim_start_matches('.').trim();
+    if cleaned.is_empty() {
+        fallback.to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+/// `.eml` name for a message; `index` numbers files exported together so
+/// they sort in thread order and same-subject replies don't collide.
+fn eml_file_name(subject: &str, index: Option<usize>) -> String {
+    let stem = safe_file_name(subject, "message");
+    match index {
+        Some(i) => format!("{:03} {stem}.eml", i + 1),
+        None => format!("{stem}.eml"),
+    }
+}
+
+/// Per-user directory for drag exports: the session runtime directory, or
+/// the cache directory where there is none. Created `0700` on first use.
+fn drag_export_dir() -> Option<PathBuf> {
+    dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .map(|d| d.join("neverlight-mail").join("drag"))
+}
+
+/// Where a message dragged to another application is written. A directory
+/// per account and email keeps the subject as the file name without clashes.
+pub(crate) fn drag_eml_path(account_id: &str, email_id: &str, subject: &str) -> Option<PathBuf> {
+    let base = drag_export_dir()?;
+    Some(drag_eml_path_in(&base, account_id, email_id, subject))
+}
+
+fn drag_eml_path_in(base: &Path, account_id: &str, email_id: &str, subject: &str) -> PathBuf {
+    base.join(safe_file_name(account_id, "account"))
+        .join(safe_file_name(email_id, "message"))
+        .join(eml_file_name(subject, None))
+}
+
+/// Create `dir` and any missing parents readable by this user only; an
+/// existing directory is tightened to `0700`.
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+    std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+}
+
+/// The mboxrd separator line: envelope sender and asctime date in UTC.
+fn mbox_from_line(from: &str, timestamp: i64) -> String {
+    let sender = sender_address(from).unwrap_or_else(|| "MAILER-DAEMON".into());
+    let date = DateTime::from_timestamp(timestamp, 0).unwrap_or_default();
+    format!("From {sender} {}\n", date.format("%a %b %e %H:%M:%S %Y"))
+}
+
+/// A message body as stored in an mbox: LF line endings, `>`-quoted
+/// `From ` lines (mboxrd, so the quoting is reversible), and a trailing
+/// blank line before the next separator.
+fn mbox_entry(raw: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(raw.len() + 64);
+    for line in raw.split_inclusive(|&b| b == b'\n') {
+        let line = line
+            .strip_suffix(b"\r\n")
+            .or_else(|| line.strip_suffix(b"\n"))
+            .unwrap_or(line);
+        let unquoted = line.iter().position(|&b| b != b'>').unwrap_or(line.len());
+        if line[unquoted..].starts_with(b"From ") {
+            out.push(b'>');
+        }
+        out.extend_from_slice(line);
+        out.push(b'\n');
+    }
+    out.push(b'\n');
+    out
+}
+
+/// Unique part of a Maildir file name; JMAP ids are already path-safe.
+fn maildir_base(timestamp: i64, email_id: &str) -> String {
+    format!(
+        "{timestamp}.{}.neverlight",
+        safe_file_name(email_id, "message")
+    )
+}
+
+/// Maildir info suffix; flags must be in ASCII order.
+fn maildir_info(is_read: bool, is_starred: bool) -> String {
+    let mut info = String::from(":2,");
+    if is_starred {
+        info.push('F');
+    }
+    if is_read {
+        info.push('S');
+    }
+    info
+}
+
+enum Writer {
+    Eml(PathBuf),
+    Mbox(tokio::io::BufWriter<tokio::fs::File>),
+    Maildir(PathBuf),
+}
+
+impl Writer {
+    async fn create(format: ExportFormat, destination: &Path) -> std::io::Result<Self> {
+        Ok(match format {
+            ExportFormat::Eml => {
+                tokio::fs::create_dir_all(destination).await?;
+                Writer::Eml(destination.to_path_buf())
+            }
+            ExportFormat::Mbox => {
+                let file = tokio::fs::File::create(destination).await?;
+                Writer::Mbox(tokio::io::BufWriter::new(file))
+            }
+            ExportFormat::Maildir => {
+                for sub in ["tmp", "new", "cur"] {
+                    tokio::fs::create_dir_all(destination.join(sub)).await?;
+                }
+                Writer::Maildir(destination.to_path_buf())
+            }
+        })
+    }
+
+    async fn write(
+        &mut self,
+        index: usize,
+        msg: &MessageSummary,
+        raw: &[u8],
+    ) -> std::io::Result<()> {
+        match self {
+            Writer::Eml(dir) => {
+                tokio::fs::write(dir.join(eml_file_name(&msg.subject, Some(index))), raw).await
+            }
+            Writer::Mbox(file) => {
+                file.write_all(mbox_from_line(&msg.from, msg.timestamp).as_bytes())
+                    .await?;
+                file.write_all(&mbox_entry(raw)).await
+            }
+            Writer::Maildir(dir) => {
+                // Written to tmp/ and renamed, so readers never see half a message.
+                let base = maildir_base(msg.timestamp, &msg.email_id);
+                let tmp = dir.join("tmp").join(&base);
+                tokio::fs::write(&tmp, raw).await?;
+                let name = format!("{base}{}", maildir_info(msg.is_read, msg.is_starred));
+                tokio::fs::rename(&tmp, dir.join("cur").join(name)).await
+            }
+        }
+    }
+
+    async fn finish(self) -> std::io::Result<()> {
+        match self {
+            Writer::Mbox(mut file) => file.flush().await,
+            Writer::Eml(_) | Writer::Maildir(_) => Ok(()),
+        }
+    }
+}
+
+/// Every message in a mailbox, oldest first: from the server when
+/// connected, otherwise whatever the cache holds.
+async fn list_mailbox(
+    client: Option<&JmapClient>,
+    cache: Option<&CacheHandle>,
+    account_id: &AccountId,
+    mailbox_id: &str,
+) -> Result<Vec<MessageSummary>, ExportError> {
+    let mut all = Vec::new();
+    loop {
+        let offset = all.len() as u32;
+        let page = if let Some(client) = client {
+            let filter = json!({ "inMailbox": mailbox_id });
+            let sort = json!([{ "property": "receivedAt", "isAscending": true }]);
+            neverlight_mail_core::email::query_and_get(
+                client,
+                &filter,
+                &sort,
+                DEFAULT_PAGE_SIZE,
+                offset,
+            )
+            .await
+            .map(|(msgs, _)| msgs)
+            .map_err(|e| ExportError::Source(e.to_string()))?
+        } else if let Some(cache) = cache {
+            let query = ListQuery {
+                order: vec![ListOrder {
+                    key: SortKey::Date,
+                    ascending: true,
+                }],
+                predicates: Vec::new(),
+                threaded: false,
+            };
+            cache
+                .load_messages(
+                    account_id.clone(),
+                    mailbox_id.to_string(),
+                    query,
+                    DEFAULT_PAGE_SIZE,
+                    offset,
+                )
+                .await
+                .map_err(ExportError::Source)?
+        } else {
+            return Err(ExportError::Source("not connected and no cache".into()));
+        };
+        let done = page.len() < DEFAULT_PAGE_SIZE as usize;
+        all.extend(page);
+        if done {
+            return Ok(all);
+        }
+    }
+}
+
+/// Raw blob of a message: cached copy first, then the server. `None` when
+/// offline and not cached.
+async fn fetch_raw(
+    client: Option<&JmapClient>,
+    cache: Option<&CacheHandle>,
+    account_id: &AccountId,
+    email_id: &str,
+) -> Result<Option<Vec<u8>>, String> {
+    if let Some(cache) = cache {
+        match cache
+            .load_raw(account_id.clone(), email_id.to_string())
+            .await
+        {
+            Ok(Some(raw)) => return Ok(Some(raw)),
+            Ok(None) => {}
+            Err(e) => log::debug!("export: cached blob for {} unavailable: {}", email_id, e),
+        }
+    }
+    let Some(client) = client else {
+        return Ok(None);
+    };
+    neverlight_mail_core::email::get_raw(client, email_id)
+        .await
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// Streams an export job: progress per message, then `ExportFinished`.
+pub(super) fn export_stream(
+    job: ExportJob,
+    client: Option<JmapClient>,
+    cache: Option<CacheHandle>,
+) -> impl futures::Stream<Item = Message> {
+    cosmic::iced_futures::stream::channel(10, move |mut output| async move {
+        let client = client.as_ref();
+        let cache = cache.as_ref();
+        let result = async {
+            let messages = match &job.source {
+                ExportSource::Messages(messages) => messages.clone(),
+                ExportSource::Mailbox(mailbox_id) => {
+                    list_mailbox(client, cache, &job.account_id, mailbox_id).await?
+                }
+            };
+            let total = messages.len();
+            let mut writer = Writer::create(job.format, &job.destination).await?;
+            let mut written = 0;
+            let mut skipped = 0;
+            for (index, msg) in messages.iter().enumerate() {
+                match fetch_raw(client, cache, &job.account_id, &msg.email_id).await {
+                    Ok(Some(raw)) => {
+                        writer.write(index, msg, &raw).await?;
+                        written += 1;
+                    }
+                    Ok(None) => skipped += 1,
+                    Err(e) => {
+                        log::warn!("export: {} skipped: {}", msg.email_id, e);
+                        skipped += 1;
+                    }
+                }
+                let _ = output
+                    .send(Message::ExportProgress {
+                        job: job.id,
+                        done: index + 1,
+                        total,
+                    })
+                    .await;
+            }
+            writer.finish().await?;
+            Ok::<_, ExportError>((written, skipped))
+        }
+        .await;
+        let _ = output
+            .send(Message::ExportFinished {
+                job: job.id,
+                result: result.map_err(|e| e.to_string()),
+            })
+            .await;
+    })
+}
+
+impl AppModel {
+    pub(super) fn handle_export(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::ExportMessage => return self.export_selected(),
+            Message::ExportConversation => {
+                let Some(first) = self.conversation.first() else {
+                    return Task::none();
+                };
+                let account_id = first.summary.account_id.clone();
+                let messages = self
+                    .conversation
+                    .iter()
+                    .map(|e| e.summary.clone())
+                    .collect();
+                return pick_destination(
+                    account_id,
+                    ExportSource::Messages(messages),
+                    ExportFormat::Eml,
+                    String::new(),
+                );
+            }
+            Message::ExportFolder(format) => {
+                let Some(acct) = self.active_account.and_then(|i| self.accounts.get(i)) else {
+                    return Task::none();
+                };
+                let Some(folder) = self.selected_folder.and_then(|i| acct.folders.get(i)) else {
+                    return Task::none();
+                };
+                return pick_destination(
+                    acct.config.id.clone(),
+                    ExportSource::Mailbox(folder.mailbox_id.clone()),
+                    format,
+                    safe_file_name(&folder.name, "folder"),
+                );
+            }
+            Message::ExportStart {
+                account_id,
+                source,
+                format,
+                destination,
+            } => {
+                if self.export.is_some() {
+                    self.status_message = "An export is already running".into();
+                    return Task::none();
+                }
+                self.export_epoch = self.export_epoch.saturating_add(1);
+                self.status_message = format!("Exporting to {}...", destination.display());
+                self.export = Some(ExportJob {
+                    id: self.export_epoch,
+                    account_id,
+                    source,
+                    format,
+                    destination,
+                    done: 0,
+                    total: None,
+                });
+            }
+            Message::ExportProgress { job, done, total } => {
+                if let Some(export) = self.export.as_mut().filter(|e| e.id == job) {
+                    export.done = done;
+                    export.total = Some(total);
+                }
+            }
+            Message::ExportFinished { job, result } => {
+                if self.export.as_ref().map(|e| e.id) != Some(job) {
+                    return Task::none();
+                }
+                let Some(export) = self.export.take() else {
+                    return Task::none();
+                };
+                self.status_message = match result {
+                    Ok((written, 0)) => format!(
+                        "Exported {written} messages to {}",
+                        export.destination.display()
+                    ),
+                    Ok((written, skipped)) => format!(
+                        "Exported {written} messages to {} ({skipped} unavailable offline or failed)",
+                        export.destination.display()
+                    ),
+                    Err(e) => {
+                        log::warn!("Export to {} failed: {}", export.destination.display(), e);
+                        format!("Export failed: {e}")
+                    }
+                };
+            }
+            Message::ExportCancel => {
+                if let Some(export) = self.export.take() {
+                    self.status_message = format!(
+                        "Export cancelled after {} messages; {} is incomplete",
+                        export.done,
+                        export.destination.display()
+                    );
+                }
+            }
+            Message::MessageExported(Ok(path)) => {
+                self.status_message = format!("Exported to {path}");
+            }
+            Message::MessageExported(Err(e)) => {
+                self.status_message = format!("Export failed: {e}");
+            }
+            Message::DragExportPrepare {
+                account_id,
+                email_id,
+            } => return self.prepare_drag_export(account_id, email_id),
+            Message::DragExportReady(Ok(())) => {}
+            Message::DragExportReady(Err(e)) => {
+                log::warn!("Drag export failed: {}", e);
+                self.status_message = format!("Could not prepare the message for dropping: {e}");
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+
+    /// Write the `.eml` a message drag offers to other applications, as the
+    /// drag starts. The blob usually comes from the cache, so the file is in
+    /// place before the drop. Already-written files are reused; messages
+    /// don't change.
+    fn prepare_drag_export(&self, account_id: AccountId, email_id: String) -> Task<Message> {
+        let Some(msg) = self
+            .messages
+            .iter()
+            .find(|m| m.account_id == account_id && m.email_id == email_id)
+        else {
+            return Task::none();
+        };
+        let Some(path) = drag_eml_path(&account_id, &email_id, &msg.subject) else {
+            return Task::none();
+        };
+        let client = self.client_for_account(&account_id);
+        let cache = self.cache.clone();
+        cosmic::task::future(async move {
+            let result = async {
+                if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+                    return Ok(());
+                }
+                let raw = fetch_raw(client.as_ref(), cache.as_ref(), &account_id, &email_id)
+                    .await?
+                    .ok_or_else(|| "not connected and the message is not cached".to_string())?;
+                if let Some(dir) = path.parent() {
+                    create_private_dir(dir).map_err(|e| e.to_string())?;
+                }
+                // Renamed into place so a drop never reads half a message.
+                let part = path.with_extension("eml.part");
+                tokio::fs::write(&part, raw)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                tokio::fs::rename(&part, &path)
+                    .await
+                    .map_err(|e| e.to_string())
+            }
+            .await;
+            Message::DragExportReady(result)
+        })
+    }
+
+    /// Save the selected message as a single `.eml` file.
+    fn export_selected(&mut self) -> Task<Message> {
+        let Some(msg) = self.selected_message.and_then(|i| self.messages.get(i)) else {
+            return Task::none();
+        };
+        let client = self.client_for_account(&msg.account_id);
+        let cache = self.cache.clone();
+        let account_id = msg.account_id.clone();
+        let email_id = msg.email_id.clone();
+        let file_name = eml_file_name(&msg.subject, None);
+        cosmic::task::future(async move {
+            let dialog = file_chooser::save::Dialog::new()
+                .title("Export message")
+                .file_name(file_name);
+            let path = match dialog.save_file().await {
+                Ok(response) => match response.url().and_then(|u| u.to_file_path().ok()) {
+                    Some(path) => path,
+                    None => return Message::Noop,
+                },
+                Err(file_chooser::Error::Cancelled) => return Message::Noop,
+                Err(e) => return Message::MessageExported(Err(format!("File picker error: {e}"))),
+            };
+            let raw = match fetch_raw(client.as_ref(), cache.as_ref(), &account_id, &email_id).await
+            {
+                Ok(Some(raw)) => raw,
+                Ok(None) => {
+                    return Message::MessageExported(Err(
+                        "not connected and the message is not cached".into(),
+                    ))
+                }
+                Err(e) => return Message::MessageExported(Err(e)),
+            };
+            match tokio::fs::write(&path, raw).await {
+                Ok(()) => Message::MessageExported(Ok(path.display().to_string())),
+                Err(e) => Message::MessageExported(Err(format!("write failed: {e}"))),
+            }
+        })
+    }
+}
+
+/// Ask where a bulk export goes: a file for mbox, a directory otherwise.
+/// Maildirs get a subdirectory named `name` inside the chosen directory.
+fn pick_destination(
+    account_id: AccountId,
+    source: ExportSource,
+    format: ExportFormat,
+    name: String,
+) -> Task<Message> {
+    cosmic::task::future(async move {
+        let chosen = match format {
+            ExportFormat::Mbox => file_chooser::save::Dialog::new()
+                .title("Export folder as mbox")
+                .file_name(format!("{name}.mbox"))
+                .save_file()
+                .await
+                .map(|response| response.url().and_then(|u| u.to_file_path().ok())),
+            ExportFormat::Eml | ExportFormat::Maildir => file_chooser::open::Dialog::new()
+                .title("Export to folder")
+                .open_folder()
+                .await
+                .map(|response| response.url().to_file_path().ok()),
+        };
+        let dir = match chosen {
+            Ok(Some(path)) => path,
+            Ok(None) | Err(file_chooser::Error::Cancelled) => return Message::Noop,
+            Err(e) => return Message::MessageExported(Err(format!("File picker error: {e}"))),
+        };
+        let destination = match format {
+            ExportFormat::Maildir => dir.join(name),
+            ExportFormat::Eml | ExportFormat::Mbox => dir,
+        };
+        Message::ExportStart {
+            account_id,
+            source,
+            format,
+            destination,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names_drop_path_and_reserved_characters() {
+        assert_eq!(
+            safe_file_name("Re: Q3/Q4 plan?", "message"),
+            "Re_ Q3_Q4 plan_"
+        );
+        assert_eq!(safe_file_name("  ../..  ", "message"), "_..");
+        assert_eq!(safe_file_name("", "message"), "message");
+        assert_eq!(
+            safe_file_name(&"x".repeat(200), "message").len(),
+            MAX_NAME_CHARS
+        );
+        assert_eq!(eml_file_name("Hello", None), "Hello.eml");
+        assert_eq!(eml_file_name("Hello", Some(4)), "005 Hello.eml");
+    }
+
+    #[test]
+    fn drag_files_are_named_after_the_subject_per_account_and_email() {
+        let path = drag_eml_path_in(
+            Path::new("/run/user/1000/drag"),
+            "acct:a",
+            "M1/x",
+            "Invoice: May",
+        );
+        assert_eq!(
+            path,
+            Path::new("/run/user/1000/drag/acct_a/M1_x/Invoice_ May.eml")
+        );
+    }
+
+    #[test]
+    fn drag_directories_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let base = std::env::temp_dir().join(format!("nm-drag-test-{}", std::process::id()));
+        let dir = base.join("acct").join("M1");
+        std::fs::create_dir_all(&base).unwrap();
+        std::fs::set_permissions(&base, std::fs::Permissions::from_mode(0o755)).unwrap();
+        create_private_dir(&dir).unwrap();
+        let mode = |p: &Path| std::fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&dir), 0o700);
+        assert_eq!(mode(dir.parent().unwrap()), 0o700);
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn mbox_separator_uses_sender_and_utc_asctime() {
+        assert_eq!(
+            mbox_from_line("Alice <Alice@Example.com>", 1_700_000_000),
+            "From alice@example.com Tue Nov 14 22:13:20 2023\n"
+        );
+        assert_eq!(
+            mbox_from_line("undisclosed", 0),
+            "From MAILER-DAEMON Thu Jan  1 00:00:00 1970\n"
+        );
+    }
+
+    #[test]
+    fn mbox_entries_quote_from_lines_and_use_lf() {
+        let raw = b"Subject: x\r\n\r\nFrom here\r\n>From there\r\nFromage\r\nend";
+        assert_eq!(
+            mbox_entry(raw),
+            b"Subject: x\n\n>From here\n>>From there\nFromage\nend\n\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn maildir_names_carry_flags_in_order() {
+        assert_eq!(
+            maildir_base(1_700_000_000, "M1a2b"),
+            "1700000000.M1a2b.neverlight"
+        );
+        assert_eq!(maildir_info(false, false), ":2,");
+        assert_eq!(maildir_info(true, false), ":2,S");
+        assert_eq!(maildir_info(true, true), ":2,FS");
+    }
+}
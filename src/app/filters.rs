@@ -0,0 +1,700 @@
+//! Server-side filters: JMAP Sieve (RFC 9661) script management.
+//!
+//! Scripts are listed, edited and activated per account; at most one is
+//! active. The rule builder appends plain Sieve to the script being edited
+//! (adding any `require` it needs), so hand edits and rules from other
+//! clients are left alone. Scripts are validated by the server
+//! (`SieveScript/validate`) before every save, and its error is shown under
+//! the editor with the offending line when it names one.
+
+use cosmic::app::Task;
+use cosmic::iced::{Color, Length};
+use cosmic::widget::{self, text_editor};
+use cosmic::Element;
+
+use neverlight_mail_core::config::AccountId;
+use neverlight_mail_core::session::CAP_SIEVE;
+use neverlight_mail_core::sieve;
+
+use super::{AppModel, FilterAction, FilterEditor, FilterField, FilterRule, FiltersPhase, Message};
+
+/// Sieve quoted string (RFC 5228 §2.4.2).
+fn sieve_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Extension a rule's action needs, if any.
+fn required_extension(action: FilterAction) -> Option<&'static str> {
+    match action {
+        FilterAction::FileInto => Some("fileinto"),
+        FilterAction::MarkRead | FilterAction::Star => Some("imap4flags"),
+        FilterAction::Discard => None,
+    }
+}
+
+/// The Sieve block for one rule, newline-terminated.
+fn rule_sieve(rule: &FilterRule) -> String {
+    let value = sieve_string(&rule.value);
+    let test = match rule.field {
+        FilterField::From => format!("address :contains \"from\" {value}"),
+        FilterField::Subject => format!("header :contains \"subject\" {value}"),
+        FilterField::List => format!("header :contains \"list-id\" {value}"),
+    };
+    let action = match rule.action {
+        FilterAction::FileInto => format!("fileinto {};", sieve_string(&rule.folder)),
+        FilterAction::MarkRead => "addflag \"\\\\Seen\";".to_string(),
+        FilterAction::Star => "addflag \"\\\\Flagged\";".to_string(),
+        FilterAction::Discard => "discard;".to_string(),
+    };
+    format!("if {test} {{\n    {action}\n}}\n")
+}
+
+/// Whether some `require` line in `script` already names `extension`.
+fn has_require(script: &str, extension: &str) -> bool {
+    let quoted = sieve_string(extension);
+    script
+        .lines()
+        .map(str::trim_start)
+        .any(|line| line.starts_with("require") && line.contains(&quoted))
+}
+
+/// `script` with `rule` appended and its extension required.
+fn with_rule(script: &str, rule: &FilterRule) -> String {
+    let mut out = String::new();
+    if let Some(ext) = required_extension(rule.action).filter(|e| !has_require(script, e)) {
+        out.push_str(&format!("require {};\n", sieve_string(ext)));
+    }
+    out.push_str(script);
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out.push_str(&rule_sieve(rule));
+    out
+}
+
+/// `script` without the block `rule` added. A `require` stays, since other
+/// rules may rely on it.
+fn without_rule(script: &str, rule: &FilterRule) -> String {
+    script.replacen(&rule_sieve(rule), "", 1)
+}
+
+fn rule_label(rule: &FilterRule) -> String {
+    let action = match rule.action {
+        FilterAction::FileInto => format!("file into {}", rule.folder),
+        FilterAction::MarkRead => "mark read".into(),
+        FilterAction::Star => "star".into(),
+        FilterAction::Discard => "discard".into(),
+    };
+    let field = FilterField::LABELS[rule.field.index()];
+    format!(
+        "If {field} contains \u{201c}{}\u{201d} → {action}",
+        rule.value
+    )
+}
+
+/// Line number named in a validation error ("line 3: …"), 1-based.
+fn error_line(description: &str) -> Option<usize> {
+    let lower = description.to_ascii_lowercase();
+    let rest = &lower[lower.find("line")? + 4..];
+    let digits: String = rest
+        .trim_start_matches([' ', ':'])
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    digits.parse().ok().filter(|&n| n > 0)
+}
+
+fn empty_draft() -> FilterRule {
+    FilterRule {
+        field: FilterField::From,
+        value: String::new(),
+        action: FilterAction::FileInto,
+        folder: String::new(),
+    }
+}
+
+impl AppModel {
+    pub(super) fn handle_filters(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::FiltersOpen(account_id) => return self.load_filters(account_id),
+            Message::FiltersLoaded { account_id, result } => {
+                let open = match &self.filters_phase {
+                    FiltersPhase::Loading(id) => *id == account_id,
+                    FiltersPhase::List { account_id: id, .. } => *id == account_id,
+                    _ => false,
+                };
+                if !open {
+                    return Task::none();
+                }
+                self.filters_phase = match result {
+                    Ok(scripts) => FiltersPhase::List {
+                        account_id,
+                        scripts,
+                        error: None,
+                        busy: false,
+                    },
+                    Err(e) => {
+                        log::warn!("Listing Sieve scripts failed: {}", e);
+                        FiltersPhase::List {
+                            account_id,
+                            scripts: Vec::new(),
+                            error: Some(format!("Loading filters failed: {e}")),
+                            busy: false,
+                        }
+                    }
+                };
+            }
+            Message::FiltersClose => self.filters_phase = FiltersPhase::Closed,
+            Message::FilterActivate(script_id) => {
+                let FiltersPhase::List { account_id, .. } = &self.filters_phase else {
+                    return Task::none();
+                };
+                let Some(client) = self.client_for_account(account_id) else {
+                    return Task::none();
+                };
+                if let FiltersPhase::List { busy, .. } = &mut self.filters_phase {
+                    *busy = true;
+                }
+                return cosmic::task::future(async move {
+                    let result = sieve::activate(&client, script_id.as_deref())
+                        .await
+                        .map_err(|e| e.to_string());
+                    Message::FilterActivated(result)
+                });
+            }
+            Message::FilterActivated(result) => {
+                let FiltersPhase::List {
+                    account_id,
+                    error,
+                    busy,
+                    ..
+                } = &mut self.filters_phase
+                else {
+                    return Task::none();
+                };
+                if let Err(e) = result {
+                    log::warn!("Activating Sieve script failed: {}", e);
+                    *error = Some(format!("Activating failed: {e}"));
+                    *busy = false;
+                    return Task::none();
+                }
+                let account_id = account_id.clone();
+                return self.load_filters(account_id);
+            }
+            Message::FilterNew => {
+                if let Some(editor) = self.open_editor(None, String::new()) {
+                    self.filters_phase = FiltersPhase::Editing(editor);
+                }
+            }
+            Message::FilterEdit(script_id) => {
+                let FiltersPhase::List {
+                    account_id,
+                    scripts,
+                    ..
+                } = &self.filters_phase
+                else {
+                    return Task::none();
+                };
+                let Some(client) = self.client_for_account(account_id) else {
+                    return Task::none();
+                };
+                let name = scripts
+                    .iter()
+                    .find(|s| s.id == script_id)
+                    .map(|s| s.name.clone())
+                    .unwrap_or_default();
+                let Some(mut editor) = self.open_editor(Some(script_id.clone()), name) else {
+                    return Task::none();
+                };
+                editor.busy = true;
+                self.filters_phase = FiltersPhase::Editing(editor);
+                return cosmic::task::future(async move {
+                    let result = sieve::get_content(&client, &script_id)
+                        .await
+                        .map_err(|e| e.to_string());
+                    Message::FilterContentLoaded { script_id, result }
+                });
+            }
+            Message::FilterContentLoaded { script_id, result } => {
+                let FiltersPhase::Editing(editor) = &mut self.filters_phase else {
+                    return Task::none();
+                };
+                if editor.script_id.as_deref() != Some(script_id.as_str()) {
+                    return Task::none();
+                }
+                editor.busy = false;
+                match result {
+                    Ok(content) => editor.script = text_editor::Content::with_text(&content),
+                    Err(e) => {
+                        log::warn!("Loading Sieve script failed: {}", e);
+                        editor.error = Some(format!("Loading the script failed: {e}"));
+                    }
+                }
+            }
+            Message::FilterNameChanged(name) => {
+                if let FiltersPhase::Editing(editor) = &mut self.filters_phase {
+                    editor.name = name;
+                }
+            }
+            Message::FilterDraftField(index) => {
+                if let FiltersPhase::Editing(editor) = &mut self.filters_phase {
+                    if let Some(field) = FilterField::ALL.get(index) {
+                        editor.draft.field = *field;
+                    }
+                }
+            }
+            Message::FilterDraftValue(value) => {
+                if let FiltersPhase::Editing(editor) = &mut self.filters_phase {
+                    editor.draft.value = value;
+                }
+            }
+            Message::FilterDraftAction(index) => {
+                if let FiltersPhase::Editing(editor) = &mut self.filters_phase {
+                    if let Some(action) = FilterAction::ALL.get(index) {
+                        editor.draft.action = *action;
+                    }
+                }
+            }
+            Message::FilterDraftFolder(index) => {
+                if let FiltersPhase::Editing(editor) = &mut self.filters_phase {
+                    if let Some(folder) = editor.folders.get(index) {
+                        editor.draft.folder = folder.clone();
+                    }
+                }
+            }
+            Message::FilterAddRule => {
+                let FiltersPhase::Editing(editor) = &mut self.filters_phase else {
+                    return Task::none();
+                };
+                let rule = editor.draft.clone();
+                if rule.value.trim().is_empty() {
+                    editor.error = Some("Enter text to match".into());
+                    return Task::none();
+                }
+                if rule.action == FilterAction::FileInto && rule.folder.is_empty() {
+                    editor.error = Some("Choose a folder to file into".into());
+                    return Task::none();
+                }
+                let script = with_rule(&editor.script.text(), &rule);
+                editor.script = text_editor::Content::with_text(&script);
+                editor.rules.push(rule);
+                editor.draft.value.clear();
+                editor.error = None;
+                editor.invalid = None;
+            }
+            Message::FilterRemoveRule(index) => {
+                if let FiltersPhase::Editing(editor) = &mut self.filters_phase {
+                    if index < editor.rules.len() {
+                        let rule = editor.rules.remove(index);
+                        let script = without_rule(&editor.script.text(), &rule);
+                        editor.script = text_editor::Content::with_text(&script);
+                    }
+                }
+            }
+            Message::FilterScriptAction(action) => {
+                if let FiltersPhase::Editing(editor) = &mut self.filters_phase {
+                    let edited = action.is_edit();
+                    editor.script.perform(action);
+                    if edited {
+                        editor.invalid = None;
+                    }
+                }
+            }
+            Message::FilterValidate => return self.validate_filter(false),
+            Message::FilterSave => return self.validate_filter(true),
+            Message::FilterValidated(result) => {
+                let FiltersPhase::Editing(editor) = &mut self.filters_phase else {
+                    return Task::none();
+                };
+                editor.busy = false;
+                match result {
+                    Ok(None) => {
+                        editor.invalid = None;
+                        self.status_message = "Filter script is valid".into();
+                    }
+                    Ok(Some(reason)) => editor.invalid = Some(reason),
+                    Err(e) => {
+                        log::warn!("Validating Sieve script failed: {}", e);
+                        editor.error = Some(format!("Validation failed: {e}"));
+                    }
+                }
+            }
+            Message::FilterSaved(result) => {
+                let FiltersPhase::Editing(editor) = &mut self.filters_phase else {
+                    return Task::none();
+                };
+                editor.busy = false;
+                match result {
+                    Ok(_) => {
+                        self.status_message = "Filter saved".into();
+                        let account_id = editor.account_id.clone();
+                        return self.load_filters(account_id);
+                    }
+                    Err(e) => {
+                        log::warn!("Saving Sieve script failed: {}", e);
+                        editor.error = Some(format!("Saving failed: {e}"));
+                    }
+                }
+            }
+            Message::FilterBack => {
+                if let FiltersPhase::Editing(editor) = &self.filters_phase {
+                    let account_id = editor.account_id.clone();
+                    return self.load_filters(account_id);
+                }
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+
+    /// Show the account's script list, fetching it from the server.
+    fn load_filters(&mut self, account_id: AccountId) -> Task<Message> {
+        let Some(client) = self
+            .client_for_account(&account_id)
+            .filter(|c| c.has_capability(CAP_SIEVE))
+        else {
+            self.filters_phase = FiltersPhase::Closed;
+            self.status_message = "Filters need the account online".into();
+            return Task::none();
+        };
+        if !matches!(self.filters_phase, FiltersPhase::List { .. }) {
+            self.filters_phase = FiltersPhase::Loading(account_id.clone());
+        }
+        cosmic::task::future(async move {
+            let result = sieve::list(&client).await.map_err(|e| e.to_string());
+            Message::FiltersLoaded { account_id, result }
+        })
+    }
+
+    /// Editor for a script of the listed account.
+    fn open_editor(&self, script_id: Option<String>, name: String) -> Option<FilterEditor> {
+        let FiltersPhase::List { account_id, .. } = &self.filters_phase else {
+            return None;
+        };
+        let folders = self
+            .account_index(account_id)
+            .map(|i| {
+                self.accounts[i]
+                    .folders
+                    .iter()
+                    .map(|f| f.path.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Some(FilterEditor {
+            account_id: account_id.clone(),
+            script_id,
+            name,
+            rules: Vec::new(),
+            draft: empty_draft(),
+            script: text_editor::Content::new(),
+            folders,
+            invalid: None,
+            error: None,
+            busy: false,
+        })
+    }
+
+    /// Ask the server to check the script, then save it if `save` and it
+    /// passed.
+    fn validate_filter(&mut self, save: bool) -> Task<Message> {
+        let FiltersPhase::Editing(editor) = &self.filters_phase else {
+            return Task::none();
+        };
+        if editor.busy {
+            return Task::none();
+        }
+        let Some(client) = self.client_for_account(&editor.account_id) else {
+            return Task::none();
+        };
+        let name = editor.name.trim().to_string();
+        if save && name.is_empty() {
+            if let FiltersPhase::Editing(editor) = &mut self.filters_phase {
+                editor.error = Some("Name the filter".into());
+            }
+            return Task::none();
+        }
+        let script = editor.script.text();
+        let script_id = editor.script_id.clone();
+        if let FiltersPhase::Editing(editor) = &mut self.filters_phase {
+            editor.busy = true;
+            editor.error = None;
+        }
+        cosmic::task::future(async move {
+            let verdict = match sieve::validate(&client, &script).await {
+                Ok(verdict) => verdict,
+                Err(e) => return Message::FilterValidated(Err(e.to_string())),
+            };
+            if !save || verdict.is_some() {
+                return Message::FilterValidated(Ok(verdict));
+            }
+            let result = sieve::save(&client, script_id.as_deref(), &name, &script)
+                .await
+                .map_err(|e| e.to_string());
+            Message::FilterSaved(result)
+        })
+    }
+
+    pub(super) fn filters_dialog(&self) -> Element<'_, Message> {
+        match &self.filters_phase {
+            FiltersPhase::Editing(editor) => self.filter_editor_dialog(editor),
+            FiltersPhase::List {
+                account_id,
+                scripts,
+                error,
+                busy,
+            } => {
+                let mut controls = widget::column().spacing(8).width(Length::Fill);
+                if scripts.is_empty() {
+                    controls = controls.push(widget::text::body("No filters on this server yet."));
+                }
+                for script in scripts {
+                    let name = if script.name.is_empty() {
+                        "(unnamed)"
+                    } else {
+                        script.name.as_str()
+                    };
+                    let mut row = widget::row()
+                        .spacing(8)
+                        .align_y(cosmic::iced::Alignment::Center)
+                        .push(widget::text::body(name).width(Length::Fill));
+                    if script.is_active {
+                        row = row.push(widget::text::caption("Active"));
+                    } else {
+                        let mut activate = widget::button::standard("Activate");
+                        if !busy {
+                            activate =
+                                activate.on_press(Message::FilterActivate(Some(script.id.clone())));
+                        }
+                        row = row.push(activate);
+                    }
+                    let mut edit = widget::button::standard("Edit");
+                    if !busy {
+                        edit = edit.on_press(Message::FilterEdit(script.id.clone()));
+                    }
+                    controls = controls.push(row.push(edit));
+                }
+                if scripts.iter().any(|s| s.is_active) {
+                    let mut off = widget::button::text("Turn filtering off");
+                    if !busy {
+                        off = off.on_press(Message::FilterActivate(None));
+                    }
+                    controls = controls.push(off);
+                }
+
+                let label = self
+                    .account_index(account_id)
+                    .map(|i| self.accounts[i].config.label.as_str())
+                    .unwrap_or_default();
+                let mut dialog = widget::dialog()
+                    .title(format!("Filters — {label}"))
+                    .control(controls)
+                    .primary_action(
+                        widget::button::suggested("New filter").on_press(Message::FilterNew),
+                    )
+                    .secondary_action(
+                        widget::button::standard("Close").on_press(Message::FiltersClose),
+                    );
+                if let Some(err) = error {
+                    dialog = dialog.body(err);
+                }
+                dialog.into()
+            }
+            FiltersPhase::Loading(_) | FiltersPhase::Closed => widget::dialog()
+                .title("Filters")
+                .body("Loading...")
+                .secondary_action(widget::button::standard("Close").on_press(Message::FiltersClose))
+                .into(),
+        }
+    }
+
+    fn filter_editor_dialog<'a>(&'a self, editor: &'a FilterEditor) -> Element<'a, Message> {
+        let mut controls = widget::column()
+            .spacing(8)
+            .width(Length::Fill)
+            .push(
+                widget::text_input("Filter name", &editor.name)
+                    .label("Name")
+                    .on_input(Message::FilterNameChanged),
+            )
+            .push(widget::text::body("Rules"));
+
+        for (i, rule) in editor.rules.iter().enumerate() {
+            controls = controls.push(
+                widget::row()
+                    .spacing(8)
+                    .align_y(cosmic::iced::Alignment::Center)
+                    .push(widget::text::caption(rule_label(rule)).width(Length::Fill))
+                    .push(
+                        widget::button::icon(widget::icon::from_name("edit-delete-symbolic"))
+                            .on_press(Message::FilterRemoveRule(i))
+                            .padding(4)
+                            .class(cosmic::theme::Button::Text),
+                    ),
+            );
+        }
+
+        let draft = &editor.draft;
+        let mut draft_row = widget::row()
+            .spacing(8)
+            .align_y(cosmic::iced::Alignment::Center)
+            .push(widget::dropdown(
+                FilterField::LABELS,
+                Some(draft.field.index()),
+                Message::FilterDraftField,
+            ))
+            .push(
+                widget::text_input("contains…", &draft.value)
+                    .on_input(Message::FilterDraftValue)
+                    .on_submit(|_| Message::FilterAddRule),
+            )
+            .push(widget::dropdown(
+                FilterAction::LABELS,
+                Some(draft.action.index()),
+                Message::FilterDraftAction,
+            ));
+        if draft.action == FilterAction::FileInto {
+            draft_row = draft_row.push(widget::dropdown(
+                editor.folders.as_slice(),
+                editor.folders.iter().position(|f| *f == draft.folder),
+                Message::FilterDraftFolder,
+            ));
+        }
+        controls = controls
+            .push(draft_row.push(widget::button::standard("Add").on_press(Message::FilterAddRule)))
+            .push(widget::text::body("Script"))
+            .push(
+                widget::text_editor(&editor.script)
+                    .font(cosmic::font::mono())
+                    .on_action(Message::FilterScriptAction)
+                    .height(Length::Fixed(220.0)),
+            );
+
+        if let Some(reason) = &editor.invalid {
+            let script = editor.script.text();
+            let offending = error_line(reason).and_then(|n| {
+                script
+                    .lines()
+                    .nth(n - 1)
+                    .map(|line| format!("{n} │ {line}"))
+            });
+            let mut note = widget::column().spacing(2);
+            if let Some(line) = offending {
+                note = note.push(widget::text::caption(line).font(cosmic::font::mono()));
+            }
+            note = note.push(widget::text::caption(reason.as_str()));
+            controls = controls.push(
+                widget::container(note)
+                    .padding([4, 8])
+                    .width(Length::Fill)
+                    .style(|theme: &cosmic::Theme| {
+                        let destructive: Color = theme.cosmic().destructive_color().into();
+                        cosmic::iced_widget::container::Style {
+                            text_color: Some(destructive),
+                            border: cosmic::iced::Border {
+                                color: destructive,
+                                width: 1.0,
+                                radius: 4.0.into(),
+                            },
+                            ..Default::default()
+                        }
+                    }),
+            );
+        }
+
+        let busy = editor.busy;
+        let mut save = widget::button::suggested(if busy { "Working..." } else { "Save" });
+        let mut validate = widget::button::standard("Validate");
+        if !busy {
+            save = save.on_press(Message::FilterSave);
+            validate = validate.on_press(Message::FilterValidate);
+        }
+        let title = if editor.script_id.is_some() {
+            "Edit filter"
+        } else {
+            "New filter"
+        };
+        let mut dialog = widget::dialog()
+            .title(title)
+            .control(controls)
+            .primary_action(save)
+            .secondary_action(widget::button::standard("Back").on_press(Message::FilterBack))
+            .tertiary_action(validate);
+        if let Some(err) = &editor.error {
+            dialog = dialog.body(err.as_str());
+        }
+        dialog.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(field: FilterField, value: &str, action: FilterAction) -> FilterRule {
+        FilterRule {
+            field,
+            value: value.into(),
+            action,
+            folder: "Lists/Rust".into(),
+        }
+    }
+
+    #[test]
+    fn rules_generate_sieve() {
+        assert_eq!(
+            rule_sieve(&rule(
+                FilterField::List,
+                "rust-users",
+                FilterAction::FileInto
+            )),
+            "if header :contains \"list-id\" \"rust-users\" {\n    fileinto \"Lists/Rust\";\n}\n"
+        );
+        assert_eq!(
+            rule_sieve(&rule(FilterField::From, "a\"b\\c", FilterAction::Star)),
+            "if address :contains \"from\" \"a\\\"b\\\\c\" {\n    addflag \"\\\\Flagged\";\n}\n"
+        );
+    }
+
+    #[test]
+    fn adding_rules_requires_extensions_once() {
+        let read = rule(FilterField::Subject, "digest", FilterAction::MarkRead);
+        let star = rule(FilterField::From, "boss@example.com", FilterAction::Star);
+        let script = with_rule("", &read);
+        assert!(script.starts_with("require \"imap4flags\";\n"));
+        let script = with_rule(&script, &star);
+        assert_eq!(script.matches("require").count(), 1);
+        let script = with_rule(
+            &script,
+            &rule(FilterField::From, "x", FilterAction::Discard),
+        );
+        assert_eq!(script.matches("require").count(), 1);
+
+        let kept = "# mine\nkeep;";
+        assert_eq!(
+            with_rule(kept, &read),
+            format!(
+                "require \"imap4flags\";\n# mine\nkeep;\n{}",
+                rule_sieve(&read)
+            )
+        );
+    }
+
+    #[test]
+    fn removing_a_rule_leaves_the_rest() {
+        let read = rule(FilterField::Subject, "digest", FilterAction::MarkRead);
+        let star = rule(FilterField::From, "boss@example.com", FilterAction::Star);
+        let script = with_rule(&with_rule("", &read), &star);
+        assert_eq!(
+            without_rule(&script, &read),
+            format!("require \"imap4flags\";\n{}", rule_sieve(&star))
+        );
+    }
+
+    #[test]
+    fn validation_errors_name_their_line() {
+        assert_eq!(error_line("line 3: unknown command \"filein\""), Some(3));
+        assert_eq!(error_line("Parse error at Line: 12 column 4"), Some(12));
+        assert_eq!(error_line("unexpected end of script"), None);
+    }
+}
@@ -0,0 +1,262 @@
+//! Search-term highlighting: result snippets, preview bodies and n/N jumps.
+//!
+//! Matching is a case-insensitive substring scan, close enough to the FTS
+//! tokenizer for display purposes.
+
+use std::ops::Range;
+
+use cosmic::widget::text_editor::{self, Action, Motion};
+
+/// Snippets from FTS5 `snippet()` and JMAP `SearchSnippet/get` arrive as
+/// HTML-escaped text with `<mark>` around matches.
+const MARK_OPEN: &str = "<mark>";
+const MARK_CLOSE: &str = "</mark>";
+
+/// One match in a plain-text body, in text editor cursor steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct TextMatch {
+    pub line: usize,
+    /// Characters from the start of the document, counting each line break as one.
+    pub offset: usize,
+    /// Length in characters.
+    pub len: usize,
+}
+
+/// Byte length of `term` matched case-insensitively at the start of `text`.
+fn match_len(text: &str, term: &str) -> Option<usize> {
+    let mut text_chars = text.char_indices();
+    for t in term.chars() {
+        let (_, c) = text_chars.next()?;
+        if !c.to_lowercase().eq(t.to_lowercase()) {
+            return None;
+        }
+    }
+    Some(text_chars.next().map_or(text.len(), |(i, _)| i))
+}
+
+/// Non-overlapping byte ranges where any of `terms` occurs in `text`, in
+/// order. The longest term wins when several start at the same place.
+pub(crate) fn find_matches(text: &str, terms: &[String]) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut skip_until = 0;
+    for (i, _) in text.char_indices() {
+        if i < skip_until {
+            continue;
+        }
+        let longest = terms
+            .iter()
+            .filter(|t| !t.is_empty())
+            .filter_map(|t| match_len(&text[i..], t))
+            .max();
+        if let Some(len) = longest {
+            ranges.push(i..i + len);
+            skip_until = i + len;
+        }
+    }
+    ranges
+}
+
+/// Matches in `text` as laid out by a text editor.
+pub(super) fn match_positions(text: &str, terms: &[String]) -> Vec<TextMatch> {
+    let mut positions = Vec::new();
+    let mut line_start = 0;
+    for (line, content) in text.lines().enumerate() {
+        for range in find_matches(content, terms) {
+            positions.push(TextMatch {
+                line,
+                offset: line_start + content[..range.start].chars().count(),
+                len: content[range].chars().count(),
+            });
+        }
+        line_start += content.chars().count() + 1;
+    }
+    positions
+}
+
+/// Index of the match after (or before) `current`, wrapping around.
+pub(super) fn step_match(current: Option<usize>, count: usize, forward: bool) -> usize {
+    match current {
+        Some(i) if i < count => {
+            if forward {
+                (i + 1) % count
+            } else {
+                (i + count - 1) % count
+            }
+        }
+        _ if forward => 0,
+        _ => count.saturating_sub(1),
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Single-line caption text for a snippet, with matches in «guillemets».
+pub(super) fn plain_snippet(html: &str) -> String {
+    let marked = html.replace(MARK_OPEN, "«").replace(MARK_CLOSE, "»");
+    decode_entities(&marked)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Copy `text` until `close` (inclusive) into `out`; returns the rest.
+fn copy_through<'a>(text: &'a str, close: char, out: &mut String) -> &'a str {
+    let end = text[1..]
+        .find(close)
+        .map_or(text.len(), |i| i + 1 + close.len_utf8());
+    out.push_str(&text[..end]);
+    &text[end..]
+}
+
+fn highlight_markdown_line(line: &str, terms: &[String], out: &mut String) {
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        // Code spans, link targets and autolinks must stay byte-for-byte.
+        if c == '`' {
+            rest = copy_through(rest, '`', out);
+        } else if rest.starts_with("](") {
+            out.push(']');
+            rest = copy_through(&rest[1..], ')', out);
+        } else if c == '<' && rest[1..].starts_with(|ch: char| ch.is_ascii_alphabetic()) {
+            rest = copy_through(rest, '>', out);
+        } else if let Some(len) = terms
+            .iter()
+            .filter(|t| !t.is_empty())
+            .filter_map(|t| match_len(rest, t))
+            .max()
+        {
+            out.push_str("**");
+            out.push_str(&rest[..len]);
+            out.push_str("**");
+            rest = &rest[len..];
+        } else {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+}
+
+/// Wrap matches in `**` so the markdown preview renders them bold. Fenced
+/// code blocks, code spans and link targets are left untouched.
+pub(super) fn highlight_markdown(markdown: &str, terms: &[String]) -> String {
+    if terms.iter().all(|t| t.is_empty()) {
+        return markdown.to_string();
+    }
+    let mut out = String::with_capacity(markdown.len());
+    let mut in_fence = false;
+    for line in markdown.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            out.push_str(line);
+        } else if in_fence {
+            out.push_str(line);
+        } else {
+            highlight_markdown_line(line, terms, &mut out);
+        }
+    }
+    out
+}
+
+/// Move the editor selection onto `m`. The editor has no absolute cursor
+/// placement, so this walks from the document start.
+pub(super) fn select_match(editor: &mut text_editor::Content, m: TextMatch) {
+    editor.perform(Action::Move(Motion::DocumentStart));
+    for _ in 0..m.offset {
+        editor.perform(Action::Move(Motion::Right));
+    }
+    for _ in 0..m.len {
+        editor.perform(Action::Select(Motion::Right));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn matches_are_case_insensitive_and_prefer_longest() {
+        let text = "Invoice and INVOICES";
+        let ranges = find_matches(text, &terms(&["invoice", "invoices"]));
+        assert_eq!(ranges, vec![0..7, 12..20]);
+    }
+
+    #[test]
+    fn matches_handle_multibyte_text() {
+        let text = "Größe: GRÖSSE größe";
+        let ranges = find_matches(text, &terms(&["größe"]));
+        let found: Vec<&str> = ranges.into_iter().map(|r| &text[r]).collect();
+        assert_eq!(found, vec!["Größe", "größe"]);
+    }
+
+    #[test]
+    fn positions_count_line_breaks_as_one_step() {
+        let text = "first line\nsecond budget\r\nbudget";
+        let positions = match_positions(text, &terms(&["budget"]));
+        assert_eq!(
+            positions,
+            vec![
+                TextMatch {
+                    line: 1,
+                    offset: 18,
+                    len: 6
+                },
+                TextMatch {
+                    line: 2,
+                    offset: 25,
+                    len: 6
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn match_steps_wrap_both_ways() {
+        assert_eq!(step_match(None, 3, true), 0);
+        assert_eq!(step_match(None, 3, false), 2);
+        assert_eq!(step_match(Some(2), 3, true), 0);
+        assert_eq!(step_match(Some(0), 3, false), 2);
+        // A stale index from a longer body restarts.
+        assert_eq!(step_match(Some(7), 3, true), 0);
+    }
+
+    #[test]
+    fn snippet_marks_become_guillemets() {
+        assert_eq!(
+            plain_snippet("the <mark>budget</mark> &amp; the\n  <mark>plan</mark>"),
+            "the «budget» & the «plan»"
+        );
+    }
+
+    #[test]
+    fn markdown_matches_are_bolded() {
+        assert_eq!(
+            highlight_markdown("Q3 Budget review\n", &terms(&["budget"])),
+            "Q3 **Budget** review\n"
+        );
+    }
+
+    #[test]
+    fn markdown_code_and_links_are_untouched() {
+        let md = "see `budget.rs` and [budget](https://x.test/budget)\n```\nbudget\n```\n";
+        assert_eq!(
+            highlight_markdown(md, &terms(&["budget"])),
+            "see `budget.rs` and [**budget**](https://x.test/budget)\n```\nbudget\n```\n"
+        );
+    }
+
+    #[test]
+    fn markdown_without_terms_is_unchanged() {
+        assert_eq!(highlight_markdown("budget", &[]), "budget");
+    }
+}
@@ -0,0 +1,481 @@
+//! Importing `.eml` files and mbox archives into a mailbox with JMAP
+//! `Email/import`.
+//!
+//! Like exports, an import runs as a subscription while `AppModel::import`
+//! is set. mbox files are read line by line and uploaded one message at a
+//! time, so archive size doesn't matter. Keywords come from Maildir file
+//! name flags and mbox `Status:`/`X-Status:` headers; the received date from
+//! the mbox separator line, else the newest `Received:` header, else `Date:`.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, NaiveDateTime};
+use cosmic::app::Task;
+use cosmic::dialog::file_chooser;
+use futures::channel::mpsc;
+use futures::SinkExt;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+
+use neverlight_mail_core::client::JmapClient;
+use neverlight_mail_core::email::ImportRequest;
+
+use super::compose::parse_uri_list;
+use super::source::parse_headers;
+use super::{AppModel, ImportJob, MailboxIdentity, Message};
+use crate::dnd_models::DraggedFiles;
+
+/// A message ready for `Email/import`.
+struct ImportItem {
+    raw: Vec<u8>,
+    keywords: Vec<String>,
+    received_at: Option<i64>,
+}
+
+/// One mbox message as split from the archive.
+#[derive(Debug, PartialEq)]
+struct MboxEntry {
+    /// Date from the `From ` separator line.
+    received_at: Option<i64>,
+    raw: Vec<u8>,
+}
+
+/// Keywords for Maildir info flags in a file name (`…:2,FRS`).
+fn maildir_keywords(file_name: &str) -> Vec<String> {
+    let Some((_, flags)) = file_name.rsplit_once(":2,") else {
+        return Vec::new();
+    };
+    flags
+        .chars()
+        .filter_map(|flag| match flag {
+            'D' => Some("$draft"),
+            'F' => Some("$flagged"),
+            'R' => Some("$answered"),
+            'S' => Some("$seen"),
+            _ => None,
+        })
+        .map(String::from)
+        .collect()
+}
+
+/// Keywords from the `Status:` (`R` read) and `X-Status:` (`F` flagged,
+/// `A` answered, `D` draft) headers mbox writers add.
+fn status_keywords(headers: &[(String, String)]) -> Vec<String> {
+    let mut keywords: Vec<String> = Vec::new();
+    for (name, value) in headers {
+        let map: fn(char) -> Option<&'static str> = if name.eq_ignore_ascii_case("Status") {
+            |c| (c == 'R').then_some("$seen")
+        } else if name.eq_ignore_ascii_case("X-Status") {
+            |c| match c {
+                'F' => Some("$flagged"),
+                'A' => Some("$answered"),
+                'D' => Some("$draft"),
+                _ => None,
+            }
+        } else {
+            continue;
+        };
+        keywords.extend(value.chars().filter_map(map).map(String::from));
+    }
+    keywords.sort();
+    keywords.dedup();
+    keywords
+}
+
+fn parse_rfc2822(value: &str) -> Option<i64> {
+    // Drop trailing comments such as "(UTC)".
+    let value = value.split('(').next()?.trim();
+    DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|d| d.timestamp())
+}
+
+/// Delivery time from the topmost `Received:` header, else the `Date:`.
+fn header_received_at(headers: &[(String, String)]) -> Option<i64> {
+    let received = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("Received"))
+        .and_then(|(_, value)| parse_rfc2822(value.rsplit_once(';')?.1));
+    received.or_else(|| {
+        headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("Date"))
+            .and_then(|(_, value)| parse_rfc2822(value))
+    })
+}
+
+/// Date of an mbox separator line (`From sender Tue Nov 14 22:13:20 2023`).
+fn separator_date(line: &[u8]) -> Option<i64> {
+    let line = std::str::from_utf8(line).ok()?;
+    let date = line
+        .split_whitespace()
+        .skip(2)
+        .take(5)
+        .collect::<Vec<_>>()
+        .join(" ");
+    NaiveDateTime::parse_from_str(&date, "%a %b %d %H:%M:%S %Y")
+        .ok()
+        .map(|d| d.and_utc().timestamp())
+}
+
+/// Header block of a raw message, decoded for `parse_headers`.
+fn headers_of(raw: &[u8]) -> Vec<(String, String)> {
+    let end = raw
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .or_else(|| raw.windows(2).position(|w| w == b"\n\n"))
+        .unwrap_or(raw.len());
+    parse_headers(&String::from_utf8_lossy(&raw[..end]))
+}
+
+fn eml_item(raw: Vec<u8>, path: &Path) -> ImportItem {
+    let headers = headers_of(&raw);
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    ImportItem {
+        keywords: maildir_keywords(&file_name),
+        received_at: header_received_at(&headers),
+        raw,
+    }
+}
+
+fn mbox_item(entry: MboxEntry) -> ImportItem {
+    let headers = headers_of(&entry.raw);
+    ImportItem {
+        keywords: status_keywords(&headers),
+        received_at: entry.received_at.or_else(|| header_received_at(&headers)),
+        raw: entry.raw,
+    }
+}
+
+/// Splits an mbox fed line by line into messages, undoing mboxrd `>From `
+/// quoting and restoring CRLF line endings.
+#[derive(Default)]
+struct MboxSplitter {
+    current: Option<MboxEntry>,
+    previous_blank: bool,
+}
+
+impl MboxSplitter {
+    /// Feed one line; returns the previous message when this line starts
+    /// the next one.
+    fn push_line(&mut self, line: &[u8]) -> Option<MboxEntry> {
+        let line = line
+            .strip_suffix(b"\n")
+            .map(|l| l.strip_suffix(b"\r").unwrap_or(l))
+            .unwrap_or(line);
+        let at_boundary = self.current.is_none() || self.previous_blank;
+        if at_boundary && line.starts_with(b"From ") {
+            self.previous_blank = false;
+            let next = MboxEntry {
+                received_at: separator_date(line),
+                raw: Vec::new(),
+            };
+            return self.current.replace(next).map(close_entry);
+        }
+        self.previous_blank = line.is_empty();
+        let entry = self.current.as_mut()?;
+        let quotes = line.iter().take_while(|&&b| b == b'>').count();
+        let line = if quotes > 0 && line[quotes..].starts_with(b"From ") {
+            &line[1..]
+        } else {
+            line
+        };
+        entry.raw.extend_from_slice(line);
+        entry.raw.extend_from_slice(b"\r\n");
+        None
+    }
+
+    fn finish(self) -> Option<MboxEntry> {
+        self.current.map(close_entry)
+    }
+}
+
+/// Drop the blank line that separates a message from the next `From `.
+fn close_entry(mut entry: MboxEntry) -> MboxEntry {
+    if entry.raw.ends_with(b"\r\n\r\n") {
+        entry.raw.truncate(entry.raw.len() - 2);
+    }
+    entry
+}
+
+/// Uploads messages into one mailbox and reports progress.
+struct Importer<'a> {
+    client: &'a JmapClient,
+    job: &'a ImportJob,
+    output: &'a mut mpsc::Sender<Message>,
+    files_done: usize,
+    imported: usize,
+    failed: usize,
+}
+
+impl Importer<'_> {
+    async fn import(&mut self, item: ImportItem) {
+        let request = ImportRequest {
+            mailbox_id: self.job.dest.mailbox_id.clone(),
+            raw: item.raw,
+            keywords: item.keywords,
+            received_at: item.received_at,
+        };
+        match neverlight_mail_core::email::import(self.client, request).await {
+            Ok(_) => self.imported += 1,
+            Err(e) => {
+                log::warn!("import: message rejected: {}", e);
+                self.failed += 1;
+            }
+        }
+        let _ = self
+            .output
+            .send(Message::ImportProgress {
+                job: self.job.id,
+                files_done: self.files_done,
+                imported: self.imported,
+            })
+            .await;
+    }
+
+    /// Import one file: an mbox when it starts with a `From ` line,
+    /// otherwise a single message.
+    async fn import_path(&mut self, path: &Path) -> std::io::Result<()> {
+        let file = tokio::fs::File::open(path).await?;
+        let mut reader = tokio::io::BufReader::new(file);
+        let mut line = Vec::new();
+        reader.read_until(b'\n', &mut line).await?;
+        if !line.starts_with(b"From ") {
+            let mut raw = line;
+            reader.read_to_end(&mut raw).await?;
+            self.import(eml_item(raw, path)).await;
+            return Ok(());
+        }
+        let mut splitter = MboxSplitter::default();
+        loop {
+            if let Some(entry) = splitter.push_line(&line) {
+                self.import(mbox_item(entry)).await;
+            }
+            line.clear();
+            if reader.read_until(b'\n', &mut line).await? == 0 {
+                break;
+            }
+        }
+        if let Some(entry) = splitter.finish() {
+            self.import(mbox_item(entry)).await;
+        }
+        Ok(())
+    }
+}
+
+/// Streams an import job: progress per message, then `ImportFinished`.
+pub(super) fn import_stream(
+    job: ImportJob,
+    client: Option<JmapClient>,
+) -> impl futures::Stream<Item = Message> {
+    cosmic::iced_futures::stream::channel(10, move |mut output| async move {
+        let Some(client) = client else {
+            let _ = output
+                .send(Message::ImportFinished {
+                    job: job.id,
+                    result: Err("not connected".into()),
+                })
+                .await;
+            return;
+        };
+        let mut importer = Importer {
+            client: &client,
+            job: &job,
+            output: &mut output,
+            files_done: 0,
+            imported: 0,
+            failed: 0,
+        };
+        for path in &job.paths {
+            if let Err(e) = importer.import_path(path).await {
+                log::warn!("import: {} unreadable: {}", path.display(), e);
+                importer.failed += 1;
+            }
+            importer.files_done += 1;
+        }
+        let result = Ok((importer.imported, importer.failed));
+        let _ = output
+            .send(Message::ImportFinished {
+                job: job.id,
+                result,
+            })
+            .await;
+    })
+}
+
+impl AppModel {
+    pub(super) fn handle_import(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::ImportFiles => {
+                let Some(acct) = self.active_account.and_then(|i| self.accounts.get(i)) else {
+                    return Task::none();
+                };
+                let Some(folder) = self.selected_folder.and_then(|i| acct.folders.get(i)) else {
+                    return Task::none();
+                };
+                let dest = MailboxIdentity {
+                    account_id: acct.config.id.clone(),
+                    mailbox_id: folder.mailbox_id.clone(),
+                };
+                let title = format!("Import into {}", folder.name);
+                return cosmic::task::future(async move {
+                    let dialog = file_chooser::open::Dialog::new().title(title);
+                    match dialog.open_files().await {
+                        Ok(response) => Message::ImportStart {
+                            dest,
+                            paths: response
+                                .urls()
+                                .iter()
+                                .filter_map(|url| url.to_file_path().ok())
+                                .collect(),
+                        },
+                        Err(file_chooser::Error::Cancelled) => Message::Noop,
+                        Err(e) => {
+                            log::warn!("Import file picker failed: {}", e);
+                            Message::Noop
+                        }
+                    }
+                });
+            }
+            Message::ImportDropped {
+                dest,
+                files: DraggedFiles(uri_list),
+            } => {
+                self.folder_drag_target = None;
+                let paths: Vec<PathBuf> = parse_uri_list(&uri_list)
+                    .into_iter()
+                    .map(PathBuf::from)
+                    .collect();
+                return self.dispatch(Message::ImportStart { dest, paths });
+            }
+            Message::ImportStart { dest, paths } => {
+                if paths.is_empty() {
+                    return Task::none();
+                }
+                if self.import.is_some() {
+                    self.status_message = "An import is already running".into();
+                    return Task::none();
+                }
+                if self.client_for_account(&dest.account_id).is_none() {
+                    self.status_message = "Not connected — importing needs the server".into();
+                    return Task::none();
+                }
+                self.import_epoch = self.import_epoch.saturating_add(1);
+                self.status_message = format!("Importing {} files...", paths.len());
+                self.import = Some(ImportJob {
+                    id: self.import_epoch,
+                    dest,
+                    paths,
+                    files_done: 0,
+                    imported: 0,
+                });
+            }
+            Message::ImportProgress {
+                job,
+                files_done,
+                imported,
+            } => {
+                if let Some(import) = self.import.as_mut().filter(|i| i.id == job) {
+                    import.files_done = files_done;
+                    import.imported = imported;
+                }
+            }
+            Message::ImportFinished { job, result } => {
+                if self.import.as_ref().map(|i| i.id) != Some(job) {
+                    return Task::none();
+                }
+                self.import = None;
+                match result {
+                    Ok((imported, 0)) => {
+                        self.status_message = format!("Imported {imported} messages");
+                    }
+                    Ok((imported, failed)) => {
+                        self.status_message =
+                            format!("Imported {imported} messages ({failed} failed, see log)");
+                    }
+                    Err(e) => {
+                        self.status_message = format!("Import failed: {e}");
+                        return Task::none();
+                    }
+                }
+                return self.dispatch(Message::Refresh);
+            }
+            Message::ImportCancel => {
+                if let Some(import) = self.import.take() {
+                    self.status_message = format!(
+                        "Import cancelled; {} messages were already imported",
+                        import.imported
+                    );
+                }
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(mbox: &[u8]) -> Vec<MboxEntry> {
+        let mut splitter = MboxSplitter::default();
+        let mut entries: Vec<MboxEntry> = mbox
+            .split_inclusive(|&b| b == b'\n')
+            .filter_map(|line| splitter.push_line(line))
+            .collect();
+        entries.extend(splitter.finish());
+        entries
+    }
+
+    #[test]
+    fn mbox_splits_on_separators_after_blank_lines() {
+        let mbox = b"From a@x Tue Nov 14 22:13:20 2023\nSubject: one\n\nhi\nFrom me, not a separator\n\nFrom b@x Thu Jan  1 00:00:00 1970\nSubject: two\n\n>From quoted\n>>From twice\n";
+        assert_eq!(
+            split(mbox),
+            vec![
+                MboxEntry {
+                    received_at: Some(1_700_000_000),
+                    raw: b"Subject: one\r\n\r\nhi\r\nFrom me, not a separator\r\n".to_vec(),
+                },
+                MboxEntry {
+                    received_at: Some(0),
+                    raw: b"Subject: two\r\n\r\nFrom quoted\r\n>From twice\r\n".to_vec(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn mbox_ignores_text_before_the_first_separator() {
+        assert!(split(b"garbage\n").is_empty());
+    }
+
+    #[test]
+    fn keywords_from_maildir_names_and_status_headers() {
+        assert_eq!(
+            maildir_keywords("1700000000.M1.host:2,FRS"),
+            vec!["$flagged", "$answered", "$seen"]
+        );
+        assert!(maildir_keywords("message.eml").is_empty());
+        let headers = vec![
+            ("Status".to_string(), "RO".to_string()),
+            ("X-Status".to_string(), "AF".to_string()),
+        ];
+        assert_eq!(
+            status_keywords(&headers),
+            vec!["$answered", "$flagged", "$seen"]
+        );
+    }
+
+    #[test]
+    fn received_date_prefers_the_newest_received_header() {
+        let raw = b"Received: from a by b; Tue, 14 Nov 2023 22:13:20 +0000 (UTC)\r\nDate: Mon, 13 Nov 2023 10:00:00 +0000\r\n\r\nbody";
+        assert_eq!(header_received_at(&headers_of(raw)), Some(1_700_000_000));
+        let raw = b"Date: Tue, 14 Nov 2023 23:13:20 +0100\n\nbody";
+        assert_eq!(header_received_at(&headers_of(raw)), Some(1_700_000_000));
+        assert_eq!(header_received_at(&headers_of(b"Subject: x\n\n")), None);
+    }
+}
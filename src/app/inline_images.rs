@@ -0,0 +1,236 @@
+//! Inline `cid:` images.
+//!
+//! The HTML→markdown conversion keeps images that point into the message
+//! itself as `![alt](cid:…)`. The body is split around those references so
+//! each image renders where the sender placed it, using the handle built for
+//! the matching attachment.
+
+use cosmic::widget::{image, markdown};
+
+use neverlight_mail_core::models::AttachmentData;
+
+use super::BodyBlock;
+
+const MAX_MD_ITEMS: usize = 200;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Image { cid: String, alt: String },
+}
+
+/// `(text before, alt, cid, rest)` for the first `![alt](cid:…)` in `line`
+/// that is not inside a code span.
+fn next_cid_image(line: &str) -> Option<(&str, &str, &str, &str)> {
+    let mut from = 0;
+    while let Some(found) = line[from..].find("![") {
+        let start = from + found;
+        from = start + 2;
+        if line[..start].matches('`').count() % 2 == 1 {
+            continue;
+        }
+        let Some(alt_len) = line[start + 2..].find(']') else {
+            continue;
+        };
+        let alt_end = start + 2 + alt_len;
+        let target = &line[alt_end + 1..];
+        if !target
+            .get(..5)
+            .is_some_and(|p| p.eq_ignore_ascii_case("(cid:"))
+        {
+            continue;
+        }
+        let Some(close) = target.find(')') else {
+            continue;
+        };
+        return Some((
+            &line[..start],
+            &line[start + 2..alt_end],
+            &target[5..close],
+            &target[close + 1..],
+        ));
+    }
+    None
+}
+
+/// Split markdown into text runs and `cid:` image references. Fenced code
+/// blocks are left whole.
+fn split_cid_images(markdown: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut in_fence = false;
+    for line in markdown.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let is_fence = trimmed.starts_with("```") || trimmed.starts_with("~~~");
+        if is_fence {
+            in_fence = !in_fence;
+        }
+        if in_fence || is_fence {
+            text.push_str(line);
+            continue;
+        }
+        let mut rest = line;
+        while let Some((before, alt, cid, after)) = next_cid_image(rest) {
+            text.push_str(before);
+            if !text.trim().is_empty() {
+                segments.push(Segment::Text(std::mem::take(&mut text)));
+            }
+            text.clear();
+            segments.push(Segment::Image {
+                cid: cid.to_string(),
+                alt: alt.to_string(),
+            });
+            rest = after;
+        }
+        text.push_str(rest);
+    }
+    if !text.trim().is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    segments
+}
+
+/// Content-ID as compared: `cid:` URLs are percent-encoded (RFC 2392) and
+/// headers wrap the id in angle brackets.
+fn normalize_cid(raw: &str) -> String {
+    let bytes = raw.trim().as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded)
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .to_lowercase()
+}
+
+fn find_attachment(attachments: &[AttachmentData], cid: &str) -> Option<usize> {
+    let cid = normalize_cid(cid);
+    attachments.iter().position(|a| {
+        a.content_id
+            .as_deref()
+            .is_some_and(|id| normalize_cid(id) == cid)
+    })
+}
+
+/// Body blocks for the rich preview, plus the attachment indices now shown
+/// inline. Unresolved references fall back to their alt text; bodies too
+/// large for the markdown widget fall back to plain text without images.
+pub(super) fn body_blocks(
+    markdown_body: &str,
+    plain_body: &str,
+    attachments: &[AttachmentData],
+    image_handles: &[Option<image::Handle>],
+) -> (Vec<BodyBlock>, Vec<usize>) {
+    let mut blocks = Vec::new();
+    let mut inlined = Vec::new();
+    let mut text = String::new();
+    for segment in split_cid_images(markdown_body) {
+        match segment {
+            Segment::Text(t) => text.push_str(&t),
+            Segment::Image { cid, alt } => {
+                let handle = find_attachment(attachments, &cid)
+                    .and_then(|i| Some((i, image_handles.get(i)?.clone()?)));
+                let Some((index, handle)) = handle else {
+                    text.push_str(&alt);
+                    continue;
+                };
+                if !text.trim().is_empty() {
+                    blocks.push(BodyBlock::Markdown(markdown::parse(&text).collect()));
+                }
+                text.clear();
+                blocks.push(BodyBlock::Image(handle));
+                inlined.push(index);
+            }
+        }
+    }
+    if !text.trim().is_empty() {
+        blocks.push(BodyBlock::Markdown(markdown::parse(&text).collect()));
+    }
+
+    let items: usize = blocks
+        .iter()
+        .map(|b| match b {
+            BodyBlock::Markdown(items) => items.len(),
+            BodyBlock::Image(_) => 0,
+        })
+        .sum();
+    if items > MAX_MD_ITEMS {
+        log::warn!(
+            "Markdown items ({}) exceed cap ({}), falling back to plain text",
+            items,
+            MAX_MD_ITEMS
+        );
+        return (text_blocks(plain_body), Vec::new());
+    }
+    (blocks, inlined)
+}
+
+/// A plain message (status or error text) as body blocks.
+pub(super) fn text_blocks(text: &str) -> Vec<BodyBlock> {
+    vec![BodyBlock::Markdown(markdown::parse(text).collect())]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(cid: &str, alt: &str) -> Segment {
+        Segment::Image {
+            cid: cid.to_string(),
+            alt: alt.to_string(),
+        }
+    }
+
+    fn text(t: &str) -> Segment {
+        Segment::Text(t.to_string())
+    }
+
+    #[test]
+    fn images_split_the_body_in_place() {
+        let md = "Hello\n\n![logo](cid:logo@x) and more\n![](CID:chart)\nbye\n";
+        assert_eq!(
+            split_cid_images(md),
+            vec![
+                text("Hello\n\n"),
+                image("logo@x", "logo"),
+                text(" and more\n"),
+                image("chart", ""),
+                text("\nbye\n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn remote_links_and_code_are_not_images() {
+        let md = "![x](https://x.test/a.png) `![y](cid:y)`\n```\n![z](cid:z)\n```\n";
+        assert_eq!(split_cid_images(md), vec![text(md)]);
+    }
+
+    #[test]
+    fn content_ids_compare_decoded_and_unbracketed() {
+        assert_eq!(
+            normalize_cid("Part1.ABC%40example.com"),
+            "part1.abc@example.com"
+        );
+        assert_eq!(
+            normalize_cid("<part1.abc@example.com>"),
+            "part1.abc@example.com"
+        );
+        assert_eq!(normalize_cid("100%"), "100%");
+    }
+}
@@ -0,0 +1,578 @@
+//! Checks before a clicked link leaves the app.
+//!
+//! Links open straight away unless something about them deserves a second
+//! look: visible text naming a different domain than the real destination,
+//! an internationalized host (shown decoded, with look-alike letters
+//! spelled out), credentials hiding the host, or a scheme other than web
+//! and mail. Those open only after confirming in a dialog.
+//!
+//! With "Clean links" on (the default), known redirect wrappers are
+//! unwrapped and tracking parameters dropped before opening. The domain
+//! check always looks through wrappers, since the wrapped URL is where the
+//! reader ends up.
+
+use std::collections::HashMap;
+
+use cosmic::app::Task;
+use cosmic::iced::Length;
+use cosmic::widget;
+use cosmic::Element;
+use url::Url;
+
+use super::auth_results::aligned;
+use super::{AppModel, LinkCheck, Message};
+
+/// Query parameters that only identify the campaign or the click.
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid",
+    "gclid",
+    "dclid",
+    "gbraid",
+    "wbraid",
+    "msclkid",
+    "yclid",
+    "twclid",
+    "igshid",
+    "mc_cid",
+    "mc_eid",
+    "_hsenc",
+    "_hsmi",
+    "mkt_tok",
+    "oly_anon_id",
+    "oly_enc_id",
+    "vero_id",
+    "wickedid",
+    "__s",
+];
+
+/// Redirect wrappers nest at most this deep before we give up.
+const MAX_UNWRAP: usize = 4;
+
+/// Visible text of each inline link in a markdown body, keyed by the
+/// normalized URL `markdown::view` reports on click. Image links have no
+/// text to compare and are left out.
+pub(super) fn link_texts(markdown_body: &str) -> HashMap<String, String> {
+    let bytes = markdown_body.as_bytes();
+    let mut texts = HashMap::new();
+    let mut opens: Vec<usize> = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => {
+                i += 2;
+                continue;
+            }
+            b'[' => opens.push(i),
+            b']' if bytes.get(i + 1) == Some(&b'(') => {
+                let Some(start) = opens.pop() else {
+                    i += 1;
+                    continue;
+                };
+                let Some((dest, len)) = link_destination(&markdown_body[i + 2..]) else {
+                    i += 1;
+                    continue;
+                };
+                let is_image = start > 0 && bytes[start - 1] == b'!';
+                let text = markdown_body[start + 1..i].replace(['*', '_', '`'], "");
+                let text = text.trim();
+                if !is_image && !text.is_empty() && !text.contains("![") {
+                    if let Ok(url) = Url::parse(&dest) {
+                        texts.insert(url.to_string(), text.to_string());
+                    }
+                }
+                i += 2 + len;
+                continue;
+            }
+            b']' => {
+                opens.pop();
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    texts
+}
+
+/// Destination of a link starting after `](`, and the length up to and
+/// including the closing `)`.
+fn link_destination(s: &str) -> Option<(String, usize)> {
+    let s_trimmed = s.trim_start();
+    let lead = s.len() - s_trimmed.len();
+    let (dest, after) = if let Some(inner) = s_trimmed.strip_prefix('<') {
+        let close = inner.find('>')?;
+        (&inner[..close], close + 2)
+    } else {
+        let mut depth = 0usize;
+        let mut end = s_trimmed.len();
+        for (i, c) in s_trimmed.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 => {
+                    end = i;
+                    break;
+                }
+                ')' => depth -= 1,
+                c if c.is_whitespace() => {
+                    end = i;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        (&s_trimmed[..end], end)
+    };
+    let close = after + s_trimmed[after..].find(')')?;
+    Some((dest.to_string(), lead + close + 1))
+}
+
+/// Domain named by link text such as `www.example.com` or
+/// `https://example.com/offer`, lowercased without `www.`.
+fn text_domain(text: &str) -> Option<String> {
+    let text = text.trim().to_lowercase();
+    let rest = text
+        .strip_prefix("https://")
+        .or_else(|| text.strip_prefix("http://"))
+        .unwrap_or(&text);
+    let host = rest.split(['/', '?', '#']).next()?.trim_end_matches('.');
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    let tld = host.rsplit_once('.')?.1;
+    let is_domain = host
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '-' || c == '.')
+        && tld.chars().count() >= 2
+        && tld.chars().all(char::is_alphabetic);
+    is_domain.then(|| host.to_string())
+}
+
+/// The URL a known redirect wrapper points to.
+fn unwrap_once(url: &Url) -> Option<Url> {
+    let host = url.host_str()?;
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    let inner = if host.ends_with("safelinks.protection.outlook.com") {
+        param("url")?
+    } else if (host == "www.google.com" || host == "google.com") && url.path() == "/url" {
+        param("q").or_else(|| param("url"))?
+    } else if matches!(host, "l.facebook.com" | "lm.facebook.com") && url.path() == "/l.php" {
+        param("u")?
+    } else if host == "l.instagram.com" || host == "out.reddit.com" {
+        param("u").or_else(|| param("url"))?
+    } else if host == "urldefense.proofpoint.com" && url.path() == "/v2/url" {
+        // Proofpoint v2 writes `%` as `-` and `/` as `_`.
+        let encoded = param("u")?.replace('-', "%").replace('_', "/");
+        url::form_urlencoded::parse(format!("u={}", encoded.replace('+', "%2B")).as_bytes())
+            .next()?
+            .1
+            .into_owned()
+    } else if host == "urldefense.com" {
+        // Proofpoint v3 keeps the URL readable between `__` markers; `*`
+        // stands for characters it rewrote, which we cannot restore.
+        let rest = url.as_str().split_once("/v3/__")?.1;
+        let inner = rest.split_once("__;")?.0;
+        if inner.contains('*') {
+            return None;
+        }
+        inner.to_string()
+    } else {
+        return None;
+    };
+    Url::parse(&inner)
+        .ok()
+        .filter(|u| matches!(u.scheme(), "http" | "https"))
+}
+
+/// Where a link really goes, through any known redirect wrappers.
+fn unwrap_redirects(url: &Url) -> Url {
+    let mut url = url.clone();
+    for _ in 0..MAX_UNWRAP {
+        match unwrap_once(&url) {
+            Some(inner) => url = inner,
+            None => break,
+        }
+    }
+    url
+}
+
+fn is_tracking_param(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.starts_with("utm_") || TRACKING_PARAMS.contains(&name.as_str())
+}
+
+/// The URL without tracking parameters; others keep their order.
+fn strip_tracking(url: &Url) -> Url {
+    let mut url = url.clone();
+    if !url.query_pairs().any(|(key, _)| is_tracking_param(&key)) {
+        return url;
+    }
+    let kept: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| !is_tracking_param(key))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    if kept.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(kept);
+    }
+    url
+}
+
+const BASE: u32 = 36;
+const T_MIN: u32 = 1;
+const T_MAX: u32 = 26;
+
+fn adapt(delta: u32, num_points: u32, first: bool) -> u32 {
+    let mut delta = if first { delta / 700 } else { delta / 2 };
+    delta += delta / num_points;
+    let mut k = 0;
+    while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+        delta /= BASE - T_MIN;
+        k += BASE;
+    }
+    k + (BASE - T_MIN + 1) * delta / (delta + 38)
+}
+
+/// Decode one punycode label without its `xn--` prefix (RFC 3492 §6.2).
+fn punycode_decode(input: &str) -> Option<String> {
+    let (basic, extended) = match input.rfind('-') {
+        Some(i) => (&input[..i], &input[i + 1..]),
+        None => ("", input),
+    };
+    if !basic.is_ascii() {
+        return None;
+    }
+    let mut output: Vec<char> = basic.chars().collect();
+    let mut n: u32 = 128;
+    let mut i: u32 = 0;
+    let mut bias: u32 = 72;
+    let mut digits = extended.bytes().peekable();
+    while digits.peek().is_some() {
+        let old_i = i;
+        let mut w: u32 = 1;
+        let mut k = BASE;
+        loop {
+            let digit = match digits.next()? {
+                c @ b'a'..=b'z' => c - b'a',
+                c @ b'A'..=b'Z' => c - b'A',
+                c @ b'0'..=b'9' => c - b'0' + 26,
+                _ => return None,
+            } as u32;
+            i = i.checked_add(digit.checked_mul(w)?)?;
+            let t = if k <= bias {
+                T_MIN
+            } else if k >= bias + T_MAX {
+                T_MAX
+            } else {
+                k - bias
+            };
+            if digit < t {
+                break;
+            }
+            w = w.checked_mul(BASE - t)?;
+            k += BASE;
+        }
+        let len = output.len() as u32 + 1;
+        bias = adapt(i - old_i, len, old_i == 0);
+        n = n.checked_add(i / len)?;
+        i %= len;
+        output.insert(i as usize, char::from_u32(n)?);
+        i += 1;
+    }
+    Some(output.into_iter().collect())
+}
+
+/// Host as a reader would see it, with `xn--` labels decoded.
+fn unicode_host(host: &str) -> String {
+    host.split('.')
+        .map(|label| {
+            label
+                .strip_prefix("xn--")
+                .and_then(punycode_decode)
+                .unwrap_or_else(|| label.to_string())
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Latin letter a Cyrillic or Greek one is commonly mistaken for.
+fn latin_lookalike(c: char) -> Option<char> {
+    Some(match c {
+        'а' | 'α' => 'a',
+        'с' | 'ϲ' => 'c',
+        'ԁ' => 'd',
+        'е' | 'ε' => 'e',
+        'һ' => 'h',
+        'і' | 'ι' => 'i',
+        'ј' => 'j',
+        'к' | 'κ' => 'k',
+        'ӏ' => 'l',
+        'о' | 'ο' => 'o',
+        'р' | 'ρ' => 'p',
+        'ԛ' => 'q',
+        'ѕ' => 's',
+        'т' | 'τ' => 't',
+        'υ' => 'u',
+        'ν' | 'ѵ' => 'v',
+        'ԝ' => 'w',
+        'х' | 'χ' => 'x',
+        'у' | 'γ' => 'y',
+        _ => return None,
+    })
+}
+
+/// Warning for an internationalized host; look-alike spellings of an
+/// all-Latin name are called out as such.
+fn idn_warning(host: &str) -> Option<String> {
+    if !host.split('.').any(|label| label.starts_with("xn--")) {
+        return None;
+    }
+    let shown = unicode_host(host);
+    let skeleton: String = shown
+        .chars()
+        .map(|c| latin_lookalike(c).unwrap_or(c))
+        .collect();
+    if skeleton.is_ascii() {
+        Some(format!(
+            "{shown} imitates {skeleton} with look-alike letters (real address {host})"
+        ))
+    } else {
+        Some(format!(
+            "{shown} is an international address (real address {host})"
+        ))
+    }
+}
+
+/// Decide how a clicked link opens: directly when `warnings` is empty,
+/// otherwise after confirmation.
+pub(super) fn check_link(url: &Url, text: Option<&str>, clean: bool) -> LinkCheck {
+    let destination = unwrap_redirects(url);
+    let mut warnings = Vec::new();
+    match destination.scheme() {
+        "http" | "https" => {
+            let host = destination.host_str().unwrap_or_default().to_lowercase();
+            let bare = host.strip_prefix("www.").unwrap_or(&host);
+            if let Some(shown) = text.and_then(text_domain) {
+                if !aligned(&shown, bare) {
+                    warnings.push(format!("The link shows {shown} but goes to {host}"));
+                }
+            }
+            if !destination.username().is_empty() || destination.password().is_some() {
+                warnings.push(format!(
+                    "Everything before \"@\" in this address is ignored; it goes to {host}"
+                ));
+            }
+            warnings.extend(idn_warning(&host));
+        }
+        "mailto" => {}
+        other => warnings.push(format!("Opens a {other}: link outside the browser")),
+    }
+    let target = if clean {
+        strip_tracking(&destination)
+    } else {
+        url.clone()
+    };
+    LinkCheck {
+        url: target.to_string(),
+        text: text.map(str::to_string),
+        warnings,
+    }
+}
+
+impl AppModel {
+    pub(super) fn handle_links(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::LinkClicked(url) => {
+                let text = self.link_texts.get(url.as_str()).map(String::as_str);
+                let check = check_link(&url, text, self.settings.clean_links);
+                if check.warnings.is_empty() {
+                    neverlight_mail_core::mime::open_link(&check.url);
+                } else {
+                    self.link_check = Some(check);
+                }
+            }
+            Message::LinkOpen => {
+                if let Some(check) = self.link_check.take() {
+                    neverlight_mail_core::mime::open_link(&check.url);
+                }
+            }
+            Message::LinkCopy => {
+                if let Some(check) = self.link_check.take() {
+                    self.status_message = "Link copied".into();
+                    return cosmic::iced::clipboard::write(check.url);
+                }
+            }
+            Message::LinkCancel => {
+                self.link_check = None;
+            }
+            Message::CleanLinksToggled(clean) => {
+                self.settings.clean_links = clean;
+                return self.save_settings();
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+
+    pub(super) fn link_dialog(&self) -> Element<'_, Message> {
+        let Some(check) = &self.link_check else {
+            return widget::column().into();
+        };
+        let mut controls = widget::column().spacing(8).width(Length::Fill);
+        for warning in &check.warnings {
+            controls = controls.push(widget::text::body(format!("\u{26A0} {warning}")));
+        }
+        if let Some(text) = &check.text {
+            controls = controls.push(widget::text::caption(format!("Link text: {text}")));
+        }
+        controls = controls.push(widget::text::caption(format!("Opens: {}", check.url)));
+        widget::dialog()
+            .title("Open this link?")
+            .control(controls)
+            .primary_action(widget::button::destructive("Open anyway").on_press(Message::LinkOpen))
+            .secondary_action(widget::button::standard("Cancel").on_press(Message::LinkCancel))
+            .tertiary_action(widget::button::text("Copy link").on_press(Message::LinkCopy))
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(url: &str) -> Url {
+        Url::parse(url).expect("test url")
+    }
+
+    #[test]
+    fn link_texts_come_from_inline_links() {
+        let texts = link_texts(
+            "See [**www.bank.example**](https://evil.test/login \"Log in\") and \
+             [docs](<https://docs.example/a b>) or [![logo](https://cdn.example/l.png)](https://shop.example/) \
+             \\[not](https://x.example/) [a (paren)](https://wiki.example/Rust_(language))",
+        );
+        assert_eq!(
+            texts.get("https://evil.test/login").map(String::as_str),
+            Some("www.bank.example")
+        );
+        assert_eq!(
+            texts
+                .get("https://wiki.example/Rust_(language)")
+                .map(String::as_str),
+            Some("a (paren)")
+        );
+        assert!(!texts.contains_key("https://shop.example/"));
+        assert!(!texts.contains_key("https://cdn.example/l.png"));
+        assert!(!texts.contains_key("https://x.example/"));
+    }
+
+    #[test]
+    fn only_domain_like_text_names_a_domain() {
+        assert_eq!(text_domain("www.PayPal.com"), Some("paypal.com".into()));
+        assert_eq!(
+            text_domain("https://bank.example/login?x=1"),
+            Some("bank.example".into())
+        );
+        assert_eq!(text_domain("Click here"), None);
+        assert_eq!(text_domain("e.g"), None);
+        assert_eq!(text_domain("version 1.2"), None);
+    }
+
+    #[test]
+    fn redirect_wrappers_unwrap() {
+        let safelink = link(
+            "https://eur01.safelinks.protection.outlook.com/?url=https%3A%2F%2Fexample.com%2Fa%3Fb%3D1&data=x",
+        );
+        assert_eq!(
+            unwrap_redirects(&safelink).as_str(),
+            "https://example.com/a?b=1"
+        );
+        let google = link("https://www.google.com/url?q=https://example.org/&sa=D");
+        assert_eq!(unwrap_redirects(&google).as_str(), "https://example.org/");
+        let proofpoint =
+            link("https://urldefense.proofpoint.com/v2/url?u=https-3A__example.com_path&d=DwM");
+        assert_eq!(
+            unwrap_redirects(&proofpoint).as_str(),
+            "https://example.com/path"
+        );
+        let v3 = link("https://urldefense.com/v3/__https://example.net/x__;!!abc$");
+        assert_eq!(unwrap_redirects(&v3).as_str(), "https://example.net/x");
+        let plain = link("https://example.com/?url=https://other.example/");
+        assert_eq!(unwrap_redirects(&plain), plain);
+    }
+
+    #[test]
+    fn tracking_parameters_are_dropped() {
+        let url = link("https://shop.example/item?id=7&utm_source=news&UTM_Medium=mail&fbclid=abc");
+        assert_eq!(
+            strip_tracking(&url).as_str(),
+            "https://shop.example/item?id=7"
+        );
+        let url = link("https://shop.example/?utm_campaign=x");
+        assert_eq!(strip_tracking(&url).as_str(), "https://shop.example/");
+    }
+
+    #[test]
+    fn punycode_decodes() {
+        assert_eq!(punycode_decode("bcher-kva").as_deref(), Some("bücher"));
+        assert_eq!(punycode_decode("80ak6aa92e").as_deref(), Some("аррӏе"));
+        assert_eq!(punycode_decode("pple-43d").as_deref(), Some("аpple"));
+        assert_eq!(punycode_decode("!!"), None);
+    }
+
+    #[test]
+    fn look_alike_hosts_are_spelled_out() {
+        assert_eq!(
+            idn_warning("xn--80ak6aa92e.com"),
+            Some("аррӏе.com imitates apple.com with look-alike letters (real address xn--80ak6aa92e.com)".into())
+        );
+        assert_eq!(
+            idn_warning("xn--bcher-kva.example"),
+            Some(
+                "bücher.example is an international address (real address xn--bcher-kva.example)"
+                    .into()
+            )
+        );
+        assert_eq!(idn_warning("example.com"), None);
+    }
+
+    #[test]
+    fn mismatched_text_needs_confirmation() {
+        let check = check_link(
+            &link("https://evil.test/login?utm_source=x"),
+            Some("www.bank.example"),
+            true,
+        );
+        assert_eq!(
+            check.warnings,
+            vec!["The link shows bank.example but goes to evil.test".to_string()]
+        );
+        assert_eq!(check.url, "https://evil.test/login");
+
+        let check = check_link(
+            &link("https://news.bank.example/offer?utm_source=x"),
+            Some("bank.example"),
+            false,
+        );
+        assert!(check.warnings.is_empty());
+        assert_eq!(check.url, "https://news.bank.example/offer?utm_source=x");
+    }
+
+    #[test]
+    fn hidden_hosts_and_odd_schemes_need_confirmation() {
+        let check = check_link(&link("https://bank.example@evil.test/"), None, true);
+        assert_eq!(check.warnings.len(), 1);
+        let check = check_link(&link("file:///etc/passwd"), None, true);
+        assert_eq!(
+            check.warnings,
+            vec!["Opens a file: link outside the browser".to_string()]
+        );
+        assert!(
+            check_link(&link("mailto:a@example.com"), Some("Mail us"), true)
+                .warnings
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! Per-folder message list order and quick filters.
+//!
+//! The preference compiles to a cache `ListQuery` and, for accounts read
+//! without a cache, to a JMAP `Email/query` filter and sort.
+
+use cosmic::app::Task;
+use serde_json::{json, Value};
+
+use neverlight_mail_core::config::AccountId;
+use neverlight_mail_core::store::{
+    ListOrder, ListQuery, SearchPredicate, SortKey, DEFAULT_PAGE_SIZE,
+};
+
+use super::{AppModel, FolderView, ListSort, Message, QuickFilter};
+
+fn sort_order(sort: ListSort) -> Vec<ListOrder> {
+    let order = |key, ascending| ListOrder { key, ascending };
+    match sort {
+        ListSort::DateDesc => vec![order(SortKey::Date, false)],
+        ListSort::DateAsc => vec![order(SortKey::Date, true)],
+        ListSort::Sender => vec![order(SortKey::Sender, true), order(SortKey::Date, false)],
+        ListSort::Subject => vec![order(SortKey::Subject, true), order(SortKey::Date, false)],
+        ListSort::Size => vec![order(SortKey::Size, false)],
+        ListSort::UnreadFirst => vec![order(SortKey::Unread, false), order(SortKey::Date, false)],
+    }
+}
+
+/// Cache query for a folder view. `my_addresses` backs the "to me" filter.
+pub(super) fn local_query(view: &FolderView, my_addresses: &[String]) -> ListQuery {
+    let predicates = view
+        .filters
+        .iter()
+        .map(|filter| match filter {
+            QuickFilter::Unread => SearchPredicate::Unread,
+            QuickFilter::Starred => SearchPredicate::Starred,
+            QuickFilter::HasAttachment => SearchPredicate::HasAttachment,
+            QuickFilter::ToMe => SearchPredicate::AddressedTo(my_addresses.to_vec()),
+        })
+        .collect();
+    ListQuery {
+        order: sort_order(view.sort),
+        predicates,
+        threaded: view.is_default(),
+    }
+}
+
+/// RFC 8621 `Email/query` sort for a list order.
+pub(super) fn jmap_sort(sort: ListSort) -> Value {
+    let comparator = |order: &ListOrder| match order.key {
+        SortKey::Date => json!({ "property": "receivedAt", "isAscending": order.ascending }),
+        SortKey::Sender => json!({ "property": "from", "isAscending": order.ascending }),
+        SortKey::Subject => json!({ "property": "subject", "isAscending": order.ascending }),
+        SortKey::Size => json!({ "property": "size", "isAscending": order.ascending }),
+        // Seen sorts after unseen when ascending on the `$seen` keyword.
+        SortKey::Unread => json!({
+            "property": "hasKeyword",
+            "keyword": "$seen",
+            "isAscending": !order.ascending,
+        }),
+    };
+    Value::Array(sort_order(sort).iter().map(comparator).collect())
+}
+
+/// RFC 8621 `Email/query` filter for one mailbox under a folder view.
+pub(super) fn jmap_filter(view: &FolderView, mailbox_id: &str, my_addresses: &[String]) -> Value {
+    let mut conditions = vec![json!({ "inMailbox": mailbox_id })];
+    for filter in &view.filters {
+        conditions.push(match filter {
+            QuickFilter::Unread => json!({ "notKeyword": "$seen" }),
+            QuickFilter::Starred => json!({ "hasKeyword": "$flagged" }),
+            QuickFilter::HasAttachment => json!({ "hasAttachment": true }),
+            QuickFilter::ToMe => json!({
+                "operator": "OR",
+                "conditions": my_addresses
+                    .iter()
+                    .flat_map(|a| [json!({ "to": a }), json!({ "cc": a })])
+                    .collect::<Vec<_>>(),
+            }),
+        });
+    }
+    match conditions.len() {
+        1 => conditions.remove(0),
+        _ => json!({ "operator": "AND", "conditions": conditions }),
+    }
+}
+
+impl AppModel {
+    fn selected_folder_key(&self) -> Option<(AccountId, String)> {
+        let acct = self.accounts.get(self.active_account?)?;
+        let folder = acct.folders.get(self.selected_folder?)?;
+        Some((acct.config.id.clone(), folder.mailbox_id.clone()))
+    }
+
+    /// View preference of the selected folder, if one is selected.
+    pub(super) fn selected_folder_view(&self) -> Option<FolderView> {
+        let (account_id, mailbox_id) = self.selected_folder_key()?;
+        Some(self.settings.folder_view(&account_id, &mailbox_id))
+    }
+
+    /// Cache query for `mailbox_id` of account `acct_idx`.
+    pub(super) fn folder_list_query(&self, acct_idx: usize, mailbox_id: &str) -> ListQuery {
+        let Some(acct) = self.accounts.get(acct_idx) else {
+            return local_query(&FolderView::default(), &[]);
+        };
+        let view = self.settings.folder_view(&acct.config.id, mailbox_id);
+        local_query(&view, &acct.config.email_addresses)
+    }
+
+    /// `Email/query` filter and sort for `mailbox_id` of account `acct_idx`.
+    pub(super) fn folder_jmap_query(&self, acct_idx: usize, mailbox_id: &str) -> (Value, Value) {
+        let Some(acct) = self.accounts.get(acct_idx) else {
+            let view = FolderView::default();
+            return (jmap_filter(&view, mailbox_id, &[]), jmap_sort(view.sort));
+        };
+        let view = self.settings.folder_view(&acct.config.id, mailbox_id);
+        (
+            jmap_filter(&view, mailbox_id, &acct.config.email_addresses),
+            jmap_sort(view.sort),
+        )
+    }
+
+    pub(super) fn handle_list_view(&mut self, message: Message) -> Task<Message> {
+        let Some((account_id, mailbox_id)) = self.selected_folder_key() else {
+            return Task::none();
+        };
+        let mut view = self.settings.folder_view(&account_id, &mailbox_id);
+        match message {
+            Message::ListSortChanged(sort) => view.sort = sort,
+            Message::QuickFilterToggled(filter) => {
+                if !view.filters.remove(&filter) {
+                    view.filters.insert(filter);
+                }
+            }
+            _ => return Task::none(),
+        }
+        self.settings
+            .set_folder_view(&account_id, &mailbox_id, view);
+        let save = self.save_settings();
+        self.collapsed_threads.clear();
+        self.list_scroll_y = 0.0;
+        let reload = self
+            .load_list_window(0, DEFAULT_PAGE_SIZE)
+            .unwrap_or_else(Task::none);
+        cosmic::task::batch(vec![save, reload])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    fn me() -> Vec<String> {
+        vec!["me@example.com".to_string()]
+    }
+
+    #[test]
+    fn default_view_is_threaded_newest_first() {
+        let query = local_query(&FolderView::default(), &me());
+        assert!(query.threaded);
+        assert!(query.predicates.is_empty());
+        assert_eq!(
+            query.order,
+            vec![ListOrder {
+                key: SortKey::Date,
+                ascending: false
+            }]
+        );
+    }
+
+    #[test]
+    fn filters_become_predicates_and_flatten_threads() {
+        let view = FolderView {
+            sort: ListSort::DateDesc,
+            filters: BTreeSet::from([QuickFilter::Unread, QuickFilter::ToMe]),
+        };
+        let query = local_query(&view, &me());
+        assert!(!query.threaded);
+        assert_eq!(
+            query.predicates,
+            vec![SearchPredicate::Unread, SearchPredicate::AddressedTo(me())]
+        );
+    }
+
+    #[test]
+    fn unread_first_sorts_unseen_before_seen_on_server() {
+        assert_eq!(
+            jmap_sort(ListSort::UnreadFirst),
+            json!([
+                { "property": "hasKeyword", "keyword": "$seen", "isAscending": true },
+                { "property": "receivedAt", "isAscending": false },
+            ])
+        );
+    }
+
+    #[test]
+    fn server_filter_without_toggles_is_just_the_mailbox() {
+        assert_eq!(
+            jmap_filter(&FolderView::default(), "M1", &me()),
+            json!({ "inMailbox": "M1" })
+        );
+    }
+
+    #[test]
+    fn server_filter_ands_toggles() {
+        let view = FolderView {
+            sort: ListSort::Size,
+            filters: BTreeSet::from([QuickFilter::Starred, QuickFilter::ToMe]),
+        };
+        assert_eq!(
+            jmap_filter(&view, "M1", &me()),
+            json!({
+                "operator": "AND",
+                "conditions": [
+                    { "inMailbox": "M1" },
+                    { "hasKeyword": "$flagged" },
+                    {
+                        "operator": "OR",
+                        "conditions": [{ "to": "me@example.com" }, { "cc": "me@example.com" }],
+                    },
+                ],
+            })
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Mailing-list awareness.
+//!
+//! The cache keeps each message's raw `List-Id` (RFC 2919) and `List-Post`
+//! (RFC 2369) values. The identifier groups messages by list across
+//! folders: the sidebar's "Lists" section counts unread mail per list, and
+//! opening one runs a `list:` search over every account. `List-Post` gives
+//! the address "Reply to list" writes to.
+
+use cosmic::app::Task;
+
+use neverlight_mail_core::config::AccountId;
+use neverlight_mail_core::store::ListCount;
+
+use super::unsubscribe::mailto_parts;
+use super::{AppModel, MailingList, Message, SearchPhase};
+
+/// Identifier and display name of a `List-Id` value, e.g.
+/// `"Rust users" <rust-users.example.org>`. Without a phrase the name is the
+/// identifier's first label; identifiers always have a namespace after a dot.
+pub(crate) fn parse_list_id(value: &str) -> Option<(String, String)> {
+    let (phrase, id) = match value.rfind('<') {
+        Some(open) => (&value[..open], value[open + 1..].split('>').next()?),
+        None => ("", value),
+    };
+    let id = id.trim().to_lowercase();
+    if !id.contains('.') || id.contains(char::is_whitespace) {
+        return None;
+    }
+    let phrase = phrase.trim().trim_matches('"').trim();
+    let name = if phrase.is_empty() {
+        id.split('.').next().unwrap_or(&id).to_string()
+    } else {
+        phrase.to_string()
+    };
+    Some((id, name))
+}
+
+/// Posting address of a `List-Post` value; `NO` means the list takes none.
+pub(crate) fn list_post_address(value: &str) -> Option<String> {
+    value
+        .split(',')
+        .filter_map(|part| part.trim().strip_prefix('<')?.split('>').next())
+        .find_map(|uri| mailto_parts(uri.trim()).map(|(to, _, _)| to))
+}
+
+/// Per-account counts merged by list identifier, sorted by name.
+fn merge_list_counts(counts: Vec<ListCount>) -> Vec<MailingList> {
+    let mut lists: Vec<MailingList> = Vec::new();
+    for count in counts {
+        let Some((id, name)) = parse_list_id(&count.list_id) else {
+            continue;
+        };
+        match lists.iter_mut().find(|l| l.id == id) {
+            Some(list) => list.unread += count.unread,
+            None => lists.push(MailingList {
+                id,
+                name,
+                unread: count.unread,
+            }),
+        }
+    }
+    lists.sort_by_key(|l| l.name.to_lowercase());
+    lists
+}
+
+impl AppModel {
+    pub(super) fn handle_lists(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::ListsLoaded { epoch, lists } => {
+                if epoch != self.lists_epoch {
+                    self.stale_apply_drop_count = self.stale_apply_drop_count.saturating_add(1);
+                    return Task::none();
+                }
+                self.mailing_lists = lists;
+            }
+            Message::ListOpen(id) => {
+                let account_ids: Vec<AccountId> =
+                    self.accounts.iter().map(|a| a.config.id.clone()).collect();
+                let query = format!("list:{id}");
+                self.active_saved_search = None;
+                self.search_phase = SearchPhase::Results;
+                self.search_query = query.clone();
+                return self.start_search(query, account_ids, None);
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+
+    /// Recount lists and their unread mail from the cache.
+    pub(super) fn refresh_lists(&mut self) -> Task<Message> {
+        let Some(cache) = self.cache.clone() else {
+            return Task::none();
+        };
+        let account_ids: Vec<AccountId> =
+            self.accounts.iter().map(|a| a.config.id.clone()).collect();
+        self.lists_epoch = self.lists_epoch.saturating_add(1);
+        let epoch = self.lists_epoch;
+        cosmic::task::future(async move {
+            let mut counts = Vec::new();
+            for account_id in account_ids {
+                match cache.list_counts(account_id.clone()).await {
+                    Ok(per_list) => counts.extend(per_list),
+                    Err(e) => log::warn!("List counts for {} failed: {}", account_id, e),
+                }
+            }
+            Message::ListsLoaded {
+                epoch,
+                lists: merge_list_counts(counts),
+            }
+        })
+    }
+
+    /// List whose messages are shown, when the results are a `list:` search.
+    pub(super) fn active_list(&self) -> Option<&str> {
+        if self.search_phase != SearchPhase::Results {
+            return None;
+        }
+        self.search_query.strip_prefix("list:")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(list_id: &str, unread: usize) -> ListCount {
+        ListCount {
+            list_id: list_id.to_string(),
+            unread,
+        }
+    }
+
+    #[test]
+    fn list_id_phrase_is_the_name() {
+        assert_eq!(
+            parse_list_id("\"Rust Users\" <Rust-Users.Example.org>"),
+            Some(("rust-users.example.org".into(), "Rust Users".into()))
+        );
+        assert_eq!(
+            parse_list_id("<announce.lists.example.com>"),
+            Some(("announce.lists.example.com".into(), "announce".into()))
+        );
+        assert_eq!(
+            parse_list_id("bare.example.net"),
+            Some(("bare.example.net".into(), "bare".into()))
+        );
+        assert_eq!(parse_list_id("<>"), None);
+        assert_eq!(parse_list_id("<nodot>"), None);
+        assert_eq!(parse_list_id("not a list id"), None);
+    }
+
+    #[test]
+    fn list_post_takes_the_mailto_address() {
+        assert_eq!(
+            list_post_address("<https://example.org/post>, <mailto:users@example.org>"),
+            Some("users@example.org".into())
+        );
+        assert_eq!(list_post_address("NO"), None);
+        assert_eq!(list_post_address("NO (posting not allowed)"), None);
+    }
+
+    #[test]
+    fn counts_merge_across_accounts_by_id() {
+        let lists = merge_list_counts(vec![
+            count("\"zeta\" <zeta.example.org>", 1),
+            count("Rust users <rust-users.example.org>", 2),
+            count("<rust-users.example.org>", 3),
+            count("garbage", 4),
+            count("", 5),
+        ]);
+        assert_eq!(
+            lists,
+            vec![
+                MailingList {
+                    id: "rust-users.example.org".into(),
+                    name: "Rust users".into(),
+                    unread: 5,
+                },
+                MailingList {
+                    id: "zeta.example.org".into(),
+                    name: "zeta".into(),
+                    unread: 1,
+                },
+            ]
+        );
+    }
+}
@@ -1,18 +1,50 @@
 mod accounts;
 mod actions;
+mod auth_results;
 mod backfill;
 mod body;
+mod calendar;
 mod compose;
+mod export;
+mod filters;
+mod highlight;
+mod import;
+mod inline_images;
 mod layout;
+mod link_safety;
+mod list_view;
+mod lists;
 mod navigation;
+mod query;
+mod receipts;
+mod remote_images;
+mod rules;
+mod saved_search;
 mod search;
+mod settings;
 mod setup;
+mod snooze;
+mod source;
 mod sync;
 mod sync_apply;
+#[cfg(test)]
+mod test_support;
 mod types;
+mod unified;
+mod unsubscribe;
+mod vacation;
 mod watch;
 
+pub(crate) use auth_results::auth_verdict;
+pub(crate) use calendar::event_time_label;
+pub(crate) use export::drag_eml_path;
+pub(crate) use highlight::find_matches;
+pub(crate) use lists::{list_post_address, parse_list_id};
+pub(crate) use receipts::receipt_label;
+pub use settings::{FolderView, ListSort, QuickFilter, SavedSearch, SearchScope};
+pub(crate) use snooze::{is_snoozed_folder, wake_label};
 pub use types::*;
+pub(crate) use vacation::vacation_label;
 
 use std::collections::{HashMap, HashSet};
 
@@ -99,16 +131,31 @@ impl cosmic::Application for AppModel {
             selected_folder: None,
             selected_mailbox_id: None,
             selected_folder_evicted: false,
+            virtual_view: None,
             messages: Vec::new(),
             selected_message: None,
             messages_offset: 0,
+            pending_messages_offset: 0,
             has_more_messages: false,
+            list_scroll_y: 0.0,
+            list_viewport_height: 0.0,
             preview_body: String::new(),
-            preview_markdown: Vec::new(),
+            preview_blocks: Vec::new(),
             preview_editor: text_editor::Content::new(),
             preview_selectable: false,
             preview_attachments: Vec::new(),
             preview_image_handles: Vec::new(),
+            remote_images: None,
+            invites: HashMap::new(),
+            link_texts: HashMap::new(),
+            link_check: None,
+            read_receipts: HashMap::new(),
+            raw_source: None,
+            show_source: false,
+            headers_expanded: false,
+            export: None,
+            import: None,
+            unsubscribe_phase: UnsubscribePhase::Closed,
             conversation: Vec::new(),
             conversation_editors: Vec::new(),
             active_conversation_id: None,
@@ -122,6 +169,8 @@ impl cosmic::Application for AppModel {
             folder_abort: None,
             message_abort: None,
             body_abort: None,
+            remote_images_abort: None,
+            source_abort: None,
             status_message: "Starting up...".into(),
             error_surface: None,
             phase: Phase::Loading,
@@ -132,6 +181,8 @@ impl cosmic::Application for AppModel {
             mutation_epoch: 0,
             flag_epoch: 0,
             body_epoch: 0,
+            export_epoch: 0,
+            import_epoch: 0,
             refresh_phase: RefreshPhase::Idle,
             refresh_accounts_outstanding: HashSet::new(),
             refresh_started_at: None,
@@ -140,6 +191,8 @@ impl cosmic::Application for AppModel {
             flag_in_flight_accounts: HashSet::new(),
             pending_move_intents: HashMap::new(),
             pending_flag_intents: HashMap::new(),
+            background_move_intents: HashMap::new(),
+            background_flag_intents: HashMap::new(),
             notified_messages: HashSet::new(),
             stale_apply_drop_count: 0,
             toc_drift_count: 0,
@@ -152,6 +205,25 @@ impl cosmic::Application for AppModel {
 
             search_phase: SearchPhase::Inactive,
             search_query: String::new(),
+            search_accounts: Vec::new(),
+            search_mailbox: None,
+            search_account_counts: Vec::new(),
+            server_hits: HashSet::new(),
+            search_terms: Vec::new(),
+            search_snippets: HashMap::new(),
+            search_match: None,
+
+            settings: settings::AppSettings::load(),
+            settings_write: SettingsWrite::Idle,
+            saved_search_phase: SavedSearchPhase::Closed,
+            saved_search_counts: HashMap::new(),
+            saved_search_epoch: 0,
+            saved_search_dirty: HashSet::new(),
+            saved_search_recounting: HashSet::new(),
+            active_saved_search: None,
+
+            mailing_lists: Vec::new(),
+            lists_epoch: 0,
 
             compose_phase: ComposePhase::Closed,
             compose_mode: ComposeMode::New,
@@ -163,6 +235,7 @@ impl cosmic::Application for AppModel {
             compose_in_reply_to: None,
             compose_references: None,
             compose_attachments: Vec::new(),
+            compose_request_receipt: false,
             compose_error: None,
             compose_drag_hover: false,
             compose_account_labels: Vec::new(),
@@ -174,6 +247,13 @@ impl cosmic::Application for AppModel {
             oauth_phase: OAuthSetupPhase::Inactive,
             oauth_error: None,
 
+            snooze_phase: SnoozePhase::Closed,
+            vacation_phase: VacationPhase::Closed,
+            filters_phase: FiltersPhase::Closed,
+            rules_phase: RulesPhase::Closed,
+            snooze_schedule: HashMap::new(),
+            snooze_waking: HashSet::new(),
+
             folder_drag_target: None,
             pending_body: None,
             body_defer_retries: 0,
@@ -256,6 +336,27 @@ impl cosmic::Application for AppModel {
         if self.setup_model.is_some() {
             return Some(self.setup_dialog());
         }
+        if self.snooze_phase.is_open() {
+            return Some(self.snooze_dialog());
+        }
+        if self.saved_search_phase.is_open() {
+            return Some(self.saved_search_dialog());
+        }
+        if self.unsubscribe_phase.is_open() {
+            return Some(self.unsubscribe_dialog());
+        }
+        if self.link_check.is_some() {
+            return Some(self.link_dialog());
+        }
+        if self.vacation_phase.is_open() {
+            return Some(self.vacation_dialog());
+        }
+        if self.filters_phase.is_open() {
+            return Some(self.filters_dialog());
+        }
+        if self.rules_phase.is_open() {
+            return Some(self.rules_dialog());
+        }
         if self.compose_phase.is_open() {
             return Some(crate::ui::compose_dialog::view(
                 crate::ui::compose_dialog::ComposeViewState {
@@ -268,6 +369,7 @@ impl cosmic::Application for AppModel {
                     subject: &self.compose_subject,
                     body: &self.compose_body,
                     attachments: &self.compose_attachments,
+                    request_receipt: self.compose_request_receipt,
                     error: self.compose_error.as_deref(),
                     is_sending: self.compose_phase == ComposePhase::Sending,
                     drag_hover: self.compose_drag_hover,
@@ -334,6 +436,16 @@ impl cosmic::Application for AppModel {
                             keyboard::Key::Character(ref c) if c.as_str() == " " => {
                                 Some(Message::ToggleThreadCollapse)
                             }
+                            keyboard::Key::Character(ref c)
+                                if c.as_str() == "n" && !modifiers.control() =>
+                            {
+                                Some(Message::SearchMatchNext)
+                            }
+                            keyboard::Key::Character(ref c)
+                                if c.as_str() == "N" && !modifiers.control() =>
+                            {
+                                Some(Message::SearchMatchPrev)
+                            }
                             keyboard::Key::Character(ref c)
                                 if c.as_str() == "c" && !modifiers.control() =>
                             {
@@ -402,6 +514,26 @@ impl cosmic::Application for AppModel {
             }
         }
 
+        // Running export
+        if let Some(job) = &self.export {
+            subs.push(Subscription::run_with_id(
+                format!("export-{}", job.id),
+                export::export_stream(
+                    job.clone(),
+                    self.client_for_account(&job.account_id),
+                    self.cache.clone(),
+                ),
+            ));
+        }
+
+        // Running import
+        if let Some(job) = &self.import {
+            subs.push(Subscription::run_with_id(
+                format!("import-{}", job.id),
+                import::import_stream(job.clone(), self.client_for_account(&job.dest.account_id)),
+            ));
+        }
+
         // Periodic full sync fallback (5 minutes)
         let has_any_client = self.accounts.iter().any(|a| a.client.is_some());
         if has_any_client {
@@ -415,6 +547,7 @@ impl cosmic::Application for AppModel {
                     loop {
                         interval.tick().await;
                         let _ = output.send(Message::Refresh).await;
+                        let _ = output.send(Message::SnoozeWakeCheck).await;
                     }
                 }),
             ));
@@ -430,7 +563,18 @@ impl cosmic::Application for AppModel {
                     &self.accounts,
                     self.active_account,
                     self.selected_folder,
+                    self.virtual_view,
                     self.folder_drag_target,
+                    &self.snooze_schedule,
+                    crate::ui::sidebar::SavedSearchState {
+                        searches: &self.settings.saved_searches,
+                        unread_counts: &self.saved_search_counts,
+                        active: self.active_saved_search.as_deref(),
+                    },
+                    crate::ui::sidebar::ListsState {
+                        lists: &self.mailing_lists,
+                        active: self.active_list(),
+                    },
                     crate::ui::sidebar::DiagnosticsState {
                         collapsed: self.diagnostics_collapsed,
                         phase: self.phase,
@@ -451,6 +595,7 @@ impl cosmic::Application for AppModel {
                 PaneKind::MessageList => crate::ui::message_list::view(
                     crate::ui::message_list::MessageListState {
                         messages: &self.messages,
+                        first_row: self.messages_offset as usize,
                         visible_indices: &self.visible_indices,
                         selected: self.selected_message,
                         has_more: self.has_more_messages && !self.search_phase.is_active(),
@@ -458,14 +603,32 @@ impl cosmic::Application for AppModel {
                         thread_sizes: &self.thread_sizes,
                         search_active: self.search_phase.is_active(),
                         search_query: &self.search_query,
+                        snoozed_until: &self.snooze_schedule,
+                        server_hits: &self.server_hits,
+                        snippets: &self.search_snippets,
+                        search_scope: self.settings.search_scope,
+                        account_counts: &self.search_account_counts,
+                        show_previews: self.settings.show_previews,
+                        list_view: if self.search_phase.is_active() || self.virtual_view.is_some() {
+                            None
+                        } else {
+                            self.selected_folder_view()
+                        },
+                        scroll_y: self.list_scroll_y,
+                        viewport_height: self.list_viewport_height,
                     },
                 ),
                 PaneKind::MessageView => {
                     let selected_msg = self
                         .selected_message
                         .and_then(|i| self.messages.get(i).map(|msg| (i, msg)));
+                    let own_addresses: Vec<String> = self
+                        .accounts
+                        .iter()
+                        .flat_map(|a| a.config.email_addresses.iter().cloned())
+                        .collect();
                     crate::ui::message_view::view(
-                        &self.preview_markdown,
+                        &self.preview_blocks,
                         &self.preview_editor,
                         self.preview_selectable,
                         selected_msg,
@@ -474,6 +637,25 @@ impl cosmic::Application for AppModel {
                         &self.conversation,
                         &self.conversation_editors,
                         self.active_conversation_id.as_deref(),
+                        &self.search_terms,
+                        &self.invites,
+                        &own_addresses,
+                        self.settings.clean_links,
+                        crate::ui::message_view::RemoteImagesState {
+                            images: self.remote_images.as_ref(),
+                            sender_allowed: self.selected_sender_allows_remote_images(),
+                        },
+                        crate::ui::message_view::ReceiptsState {
+                            prompt: self.receipt_prompt(),
+                            receipts: &self.read_receipts,
+                        },
+                        crate::ui::message_view::SourceState {
+                            raw: self.raw_source.as_ref().filter(|r| {
+                                selected_msg.is_some_and(|(_, m)| m.email_id == r.email_id)
+                            }),
+                            show_source: self.show_source,
+                            headers_expanded: self.headers_expanded,
+                        },
                     )
                 }
             };
@@ -483,7 +665,31 @@ impl cosmic::Application for AppModel {
         .width(Length::Fill)
         .height(Length::Fill);
 
-        let status_bar = widget::container(widget::text::caption(&self.status_message))
+        let mut status_row = widget::row()
+            .spacing(8)
+            .align_y(cosmic::iced::Alignment::Center)
+            .push(widget::text::caption(&self.status_message).width(Length::Fill));
+        if let Some(job) = &self.export {
+            let progress = match job.total {
+                Some(total) => format!("Exporting {} of {total}", job.done),
+                None => "Listing messages...".into(),
+            };
+            status_row = status_row
+                .push(widget::text::caption(progress))
+                .push(widget::button::text("Cancel export").on_press(Message::ExportCancel));
+        }
+        if let Some(job) = &self.import {
+            let progress = format!(
+                "Imported {} messages ({} of {} files)",
+                job.imported,
+                job.files_done,
+                job.paths.len()
+            );
+            status_row = status_row
+                .push(widget::text::caption(progress))
+                .push(widget::button::text("Cancel import").on_press(Message::ImportCancel));
+        }
+        let status_bar = widget::container(status_row)
             .padding([4, 8])
             .width(Length::Fill);
 
@@ -515,6 +721,7 @@ impl cosmic::Application for AppModel {
             // Compose
             Message::ComposeNew
             | Message::ComposeReply
+            | Message::ComposeReplyList
             | Message::ComposeForward
             | Message::ComposeAccountChanged(_)
             | Message::ComposeFromChanged(_)
@@ -529,6 +736,7 @@ impl cosmic::Application for AppModel {
             | Message::ComposeFileTransferResolved(_)
             | Message::ComposeDragEnter
             | Message::ComposeDragLeave
+            | Message::ComposeRequestReceiptToggled(_)
             | Message::ComposeSend
             | Message::ComposeCancel
             | Message::SendComplete(_) => self.handle_compose(message),
@@ -560,10 +768,14 @@ impl cosmic::Application for AppModel {
             | Message::SyncFoldersComplete { .. }
             | Message::SyncMessagesComplete { .. }
             | Message::SelectFolder(_, _)
-            | Message::LoadMoreMessages
             | Message::ForceReconnect(_)
             | Message::Refresh => self.handle_sync(message),
 
+            // Cross-account virtual views
+            Message::SelectVirtualView(_)
+            | Message::VirtualViewLoaded { .. }
+            | Message::VirtualViewSynced { .. } => self.handle_unified(message),
+
             // Body / attachment viewing
             Message::ViewBody(_)
             | Message::BodyDeferred { .. }
@@ -572,7 +784,6 @@ impl cosmic::Application for AppModel {
             | Message::ConversationBodyLoaded { .. }
             | Message::SetActiveConversation(_)
             | Message::SaveConversationAttachment { .. }
-            | Message::LinkClicked(_)
             | Message::CopyBody
             | Message::ToggleSelectableView
             | Message::PreviewBodyAction(_)
@@ -580,6 +791,122 @@ impl cosmic::Application for AppModel {
             | Message::SaveAttachment(_)
             | Message::SaveAttachmentComplete(_) => self.handle_body(message),
 
+            // Raw source / full headers
+            Message::ToggleSourceView
+            | Message::ToggleAllHeaders
+            | Message::RawSourceLoaded { .. }
+            | Message::SourceAction(_) => self.handle_source(message),
+
+            // Export
+            Message::ExportMessage
+            | Message::ExportConversation
+            | Message::ExportFolder(_)
+            | Message::ExportStart { .. }
+            | Message::ExportProgress { .. }
+            | Message::ExportFinished { .. }
+            | Message::ExportCancel
+            | Message::MessageExported(_)
+            | Message::DragExportPrepare { .. }
+            | Message::DragExportReady(_) => self.handle_export(message),
+
+            // Import
+            Message::ImportFiles
+            | Message::ImportDropped { .. }
+            | Message::ImportStart { .. }
+            | Message::ImportProgress { .. }
+            | Message::ImportFinished { .. }
+            | Message::ImportCancel => self.handle_import(message),
+
+            // List unsubscribe
+            Message::UnsubscribeOpen
+            | Message::UnsubscribeArchiveToggled(_)
+            | Message::UnsubscribeConfirm
+            | Message::UnsubscribeCancel
+            | Message::UnsubscribeSent(_)
+            | Message::UnsubscribeArchived(_) => self.handle_unsubscribe(message),
+
+            // Link safety
+            Message::LinkClicked(_)
+            | Message::LinkOpen
+            | Message::LinkCopy
+            | Message::LinkCancel
+            | Message::CleanLinksToggled(_) => self.handle_links(message),
+
+            // Read receipts
+            Message::ReceiptSend(_)
+            | Message::ReceiptIgnore(_)
+            | Message::ReceiptAlwaysIgnore
+            | Message::ReceiptAnswered { .. }
+            | Message::ReadReceiptsLoaded(_) => self.handle_receipts(message),
+
+            // Vacation response
+            Message::VacationOpen(_)
+            | Message::VacationLoaded { .. }
+            | Message::VacationEnabledToggled(_)
+            | Message::VacationFromChanged(_)
+            | Message::VacationToChanged(_)
+            | Message::VacationSubjectChanged(_)
+            | Message::VacationTextAction(_)
+            | Message::VacationHtmlAction(_)
+            | Message::VacationSave
+            | Message::VacationCancel
+            | Message::VacationSaved { .. } => self.handle_vacation(message),
+
+            // Server-side filters
+            Message::FiltersOpen(_)
+            | Message::FiltersLoaded { .. }
+            | Message::FiltersClose
+            | Message::FilterActivate(_)
+            | Message::FilterActivated(_)
+            | Message::FilterNew
+            | Message::FilterEdit(_)
+            | Message::FilterContentLoaded { .. }
+            | Message::FilterNameChanged(_)
+            | Message::FilterDraftField(_)
+            | Message::FilterDraftValue(_)
+            | Message::FilterDraftAction(_)
+            | Message::FilterDraftFolder(_)
+            | Message::FilterAddRule
+            | Message::FilterRemoveRule(_)
+            | Message::FilterScriptAction(_)
+            | Message::FilterValidate
+            | Message::FilterValidated(_)
+            | Message::FilterSave
+            | Message::FilterSaved(_)
+            | Message::FilterBack => self.handle_filters(message),
+
+            // Local mail rules
+            Message::RulesOpen
+            | Message::RulesClose
+            | Message::RuleToggled(_, _)
+            | Message::RuleEdit(_)
+            | Message::RuleDelete(_)
+            | Message::RuleNameChanged(_)
+            | Message::RuleConditionKindChanged(_)
+            | Message::RuleConditionValueChanged(_)
+            | Message::RuleConditionAdd
+            | Message::RuleConditionRemove(_)
+            | Message::RuleActionKindChanged(_)
+            | Message::RuleActionFolderChanged(_)
+            | Message::RuleActionAdd
+            | Message::RuleActionRemove(_)
+            | Message::RuleSave
+            | Message::RulesPreview
+            | Message::RulesApplyNow
+            | Message::RulesFolderLoaded { .. }
+            | Message::RulesBack
+            | Message::RulesArrivals { .. } => self.handle_rules(message),
+
+            // Calendar invites
+            Message::InviteRespond { .. } | Message::InviteResponded { .. } => {
+                self.handle_calendar(message)
+            }
+
+            // Remote images
+            Message::LoadRemoteImages
+            | Message::AlwaysLoadRemoteImages
+            | Message::RemoteImagesLoaded { .. } => self.handle_remote_images(message),
+
             // Flag / move actions
             Message::ToggleRead(_)
             | Message::ToggleStar(_)
@@ -600,15 +927,45 @@ impl cosmic::Application for AppModel {
             Message::SelectionUp
             | Message::SelectionDown
             | Message::ActivateSelection
-            | Message::ToggleThreadCollapse => self.handle_navigation(message),
+            | Message::ToggleThreadCollapse
+            | Message::MessageListScrolled(_) => self.handle_navigation(message),
 
             // Search
             Message::SearchActivate
             | Message::SearchQueryChanged(_)
             | Message::SearchExecute
+            | Message::SearchScopeChanged(_)
             | Message::SearchResultsLoaded { .. }
+            | Message::SearchServer
+            | Message::ServerSearchLoaded { .. }
+            | Message::SearchSnippetsLoaded { .. }
+            | Message::SearchMatchNext
+            | Message::SearchMatchPrev
             | Message::SearchClear => self.handle_search(message),
 
+            // Saved searches
+            Message::SavedSearchSaveOpen
+            | Message::SavedSearchNameChanged(_)
+            | Message::SavedSearchAllAccountsToggled(_)
+            | Message::SavedSearchSaveConfirm
+            | Message::SavedSearchSaveCancel
+            | Message::SavedSearchOpen(_)
+            | Message::SavedSearchDelete(_)
+            | Message::SavedSearchCountsLoaded { .. } => self.handle_saved_search(message),
+
+            // Mailing lists
+            Message::ListsLoaded { .. } | Message::ListOpen(_) => self.handle_lists(message),
+
+            // Snooze
+            Message::SnoozeOpen(_)
+            | Message::SnoozeCustomChanged(_)
+            | Message::SnoozeConfirm(_)
+            | Message::SnoozeCancel
+            | Message::SnoozeMailboxReady { .. }
+            | Message::SnoozeWakeCheck
+            | Message::SnoozeScheduleLoaded { .. }
+            | Message::SnoozeWoken { .. } => self.handle_snooze(message),
+
             // EventSource push events
             Message::PushStateChanged(_)
             | Message::PushError(_, _)
@@ -629,6 +986,24 @@ impl cosmic::Application for AppModel {
                 self.diagnostics_collapsed = !self.diagnostics_collapsed;
                 Task::none()
             }
+            Message::ShowPreviewsToggled(show) => {
+                self.settings.show_previews = show;
+                self.save_settings()
+            }
+            Message::SettingsSaved(result) => {
+                let pending = self.settings_write == SettingsWrite::InFlight { pending: true };
+                self.settings_write = SettingsWrite::Idle;
+                if let Err(e) = result {
+                    self.set_status_error(format!("Failed to save settings: {e}"));
+                }
+                if pending {
+                    return self.save_settings();
+                }
+                Task::none()
+            }
+            Message::ListSortChanged(_) | Message::QuickFilterToggled(_) => {
+                self.handle_list_view(message)
+            }
             Message::Noop => Task::none(),
         }
     }
@@ -656,6 +1031,30 @@ impl AppModel {
         self.phase = Phase::Error;
     }
 
+    /// Persist `settings` off the update loop; a failure surfaces in the
+    /// status bar. Saves during a write are folded into one follow-up write.
+    pub(super) fn save_settings(&mut self) -> Task<Message> {
+        if let SettingsWrite::InFlight { pending } = &mut self.settings_write {
+            *pending = true;
+            return Task::none();
+        }
+        let data = match self.settings.to_json() {
+            Ok(data) => data,
+            Err(e) => {
+                self.set_status_error(format!("Failed to save settings: {e}"));
+                return Task::none();
+            }
+        };
+        self.settings_write = SettingsWrite::InFlight { pending: false };
+        cosmic::task::future(async move {
+            let result = tokio::task::spawn_blocking(move || settings::AppSettings::write(&data))
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|written| written);
+            Message::SettingsSaved(result)
+        })
+    }
+
     pub(super) fn set_recoverable_action_error(&mut self, error: RecoverableActionError) {
         self.status_message = error.message.clone();
         self.error_surface = Some(ErrorSurface::RecoverableAction(error));
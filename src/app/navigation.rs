@@ -1,6 +1,86 @@
+use std::ops::Range;
+
 use cosmic::app::Task;
+use cosmic::iced::widget::scrollable::{self, AbsoluteOffset};
 
 use super::{AppModel, Message};
+use crate::ui::message_list;
+
+/// Rows loaded past the overscan on each side of the viewport, so small
+/// scrolls stay inside the loaded window.
+const PREFETCH_ROWS: usize = 50;
+
+/// Scroll offset that brings row `pos` fully into view, or None if it
+/// already is.
+fn reveal_offset(pos: usize, row_height: f32, scroll_y: f32, viewport_height: f32) -> Option<f32> {
+    let top = pos as f32 * row_height;
+    let bottom = top + row_height;
+    if top < scroll_y {
+        Some(top)
+    } else if viewport_height > 0.0 && bottom > scroll_y + viewport_height {
+        Some(bottom - viewport_height)
+    } else {
+        None
+    }
+}
+
+/// How the rows of the rendered list map to folder (cache) rows. The list
+/// is one spacer row per folder row before the loaded window, the window's
+/// visible rows, then one row per folder row past it. Only inside the
+/// window are collapsed thread replies known, and hidden.
+struct ListRows<'a> {
+    /// Folder row of the window's first message.
+    offset: usize,
+    /// Messages in the window, hidden ones included.
+    loaded: usize,
+    /// Window indices of the rows shown, in order.
+    visible: &'a [usize],
+}
+
+impl ListRows<'_> {
+    /// Folder row shown at list row `row`.
+    fn folder_row(&self, row: usize) -> usize {
+        match row.checked_sub(self.offset) {
+            None => row,
+            Some(pos) => match self.visible.get(pos) {
+                Some(&i) => self.offset + i,
+                None => self.offset + self.loaded + (pos - self.visible.len()),
+            },
+        }
+    }
+
+    /// List row showing folder row `row`; a hidden reply maps to the row
+    /// of its collapsed thread.
+    fn list_row(&self, row: usize) -> usize {
+        match row.checked_sub(self.offset) {
+            None => row,
+            Some(i) if i < self.loaded => {
+                self.offset + self.visible.partition_point(|&v| v <= i).saturating_sub(1)
+            }
+            Some(i) => self.offset + self.visible.len() + (i - self.loaded),
+        }
+    }
+}
+
+/// Folder rows to load for the viewport: the rows in view plus overscan,
+/// widened by `PREFETCH_ROWS` on each side. None while the loaded window
+/// still covers them; `has_more` says whether the folder continues past it.
+fn window_for_view(
+    scroll_y: f32,
+    viewport_height: f32,
+    row_height: f32,
+    rows: &ListRows,
+    has_more: bool,
+) -> Option<Range<usize>> {
+    let shown = message_list::visible_window(scroll_y, viewport_height, row_height, usize::MAX);
+    let needed = rows.folder_row(shown.start)..rows.folder_row(shown.end - 1) + 1;
+    let loaded = rows.offset..rows.offset + rows.loaded;
+    let covered = loaded.start <= needed.start && (needed.end <= loaded.end || !has_more);
+    if covered {
+        return None;
+    }
+    Some(needed.start.saturating_sub(PREFETCH_ROWS)..needed.end + PREFETCH_ROWS)
+}
 
 fn revalidated_selection(
     old: Option<usize>,
@@ -43,7 +123,8 @@ impl AppModel {
                 };
                 if let Some(&real_index) = self.visible_indices.get(new_vis_pos) {
                     self.selected_message = Some(real_index);
-                    return self.dispatch(Message::ViewBody(real_index));
+                    let view = self.dispatch(Message::ViewBody(real_index));
+                    return cosmic::task::batch(vec![view, self.reveal_list_row(new_vis_pos)]);
                 }
             }
 
@@ -60,7 +141,8 @@ impl AppModel {
                 };
                 if let Some(&real_index) = self.visible_indices.get(new_vis_pos) {
                     self.selected_message = Some(real_index);
-                    return self.dispatch(Message::ViewBody(real_index));
+                    let view = self.dispatch(Message::ViewBody(real_index));
+                    return cosmic::task::batch(vec![view, self.reveal_list_row(new_vis_pos)]);
                 }
             }
 
@@ -99,28 +181,110 @@ impl AppModel {
                 }
             }
 
+            Message::MessageListScrolled(viewport) => {
+                self.list_scroll_y = viewport.absolute_offset().y;
+                self.list_viewport_height = viewport.bounds().height;
+                return self.ensure_list_window();
+            }
+
             _ => {}
         }
         Task::none()
     }
 
+    fn list_row_height(&self) -> f32 {
+        message_list::row_height(
+            self.settings.show_previews,
+            !self.search_snippets.is_empty(),
+        )
+    }
+
+    fn list_rows(&self) -> ListRows<'_> {
+        ListRows {
+            offset: self.messages_offset as usize,
+            loaded: self.messages.len(),
+            visible: &self.visible_indices,
+        }
+    }
+
+    /// Load the cache window around the viewport once scrolling leaves the
+    /// loaded one. `messages` holds only that window, starting at folder row
+    /// `messages_offset`.
+    pub(super) fn ensure_list_window(&mut self) -> Task<Message> {
+        if self.folder_abort.is_some() || self.search_phase.is_active() {
+            return Task::none();
+        }
+        let Some(window) = window_for_view(
+            self.list_scroll_y,
+            self.list_viewport_height,
+            self.list_row_height(),
+            &self.list_rows(),
+            self.has_more_messages,
+        ) else {
+            return Task::none();
+        };
+        self.load_list_window(window.start as u32, window.len() as u32)
+            .unwrap_or_else(Task::none)
+    }
+
+    /// Folder row at the top of the viewport and how far into it the view
+    /// is scrolled, taken before the loaded window is replaced.
+    pub(super) fn list_scroll_anchor(&self) -> (usize, f32) {
+        let height = self.list_row_height();
+        let row = (self.list_scroll_y.max(0.0) / height) as usize;
+        (
+            self.list_rows().folder_row(row),
+            self.list_scroll_y.max(0.0) - row as f32 * height,
+        )
+    }
+
+    /// Scroll so the anchored folder row is back at the top of the viewport;
+    /// the list rows above it change when the window moves past collapsed
+    /// threads.
+    pub(super) fn scroll_to_anchor(&mut self, (row, within): (usize, f32)) -> Task<Message> {
+        let list_row = self.list_rows().list_row(row);
+        self.list_scroll_y = list_row as f32 * self.list_row_height() + within;
+        self.restore_list_scroll()
+    }
+
+    /// Scroll the message list so visible row `pos` is on screen. Rows off
+    /// screen are not built, so keyboard selection has to move the viewport.
+    fn reveal_list_row(&mut self, pos: usize) -> Task<Message> {
+        let Some(y) = reveal_offset(
+            self.messages_offset as usize + pos,
+            self.list_row_height(),
+            self.list_scroll_y,
+            self.list_viewport_height,
+        ) else {
+            return Task::none();
+        };
+        self.list_scroll_y = y;
+        let scroll =
+            scrollable::scroll_to(message_list::list_scroll_id(), AbsoluteOffset { x: 0.0, y });
+        cosmic::task::batch(vec![scroll, self.ensure_list_window()])
+    }
+
+    /// Restore the list scroll offset after its rows were replaced.
+    fn restore_list_scroll(&self) -> Task<Message> {
+        scrollable::scroll_to(
+            message_list::list_scroll_id(),
+            AbsoluteOffset {
+                x: 0.0,
+                y: self.list_scroll_y,
+            },
+        )
+    }
+
     /// Rebuild `visible_indices` and `thread_sizes` based on current messages
     /// and collapsed state.
     pub(super) fn recompute_visible(&mut self) {
         self.thread_sizes.clear();
-        for msg in &self.messages {
-            if let Some(ref tid) = msg.thread_id {
-                *self.thread_sizes.entry(tid.clone()).or_insert(0) += 1;
-            }
-        }
-
         self.visible_indices.clear();
         for (i, msg) in self.messages.iter().enumerate() {
-            if msg.thread_depth > 0 {
-                if let Some(ref tid) = msg.thread_id {
-                    if self.collapsed_threads.contains(tid) {
-                        continue;
-                    }
+            if let Some(ref tid) = msg.thread_id {
+                *self.thread_sizes.entry(tid.clone()).or_insert(0) += 1;
+                if msg.thread_depth > 0 && self.collapsed_threads.contains(tid) {
+                    continue;
                 }
             }
             self.visible_indices.push(i);
@@ -137,7 +301,7 @@ impl AppModel {
             self.selected_message = next;
             self.pending_body = None;
             self.preview_body.clear();
-            self.preview_markdown.clear();
+            self.preview_blocks.clear();
             self.preview_editor = cosmic::widget::text_editor::Content::new();
             self.preview_attachments.clear();
             self.preview_image_handles.clear();
@@ -150,7 +314,89 @@ impl AppModel {
 
 #[cfg(test)]
 mod tests {
-    use super::revalidated_selection;
+    use super::{revalidated_selection, reveal_offset, window_for_view, ListRows, PREFETCH_ROWS};
+    use crate::ui::message_list::OVERSCAN_ROWS;
+
+    /// A loaded window with no collapsed threads.
+    fn all_shown(offset: usize, visible: &[usize]) -> ListRows<'_> {
+        ListRows {
+            offset,
+            loaded: visible.len(),
+            visible,
+        }
+    }
+
+    #[test]
+    fn reveal_scrolls_only_when_row_is_off_screen() {
+        // 50px rows, 500px viewport scrolled to row 10.
+        assert_eq!(reveal_offset(12, 50.0, 500.0, 500.0), None);
+        assert_eq!(reveal_offset(9, 50.0, 500.0, 500.0), Some(450.0));
+        assert_eq!(reveal_offset(20, 50.0, 500.0, 500.0), Some(550.0));
+    }
+
+    #[test]
+    fn window_is_kept_while_it_covers_the_view() {
+        let rows: Vec<usize> = (0..20).collect();
+        // Rows 0..10 in view, plus overscan below.
+        let view = |loaded: usize, has_more| {
+            window_for_view(0.0, 500.0, 50.0, &all_shown(0, &rows[..loaded]), has_more)
+        };
+        assert_eq!(view(20, true), None);
+        assert_eq!(view(5, false), None);
+        assert_eq!(view(19, true), Some(0..20 + PREFETCH_ROWS));
+    }
+
+    #[test]
+    fn loaded_window_stays_bounded_after_scrolling_far() {
+        let bound = 10 + 2 * (OVERSCAN_ROWS + PREFETCH_ROWS);
+        let rows: Vec<usize> = (0..bound).collect();
+        let mut loaded = 0..100;
+        for step in 1..=2_000 {
+            // 500px viewport of 50px rows, scrolled down 100 rows per step.
+            let scroll_y = step as f32 * 100.0 * 50.0;
+            let shown = all_shown(loaded.start, &rows[..loaded.len().min(bound)]);
+            if let Some(window) = window_for_view(scroll_y, 500.0, 50.0, &shown, true) {
+                loaded = window;
+            }
+            assert!(loaded.len() <= bound, "window {loaded:?} at step {step}");
+        }
+        assert_eq!(loaded.start, 200_000 - OVERSCAN_ROWS - PREFETCH_ROWS);
+
+        // Scrolling back to the top reloads the head, still bounded.
+        let shown = all_shown(loaded.start, &rows[..loaded.len()]);
+        let head = window_for_view(0.0, 500.0, 50.0, &shown, true).expect("reload");
+        assert_eq!(head.start, 0);
+        assert!(head.len() <= bound);
+    }
+
+    #[test]
+    fn collapsed_threads_map_list_rows_to_folder_rows() {
+        // Folder rows 100..200 are loaded; rows 101..=140 are replies hidden
+        // under the collapsed thread at row 100, so 60 list rows show.
+        let visible: Vec<usize> = std::iter::once(0).chain(41..100).collect();
+        let rows = ListRows {
+            offset: 100,
+            loaded: 100,
+            visible: &visible,
+        };
+        assert_eq!(rows.folder_row(50), 50);
+        assert_eq!(rows.folder_row(101), 141);
+        assert_eq!(rows.folder_row(159), 199);
+        assert_eq!(rows.folder_row(160), 200);
+        assert_eq!(rows.list_row(120), 100);
+        assert_eq!(rows.list_row(141), 101);
+        assert_eq!(rows.list_row(205), 165);
+        for row in [50, 100, 101, 159, 160, 170] {
+            assert_eq!(rows.list_row(rows.folder_row(row)), row);
+        }
+
+        // The last shown rows sit at list rows 150..160. Folder rows run
+        // to 200, so scrolling there needs the window past row 200, not one
+        // ending at list row 160.
+        let window = window_for_view(150.0 * 50.0, 500.0, 50.0, &rows, true).expect("load");
+        assert!(window.end > 200, "{window:?}");
+        assert!(window.contains(&199));
+    }
 
     #[test]
     fn selection_is_cleared_when_out_of_range() {
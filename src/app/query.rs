@@ -0,0 +1,888 @@
+//! Structured search syntax.
+//!
+//! `from:alice -is:read "quarterly report" in:archive older_than:2w`
+//!
+//! A query is a list of clauses ANDed together; `-` negates a clause. Bare
+//! words and quoted phrases are full-text terms. Unknown `field:` prefixes
+//! are kept as text so URLs and times still search as typed.
+//!
+//! The parsed query compiles per account (folder names resolve against that
+//! account's mailboxes) into an FTS5 expression plus cache predicates, or
+//! into a JMAP `Email/query` FilterCondition for the server.
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde_json::{json, Value};
+
+use neverlight_mail_core::models::Folder;
+use neverlight_mail_core::store::{LocalSearch, SearchPredicate};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub(super) enum QueryError {
+    #[error("{0}: needs a value")]
+    MissingValue(String),
+    #[error("unknown value \"{value}\" for {field}:")]
+    UnknownValue { field: String, value: String },
+    #[error("invalid date \"{0}\" (use YYYY-MM-DD)")]
+    InvalidDate(String),
+    #[error("invalid age \"{0}\" (use e.g. 3d, 2w, 6m, 1y)")]
+    InvalidAge(String),
+    #[error("no folder named \"{0}\"")]
+    UnknownFolder(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Term {
+    /// Bare word or quoted phrase.
+    Text(String),
+    From(String),
+    To(String),
+    Subject(String),
+    HasAttachment,
+    Unread,
+    Starred,
+    /// Folder name, path or role (`in:inbox`, `in:"Work/Clients"`).
+    InFolder(String),
+    Before(NaiveDate),
+    After(NaiveDate),
+    /// Received more than this many days ago.
+    OlderThanDays(i64),
+    /// Exact mailbox id, added by the "this folder" search scope.
+    InMailbox(String),
+    /// Mailing list by `List-Id` identifier (`list:rust-users.example.org`).
+    List(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Clause {
+    pub negated: bool,
+    pub term: Term,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct SearchQuery {
+    pub clauses: Vec<Clause>,
+}
+
+/// One whitespace-delimited token: `[-][field:]value`, with `"..."` quoting.
+struct Token {
+    negated: bool,
+    field: Option<String>,
+    value: String,
+    /// The token as typed, minus negation; used when the field is unknown.
+    raw: String,
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let negated = chars[i] == '-' && chars.get(i + 1).is_some_and(|c| !c.is_whitespace());
+        if negated {
+            i += 1;
+        }
+        let start = i;
+
+        let mut field = None;
+        if chars[i] != '"' {
+            let mut j = i;
+            while j < chars.len() && (chars[j].is_alphanumeric() || chars[j] == '_') {
+                j += 1;
+            }
+            if j > i && chars.get(j) == Some(&':') {
+                field = Some(chars[i..j].iter().collect::<String>().to_lowercase());
+                i = j + 1;
+            }
+        }
+
+        let value = if chars.get(i) == Some(&'"') {
+            let close = chars[i + 1..]
+                .iter()
+                .position(|&c| c == '"')
+                .map_or(chars.len(), |p| i + 1 + p);
+            let v: String = chars[i + 1..close].iter().collect();
+            i = (close + 1).min(chars.len());
+            v
+        } else {
+            let begin = i;
+            while i < chars.len() && !chars[i].is_whitespace() {
+                i += 1;
+            }
+            chars[begin..i].iter().collect()
+        };
+
+        tokens.push(Token {
+            negated,
+            field,
+            value,
+            raw: chars[start..i].iter().collect(),
+        });
+    }
+    tokens
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, QueryError> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y/%m/%d"))
+        .map_err(|_| QueryError::InvalidDate(value.to_string()))
+}
+
+/// `3d`, `2w`, `6m` (30 days), `1y` (365 days) → days.
+/// Longest `older_than:` age; anything beyond reaches past chrono's range.
+const MAX_AGE_DAYS: i64 = 100 * 365;
+
+fn parse_age_days(value: &str) -> Result<i64, QueryError> {
+    let invalid = || QueryError::InvalidAge(value.to_string());
+    let split = value.len().checked_sub(1).ok_or_else(invalid)?;
+    if !value.is_char_boundary(split) {
+        return Err(invalid());
+    }
+    let (count, unit) = value.split_at(split);
+    let count: i64 = count.parse().map_err(|_| invalid())?;
+    let per_unit = match unit {
+        "d" => 1,
+        "w" => 7,
+        "m" => 30,
+        "y" => 365,
+        _ => return Err(invalid()),
+    };
+    count
+        .checked_mul(per_unit)
+        .filter(|d| (0..=MAX_AGE_DAYS).contains(d))
+        .ok_or_else(invalid)
+}
+
+fn parse_term(field: &str, value: &str) -> Result<Option<(Term, bool)>, QueryError> {
+    let unknown = || QueryError::UnknownValue {
+        field: field.to_string(),
+        value: value.to_string(),
+    };
+    let term = match field {
+        "from" => Term::From(value.to_string()),
+        "to" => Term::To(value.to_string()),
+        "subject" => Term::Subject(value.to_string()),
+        "in" => Term::InFolder(value.to_string()),
+        "list" => Term::List(value.to_lowercase()),
+        "has" => match value.to_lowercase().as_str() {
+            "attachment" | "attachments" => Term::HasAttachment,
+            _ => return Err(unknown()),
+        },
+        "is" => match value.to_lowercase().as_str() {
+            "unread" => Term::Unread,
+            // is:read is the negation of is:unread
+            "read" => return Ok(Some((Term::Unread, true))),
+            "starred" | "flagged" => Term::Starred,
+            _ => return Err(unknown()),
+        },
+        "before" => Term::Before(parse_date(value)?),
+        "after" => Term::After(parse_date(value)?),
+        "older_than" => Term::OlderThanDays(parse_age_days(value)?),
+        _ => return Ok(None),
+    };
+    Ok(Some((term, false)))
+}
+
+impl SearchQuery {
+    pub(super) fn parse(input: &str) -> Result<Self, QueryError> {
+        let mut clauses = Vec::new();
+        for token in tokenize(input) {
+            let Some(field) = &token.field else {
+                if !token.value.is_empty() {
+                    clauses.push(Clause {
+                        negated: token.negated,
+                        term: Term::Text(token.value),
+                    });
+                }
+                continue;
+            };
+            if token.value.is_empty() {
+                return Err(QueryError::MissingValue(field.clone()));
+            }
+            match parse_term(field, &token.value)? {
+                Some((term, flip)) => clauses.push(Clause {
+                    negated: token.negated != flip,
+                    term,
+                }),
+                None => clauses.push(Clause {
+                    negated: token.negated,
+                    term: Term::Text(token.raw),
+                }),
+            }
+        }
+        Ok(SearchQuery { clauses })
+    }
+
+    /// Restrict the query to one mailbox.
+    pub(super) fn within_mailbox(mut self, mailbox_id: &str) -> Self {
+        self.clauses.push(Clause {
+            negated: false,
+            term: Term::InMailbox(mailbox_id.to_string()),
+        });
+        self
+    }
+
+    /// Mailboxes a positive `in:` clause confines the query to; `None` when
+    /// it can match mail in any mailbox (or the folder does not resolve).
+    pub(super) fn mailbox_scope(&self, folders: &[Folder]) -> Option<Vec<String>> {
+        self.clauses
+            .iter()
+            .filter(|c| !c.negated)
+            .find_map(|c| match &c.term {
+                Term::InFolder(name) => resolve_folder(name, folders).ok(),
+                Term::InMailbox(id) => Some(vec![id.clone()]),
+                _ => None,
+            })
+    }
+
+    /// Positive text and subject terms, as shown highlighted in results.
+    pub(super) fn highlight_terms(&self) -> Vec<String> {
+        self.clauses
+            .iter()
+            .filter(|c| !c.negated)
+            .filter_map(|c| match &c.term {
+                Term::Text(text) | Term::Subject(text) => Some(text.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Compile for the local cache: positive text terms form the FTS5 match
+    /// expression, everything else becomes a predicate.
+    pub(super) fn to_local(
+        &self,
+        folders: &[Folder],
+        now: DateTime<Utc>,
+    ) -> Result<LocalSearch, QueryError> {
+        let mut fts_terms = Vec::new();
+        let mut predicates = Vec::new();
+        for clause in &self.clauses {
+            let predicate = match &clause.term {
+                Term::Text(text) if !clause.negated => {
+                    fts_terms.push(fts_quote(text));
+                    continue;
+                }
+                Term::Text(text) => SearchPredicate::TextMatches(fts_quote(text)),
+                Term::From(v) => SearchPredicate::FromContains(v.clone()),
+                Term::To(v) => SearchPredicate::ToContains(v.clone()),
+                Term::Subject(v) => SearchPredicate::SubjectContains(v.clone()),
+                Term::HasAttachment => SearchPredicate::HasAttachment,
+                Term::Unread => SearchPredicate::Unread,
+                Term::Starred => SearchPredicate::Starred,
+                Term::InFolder(name) => {
+                    SearchPredicate::InMailboxes(resolve_folder(name, folders)?)
+                }
+                Term::Before(date) => SearchPredicate::Before(day_start(*date)),
+                Term::After(date) => SearchPredicate::After(day_start(*date)),
+                Term::OlderThanDays(days) => {
+                    SearchPredicate::Before((now - Duration::days(*days)).timestamp())
+                }
+                Term::InMailbox(id) => SearchPredicate::InMailboxes(vec![id.clone()]),
+                Term::List(id) => SearchPredicate::ListId(id.clone()),
+            };
+            predicates.push(if clause.negated {
+                SearchPredicate::Not(Box::new(predicate))
+            } else {
+                predicate
+            });
+        }
+        Ok(LocalSearch {
+            fts: (!fts_terms.is_empty()).then(|| fts_terms.join(" ")),
+            predicates,
+        })
+    }
+
+    /// Compile to an RFC 8621 `Email/query` filter (a FilterCondition, or an
+    /// AND FilterOperator when there is more than one clause).
+    pub(super) fn to_jmap_filter(
+        &self,
+        folders: &[Folder],
+        now: DateTime<Utc>,
+    ) -> Result<Value, QueryError> {
+        let mut conditions = Vec::with_capacity(self.clauses.len());
+        for clause in &self.clauses {
+            let condition = match &clause.term {
+                Term::Text(v) => json!({ "text": v }),
+                Term::From(v) => json!({ "from": v }),
+                Term::To(v) => json!({ "to": v }),
+                Term::Subject(v) => json!({ "subject": v }),
+                Term::HasAttachment => json!({ "hasAttachment": true }),
+                Term::Unread => json!({ "notKeyword": "$seen" }),
+                Term::Starred => json!({ "hasKeyword": "$flagged" }),
+                Term::InFolder(name) => {
+                    let ids = resolve_folder(name, folders)?;
+                    match ids.as_slice() {
+                        [id] => json!({ "inMailbox": id }),
+                        _ => json!({
+                            "operator": "OR",
+                            "conditions": ids.iter().map(|id| json!({ "inMailbox": id })).collect::<Vec<_>>(),
+                        }),
+                    }
+                }
+                Term::Before(date) => json!({ "before": utc_date(day_start(*date)) }),
+                Term::After(date) => json!({ "after": utc_date(day_start(*date)) }),
+                Term::OlderThanDays(days) => {
+                    json!({ "before": utc_date((now - Duration::days(*days)).timestamp()) })
+                }
+                Term::InMailbox(id) => json!({ "inMailbox": id }),
+                Term::List(id) => json!({ "header": ["List-Id", format!("<{id}>")] }),
+            };
+            conditions.push(if clause.negated {
+                json!({ "operator": "NOT", "conditions": [condition] })
+            } else {
+                condition
+            });
+        }
+        Ok(match conditions.len() {
+            0 => json!({}),
+            1 => conditions.remove(0),
+            _ => json!({ "operator": "AND", "conditions": conditions }),
+        })
+    }
+}
+
+/// Quote a term for FTS5 so operators and punctuation are matched literally.
+fn fts_quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+/// Mailboxes matching a folder name, path or role, case-insensitively.
+fn resolve_folder(name: &str, folders: &[Folder]) -> Result<Vec<String>, QueryError> {
+    let ids: Vec<String> = folders
+        .iter()
+        .filter(|f| {
+            f.name.eq_ignore_ascii_case(name)
+                || f.path.eq_ignore_ascii_case(name)
+                || f.role
+                    .as_deref()
+                    .is_some_and(|r| r.eq_ignore_ascii_case(name))
+        })
+        .map(|f| f.mailbox_id.clone())
+        .collect();
+    if ids.is_empty() {
+        return Err(QueryError::UnknownFolder(name.to_string()));
+    }
+    Ok(ids)
+}
+
+/// Midnight UTC at the start of `date`, as unix seconds.
+fn day_start(date: NaiveDate) -> i64 {
+    date.and_hms_opt(0, 0, 0)
+        .map_or(0, |dt| dt.and_utc().timestamp())
+}
+
+/// RFC 3339 UTCDate as JMAP expects (`2024-01-15T00:00:00Z`).
+fn utc_date(timestamp: i64) -> String {
+    DateTime::<Utc>::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_support::sample_folder as folder;
+
+    fn sample_folders() -> Vec<Folder> {
+        vec![
+            folder("M-inbox", "INBOX", Some("inbox")),
+            folder("M-archive", "Archive", Some("archive")),
+            folder("M-clients", "Work/Clients", None),
+        ]
+    }
+
+    fn sample_now() -> DateTime<Utc> {
+        DateTime::<Utc>::from_timestamp(1_760_000_000, 0).expect("valid timestamp")
+    }
+
+    fn parse(input: &str) -> Vec<Clause> {
+        SearchQuery::parse(input).expect("query parses").clauses
+    }
+
+    fn clause(negated: bool, term: Term) -> Clause {
+        Clause { negated, term }
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").expect("valid test date")
+    }
+
+    // -- parsing ------------------------------------------------------------
+
+    #[test]
+    fn bare_words_are_text_terms() {
+        assert_eq!(
+            parse("hello  world"),
+            vec![
+                clause(false, Term::Text("hello".into())),
+                clause(false, Term::Text("world".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_and_blank_queries_have_no_clauses() {
+        assert!(parse("").is_empty());
+        assert!(parse("   ").is_empty());
+    }
+
+    #[test]
+    fn quoted_phrase_is_one_text_term() {
+        assert_eq!(
+            parse("\"quarterly report\" draft"),
+            vec![
+                clause(false, Term::Text("quarterly report".into())),
+                clause(false, Term::Text("draft".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_quote_runs_to_end() {
+        assert_eq!(
+            parse("\"open ended"),
+            vec![clause(false, Term::Text("open ended".into()))]
+        );
+    }
+
+    #[test]
+    fn address_fields_parse() {
+        assert_eq!(
+            parse("from:alice@example.com to:bob subject:invoice"),
+            vec![
+                clause(false, Term::From("alice@example.com".into())),
+                clause(false, Term::To("bob".into())),
+                clause(false, Term::Subject("invoice".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn field_values_can_be_quoted() {
+        assert_eq!(
+            parse("subject:\"team offsite\" in:\"Work/Clients\""),
+            vec![
+                clause(false, Term::Subject("team offsite".into())),
+                clause(false, Term::InFolder("Work/Clients".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn field_names_are_case_insensitive() {
+        assert_eq!(
+            parse("FROM:alice"),
+            vec![clause(false, Term::From("alice".into()))]
+        );
+        assert_eq!(parse("is:UNREAD"), vec![clause(false, Term::Unread)]);
+    }
+
+    #[test]
+    fn flag_operators_parse() {
+        assert_eq!(
+            parse("has:attachment is:unread is:starred is:flagged"),
+            vec![
+                clause(false, Term::HasAttachment),
+                clause(false, Term::Unread),
+                clause(false, Term::Starred),
+                clause(false, Term::Starred),
+            ]
+        );
+    }
+
+    #[test]
+    fn is_read_is_negated_unread() {
+        assert_eq!(parse("is:read"), vec![clause(true, Term::Unread)]);
+        assert_eq!(parse("-is:read"), vec![clause(false, Term::Unread)]);
+    }
+
+    #[test]
+    fn negation_applies_to_any_clause() {
+        assert_eq!(
+            parse("-from:noreply -\"daily digest\" -spam"),
+            vec![
+                clause(true, Term::From("noreply".into())),
+                clause(true, Term::Text("daily digest".into())),
+                clause(true, Term::Text("spam".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn lone_dash_is_text_not_negation() {
+        assert_eq!(
+            parse("a - b"),
+            vec![
+                clause(false, Term::Text("a".into())),
+                clause(false, Term::Text("-".into())),
+                clause(false, Term::Text("b".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn dates_parse_in_both_separators() {
+        assert_eq!(
+            parse("after:2024-01-15 before:2024/02/01"),
+            vec![
+                clause(false, Term::After(date("2024-01-15"))),
+                clause(false, Term::Before(date("2024-02-01"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn older_than_units_convert_to_days() {
+        assert_eq!(
+            parse("older_than:3d"),
+            vec![clause(false, Term::OlderThanDays(3))]
+        );
+        assert_eq!(
+            parse("older_than:2w"),
+            vec![clause(false, Term::OlderThanDays(14))]
+        );
+        assert_eq!(
+            parse("older_than:6m"),
+            vec![clause(false, Term::OlderThanDays(180))]
+        );
+        assert_eq!(
+            parse("older_than:1y"),
+            vec![clause(false, Term::OlderThanDays(365))]
+        );
+    }
+
+    #[test]
+    fn unknown_fields_stay_text_as_typed() {
+        assert_eq!(
+            parse("https://example.com meeting:10"),
+            vec![
+                clause(false, Term::Text("https://example.com".into())),
+                clause(false, Term::Text("meeting:10".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_values_are_errors() {
+        assert_eq!(
+            SearchQuery::parse("from:"),
+            Err(QueryError::MissingValue("from".into()))
+        );
+        assert_eq!(
+            SearchQuery::parse("has:pdf"),
+            Err(QueryError::UnknownValue {
+                field: "has".into(),
+                value: "pdf".into()
+            })
+        );
+        assert_eq!(
+            SearchQuery::parse("is:important"),
+            Err(QueryError::UnknownValue {
+                field: "is".into(),
+                value: "important".into()
+            })
+        );
+        assert_eq!(
+            SearchQuery::parse("before:yesterday"),
+            Err(QueryError::InvalidDate("yesterday".into()))
+        );
+        assert_eq!(
+            SearchQuery::parse("before:2024-13-01"),
+            Err(QueryError::InvalidDate("2024-13-01".into()))
+        );
+    }
+
+    #[test]
+    fn malformed_ages_are_errors() {
+        for age in ["3", "d", "3h", "-2d", "x3d", "3日"] {
+            assert_eq!(
+                SearchQuery::parse(&format!("older_than:{age}")),
+                Err(QueryError::InvalidAge(age.into())),
+                "age {age:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn oversized_ages_are_errors() {
+        for age in ["1000000y", "36501d", "9223372036854775807d"] {
+            assert_eq!(
+                SearchQuery::parse(&format!("older_than:{age}")),
+                Err(QueryError::InvalidAge(age.into())),
+                "age {age:?}"
+            );
+        }
+        assert!(SearchQuery::parse("older_than:100y").is_ok());
+    }
+
+    // -- local compilation --------------------------------------------------
+
+    #[test]
+    fn local_text_terms_join_into_quoted_fts() {
+        let q = SearchQuery::parse("budget \"q3 plan\"").expect("parses");
+        let local = q
+            .to_local(&sample_folders(), sample_now())
+            .expect("compiles");
+        assert_eq!(local.fts.as_deref(), Some("\"budget\" \"q3 plan\""));
+        assert!(local.predicates.is_empty());
+    }
+
+    #[test]
+    fn local_fts_escapes_quotes_and_operators() {
+        assert_eq!(fts_quote("NOT"), "\"NOT\"");
+        assert_eq!(fts_quote("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn local_negated_text_becomes_predicate() {
+        let q = SearchQuery::parse("-newsletter").expect("parses");
+        let local = q
+            .to_local(&sample_folders(), sample_now())
+            .expect("compiles");
+        assert_eq!(local.fts, None);
+        assert_eq!(
+            local.predicates,
+            vec![SearchPredicate::Not(Box::new(
+                SearchPredicate::TextMatches("\"newsletter\"".into())
+            ))]
+        );
+    }
+
+    #[test]
+    fn local_fields_become_predicates() {
+        let q =
+            SearchQuery::parse("from:alice to:bob subject:hi has:attachment is:unread is:starred")
+                .expect("parses");
+        let local = q
+            .to_local(&sample_folders(), sample_now())
+            .expect("compiles");
+        assert_eq!(
+            local.predicates,
+            vec![
+                SearchPredicate::FromContains("alice".into()),
+                SearchPredicate::ToContains("bob".into()),
+                SearchPredicate::SubjectContains("hi".into()),
+                SearchPredicate::HasAttachment,
+                SearchPredicate::Unread,
+                SearchPredicate::Starred,
+            ]
+        );
+    }
+
+    #[test]
+    fn local_folder_resolves_by_role_name_or_path() {
+        let folders = sample_folders();
+        for (input, expected) in [
+            ("in:inbox", "M-inbox"),
+            ("in:ARCHIVE", "M-archive"),
+            ("in:clients", "M-clients"),
+            ("in:work/clients", "M-clients"),
+        ] {
+            let local = SearchQuery::parse(input)
+                .expect("parses")
+                .to_local(&folders, sample_now())
+                .expect("compiles");
+            assert_eq!(
+                local.predicates,
+                vec![SearchPredicate::InMailboxes(vec![expected.into()])],
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn mailbox_scope_follows_positive_folder_clauses() {
+        let folders = sample_folders();
+        let scope = |input: &str| {
+            SearchQuery::parse(input)
+                .expect("parses")
+                .mailbox_scope(&folders)
+        };
+        assert_eq!(scope("invoice in:archive"), Some(vec!["M-archive".into()]));
+        assert_eq!(scope("invoice -in:archive"), None);
+        assert_eq!(scope("invoice"), None);
+        assert_eq!(
+            SearchQuery::parse("invoice")
+                .expect("parses")
+                .within_mailbox("M-clients")
+                .mailbox_scope(&folders),
+            Some(vec!["M-clients".into()])
+        );
+    }
+
+    #[test]
+    fn unknown_folder_fails_to_compile() {
+        let q = SearchQuery::parse("in:nowhere").expect("parses");
+        assert_eq!(
+            q.to_local(&sample_folders(), sample_now()),
+            Err(QueryError::UnknownFolder("nowhere".into()))
+        );
+        assert_eq!(
+            q.to_jmap_filter(&sample_folders(), sample_now()),
+            Err(QueryError::UnknownFolder("nowhere".into()))
+        );
+    }
+
+    #[test]
+    fn local_dates_are_utc_day_boundaries() {
+        let q =
+            SearchQuery::parse("after:2024-01-15 before:2024-01-16 older_than:1d").expect("parses");
+        let local = q
+            .to_local(&sample_folders(), sample_now())
+            .expect("compiles");
+        assert_eq!(
+            local.predicates,
+            vec![
+                SearchPredicate::After(1_705_276_800),
+                SearchPredicate::Before(1_705_363_200),
+                SearchPredicate::Before(1_760_000_000 - 86_400),
+            ]
+        );
+    }
+
+    #[test]
+    fn local_negated_field_wraps_in_not() {
+        let q = SearchQuery::parse("-in:archive").expect("parses");
+        let local = q
+            .to_local(&sample_folders(), sample_now())
+            .expect("compiles");
+        assert_eq!(
+            local.predicates,
+            vec![SearchPredicate::Not(Box::new(
+                SearchPredicate::InMailboxes(vec!["M-archive".into()])
+            ))]
+        );
+    }
+
+    // -- JMAP compilation ---------------------------------------------------
+
+    fn jmap(input: &str) -> Value {
+        SearchQuery::parse(input)
+            .expect("parses")
+            .to_jmap_filter(&sample_folders(), sample_now())
+            .expect("compiles")
+    }
+
+    #[test]
+    fn jmap_single_clause_is_bare_condition() {
+        assert_eq!(jmap("from:alice"), json!({ "from": "alice" }));
+        assert_eq!(jmap("\"two words\""), json!({ "text": "two words" }));
+    }
+
+    #[test]
+    fn jmap_empty_query_is_empty_filter() {
+        assert_eq!(jmap(""), json!({}));
+    }
+
+    #[test]
+    fn jmap_multiple_clauses_are_anded() {
+        assert_eq!(
+            jmap("invoice is:unread in:inbox"),
+            json!({
+                "operator": "AND",
+                "conditions": [
+                    { "text": "invoice" },
+                    { "notKeyword": "$seen" },
+                    { "inMailbox": "M-inbox" },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn jmap_flags_and_attachments() {
+        assert_eq!(jmap("is:starred"), json!({ "hasKeyword": "$flagged" }));
+        assert_eq!(jmap("has:attachment"), json!({ "hasAttachment": true }));
+        assert_eq!(
+            jmap("is:read"),
+            json!({ "operator": "NOT", "conditions": [{ "notKeyword": "$seen" }] })
+        );
+    }
+
+    #[test]
+    fn list_matches_the_bracketed_list_id() {
+        assert_eq!(
+            parse("list:Rust-Users.Example.org"),
+            vec![clause(false, Term::List("rust-users.example.org".into()))]
+        );
+        assert_eq!(
+            jmap("list:rust-users.example.org"),
+            json!({ "header": ["List-Id", "<rust-users.example.org>"] })
+        );
+    }
+
+    #[test]
+    fn jmap_negation_wraps_in_not_operator() {
+        assert_eq!(
+            jmap("-subject:digest"),
+            json!({ "operator": "NOT", "conditions": [{ "subject": "digest" }] })
+        );
+    }
+
+    #[test]
+    fn jmap_dates_are_utc_dates() {
+        assert_eq!(
+            jmap("after:2024-01-15 older_than:1d"),
+            json!({
+                "operator": "AND",
+                "conditions": [
+                    { "after": "2024-01-15T00:00:00Z" },
+                    { "before": "2025-10-08T08:53:20Z" },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn jmap_folder_matching_several_mailboxes_is_ored() {
+        let mut folders = sample_folders();
+        folders.push(folder("M-archive-2", "Old/Archive", None));
+        let filter = SearchQuery::parse("in:archive")
+            .expect("parses")
+            .to_jmap_filter(&folders, sample_now())
+            .expect("compiles");
+        assert_eq!(
+            filter,
+            json!({
+                "operator": "OR",
+                "conditions": [{ "inMailbox": "M-archive" }, { "inMailbox": "M-archive-2" }],
+            })
+        );
+    }
+
+    #[test]
+    fn highlight_terms_skip_negated_and_non_text() {
+        let query =
+            SearchQuery::parse("\"quarterly report\" subject:budget -draft from:alice is:unread")
+                .expect("parses");
+        assert_eq!(query.highlight_terms(), vec!["quarterly report", "budget"]);
+    }
+
+    #[test]
+    fn mailbox_scope_compiles_to_exact_mailbox() {
+        let query = SearchQuery::parse("budget")
+            .expect("parses")
+            .within_mailbox("M-clients");
+        let local = query
+            .to_local(&sample_folders(), sample_now())
+            .expect("compiles");
+        assert_eq!(
+            local.predicates,
+            vec![SearchPredicate::InMailboxes(vec!["M-clients".into()])]
+        );
+        let filter = query
+            .to_jmap_filter(&sample_folders(), sample_now())
+            .expect("compiles");
+        assert_eq!(
+            filter,
+            json!({
+                "operator": "AND",
+                "conditions": [{ "text": "budget" }, { "inMailbox": "M-clients" }],
+            })
+        );
+    }
+}
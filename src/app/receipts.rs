@@ -0,0 +1,335 @@
+//! Read receipts (message disposition notifications, RFC 8098).
+//!
+//! A message carrying `Disposition-Notification-To` gets a prompt until it
+//! is answered. Sending goes through JMAP MDN (RFC 9007) `MDN/send`, which
+//! also sets `$MDNSent` on the message; ignoring sets the keyword alone, so
+//! other clients don't ask again (RFC 3503). Receipts are never sent
+//! without asking.
+//!
+//! Receipts coming back for our own mail are parsed by the sync engine and
+//! kept by original `Message-ID`; they show under the sent message.
+
+use chrono::{Local, TimeZone};
+use cosmic::app::Task;
+
+use neverlight_mail_core::mdn::{self, MdnRequest};
+use neverlight_mail_core::models::MessageSummary;
+use neverlight_mail_core::session::CAP_MDN;
+use neverlight_mail_core::store::ReadReceipt;
+use neverlight_mail_core::submit;
+
+use super::remote_images::sender_address;
+use super::{AppModel, Message, ReceiptPrompt};
+
+/// RFC 3503 keyword for "receipt sent or declined".
+const MDN_SENT_KEYWORD: &str = "$MDNSent";
+
+/// Where a read receipt for `msg` would go, while its request is
+/// unanswered. Our own messages never ask.
+fn receipt_request(msg: &MessageSummary, own_addresses: &[String]) -> Option<String> {
+    if msg.mdn_sent {
+        return None;
+    }
+    let header = msg.disposition_notification_to.as_deref()?;
+    let notify = sender_address(header.split(',').next()?)?;
+    let from_us = sender_address(&msg.from)
+        .is_some_and(|s| own_addresses.iter().any(|o| o.eq_ignore_ascii_case(&s)));
+    (!from_us).then_some(notify)
+}
+
+/// "Read by X at T" for one returned receipt.
+pub(crate) fn receipt_label(receipt: &ReadReceipt) -> String {
+    let verb = match receipt.disposition.as_str() {
+        "displayed" => "Read",
+        "deleted" => "Deleted unread",
+        _ => "Processed",
+    };
+    match Local.timestamp_opt(receipt.at, 0).single() {
+        Some(at) => format!(
+            "{verb} by {} at {}",
+            receipt.reader,
+            at.format("%Y-%m-%d %H:%M")
+        ),
+        None => format!("{verb} by {}", receipt.reader),
+    }
+}
+
+/// Human-readable part of a receipt, worded as RFC 8098 §9.2 suggests.
+fn receipt_text(msg: &MessageSummary) -> String {
+    format!(
+        "The message sent on {} to {} with subject \"{}\" has been displayed.\n\
+         This is no guarantee that the message has been read or understood.\n",
+        msg.date, msg.to, msg.subject
+    )
+}
+
+impl AppModel {
+    pub(super) fn handle_receipts(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::ReceiptSend(email_id) => return self.send_receipt(email_id),
+            Message::ReceiptIgnore(email_id) => {
+                let Some(msg) = self.mark_receipt_answered(&email_id, true) else {
+                    return Task::none();
+                };
+                let cache = self.cache.clone();
+                let client = self.client_for_account(&msg.account_id);
+                return cosmic::task::future(async move {
+                    let account_id = msg.account_id.clone();
+                    if let Some(cache) = cache {
+                        if let Err(e) = cache.upsert_messages(account_id, vec![msg.clone()]).await {
+                            log::warn!("Caching receipt state failed: {}", e);
+                        }
+                    }
+                    let result = match client {
+                        Some(client) => neverlight_mail_core::email::set_keyword(
+                            &client,
+                            &msg.email_id,
+                            MDN_SENT_KEYWORD,
+                            true,
+                        )
+                        .await
+                        .map_err(|e| e.to_string()),
+                        None => Ok(()),
+                    };
+                    Message::ReceiptAnswered {
+                        email_id: msg.email_id,
+                        sent: false,
+                        result,
+                    }
+                });
+            }
+            Message::ReceiptAlwaysIgnore => {
+                self.settings.ignore_receipt_requests = true;
+                self.status_message = "Read receipt requests will be ignored".into();
+                return self.save_settings();
+            }
+            Message::ReceiptAnswered {
+                email_id,
+                sent,
+                result,
+            } => match result {
+                Ok(()) if sent => self.status_message = "Read receipt sent".into(),
+                Ok(()) => {}
+                Err(e) => {
+                    log::warn!("Answering read receipt request failed: {}", e);
+                    if sent {
+                        self.mark_receipt_answered(&email_id, false);
+                        self.status_message = format!("Sending read receipt failed: {e}");
+                    }
+                }
+            },
+            Message::ReadReceiptsLoaded(loaded) => {
+                for (email_id, receipts) in loaded {
+                    let shown = self.conversation.iter().any(|e| e.email_id == email_id)
+                        || self
+                            .selected_message
+                            .and_then(|i| self.messages.get(i))
+                            .is_some_and(|m| m.email_id == email_id);
+                    if shown && !receipts.is_empty() {
+                        self.read_receipts.insert(email_id, receipts);
+                    }
+                }
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+
+    /// Unanswered receipt request on the selected message, if it should be
+    /// asked about.
+    pub(super) fn receipt_prompt(&self) -> Option<ReceiptPrompt> {
+        if self.settings.ignore_receipt_requests {
+            return None;
+        }
+        let msg = self.selected_message.and_then(|i| self.messages.get(i))?;
+        let own: Vec<String> = self
+            .accounts
+            .iter()
+            .flat_map(|a| a.config.email_addresses.iter().cloned())
+            .collect();
+        let notify = receipt_request(msg, &own)?;
+        let can_send = self
+            .client_for_account(&msg.account_id)
+            .is_some_and(|c| c.has_capability(CAP_MDN));
+        let sender = sender_address(&msg.from);
+        Some(ReceiptPrompt {
+            email_id: msg.email_id.clone(),
+            notify: (sender.as_deref() != Some(notify.as_str())).then_some(notify),
+            can_send,
+        })
+    }
+
+    /// Set `mdn_sent` on every loaded copy of the message; returns the
+    /// updated summary.
+    fn mark_receipt_answered(&mut self, email_id: &str, answered: bool) -> Option<MessageSummary> {
+        let mut updated = None;
+        let listed = self.messages.iter_mut().filter(|m| m.email_id == email_id);
+        let in_thread = self
+            .conversation
+            .iter_mut()
+            .map(|e| &mut e.summary)
+            .filter(|m| m.email_id == email_id);
+        for msg in listed.chain(in_thread) {
+            msg.mdn_sent = answered;
+            updated = Some(msg.clone());
+        }
+        updated
+    }
+
+    fn send_receipt(&mut self, email_id: String) -> Task<Message> {
+        let Some(msg) = self.messages.iter().find(|m| m.email_id == email_id) else {
+            return Task::none();
+        };
+        let Some(acct) = self
+            .account_index(&msg.account_id)
+            .and_then(|i| self.accounts.get(i))
+        else {
+            return Task::none();
+        };
+        let Some(client) = acct.client.clone() else {
+            self.status_message = "Not connected — cannot send a read receipt".into();
+            return Task::none();
+        };
+        if !client.has_capability(CAP_MDN) {
+            self.status_message = "This server cannot send read receipts".into();
+            return Task::none();
+        }
+        // Answer from the address the message was sent to, if it is ours.
+        let recipients = msg.to.to_lowercase();
+        let Some(reader) = acct
+            .config
+            .email_addresses
+            .iter()
+            .find(|a| recipients.contains(&a.to_lowercase()))
+            .or_else(|| acct.config.email_addresses.first())
+            .cloned()
+        else {
+            self.status_message = "No email address configured. Re-run setup to add one.".into();
+            return Task::none();
+        };
+        let subject = format!("Read: {}", msg.subject);
+        let text = receipt_text(msg);
+        let cache = self.cache.clone();
+        let Some(msg) = self.mark_receipt_answered(&email_id, true) else {
+            return Task::none();
+        };
+        self.status_message = "Sending read receipt...".into();
+
+        cosmic::task::future(async move {
+            let result = async {
+                let identities = submit::get_identities(&client)
+                    .await
+                    .map_err(|e| format!("Failed to fetch identities: {e}"))?;
+                let identity = submit::find_identity_for_address(&identities, &reader)
+                    .ok_or_else(|| "No sender identity found".to_string())?;
+                let req = MdnRequest {
+                    identity_id: &identity.id,
+                    for_email_id: &email_id,
+                    subject: &subject,
+                    text_body: &text,
+                };
+                mdn::send(&client, &req).await.map_err(|e| e.to_string())
+            }
+            .await;
+            if result.is_ok() {
+                if let Some(cache) = cache {
+                    let account_id = msg.account_id.clone();
+                    if let Err(e) = cache.upsert_messages(account_id, vec![msg]).await {
+                        log::warn!("Caching receipt state failed: {}", e);
+                    }
+                }
+            }
+            Message::ReceiptAnswered {
+                email_id,
+                sent: true,
+                result,
+            }
+        })
+    }
+
+    /// Receipts returned for whichever of `msgs` we sent.
+    pub(super) fn load_read_receipts<'m>(
+        &self,
+        msgs: impl IntoIterator<Item = &'m MessageSummary>,
+    ) -> Task<Message> {
+        let Some(cache) = self.cache.clone() else {
+            return Task::none();
+        };
+        let sent: Vec<(String, String, String)> = msgs
+            .into_iter()
+            .filter(|m| self.is_sent_message(&m.from, &m.account_id))
+            .map(|m| {
+                (
+                    m.account_id.clone(),
+                    m.email_id.clone(),
+                    m.message_id.clone(),
+                )
+            })
+            .collect();
+        if sent.is_empty() {
+            return Task::none();
+        }
+        cosmic::task::future(async move {
+            let mut loaded = Vec::new();
+            for (account_id, email_id, message_id) in sent {
+                match cache.read_receipts(account_id, message_id).await {
+                    Ok(receipts) => loaded.push((email_id, receipts)),
+                    Err(e) => log::warn!("Loading read receipts failed: {}", e),
+                }
+            }
+            Message::ReadReceiptsLoaded(loaded)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_support::sample_summary;
+
+    fn requesting(notify: &str) -> MessageSummary {
+        let mut msg = sample_summary("acct-a", "E1");
+        msg.disposition_notification_to = Some(notify.to_string());
+        msg
+    }
+
+    #[test]
+    fn unanswered_requests_prompt() {
+        let own = vec!["bob@example.com".to_string()];
+        assert_eq!(
+            receipt_request(&requesting("Alice <Alice@Example.com>"), &own),
+            Some("alice@example.com".into())
+        );
+        assert_eq!(receipt_request(&sample_summary("acct-a", "E1"), &own), None);
+
+        let mut answered = requesting("alice@example.com");
+        answered.mdn_sent = true;
+        assert_eq!(receipt_request(&answered, &own), None);
+    }
+
+    #[test]
+    fn our_own_messages_never_prompt() {
+        let own = vec!["alice@example.com".to_string()];
+        assert_eq!(
+            receipt_request(&requesting("alice@example.com"), &own),
+            None
+        );
+    }
+
+    #[test]
+    fn receipts_label_by_disposition() {
+        let receipt = |disposition: &str| ReadReceipt {
+            reader: "carol@example.org".into(),
+            disposition: disposition.into(),
+            at: i64::MAX,
+        };
+        assert_eq!(
+            receipt_label(&receipt("displayed")),
+            "Read by carol@example.org"
+        );
+        assert_eq!(
+            receipt_label(&receipt("deleted")),
+            "Deleted unread by carol@example.org"
+        );
+    }
+}
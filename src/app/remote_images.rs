@@ -0,0 +1,251 @@
+//! Opt-in remote images: per message on request, or automatically for
+//! allow-listed senders.
+//!
+//! The sanitizer still strips remote images from every body. When the user
+//! asks, the image URLs are fetched here with a bare HTTP client — no cookie
+//! store, no referrer, no user agent — and shown below the body.
+
+use std::time::Duration;
+
+use cosmic::app::Task;
+use cosmic::widget::image;
+use futures::future::{AbortHandle, Abortable};
+
+use super::{AppModel, Message, RemoteImages};
+
+/// Newsletters embed dozens of spacers and pixels; the first images are the
+/// content.
+const MAX_REMOTE_IMAGES: usize = 20;
+const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+const MAX_REDIRECTS: usize = 3;
+
+#[derive(Debug, thiserror::Error)]
+enum RemoteImageError {
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error("server answered {0}")]
+    Status(reqwest::StatusCode),
+    #[error("not an image ({0})")]
+    NotImage(String),
+    #[error("larger than {} MB", MAX_IMAGE_BYTES / (1024 * 1024))]
+    TooLarge,
+}
+
+/// Bare address of a `From` value (`Name <addr>` or `addr`), lowercased.
+pub(super) fn sender_address(from: &str) -> Option<String> {
+    let addr = match (from.rfind('<'), from.rfind('>')) {
+        (Some(open), Some(close)) if open < close => &from[open + 1..close],
+        _ => from,
+    };
+    let addr = addr.trim();
+    addr.contains('@').then(|| addr.to_lowercase())
+}
+
+/// Only plain web URLs are fetched; `data:`, `file:` and friends are not.
+fn is_fetchable(url: &str) -> bool {
+    url::Url::parse(url).is_ok_and(|u| matches!(u.scheme(), "http" | "https"))
+}
+
+fn fetch_client() -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .referer(false)
+        .timeout(FETCH_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::limited(MAX_REDIRECTS))
+        .build()
+}
+
+async fn fetch_image(client: &reqwest::Client, url: &str) -> Result<Vec<u8>, RemoteImageError> {
+    let mut response = client.get(url).send().await?;
+    if !response.status().is_success() {
+        return Err(RemoteImageError::Status(response.status()));
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    if !content_type.starts_with("image/") {
+        return Err(RemoteImageError::NotImage(content_type));
+    }
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_IMAGE_BYTES as u64)
+    {
+        return Err(RemoteImageError::TooLarge);
+    }
+    let mut data = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if data.len() + chunk.len() > MAX_IMAGE_BYTES {
+            return Err(RemoteImageError::TooLarge);
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
+
+async fn fetch_remote_images(urls: Vec<String>) -> Result<(Vec<image::Handle>, usize), String> {
+    let client = fetch_client().map_err(|e| e.to_string())?;
+    let mut handles = Vec::new();
+    let mut failed = 0;
+    for url in urls
+        .iter()
+        .filter(|u| is_fetchable(u))
+        .take(MAX_REMOTE_IMAGES)
+    {
+        match fetch_image(&client, url).await {
+            Ok(data) => handles.push(image::Handle::from_bytes(data)),
+            Err(e) => {
+                log::debug!("Remote image {} skipped: {}", url, e);
+                failed += 1;
+            }
+        }
+    }
+    Ok((handles, failed))
+}
+
+impl AppModel {
+    pub(super) fn handle_remote_images(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::LoadRemoteImages => return self.load_remote_images(),
+            Message::AlwaysLoadRemoteImages => {
+                let Some(sender) = self
+                    .selected_message
+                    .and_then(|i| self.messages.get(i))
+                    .and_then(|m| sender_address(&m.from))
+                else {
+                    return Task::none();
+                };
+                self.settings.allow_remote_images_from(&sender);
+                let save = self.save_settings();
+                let loaded = self
+                    .remote_images
+                    .as_ref()
+                    .is_some_and(|r| Some(r.email_id.as_str()) == self.selected_email_id());
+                if !loaded {
+                    return cosmic::task::batch(vec![save, self.load_remote_images()]);
+                }
+                return save;
+            }
+            Message::RemoteImagesLoaded {
+                email_id,
+                epoch,
+                result,
+            } => {
+                if epoch != self.body_epoch || self.selected_email_id() != Some(email_id.as_str()) {
+                    self.stale_apply_drop_count = self.stale_apply_drop_count.saturating_add(1);
+                    return Task::none();
+                }
+                self.remote_images_abort = None;
+                match result {
+                    Ok(images) => {
+                        self.status_message = match (images.handles.len(), images.failed) {
+                            (0, 0) => "No remote images in this message".into(),
+                            (n, 0) => format!("Loaded {n} remote images"),
+                            (n, failed) => format!("Loaded {n} remote images ({failed} failed)"),
+                        };
+                        self.remote_images = Some(images);
+                    }
+                    Err(e) => {
+                        log::warn!("Remote images for {} failed: {}", email_id, e);
+                        self.status_message = format!("Remote images failed: {e}");
+                    }
+                }
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+
+    fn selected_email_id(&self) -> Option<&str> {
+        self.selected_message
+            .and_then(|i| self.messages.get(i))
+            .map(|m| m.email_id.as_str())
+    }
+
+    /// Whether the selected message's sender is on the allow-list.
+    pub(super) fn selected_sender_allows_remote_images(&self) -> bool {
+        self.selected_message
+            .and_then(|i| self.messages.get(i))
+            .and_then(|m| sender_address(&m.from))
+            .is_some_and(|sender| self.settings.allows_remote_images(&sender))
+    }
+
+    /// Fetch remote images for the selected message if its sender is
+    /// allow-listed; called once its body has been shown.
+    pub(super) fn auto_load_remote_images(&mut self) -> Task<Message> {
+        if self.selected_sender_allows_remote_images() {
+            self.load_remote_images()
+        } else {
+            Task::none()
+        }
+    }
+
+    fn load_remote_images(&mut self) -> Task<Message> {
+        let Some(msg) = self.selected_message.and_then(|i| self.messages.get(i)) else {
+            return Task::none();
+        };
+        let email_id = msg.email_id.clone();
+        let Some(client) = self.client_for_account(&msg.account_id) else {
+            self.status_message = "Not connected — remote images need the server".into();
+            return Task::none();
+        };
+        if let Some(handle) = self.remote_images_abort.take() {
+            handle.abort();
+        }
+        let (abort_handle, abort_reg) = AbortHandle::new_pair();
+        self.remote_images_abort = Some(abort_handle);
+        let epoch = self.body_epoch;
+        self.status_message = "Loading remote images...".into();
+        cosmic::task::future(async move {
+            let load = async {
+                let urls = neverlight_mail_core::email::remote_image_urls(&client, &email_id)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let (handles, failed) = fetch_remote_images(urls).await?;
+                Ok::<_, String>(RemoteImages {
+                    email_id: email_id.clone(),
+                    handles,
+                    failed,
+                })
+            };
+            match Abortable::new(load, abort_reg).await {
+                Ok(result) => Message::RemoteImagesLoaded {
+                    email_id,
+                    epoch,
+                    result,
+                },
+                Err(_) => Message::Noop,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sender_address_handles_display_names() {
+        assert_eq!(
+            sender_address("News Team <News@Example.com>").as_deref(),
+            Some("news@example.com")
+        );
+        assert_eq!(
+            sender_address(" shop@example.com ").as_deref(),
+            Some("shop@example.com")
+        );
+        assert_eq!(sender_address("Undisclosed recipients"), None);
+    }
+
+    #[test]
+    fn only_web_urls_are_fetched() {
+        assert!(is_fetchable("https://cdn.example.com/a.png"));
+        assert!(is_fetchable("http://example.com/b.gif"));
+        assert!(!is_fetchable("data:image/png;base64,AAAA"));
+        assert!(!is_fetchable("file:///etc/passwd"));
+        assert!(!is_fetchable("cid:logo@example.com"));
+        assert!(!is_fetchable("not a url"));
+    }
+}
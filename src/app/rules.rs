@@ -0,0 +1,1016 @@
+//! Local mail rules, for providers without server-side filtering.
+//!
+//! Rules live in settings, per account. On connect and whenever push reports
+//! a change, the account's inbox is synced and every message received after
+//! the account's mark is matched, so mail that came in while offline counts
+//! and old mail moved back into the inbox does not. The first pass after an
+//! account gains a rule only sets the mark.
+//! Actions go through the ordinary flag and move queues, so they update the
+//! list and roll back on failure like any other change. Rules can also be
+//! run over the selected folder, or dry-run to preview what they would do.
+
+use cosmic::app::Task;
+use cosmic::iced::Length;
+use cosmic::widget;
+use cosmic::Element;
+
+use neverlight_mail_core::config::AccountId;
+use neverlight_mail_core::models::MessageSummary;
+use neverlight_mail_core::store::{
+    self, CacheHandle, ListQuery, LocalSearch, SearchPredicate, DEFAULT_PAGE_SIZE,
+};
+
+use super::settings::{MailRule, RuleAction, RuleCondition, RulesMark};
+use super::{
+    AppModel, FlagIntentKind, IntentOrigin, MailboxIdentity, Message, MessageIdentity, MoveKind,
+    PendingFlagIntent, PendingMoveIntent, RuleDraft, RulePreviewRow, RulesPhase,
+};
+
+const CONDITION_LABELS: &[&str] = &[
+    "Sender contains",
+    "Recipient contains",
+    "Subject contains",
+    "List-Id contains",
+    "Has attachment",
+];
+const ACTION_LABELS: &[&str] = &["Move to", "Label with", "Mark read", "Star", "Notify"];
+
+/// Preview rows shown before "and N more".
+const PREVIEW_LIMIT: usize = 200;
+
+fn condition_from(kind: usize, value: &str) -> Option<RuleCondition> {
+    let value = value.trim();
+    let text = (!value.is_empty()).then(|| value.to_string());
+    match kind {
+        0 => text.map(RuleCondition::Sender),
+        1 => text.map(RuleCondition::Recipient),
+        2 => text.map(RuleCondition::Subject),
+        3 => text.map(RuleCondition::ListId),
+        4 => Some(RuleCondition::HasAttachment),
+        _ => None,
+    }
+}
+
+fn action_from(kind: usize, folder: Option<&String>) -> Option<RuleAction> {
+    match kind {
+        0 => folder.cloned().map(RuleAction::Move),
+        1 => folder.cloned().map(RuleAction::Label),
+        2 => Some(RuleAction::MarkRead),
+        3 => Some(RuleAction::Star),
+        4 => Some(RuleAction::Notify),
+        _ => None,
+    }
+}
+
+fn condition_label(condition: &RuleCondition) -> String {
+    match condition {
+        RuleCondition::Sender(v) => format!("sender contains \u{201c}{v}\u{201d}"),
+        RuleCondition::Recipient(v) => format!("recipient contains \u{201c}{v}\u{201d}"),
+        RuleCondition::Subject(v) => format!("subject contains \u{201c}{v}\u{201d}"),
+        RuleCondition::ListId(v) => format!("list contains \u{201c}{v}\u{201d}"),
+        RuleCondition::HasAttachment => "has attachment".into(),
+    }
+}
+
+fn action_label(action: &RuleAction) -> String {
+    match action {
+        RuleAction::Move(folder) => format!("move to {folder}"),
+        RuleAction::Label(folder) => format!("label with {folder}"),
+        RuleAction::MarkRead => "mark read".into(),
+        RuleAction::Star => "star".into(),
+        RuleAction::Notify => "notify".into(),
+    }
+}
+
+fn rule_summary(rule: &MailRule) -> String {
+    let conditions: Vec<String> = rule.conditions.iter().map(condition_label).collect();
+    let actions: Vec<String> = rule.actions.iter().map(action_label).collect();
+    format!("If {} → {}", conditions.join(" and "), actions.join(", "))
+}
+
+fn contains(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+fn condition_matches(condition: &RuleCondition, msg: &MessageSummary) -> bool {
+    match condition {
+        RuleCondition::Sender(v) => contains(&msg.from, v),
+        RuleCondition::Recipient(v) => contains(&msg.to, v),
+        RuleCondition::Subject(v) => contains(&msg.subject, v),
+        RuleCondition::ListId(v) => msg.list_id.as_deref().is_some_and(|l| contains(l, v)),
+        RuleCondition::HasAttachment => msg.has_attachment,
+    }
+}
+
+fn rule_matches(rule: &MailRule, msg: &MessageSummary) -> bool {
+    rule.enabled
+        && rule.account_id == msg.account_id
+        && !rule.conditions.is_empty()
+        && rule.conditions.iter().all(|c| condition_matches(c, msg))
+}
+
+/// What the rules would do to `msg`, sitting in `folder`: actions of every
+/// matching rule in order, without repeats or no-ops. Only the first move
+/// counts, and it runs last so labels and flags still find the message.
+fn planned_actions(rules: &[MailRule], msg: &MessageSummary, folder: &str) -> Vec<RuleAction> {
+    let mut planned: Vec<RuleAction> = Vec::new();
+    // Set by the first move even when it is a no-op, so a later rule
+    // cannot move a message an earlier rule meant to keep where it is.
+    let mut moved = false;
+    for action in rules
+        .iter()
+        .filter(|r| rule_matches(r, msg))
+        .flat_map(|r| &r.actions)
+    {
+        let no_op = match action {
+            RuleAction::Move(path) => std::mem::replace(&mut moved, true) || path == folder,
+            RuleAction::Label(path) => path == folder,
+            RuleAction::MarkRead => msg.is_read,
+            RuleAction::Star => msg.is_starred,
+            RuleAction::Notify => false,
+        };
+        if !no_op && !planned.contains(action) {
+            planned.push(action.clone());
+        }
+    }
+    planned.sort_by_key(|a| matches!(a, RuleAction::Move(_)));
+    planned
+}
+
+/// The messages of `msgs` received after `mark`, and the mark moved past
+/// everything in `msgs`. Without a mark nothing is new yet.
+fn arrivals_since(
+    mark: Option<&RulesMark>,
+    msgs: Vec<MessageSummary>,
+) -> (Vec<MessageSummary>, RulesMark) {
+    let mut next = mark.cloned().unwrap_or_default();
+    let mut arrivals = Vec::new();
+    for msg in msgs {
+        let new = mark.is_some_and(|m| {
+            msg.timestamp > m.received
+                || (msg.timestamp == m.received && !m.ids.contains(&msg.email_id))
+        });
+        if msg.timestamp > next.received {
+            next.received = msg.timestamp;
+            next.ids.clear();
+        }
+        if msg.timestamp == next.received {
+            next.ids.insert(msg.email_id.clone());
+        }
+        if new {
+            arrivals.push(msg);
+        }
+    }
+    (arrivals, next)
+}
+
+fn empty_draft() -> RuleDraft {
+    RuleDraft {
+        editing: None,
+        name: String::new(),
+        conditions: Vec::new(),
+        actions: Vec::new(),
+        condition_kind: 0,
+        condition_value: String::new(),
+        action_kind: 0,
+        action_folder: None,
+    }
+}
+
+/// Every message in a cached mailbox.
+async fn load_folder(
+    cache: CacheHandle,
+    account_id: AccountId,
+    mailbox_id: String,
+) -> Result<Vec<MessageSummary>, String> {
+    let mut all = Vec::new();
+    loop {
+        let page = cache
+            .load_messages(
+                account_id.clone(),
+                mailbox_id.clone(),
+                ListQuery::default(),
+                DEFAULT_PAGE_SIZE,
+                all.len() as u32,
+            )
+            .await?;
+        let done = page.len() < DEFAULT_PAGE_SIZE as usize;
+        all.extend(page);
+        if done {
+            return Ok(all);
+        }
+    }
+}
+
+impl AppModel {
+    pub(super) fn handle_rules(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::RulesOpen | Message::RulesBack => {
+                let Some(acct) = self.active_account.and_then(|i| self.accounts.get(i)) else {
+                    return Task::none();
+                };
+                self.rules_phase = RulesPhase::Editing {
+                    account_id: acct.config.id.clone(),
+                    draft: empty_draft(),
+                    folders: acct.folders.iter().map(|f| f.path.clone()).collect(),
+                    error: None,
+                };
+            }
+            Message::RulesClose => self.rules_phase = RulesPhase::Closed,
+            Message::RuleToggled(index, enabled) => {
+                if let Some(rule) = self.settings.mail_rules.get_mut(index) {
+                    rule.enabled = enabled;
+                    let account_id = rule.account_id.clone();
+                    return cosmic::task::batch(vec![
+                        self.rules_changed(&account_id),
+                        self.save_settings(),
+                    ]);
+                }
+            }
+            Message::RuleEdit(index) => {
+                let Some(rule) = self.settings.mail_rules.get(index) else {
+                    return Task::none();
+                };
+                if let RulesPhase::Editing { draft, error, .. } = &mut self.rules_phase {
+                    *draft = RuleDraft {
+                        editing: Some(index),
+                        name: rule.name.clone(),
+                        conditions: rule.conditions.clone(),
+                        actions: rule.actions.clone(),
+                        ..empty_draft()
+                    };
+                    *error = None;
+                }
+            }
+            Message::RuleDelete(index) => {
+                if index >= self.settings.mail_rules.len() {
+                    return Task::none();
+                }
+                let removed = self.settings.mail_rules.remove(index);
+                if let RulesPhase::Editing { draft, .. } = &mut self.rules_phase {
+                    match draft.editing {
+                        Some(i) if i == index => *draft = empty_draft(),
+                        Some(i) if i > index => draft.editing = Some(i - 1),
+                        _ => {}
+                    }
+                }
+                return cosmic::task::batch(vec![
+                    self.rules_changed(&removed.account_id),
+                    self.save_settings(),
+                ]);
+            }
+            Message::RuleNameChanged(name) => {
+                if let RulesPhase::Editing { draft, .. } = &mut self.rules_phase {
+                    draft.name = name;
+                }
+            }
+            Message::RuleConditionKindChanged(kind) => {
+                if let RulesPhase::Editing { draft, .. } = &mut self.rules_phase {
+                    draft.condition_kind = kind;
+                }
+            }
+            Message::RuleConditionValueChanged(value) => {
+                if let RulesPhase::Editing { draft, .. } = &mut self.rules_phase {
+                    draft.condition_value = value;
+                }
+            }
+            Message::RuleConditionAdd => {
+                if let RulesPhase::Editing { draft, error, .. } = &mut self.rules_phase {
+                    match condition_from(draft.condition_kind, &draft.condition_value) {
+                        Some(condition) => {
+                            if !draft.conditions.contains(&condition) {
+                                draft.conditions.push(condition);
+                            }
+                            draft.condition_value.clear();
+                            *error = None;
+                        }
+                        None => *error = Some("Enter text to match".into()),
+                    }
+                }
+            }
+            Message::RuleConditionRemove(index) => {
+                if let RulesPhase::Editing { draft, .. } = &mut self.rules_phase {
+                    if index < draft.conditions.len() {
+                        draft.conditions.remove(index);
+                    }
+                }
+            }
+            Message::RuleActionKindChanged(kind) => {
+                if let RulesPhase::Editing { draft, .. } = &mut self.rules_phase {
+                    draft.action_kind = kind;
+                }
+            }
+            Message::RuleActionFolderChanged(index) => {
+                if let RulesPhase::Editing { draft, .. } = &mut self.rules_phase {
+                    draft.action_folder = Some(index);
+                }
+            }
+            Message::RuleActionAdd => {
+                if let RulesPhase::Editing {
+                    draft,
+                    folders,
+                    error,
+                    ..
+                } = &mut self.rules_phase
+                {
+                    let folder = draft.action_folder.and_then(|i| folders.get(i));
+                    match action_from(draft.action_kind, folder) {
+                        Some(action) => {
+                            if !draft.actions.contains(&action) {
+                                draft.actions.push(action);
+                            }
+                            *error = None;
+                        }
+                        None => *error = Some("Choose a folder".into()),
+                    }
+                }
+            }
+            Message::RuleActionRemove(index) => {
+                if let RulesPhase::Editing { draft, .. } = &mut self.rules_phase {
+                    if index < draft.actions.len() {
+                        draft.actions.remove(index);
+                    }
+                }
+            }
+            Message::RuleSave => return self.save_rule(),
+            Message::RulesPreview => return self.load_rules_folder(false),
+            Message::RulesApplyNow => return self.load_rules_folder(true),
+            Message::RulesFolderLoaded {
+                account_id,
+                apply,
+                result,
+            } => {
+                let msgs = match result {
+                    Ok(msgs) => msgs,
+                    Err(e) => {
+                        log::warn!("Loading folder for rules failed: {}", e);
+                        self.set_status_error(format!("Could not load this folder: {e}"));
+                        if matches!(self.rules_phase, RulesPhase::Preview { .. }) {
+                            return self.dispatch(Message::RulesBack);
+                        }
+                        return Task::none();
+                    }
+                };
+                if apply {
+                    let (task, queued) = self.apply_rules(&account_id, msgs);
+                    self.rules_phase = RulesPhase::Closed;
+                    self.status_message = match queued {
+                        0 => "No rule matched this folder".into(),
+                        n => format!("Rules: {n} actions queued"),
+                    };
+                    return task;
+                }
+                let folders = self
+                    .account_index(&account_id)
+                    .map(|i| self.accounts[i].folders.as_slice())
+                    .unwrap_or_default();
+                let preview: Vec<RulePreviewRow> = msgs
+                    .iter()
+                    .filter_map(|msg| {
+                        let folder = folders
+                            .iter()
+                            .find(|f| f.mailbox_id == msg.context_mailbox_id)
+                            .map(|f| f.path.as_str())
+                            .unwrap_or_default();
+                        let planned = planned_actions(&self.settings.mail_rules, msg, folder);
+                        (!planned.is_empty()).then(|| RulePreviewRow {
+                            from: msg.from.clone(),
+                            subject: msg.subject.clone(),
+                            actions: planned
+                                .iter()
+                                .map(action_label)
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                        })
+                    })
+                    .collect();
+                if let RulesPhase::Preview {
+                    account_id: shown,
+                    rows,
+                    ..
+                } = &mut self.rules_phase
+                {
+                    if *shown == account_id {
+                        *rows = Some(preview);
+                    }
+                }
+            }
+            Message::RulesArrivals { account_id, result } => {
+                let msgs = match result {
+                    Ok(msgs) => msgs,
+                    Err(e) => {
+                        log::warn!("Rules pass for {} failed: {}", account_id, e);
+                        return Task::none();
+                    }
+                };
+                // The last rule went while the pass ran.
+                if !self.settings.has_rules(&account_id) {
+                    return Task::none();
+                }
+                // Against the mark as it is now, so overlapping passes
+                // don't act on the same message twice.
+                let (arrivals, mark) =
+                    arrivals_since(self.settings.rules_marks.get(&account_id), msgs);
+                let save = if self.settings.rules_marks.get(&account_id) == Some(&mark) {
+                    Task::none()
+                } else {
+                    self.settings.rules_marks.insert(account_id.clone(), mark);
+                    self.save_settings()
+                };
+                let (task, queued) = self.apply_rules(&account_id, arrivals);
+                if queued > 0 {
+                    log::info!("Rules queued {} actions for {}", queued, account_id);
+                }
+                return cosmic::task::batch(vec![save, task]);
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+
+    fn save_rule(&mut self) -> Task<Message> {
+        let RulesPhase::Editing {
+            account_id,
+            draft,
+            error,
+            ..
+        } = &mut self.rules_phase
+        else {
+            return Task::none();
+        };
+        let name = draft.name.trim();
+        let problem = if name.is_empty() {
+            Some("Name the rule")
+        } else if draft.conditions.is_empty() {
+            Some("Add a condition")
+        } else if draft.actions.is_empty() {
+            Some("Add an action")
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            *error = Some(problem.into());
+            return Task::none();
+        }
+        let rule = MailRule {
+            name: name.to_string(),
+            account_id: account_id.clone(),
+            enabled: true,
+            conditions: std::mem::take(&mut draft.conditions),
+            actions: std::mem::take(&mut draft.actions),
+        };
+        let account_id = account_id.clone();
+        match draft
+            .editing
+            .and_then(|i| self.settings.mail_rules.get_mut(i))
+        {
+            Some(existing) => {
+                *existing = MailRule {
+                    enabled: existing.enabled,
+                    ..rule
+                }
+            }
+            None => self.settings.mail_rules.push(rule),
+        }
+        *draft = empty_draft();
+        *error = None;
+        self.status_message = "Rule saved".into();
+        cosmic::task::batch(vec![self.rules_changed(&account_id), self.save_settings()])
+    }
+
+    /// Keep the account's mark in step with its rules. It goes once no rule
+    /// is enabled, so turning rules back on doesn't catch up on everything
+    /// since; the first pass after that sets it afresh.
+    fn rules_changed(&mut self, account_id: &str) -> Task<Message> {
+        if !self.settings.has_rules(account_id) {
+            self.settings.rules_marks.remove(account_id);
+            return Task::none();
+        }
+        if self.settings.rules_marks.contains_key(account_id) {
+            return Task::none();
+        }
+        self.rules_arrival_pass(account_id)
+    }
+
+    /// Load the selected folder from the cache for a dry run or to apply
+    /// the rules to it.
+    fn load_rules_folder(&mut self, apply: bool) -> Task<Message> {
+        let Some(cache) = self.cache.clone() else {
+            self.status_message = "Rules need the local cache".into();
+            return Task::none();
+        };
+        let Some((acct, folder)) = self.active_account.and_then(|ai| {
+            let acct = self.accounts.get(ai)?;
+            Some((acct, acct.folders.get(self.selected_folder?)?))
+        }) else {
+            self.status_message = "Select a folder first".into();
+            return Task::none();
+        };
+        let account_id = acct.config.id.clone();
+        let mailbox_id = folder.mailbox_id.clone();
+        if !apply {
+            self.rules_phase = RulesPhase::Preview {
+                account_id: account_id.clone(),
+                folder: folder.path.clone(),
+                rows: None,
+            };
+        }
+        cosmic::task::future(async move {
+            let result = load_folder(cache, account_id.clone(), mailbox_id).await;
+            Message::RulesFolderLoaded {
+                account_id,
+                apply,
+                result,
+            }
+        })
+    }
+
+    /// Sync the account's inbox and list what it received since the mark,
+    /// or just the newest message when there is no mark yet.
+    pub(super) fn rules_arrival_pass(&self, account_id: &str) -> Task<Message> {
+        let Some(acct) = self
+            .account_index(account_id)
+            .and_then(|i| self.accounts.get(i))
+            .filter(|_| self.settings.has_rules(account_id))
+        else {
+            return Task::none();
+        };
+        let (Some(client), Some(cache), Some(inbox_id)) = (
+            acct.client.clone(),
+            self.cache.clone(),
+            neverlight_mail_core::mailbox::find_by_role(&acct.folders, "inbox"),
+        ) else {
+            return Task::none();
+        };
+        let since = self
+            .settings
+            .rules_marks
+            .get(account_id)
+            .map(|m| m.received);
+        let account_id = account_id.to_string();
+        cosmic::task::future(async move {
+            let result = async {
+                neverlight_mail_core::sync::sync_emails(
+                    &client,
+                    &cache,
+                    &account_id,
+                    &inbox_id,
+                    DEFAULT_PAGE_SIZE,
+                )
+                .await
+                .map_err(|e| e.to_string())?;
+                match since {
+                    Some(since) => {
+                        let local = LocalSearch {
+                            fts: None,
+                            predicates: vec![
+                                SearchPredicate::InMailboxes(vec![inbox_id]),
+                                SearchPredicate::After(since),
+                            ],
+                        };
+                        cache.search_filtered(account_id.clone(), local).await
+                    }
+                    None => {
+                        let newest = ListQuery {
+                            threaded: false,
+                            ..ListQuery::default()
+                        };
+                        cache
+                            .load_messages(account_id.clone(), inbox_id, newest, 1, 0)
+                            .await
+                    }
+                }
+            }
+            .await;
+            Message::RulesArrivals { account_id, result }
+        })
+    }
+
+    /// Queue what the rules say for each of `msgs`; returns the tasks and
+    /// how many actions were queued.
+    fn apply_rules(
+        &mut self,
+        account_id: &str,
+        msgs: Vec<MessageSummary>,
+    ) -> (Task<Message>, usize) {
+        let Some(folders) = self
+            .account_index(account_id)
+            .map(|i| self.accounts[i].folders.clone())
+        else {
+            return (Task::none(), 0);
+        };
+        let mut tasks: Vec<Task<Message>> = Vec::new();
+        let mut queued = 0;
+        for msg in msgs {
+            let folder = folders
+                .iter()
+                .find(|f| f.mailbox_id == msg.context_mailbox_id)
+                .map(|f| f.path.as_str())
+                .unwrap_or_default();
+            let identity = MessageIdentity {
+                account_id: msg.account_id.clone(),
+                mailbox_id: msg.context_mailbox_id.clone(),
+                email_id: msg.email_id.clone(),
+            };
+            let flags = store::flags_to_u8(msg.is_read, msg.is_starred);
+            for action in planned_actions(&self.settings.mail_rules, &msg, folder) {
+                let task = match action {
+                    RuleAction::MarkRead | RuleAction::Star => {
+                        self.queue_or_start_flag(PendingFlagIntent {
+                            message: identity.clone(),
+                            kind: if action == RuleAction::MarkRead {
+                                FlagIntentKind::MarkRead
+                            } else {
+                                FlagIntentKind::Star
+                            },
+                            origin: IntentOrigin::Background { flags },
+                        })
+                    }
+                    RuleAction::Move(ref path) | RuleAction::Label(ref path) => {
+                        let Some(dest) = folders.iter().find(|f| f.path == *path) else {
+                            log::warn!("Rule folder {} not found in {}", path, account_id);
+                            continue;
+                        };
+                        self.queue_or_start_move(PendingMoveIntent {
+                            message: identity.clone(),
+                            source: MailboxIdentity {
+                                account_id: msg.account_id.clone(),
+                                mailbox_id: msg.context_mailbox_id.clone(),
+                            },
+                            dest: MailboxIdentity {
+                                account_id: msg.account_id.clone(),
+                                mailbox_id: dest.mailbox_id.clone(),
+                            },
+                            kind: if matches!(action, RuleAction::Move(_)) {
+                                MoveKind::Plain
+                            } else {
+                                MoveKind::Label
+                            },
+                            origin: IntentOrigin::Background { flags },
+                        })
+                    }
+                    RuleAction::Notify => match self.notify_new_mail(&msg) {
+                        Some(task) => task,
+                        None => continue,
+                    },
+                };
+                tasks.push(task);
+                queued += 1;
+            }
+        }
+        (cosmic::task::batch(tasks), queued)
+    }
+
+    pub(super) fn rules_dialog(&self) -> Element<'_, Message> {
+        match &self.rules_phase {
+            RulesPhase::Editing {
+                account_id,
+                draft,
+                folders,
+                error,
+            } => self.rules_editor_dialog(account_id, draft, folders, error.as_deref()),
+            RulesPhase::Preview { folder, rows, .. } => {
+                let mut list = widget::column().spacing(6).width(Length::Fill);
+                match rows {
+                    None => list = list.push(widget::text::body("Loading...")),
+                    Some(rows) if rows.is_empty() => {
+                        list = list.push(widget::text::body("No rule matches a message here."));
+                    }
+                    Some(rows) => {
+                        for row in rows.iter().take(PREVIEW_LIMIT) {
+                            list = list.push(
+                                widget::column()
+                                    .push(widget::text::body(row.subject.as_str()))
+                                    .push(widget::text::caption(format!(
+                                        "{} — {}",
+                                        row.from, row.actions
+                                    ))),
+                            );
+                        }
+                        if rows.len() > PREVIEW_LIMIT {
+                            list = list.push(widget::text::caption(format!(
+                                "and {} more",
+                                rows.len() - PREVIEW_LIMIT
+                            )));
+                        }
+                    }
+                }
+                let mut apply = widget::button::suggested("Apply now");
+                if rows.as_ref().is_some_and(|r| !r.is_empty()) {
+                    apply = apply.on_press(Message::RulesApplyNow);
+                }
+                widget::dialog()
+                    .title(format!("Rules preview — {folder}"))
+                    .control(widget::scrollable(list).height(Length::Fixed(320.0)))
+                    .primary_action(apply)
+                    .secondary_action(widget::button::standard("Back").on_press(Message::RulesBack))
+                    .into()
+            }
+            RulesPhase::Closed => widget::column().into(),
+        }
+    }
+
+    fn rules_editor_dialog<'a>(
+        &'a self,
+        account_id: &'a str,
+        draft: &'a RuleDraft,
+        folders: &'a [String],
+        error: Option<&'a str>,
+    ) -> Element<'a, Message> {
+        let mut controls = widget::column().spacing(8).width(Length::Fill);
+
+        let rules = self
+            .settings
+            .mail_rules
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.account_id == account_id);
+        for (i, rule) in rules {
+            controls = controls.push(
+                widget::row()
+                    .spacing(8)
+                    .align_y(cosmic::iced::Alignment::Center)
+                    .push(
+                        widget::column()
+                            .width(Length::Fill)
+                            .push(
+                                widget::checkbox(rule.name.as_str(), rule.enabled)
+                                    .on_toggle(move |on| Message::RuleToggled(i, on)),
+                            )
+                            .push(widget::text::caption(rule_summary(rule))),
+                    )
+                    .push(widget::button::standard("Edit").on_press(Message::RuleEdit(i)))
+                    .push(
+                        widget::button::icon(widget::icon::from_name("edit-delete-symbolic"))
+                            .on_press(Message::RuleDelete(i))
+                            .padding(4)
+                            .class(cosmic::theme::Button::Text),
+                    ),
+            );
+        }
+        controls = controls.push(
+            widget::row()
+                .spacing(8)
+                .push(
+                    widget::button::standard("Preview on this folder")
+                        .on_press(Message::RulesPreview),
+                )
+                .push(
+                    widget::button::standard("Apply to this folder now")
+                        .on_press(Message::RulesApplyNow),
+                ),
+        );
+
+        controls = controls
+            .push(widget::text::body(if draft.editing.is_some() {
+                "Edit rule"
+            } else {
+                "New rule"
+            }))
+            .push(
+                widget::text_input("Rule name", &draft.name)
+                    .label("Name")
+                    .on_input(Message::RuleNameChanged),
+            );
+
+        for (i, condition) in draft.conditions.iter().enumerate() {
+            controls = controls.push(removable_row(
+                format!("If {}", condition_label(condition)),
+                Message::RuleConditionRemove(i),
+            ));
+        }
+        let mut condition_row = widget::row()
+            .spacing(8)
+            .align_y(cosmic::iced::Alignment::Center)
+            .push(widget::dropdown(
+                CONDITION_LABELS,
+                Some(draft.condition_kind),
+                Message::RuleConditionKindChanged,
+            ));
+        if draft.condition_kind != 4 {
+            condition_row = condition_row.push(
+                widget::text_input("text", &draft.condition_value)
+                    .on_input(Message::RuleConditionValueChanged)
+                    .on_submit(|_| Message::RuleConditionAdd),
+            );
+        }
+        controls =
+            controls.push(condition_row.push(
+                widget::button::standard("Add condition").on_press(Message::RuleConditionAdd),
+            ));
+
+        for (i, action) in draft.actions.iter().enumerate() {
+            controls = controls.push(removable_row(
+                format!("Then {}", action_label(action)),
+                Message::RuleActionRemove(i),
+            ));
+        }
+        let mut action_row = widget::row()
+            .spacing(8)
+            .align_y(cosmic::iced::Alignment::Center)
+            .push(widget::dropdown(
+                ACTION_LABELS,
+                Some(draft.action_kind),
+                Message::RuleActionKindChanged,
+            ));
+        if draft.action_kind <= 1 {
+            action_row = action_row.push(widget::dropdown(
+                folders,
+                draft.action_folder,
+                Message::RuleActionFolderChanged,
+            ));
+        }
+        controls = controls.push(
+            action_row
+                .push(widget::button::standard("Add action").on_press(Message::RuleActionAdd)),
+        );
+
+        let label = self
+            .account_index(account_id)
+            .map(|i| self.accounts[i].config.label.as_str())
+            .unwrap_or_default();
+        let mut dialog = widget::dialog()
+            .title(format!("Mail rules — {label}"))
+            .control(widget::scrollable(controls).height(Length::Fixed(420.0)))
+            .primary_action(widget::button::suggested("Save rule").on_press(Message::RuleSave))
+            .secondary_action(widget::button::standard("Close").on_press(Message::RulesClose));
+        if let Some(err) = error {
+            dialog = dialog.body(err);
+        }
+        dialog.into()
+    }
+}
+
+fn removable_row<'a>(label: String, remove: Message) -> Element<'a, Message> {
+    widget::row()
+        .spacing(8)
+        .align_y(cosmic::iced::Alignment::Center)
+        .push(widget::text::caption(label).width(Length::Fill))
+        .push(
+            widget::button::icon(widget::icon::from_name("edit-delete-symbolic"))
+                .on_press(remove)
+                .padding(4)
+                .class(cosmic::theme::Button::Text),
+        )
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_support::sample_summary;
+
+    fn rule(conditions: Vec<RuleCondition>, actions: Vec<RuleAction>) -> MailRule {
+        MailRule {
+            name: "rule".into(),
+            account_id: "acct-a".into(),
+            enabled: true,
+            conditions,
+            actions,
+        }
+    }
+
+    fn unread(email_id: &str) -> MessageSummary {
+        MessageSummary {
+            is_read: false,
+            list_id: Some("Rust users <rust-users.lists.example.org>".into()),
+            ..sample_summary("acct-a", email_id)
+        }
+    }
+
+    #[test]
+    fn conditions_match_case_insensitively() {
+        let msg = unread("E1");
+        assert!(condition_matches(
+            &RuleCondition::Sender("ALICE@".into()),
+            &msg
+        ));
+        assert!(condition_matches(
+            &RuleCondition::Recipient("bob".into()),
+            &msg
+        ));
+        assert!(condition_matches(
+            &RuleCondition::Subject("Subject E1".into()),
+            &msg
+        ));
+        assert!(condition_matches(
+            &RuleCondition::ListId("rust-users".into()),
+            &msg
+        ));
+        assert!(!condition_matches(&RuleCondition::HasAttachment, &msg));
+        assert!(!condition_matches(
+            &RuleCondition::ListId("x".into()),
+            &sample_summary("acct-a", "E2")
+        ));
+    }
+
+    #[test]
+    fn only_enabled_rules_of_the_account_with_conditions_apply() {
+        let msg = unread("E1");
+        let mut rules = vec![
+            rule(
+                vec![RuleCondition::Sender("alice".into())],
+                vec![RuleAction::Star],
+            ),
+            rule(vec![], vec![RuleAction::Notify]),
+        ];
+        assert_eq!(
+            planned_actions(&rules, &msg, "INBOX"),
+            vec![RuleAction::Star]
+        );
+        rules[0].enabled = false;
+        assert!(planned_actions(&rules, &msg, "INBOX").is_empty());
+        rules[0].enabled = true;
+        rules[0].account_id = "acct-b".into();
+        assert!(planned_actions(&rules, &msg, "INBOX").is_empty());
+    }
+
+    #[test]
+    fn planned_actions_skip_no_ops_and_keep_the_first_move() {
+        let msg = unread("E1");
+        let from_alice = vec![RuleCondition::Sender("alice".into())];
+        let rules = vec![
+            rule(
+                from_alice.clone(),
+                vec![RuleAction::Move("Lists".into()), RuleAction::MarkRead],
+            ),
+            rule(
+                from_alice,
+                vec![
+                    RuleAction::Move("Other".into()),
+                    RuleAction::Label("Important".into()),
+                    RuleAction::MarkRead,
+                ],
+            ),
+        ];
+        assert_eq!(
+            planned_actions(&rules, &msg, "INBOX"),
+            vec![
+                RuleAction::MarkRead,
+                RuleAction::Label("Important".into()),
+                RuleAction::Move("Lists".into()),
+            ]
+        );
+
+        let read_in_lists = MessageSummary {
+            is_read: true,
+            ..msg
+        };
+        assert_eq!(
+            planned_actions(&rules, &read_in_lists, "Lists"),
+            vec![RuleAction::Label("Important".into())]
+        );
+    }
+
+    #[test]
+    fn arrivals_are_mail_received_after_the_mark() {
+        let at = |email_id: &str, timestamp: i64| MessageSummary {
+            timestamp,
+            ..unread(email_id)
+        };
+        let ids = |msgs: &[MessageSummary]| -> Vec<String> {
+            msgs.iter().map(|m| m.email_id.clone()).collect()
+        };
+
+        // The first pass only sets the mark.
+        let (arrivals, mark) = arrivals_since(None, vec![at("E2", 200), at("E1", 100)]);
+        assert!(arrivals.is_empty());
+        assert_eq!(mark.received, 200);
+
+        // Old mail moved back in is not new; mail from the same second is.
+        let (arrivals, mark) = arrivals_since(
+            Some(&mark),
+            vec![at("E2", 200), at("E3", 200), at("E0", 50), at("E4", 300)],
+        );
+        assert_eq!(ids(&arrivals), vec!["E3", "E4"]);
+        assert_eq!(
+            mark,
+            RulesMark {
+                received: 300,
+                ids: ["E4".to_string()].into(),
+            }
+        );
+
+        let (arrivals, again) = arrivals_since(Some(&mark), vec![at("E4", 300)]);
+        assert!(arrivals.is_empty());
+        assert_eq!(again, mark);
+    }
+
+    #[test]
+    fn builder_needs_text_and_folders() {
+        assert_eq!(condition_from(0, "  "), None);
+        assert_eq!(
+            condition_from(2, " invoice "),
+            Some(RuleCondition::Subject("invoice".into()))
+        );
+        assert_eq!(condition_from(4, ""), Some(RuleCondition::HasAttachment));
+        assert_eq!(action_from(0, None), None);
+        assert_eq!(
+            action_from(1, Some(&"Receipts".to_string())),
+            Some(RuleAction::Label("Receipts".into()))
+        );
+        assert_eq!(action_from(4, None), Some(RuleAction::Notify));
+        assert_eq!(CONDITION_LABELS.len(), 5);
+        assert_eq!(ACTION_LABELS.len(), 5);
+    }
+}
@@ -0,0 +1,342 @@
+//! Saved searches: named cache queries shown as smart folders in the sidebar.
+//!
+//! Opening one runs the regular cache search. Unread counts are recomputed
+//! from the cache after a sync changes a mailbox the search covers, so the
+//! sidebar badge tracks new mail without re-running every search each sync.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+use cosmic::app::Task;
+use cosmic::widget;
+use cosmic::Element;
+
+use neverlight_mail_core::config::AccountId;
+use neverlight_mail_core::models::{Folder, MessageSummary};
+use neverlight_mail_core::store::LocalSearch;
+
+use super::query::SearchQuery;
+use super::{AppModel, Message, SavedSearch, SavedSearchPhase, SearchPhase};
+
+/// Accounts a saved search runs against. Account-scoped searches whose
+/// account has been removed resolve to nothing.
+fn saved_search_accounts(search: &SavedSearch, account_ids: &[AccountId]) -> Vec<AccountId> {
+    match &search.account_id {
+        Some(id) => account_ids.iter().filter(|a| *a == id).cloned().collect(),
+        None => account_ids.to_vec(),
+    }
+}
+
+/// Whether `search` can match mail in one of the `changed` mailboxes of
+/// `account_id`; `None` means every mailbox of the account changed.
+fn covers_changed(
+    search: &SavedSearch,
+    account_id: &str,
+    folders: &[Folder],
+    changed: Option<&HashSet<String>>,
+) -> bool {
+    if matches!(&search.account_id, Some(id) if id != account_id) {
+        return false;
+    }
+    let Some(changed) = changed else {
+        return true;
+    };
+    let scope = SearchQuery::parse(&search.query)
+        .ok()
+        .and_then(|q| q.mailbox_scope(folders));
+    match scope {
+        Some(ids) => ids.iter().any(|id| changed.contains(id)),
+        None => !changed.is_empty(),
+    }
+}
+
+fn unread_count(results: &[MessageSummary]) -> usize {
+    results.iter().filter(|m| !m.is_read).count()
+}
+
+/// Next free `ss-N` id.
+fn next_saved_search_id(existing: &[SavedSearch]) -> String {
+    let next = existing
+        .iter()
+        .filter_map(|s| s.id.strip_prefix("ss-")?.parse::<u64>().ok())
+        .max()
+        .map_or(1, |n| n + 1);
+    format!("ss-{next}")
+}
+
+impl AppModel {
+    pub(super) fn handle_saved_search(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::SavedSearchSaveOpen => {
+                let query = self.search_query.trim().to_string();
+                if query.is_empty() {
+                    return Task::none();
+                }
+                self.saved_search_phase = SavedSearchPhase::Naming {
+                    name: query.clone(),
+                    query,
+                    all_accounts: false,
+                };
+            }
+            Message::SavedSearchNameChanged(value) => {
+                if let SavedSearchPhase::Naming { name, .. } = &mut self.saved_search_phase {
+                    *name = value;
+                }
+            }
+            Message::SavedSearchAllAccountsToggled(value) => {
+                if let SavedSearchPhase::Naming { all_accounts, .. } = &mut self.saved_search_phase
+                {
+                    *all_accounts = value;
+                }
+            }
+            Message::SavedSearchSaveConfirm => {
+                return self.save_current_search();
+            }
+            Message::SavedSearchSaveCancel => {
+                self.saved_search_phase = SavedSearchPhase::Closed;
+            }
+            Message::SavedSearchOpen(id) => {
+                let Some(search) = self.settings.saved_searches.iter().find(|s| s.id == id) else {
+                    return Task::none();
+                };
+                let account_ids: Vec<AccountId> =
+                    self.accounts.iter().map(|a| a.config.id.clone()).collect();
+                let targets = saved_search_accounts(search, &account_ids);
+                let query = search.query.clone();
+                self.active_saved_search = Some(id);
+                self.search_phase = SearchPhase::Results;
+                self.search_query = query.clone();
+                return self.start_search(query, targets, None);
+            }
+            Message::SavedSearchDelete(id) => {
+                self.settings.saved_searches.retain(|s| s.id != id);
+                self.saved_search_counts.remove(&id);
+                let save = self.save_settings();
+                if self.active_saved_search.as_deref() == Some(id.as_str()) {
+                    return cosmic::task::batch(vec![save, self.dispatch(Message::SearchClear)]);
+                }
+                return save;
+            }
+            Message::SavedSearchCountsLoaded { epoch, counts } => {
+                if epoch != self.saved_search_epoch {
+                    self.stale_apply_drop_count = self.stale_apply_drop_count.saturating_add(1);
+                    return Task::none();
+                }
+                self.saved_search_recounting.clear();
+                self.saved_search_counts.extend(counts);
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+
+    fn save_current_search(&mut self) -> Task<Message> {
+        let SavedSearchPhase::Naming {
+            query,
+            name,
+            all_accounts,
+        } = &self.saved_search_phase
+        else {
+            return Task::none();
+        };
+        let name = name.trim();
+        let account_id = self.active_account_id();
+        let search = SavedSearch {
+            id: next_saved_search_id(&self.settings.saved_searches),
+            name: if name.is_empty() {
+                query.clone()
+            } else {
+                name.to_string()
+            },
+            query: query.clone(),
+            account_id: (!*all_accounts && !account_id.is_empty()).then_some(account_id),
+        };
+        self.active_saved_search = Some(search.id.clone());
+        self.saved_search_dirty.insert(search.id.clone());
+        self.settings.saved_searches.push(search);
+        self.saved_search_phase = SavedSearchPhase::Closed;
+        cosmic::task::batch(vec![
+            self.save_settings(),
+            self.refresh_saved_search_counts(),
+        ])
+    }
+
+    /// Mark the saved searches that cover a changed mailbox of `account_id`
+    /// for recounting; `None` marks every search on the account.
+    pub(super) fn mark_saved_searches_changed(
+        &mut self,
+        account_id: &str,
+        changed: Option<&HashSet<String>>,
+    ) {
+        let folders = self
+            .account_index(account_id)
+            .map(|i| self.accounts[i].folders.as_slice())
+            .unwrap_or_default();
+        let marked = self
+            .settings
+            .saved_searches
+            .iter()
+            .filter(|s| covers_changed(s, account_id, folders, changed))
+            .map(|s| s.id.clone());
+        self.saved_search_dirty.extend(marked);
+    }
+
+    /// Recount unread hits from the cache for the saved searches marked
+    /// changed. A newer recount supersedes an older one and takes over the
+    /// searches it had not finished.
+    pub(super) fn refresh_saved_search_counts(&mut self) -> Task<Message> {
+        if self.saved_search_dirty.is_empty() && self.saved_search_recounting.is_empty() {
+            return Task::none();
+        }
+        let Some(cache) = self.cache.clone() else {
+            return Task::none();
+        };
+        let mut ids = std::mem::take(&mut self.saved_search_dirty);
+        ids.extend(self.saved_search_recounting.drain());
+        self.saved_search_recounting = ids.clone();
+        let account_ids: Vec<AccountId> =
+            self.accounts.iter().map(|a| a.config.id.clone()).collect();
+        let now = Utc::now();
+        let mut jobs: Vec<(String, Vec<(AccountId, LocalSearch)>)> = Vec::new();
+        let marked = self
+            .settings
+            .saved_searches
+            .iter()
+            .filter(|s| ids.contains(&s.id));
+        for search in marked {
+            let targets = saved_search_accounts(search, &account_ids);
+            let compiled = SearchQuery::parse(&search.query)
+                .and_then(|parsed| self.compile_per_account(&targets, |f| parsed.to_local(f, now)));
+            match compiled {
+                Ok(per_account) => jobs.push((search.id.clone(), per_account)),
+                Err(e) => log::warn!("Saved search \"{}\" skipped: {}", search.name, e),
+            }
+        }
+        self.saved_search_epoch = self.saved_search_epoch.saturating_add(1);
+        let epoch = self.saved_search_epoch;
+        cosmic::task::future(async move {
+            let mut counts = HashMap::new();
+            for (id, per_account) in jobs {
+                let mut unread = 0;
+                for (account_id, local) in per_account {
+                    match cache.search_filtered(account_id, local).await {
+                        Ok(results) => unread += unread_count(&results),
+                        Err(e) => log::warn!("Saved search {} count failed: {}", id, e),
+                    }
+                }
+                counts.insert(id, unread);
+            }
+            Message::SavedSearchCountsLoaded { epoch, counts }
+        })
+    }
+
+    pub(super) fn saved_search_dialog(&self) -> Element<'_, Message> {
+        let SavedSearchPhase::Naming {
+            query,
+            name,
+            all_accounts,
+        } = &self.saved_search_phase
+        else {
+            return widget::text::body("").into();
+        };
+
+        let controls = widget::column()
+            .spacing(12)
+            .push(
+                widget::text_input("Name", name)
+                    .label("Name")
+                    .on_input(Message::SavedSearchNameChanged)
+                    .on_submit(|_| Message::SavedSearchSaveConfirm),
+            )
+            .push(
+                widget::checkbox("Search all accounts", *all_accounts)
+                    .on_toggle(Message::SavedSearchAllAccountsToggled),
+            );
+
+        widget::dialog()
+            .title("Save search")
+            .body(format!("Query: {query}"))
+            .control(controls)
+            .primary_action(
+                widget::button::suggested("Save").on_press(Message::SavedSearchSaveConfirm),
+            )
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Message::SavedSearchSaveCancel),
+            )
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_search(id: &str, account_id: Option<&str>) -> SavedSearch {
+        SavedSearch {
+            id: id.to_string(),
+            name: "Invoices".to_string(),
+            query: "invoice".to_string(),
+            account_id: account_id.map(str::to_string),
+        }
+    }
+
+    fn ids(list: &[&str]) -> Vec<AccountId> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn global_search_runs_on_every_account() {
+        let accounts = ids(&["a", "b"]);
+        assert_eq!(
+            saved_search_accounts(&sample_search("ss-1", None), &accounts),
+            accounts
+        );
+    }
+
+    #[test]
+    fn account_search_runs_on_its_account_only() {
+        let accounts = ids(&["a", "b"]);
+        assert_eq!(
+            saved_search_accounts(&sample_search("ss-1", Some("b")), &accounts),
+            ids(&["b"])
+        );
+    }
+
+    #[test]
+    fn account_search_for_removed_account_runs_nowhere() {
+        let accounts = ids(&["a"]);
+        assert!(saved_search_accounts(&sample_search("ss-1", Some("gone")), &accounts).is_empty());
+    }
+
+    #[test]
+    fn saved_search_ids_increment_past_existing() {
+        assert_eq!(next_saved_search_id(&[]), "ss-1");
+        let existing = vec![
+            sample_search("ss-2", None),
+            sample_search("ss-7", None),
+            sample_search("legacy", None),
+        ];
+        assert_eq!(next_saved_search_id(&existing), "ss-8");
+    }
+
+    #[test]
+    fn only_searches_covering_a_changed_mailbox_recount() {
+        use crate::app::test_support::sample_folder;
+        let folders = vec![
+            sample_folder("M-inbox", "INBOX", Some("inbox")),
+            sample_folder("M-archive", "Archive", Some("archive")),
+        ];
+        let inbox: HashSet<String> = ["M-inbox".to_string()].into_iter().collect();
+        let nothing = HashSet::new();
+        let anywhere = sample_search("ss-1", None);
+        let other_account = sample_search("ss-2", Some("b"));
+        let mut in_archive = sample_search("ss-3", None);
+        in_archive.query = "invoice in:archive".to_string();
+
+        assert!(covers_changed(&anywhere, "a", &folders, Some(&inbox)));
+        assert!(!covers_changed(&anywhere, "a", &folders, Some(&nothing)));
+        assert!(!covers_changed(&in_archive, "a", &folders, Some(&inbox)));
+        assert!(covers_changed(&in_archive, "a", &folders, None));
+        assert!(!covers_changed(&other_account, "a", &folders, None));
+    }
+}
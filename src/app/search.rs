@@ -1,8 +1,22 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
 use cosmic::app::Task;
 use cosmic::widget;
-use futures::future::{AbortHandle, Abortable};
+use futures::future::{AbortHandle, Abortable, BoxFuture};
+use futures::FutureExt;
+
+use neverlight_mail_core::config::AccountId;
+use neverlight_mail_core::models::{Folder, MessageSummary};
+use neverlight_mail_core::store::DEFAULT_PAGE_SIZE;
+
+use super::highlight::{match_positions, plain_snippet, select_match, step_match};
+use super::query::{QueryError, SearchQuery};
+use super::unified::merge_newest_first;
+use super::{AppModel, ComposePhase, Message, Phase, SearchPhase, SearchScope};
 
-use super::{AppModel, ComposePhase, Message, Phase, SearchPhase};
+/// Cap on hits fetched per account by "Search server".
+const SERVER_SEARCH_LIMIT: u32 = 100;
 
 fn should_apply_search_results(
     current_epoch: u64,
@@ -13,7 +27,510 @@ fn should_apply_search_results(
     current_epoch == incoming_epoch && current_query.trim() == incoming_query
 }
 
+/// Accounts and, for folder scope, the mailbox a search runs in. Folder
+/// scope widens to the account when no folder is selected.
+fn scope_targets(
+    scope: SearchScope,
+    active_account: AccountId,
+    all_accounts: &[AccountId],
+    selected_mailbox: Option<&str>,
+) -> (Vec<AccountId>, Option<String>) {
+    match (scope, selected_mailbox) {
+        (SearchScope::AllAccounts, _) => (all_accounts.to_vec(), None),
+        (SearchScope::Folder, Some(mailbox_id)) => {
+            (vec![active_account], Some(mailbox_id.to_string()))
+        }
+        (SearchScope::Folder | SearchScope::Account, _) => (vec![active_account], None),
+    }
+}
+
+fn scoped_query(query: &str, mailbox_id: Option<&str>) -> Result<SearchQuery, QueryError> {
+    let parsed = SearchQuery::parse(query)?;
+    Ok(match mailbox_id {
+        Some(id) => parsed.within_mailbox(id),
+        None => parsed,
+    })
+}
+
+/// Hit count per searched account, in account order.
+fn results_per_account(
+    results: &[MessageSummary],
+    accounts: &[(AccountId, String)],
+) -> Vec<(String, usize)> {
+    accounts
+        .iter()
+        .map(|(id, label)| {
+            let count = results.iter().filter(|m| &m.account_id == id).count();
+            (label.clone(), count)
+        })
+        .collect()
+}
+
+/// Merge server hits into the local results. A message already found
+/// locally keeps its cached row; the returned set holds the rows that only
+/// the server knew about.
+fn merge_server_hits(
+    local: Vec<MessageSummary>,
+    server: Vec<MessageSummary>,
+) -> (Vec<MessageSummary>, HashSet<(AccountId, String)>) {
+    let known: HashSet<(AccountId, String)> = local
+        .iter()
+        .map(|m| (m.account_id.clone(), m.email_id.clone()))
+        .collect();
+    let new_hits: HashSet<(AccountId, String)> = server
+        .iter()
+        .map(|m| (m.account_id.clone(), m.email_id.clone()))
+        .filter(|key| !known.contains(key))
+        .collect();
+    (
+        merge_newest_first(local.into_iter().chain(server)),
+        new_hits,
+    )
+}
+
+/// Fold per-account search outcomes. A failing account is logged and
+/// skipped so the others' hits still show; it is an error only when every
+/// account failed. Returns the hit batches and the skipped accounts.
+fn gather_account_hits(
+    outcomes: Vec<(AccountId, Result<Vec<MessageSummary>, String>)>,
+) -> (Result<Vec<Vec<MessageSummary>>, String>, Vec<AccountId>) {
+    let mut batches = Vec::with_capacity(outcomes.len());
+    let mut skipped = Vec::new();
+    let mut last_err = None;
+    for (account_id, outcome) in outcomes {
+        match outcome {
+            Ok(hits) => batches.push(hits),
+            Err(e) => {
+                log::warn!("Search failed for {}: {}", account_id, e);
+                skipped.push(account_id);
+                last_err = Some(e);
+            }
+        }
+    }
+    match last_err {
+        Some(e) if batches.is_empty() => (Err(e), skipped),
+        _ => (Ok(batches), skipped),
+    }
+}
+
 impl AppModel {
+    /// Compile a parsed query against each account's folders. Accounts the
+    /// query can't apply to (no matching `in:` folder) are skipped; it is an
+    /// error only when none remain.
+    pub(super) fn compile_per_account<T>(
+        &self,
+        account_ids: &[AccountId],
+        compile: impl Fn(&[Folder]) -> Result<T, QueryError>,
+    ) -> Result<Vec<(AccountId, T)>, QueryError> {
+        let mut compiled = Vec::with_capacity(account_ids.len());
+        let mut last_err = None;
+        for account_id in account_ids {
+            let folders = self
+                .account_index(account_id)
+                .map(|i| self.accounts[i].folders.as_slice())
+                .unwrap_or_default();
+            match compile(folders) {
+                Ok(t) => compiled.push((account_id.clone(), t)),
+                Err(e) => {
+                    log::debug!("Query skipped for {}: {}", account_id, e);
+                    last_err = Some(e);
+                }
+            }
+        }
+        match last_err {
+            Some(e) if compiled.is_empty() => Err(e),
+            _ => Ok(compiled),
+        }
+    }
+
+    /// Per-account hit counts for the search bar; empty for single-account
+    /// searches, where the status line already says it.
+    fn count_results_per_account(&self) -> Vec<(String, usize)> {
+        if self.search_accounts.len() < 2 {
+            return Vec::new();
+        }
+        let labels: Vec<(AccountId, String)> = self
+            .search_accounts
+            .iter()
+            .filter_map(|id| {
+                let idx = self.account_index(id)?;
+                Some((id.clone(), self.accounts[idx].config.label.clone()))
+            })
+            .collect();
+        results_per_account(&self.messages, &labels)
+    }
+
+    /// Status suffix naming the accounts a search had to skip.
+    fn skipped_accounts_note(&self, skipped: &[AccountId]) -> String {
+        if skipped.is_empty() {
+            return String::new();
+        }
+        let labels: Vec<&str> = skipped
+            .iter()
+            .map(|id| match self.account_index(id) {
+                Some(i) => self.accounts[i].config.label.as_str(),
+                None => id.as_str(),
+            })
+            .collect();
+        format!("; {} not searched", labels.join(", "))
+    }
+
+    /// Accounts in `jobs` without a live client, logged as skipped.
+    fn offline_accounts<T>(&self, jobs: &[(AccountId, T)]) -> Vec<AccountId> {
+        jobs.iter()
+            .filter(|(aid, _)| self.client_for_account(aid).is_none())
+            .map(|(aid, _)| {
+                log::info!("Search skipped for {}: not connected", aid);
+                aid.clone()
+            })
+            .collect()
+    }
+
+    /// Run `query` for each account (optionally within one mailbox):
+    /// against the cache when there is one, otherwise as a JMAP
+    /// `Email/query`. Hits from more than one account are merged newest first.
+    pub(super) fn start_search(
+        &mut self,
+        query: String,
+        account_ids: Vec<AccountId>,
+        mailbox_id: Option<String>,
+    ) -> Task<Message> {
+        let parsed = match scoped_query(&query, mailbox_id.as_deref()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                self.set_status_error(format!("Search: {e}"));
+                return Task::none();
+            }
+        };
+        let now = Utc::now();
+        let mut offline = Vec::new();
+        let search: BoxFuture<'static, Vec<(AccountId, Result<Vec<MessageSummary>, String>)>> =
+            if let Some(cache) = self.cache.clone() {
+                let jobs = match self.compile_per_account(&account_ids, |f| parsed.to_local(f, now))
+                {
+                    Ok(jobs) => jobs,
+                    Err(e) => {
+                        self.set_status_error(format!("Search: {e}"));
+                        return Task::none();
+                    }
+                };
+                async move {
+                    let mut outcomes = Vec::with_capacity(jobs.len());
+                    for (account_id, local) in jobs {
+                        let found = cache.search_filtered(account_id.clone(), local).await;
+                        outcomes.push((account_id, found));
+                    }
+                    outcomes
+                }
+                .boxed()
+            } else {
+                let jobs = match self
+                    .compile_per_account(&account_ids, |f| parsed.to_jmap_filter(f, now))
+                {
+                    Ok(jobs) => jobs,
+                    Err(e) => {
+                        self.set_status_error(format!("Search: {e}"));
+                        return Task::none();
+                    }
+                };
+                offline = self.offline_accounts(&jobs);
+                let jobs: Vec<_> = jobs
+                    .into_iter()
+                    .filter_map(|(aid, filter)| Some((self.client_for_account(&aid)?, aid, filter)))
+                    .collect();
+                async move {
+                    let mut outcomes = Vec::with_capacity(jobs.len());
+                    for (client, account_id, filter) in jobs {
+                        let found = neverlight_mail_core::email::query_filter(
+                            &client,
+                            &filter,
+                            DEFAULT_PAGE_SIZE,
+                        )
+                        .await
+                        .map_err(|e| e.to_string());
+                        outcomes.push((account_id, found));
+                    }
+                    outcomes
+                }
+                .boxed()
+            };
+
+        self.search_accounts = account_ids;
+        self.search_mailbox = mailbox_id;
+        self.search_account_counts.clear();
+        self.search_terms = parsed.highlight_terms();
+        self.search_snippets.clear();
+        self.server_hits.clear();
+        self.search_epoch = self.search_epoch.saturating_add(1);
+        let epoch = self.search_epoch;
+        self.status_message = "Searching...".into();
+        self.phase = Phase::Searching;
+        if let Some(handle) = self.search_abort.take() {
+            handle.abort();
+        }
+        let (abort_handle, abort_reg) = AbortHandle::new_pair();
+        self.search_abort = Some(abort_handle);
+        cosmic::task::future(async move {
+            match Abortable::new(search, abort_reg).await {
+                Ok(outcomes) => {
+                    let (result, mut skipped) = gather_account_hits(outcomes);
+                    skipped.extend(offline);
+                    Message::SearchResultsLoaded {
+                        query,
+                        epoch,
+                        result: result.map(|mut batches| {
+                            if batches.len() == 1 {
+                                batches.remove(0)
+                            } else {
+                                merge_newest_first(batches.into_iter().flatten())
+                            }
+                        }),
+                        skipped,
+                    }
+                }
+                Err(_) => Message::Noop,
+            }
+        })
+    }
+
+    /// Run the listed search as JMAP `Email/query` on every searched account
+    /// that is online. Hits are written to the cache so they open offline.
+    fn start_server_search(&mut self) -> Task<Message> {
+        if self.search_phase != SearchPhase::Results || self.phase == Phase::Searching {
+            return Task::none();
+        }
+        let query = self.search_query.trim().to_string();
+        let parsed = match scoped_query(&query, self.search_mailbox.as_deref()) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                self.set_status_error(format!("Search: {e}"));
+                return Task::none();
+            }
+        };
+        let now = Utc::now();
+        let jobs = match self
+            .compile_per_account(&self.search_accounts, |f| parsed.to_jmap_filter(f, now))
+        {
+            Ok(jobs) => jobs,
+            Err(e) => {
+                self.set_status_error(format!("Search: {e}"));
+                return Task::none();
+            }
+        };
+        let offline = self.offline_accounts(&jobs);
+        let jobs: Vec<_> = jobs
+            .into_iter()
+            .filter_map(|(aid, filter)| Some((aid.clone(), self.client_for_account(&aid)?, filter)))
+            .collect();
+        if jobs.is_empty() {
+            self.set_status_error("Search server: no connected account".into());
+            return Task::none();
+        }
+        let cache = self.cache.clone();
+
+        self.search_epoch = self.search_epoch.saturating_add(1);
+        let epoch = self.search_epoch;
+        self.status_message = "Searching server...".into();
+        self.phase = Phase::Searching;
+        if let Some(handle) = self.search_abort.take() {
+            handle.abort();
+        }
+        let (abort_handle, abort_reg) = AbortHandle::new_pair();
+        self.search_abort = Some(abort_handle);
+        cosmic::task::future(async move {
+            let search = async {
+                let mut outcomes = Vec::with_capacity(jobs.len());
+                for (account_id, client, filter) in jobs {
+                    let found = neverlight_mail_core::email::query_filter(
+                        &client,
+                        &filter,
+                        SERVER_SEARCH_LIMIT,
+                    )
+                    .await
+                    .map_err(|e| e.to_string());
+                    if let (Some(cache), Ok(found)) = (&cache, &found) {
+                        // Not fatal: the hits still show, they just won't open offline.
+                        if let Err(e) = cache
+                            .upsert_messages(account_id.clone(), found.clone())
+                            .await
+                        {
+                            log::warn!("Failed to cache server hits for {}: {}", account_id, e);
+                        }
+                    }
+                    outcomes.push((account_id, found));
+                }
+                outcomes
+            };
+            match Abortable::new(search, abort_reg).await {
+                Ok(outcomes) => {
+                    let (result, mut skipped) = gather_account_hits(outcomes);
+                    skipped.extend(offline);
+                    Message::ServerSearchLoaded {
+                        query,
+                        epoch,
+                        result: result.map(|batches| batches.into_iter().flatten().collect()),
+                        skipped,
+                    }
+                }
+                Err(_) => Message::Noop,
+            }
+        })
+    }
+
+    /// Fetch match snippets for result rows: FTS5 `snippet()` from the
+    /// cache, or JMAP `SearchSnippet/get` for rows only the server has.
+    fn load_snippets(
+        &self,
+        query: String,
+        hits: &[(AccountId, String)],
+        from_server: bool,
+    ) -> Task<Message> {
+        let Ok(parsed) = scoped_query(&query, self.search_mailbox.as_deref()) else {
+            return Task::none();
+        };
+        let mut by_account: HashMap<AccountId, Vec<String>> = HashMap::new();
+        for (account_id, email_id) in hits {
+            by_account
+                .entry(account_id.clone())
+                .or_default()
+                .push(email_id.clone());
+        }
+        let accounts: Vec<AccountId> = by_account.keys().cloned().collect();
+        let now = Utc::now();
+        let fetch: BoxFuture<'static, HashMap<(AccountId, String), String>> = if from_server {
+            // Accounts that fail to compile or are offline go without snippets.
+            let jobs = self
+                .compile_per_account(&accounts, |f| parsed.to_jmap_filter(f, now))
+                .unwrap_or_default();
+            let jobs: Vec<_> = jobs
+                .into_iter()
+                .filter_map(|(aid, filter)| {
+                    let ids = by_account.remove(&aid)?;
+                    let Some(client) = self.client_for_account(&aid) else {
+                        log::info!("Snippets skipped for {}: not connected", aid);
+                        return None;
+                    };
+                    Some((aid, client, filter, ids))
+                })
+                .collect();
+            async move {
+                let mut snippets = HashMap::new();
+                for (aid, client, filter, ids) in jobs {
+                    match neverlight_mail_core::email::search_snippets(&client, &filter, &ids).await
+                    {
+                        Ok(found) => snippets.extend(
+                            found
+                                .into_iter()
+                                .map(|(eid, html)| ((aid.clone(), eid), plain_snippet(&html))),
+                        ),
+                        Err(e) => log::warn!("SearchSnippet/get failed for {}: {}", aid, e),
+                    }
+                }
+                snippets
+            }
+            .boxed()
+        } else {
+            let Some(cache) = self.cache.clone() else {
+                return Task::none();
+            };
+            let jobs = self
+                .compile_per_account(&accounts, |f| parsed.to_local(f, now))
+                .unwrap_or_default();
+            let jobs: Vec<_> = jobs
+                .into_iter()
+                .filter_map(|(aid, local)| {
+                    let ids = by_account.remove(&aid)?;
+                    Some((aid, local, ids))
+                })
+                .collect();
+            async move {
+                let mut snippets = HashMap::new();
+                for (aid, local, ids) in jobs {
+                    match cache.search_snippets(aid.clone(), local, ids).await {
+                        Ok(found) => snippets.extend(
+                            found
+                                .into_iter()
+                                .map(|(eid, html)| ((aid.clone(), eid), plain_snippet(&html))),
+                        ),
+                        Err(e) => log::warn!("Snippet lookup failed for {}: {}", aid, e),
+                    }
+                }
+                snippets
+            }
+            .boxed()
+        };
+        cosmic::task::future(async move {
+            Message::SearchSnippetsLoaded {
+                query,
+                snippets: fetch.await,
+            }
+        })
+    }
+
+    /// Step to the next/previous match in the previewed body (the active
+    /// entry in conversation mode). Selectable views select the match; the
+    /// single-message view also scrolls to it.
+    fn jump_to_match(&mut self, forward: bool) -> Task<Message> {
+        if !self.search_phase.is_active() || self.search_terms.is_empty() {
+            return Task::none();
+        }
+        let entry_idx = self
+            .active_conversation_id
+            .as_ref()
+            .and_then(|id| self.conversation.iter().position(|e| &e.email_id == id));
+        let text = match entry_idx {
+            Some(i) => self.conversation[i].plain_body.as_str(),
+            None => self.preview_body.as_str(),
+        };
+        let matches = match_positions(text, &self.search_terms);
+        let last_line = text.lines().count().saturating_sub(1).max(1);
+        if matches.is_empty() {
+            self.status_message = "No matches in this message".into();
+            return Task::none();
+        }
+        let next = step_match(self.search_match, matches.len(), forward);
+        let target = matches[next];
+        self.search_match = Some(next);
+        self.status_message = format!("Match {} of {}", next + 1, matches.len());
+        if self.preview_selectable {
+            let editor = match entry_idx {
+                Some(i) => self.conversation_editors.get_mut(i),
+                None => Some(&mut self.preview_editor),
+            };
+            if let Some(editor) = editor {
+                select_match(editor, target);
+            }
+        }
+        if entry_idx.is_some() {
+            return Task::none();
+        }
+        cosmic::iced::widget::scrollable::snap_to(
+            crate::ui::message_view::preview_scroll_id(),
+            cosmic::iced::widget::scrollable::RelativeOffset {
+                x: 0.0,
+                y: (target.line as f32 / last_line as f32).min(1.0),
+            },
+        )
+    }
+
+    /// Run the search bar query in the remembered scope.
+    fn execute_scoped_search(&mut self) -> Task<Message> {
+        let query = self.search_query.trim().to_string();
+        if query.is_empty() {
+            return Task::none();
+        }
+        self.active_saved_search = None;
+        let all_accounts: Vec<AccountId> =
+            self.accounts.iter().map(|a| a.config.id.clone()).collect();
+        let (account_ids, mailbox_id) = scope_targets(
+            self.settings.search_scope,
+            self.active_account_id(),
+            &all_accounts,
+            self.selected_mailbox_id.as_deref(),
+        );
+        self.start_search(query, account_ids, mailbox_id)
+    }
+
     pub(super) fn handle_search(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::SearchActivate => {
@@ -28,38 +545,21 @@ impl AppModel {
                 self.search_query = q;
             }
             Message::SearchExecute => {
-                let query = self.search_query.trim().to_string();
-                if query.is_empty() {
-                    return Task::none();
-                }
-                if let Some(cache) = &self.cache {
-                    let cache = cache.clone();
-                    let account_id = self.active_account_id();
-                    self.search_epoch = self.search_epoch.saturating_add(1);
-                    let epoch = self.search_epoch;
-                    self.status_message = "Searching...".into();
-                    self.phase = Phase::Searching;
-                    if let Some(handle) = self.search_abort.take() {
-                        handle.abort();
-                    }
-                    let (abort_handle, abort_reg) = AbortHandle::new_pair();
-                    self.search_abort = Some(abort_handle);
-                    return cosmic::task::future(async move {
-                        match Abortable::new(cache.search(account_id, query.clone()), abort_reg).await {
-                            Ok(result) => Message::SearchResultsLoaded {
-                                query,
-                                epoch,
-                                result,
-                            },
-                            Err(_) => Message::Noop,
-                        }
-                    });
+                return self.execute_scoped_search();
+            }
+            Message::SearchScopeChanged(scope) => {
+                self.settings.search_scope = scope;
+                let save = self.save_settings();
+                if self.search_phase == SearchPhase::Results {
+                    return cosmic::task::batch(vec![save, self.execute_scoped_search()]);
                 }
+                return save;
             }
             Message::SearchResultsLoaded {
                 query,
                 epoch,
                 result: Ok(results),
+                skipped,
             } => {
                 if !should_apply_search_results(
                     self.search_epoch,
@@ -72,24 +572,34 @@ impl AppModel {
                 }
                 self.search_abort = None;
                 let count = results.len();
+                let hits: Vec<(AccountId, String)> = results
+                    .iter()
+                    .map(|m| (m.account_id.clone(), m.email_id.clone()))
+                    .collect();
                 self.messages = results;
                 self.selected_message = None;
                 self.preview_body.clear();
-                self.preview_markdown.clear();
+                self.preview_blocks.clear();
                 self.preview_editor = cosmic::widget::text_editor::Content::new();
                 self.preview_attachments.clear();
                 self.preview_image_handles.clear();
                 self.collapsed_threads.clear();
+                self.messages_offset = 0;
+                self.pending_messages_offset = 0;
                 self.has_more_messages = false;
                 self.recompute_visible();
+                self.search_account_counts = self.count_results_per_account();
                 self.search_phase = SearchPhase::Results;
                 if count > 0 {
                     self.status_message = format!("Search: {} results for \"{}\"", count, query);
                 } else {
                     self.status_message = format!("Search: no results for \"{}\"", query);
                 }
+                let note = self.skipped_accounts_note(&skipped);
+                self.status_message.push_str(&note);
                 self.clear_error_surface();
                 self.phase = Phase::Idle;
+                return self.load_snippets(query, &hits, self.cache.is_none());
             }
             Message::SearchResultsLoaded {
                 epoch,
@@ -105,6 +615,79 @@ impl AppModel {
                 log::error!("Search failed: {}", e);
                 self.set_status_error(format!("Search failed: {}", e));
             }
+            Message::SearchServer => {
+                return self.start_server_search();
+            }
+            Message::ServerSearchLoaded {
+                query,
+                epoch,
+                result: Ok(hits),
+                skipped,
+            } => {
+                if !should_apply_search_results(
+                    self.search_epoch,
+                    epoch,
+                    self.search_query.as_str(),
+                    query.as_str(),
+                ) {
+                    self.stale_apply_drop_count = self.stale_apply_drop_count.saturating_add(1);
+                    return Task::none();
+                }
+                self.search_abort = None;
+                let prev = self.selected_message.and_then(|i| {
+                    self.messages
+                        .get(i)
+                        .map(|m| (m.account_id.clone(), m.email_id.clone()))
+                });
+                let (merged, new_hits) =
+                    merge_server_hits(std::mem::take(&mut self.messages), hits);
+                self.messages = merged;
+                self.selected_message = prev.and_then(|(aid, eid)| {
+                    self.messages
+                        .iter()
+                        .position(|m| m.account_id == aid && m.email_id == eid)
+                });
+                self.recompute_visible();
+                self.search_account_counts = self.count_results_per_account();
+                self.status_message = format!(
+                    "Search: {} results for \"{}\" ({} from server{})",
+                    self.messages.len(),
+                    query,
+                    new_hits.len(),
+                    self.skipped_accounts_note(&skipped)
+                );
+                let new_hits_list: Vec<(AccountId, String)> = new_hits.iter().cloned().collect();
+                self.server_hits = new_hits;
+                self.clear_error_surface();
+                self.phase = Phase::Idle;
+                return self.load_snippets(query, &new_hits_list, true);
+            }
+            Message::ServerSearchLoaded {
+                epoch,
+                result: Err(e),
+                ..
+            } => {
+                if epoch != self.search_epoch {
+                    self.stale_apply_drop_count = self.stale_apply_drop_count.saturating_add(1);
+                    return Task::none();
+                }
+                self.search_abort = None;
+                log::error!("Server search failed: {}", e);
+                self.set_status_error(format!("Server search failed: {}", e));
+            }
+            Message::SearchSnippetsLoaded { query, snippets } => {
+                if !self.search_phase.is_active() || self.search_query.trim() != query {
+                    self.stale_apply_drop_count = self.stale_apply_drop_count.saturating_add(1);
+                    return Task::none();
+                }
+                self.search_snippets.extend(snippets);
+            }
+            Message::SearchMatchNext => {
+                return self.jump_to_match(true);
+            }
+            Message::SearchMatchPrev => {
+                return self.jump_to_match(false);
+            }
             Message::SearchClear => {
                 if self.search_phase.is_active() {
                     if let Some(handle) = self.search_abort.take() {
@@ -112,7 +695,19 @@ impl AppModel {
                     }
                     self.search_phase = SearchPhase::Inactive;
                     self.search_query.clear();
+                    self.search_accounts.clear();
+                    self.search_mailbox = None;
+                    self.search_account_counts.clear();
+                    self.server_hits.clear();
+                    self.search_terms.clear();
+                    self.search_snippets.clear();
+                    self.search_match = None;
+                    self.active_saved_search = None;
                     // Restore previous folder view
+                    if let Some(view) = self.virtual_view {
+                        self.phase = Phase::Loading;
+                        return self.dispatch(Message::SelectVirtualView(view));
+                    }
                     if let Some(acct_idx) = self.active_account {
                         if let Some(folder_idx) = self.selected_folder {
                             self.phase = Phase::Loading;
@@ -133,7 +728,12 @@ impl AppModel {
 
 #[cfg(test)]
 mod tests {
-    use super::should_apply_search_results;
+    use super::{
+        gather_account_hits, merge_server_hits, results_per_account, scope_targets,
+        should_apply_search_results, SearchScope,
+    };
+    use crate::app::test_support::sample_summary;
+    use neverlight_mail_core::models::MessageSummary;
 
     #[test]
     fn search_results_apply_when_epoch_and_query_match() {
@@ -150,4 +750,115 @@ mod tests {
     fn search_results_drop_on_query_mismatch() {
         assert!(!should_apply_search_results(8, 8, "inbox", "sent"));
     }
+
+    #[test]
+    fn failing_account_is_skipped_not_fatal() {
+        let (result, skipped) = gather_account_hits(vec![
+            ("a".to_string(), Ok(vec![sample_summary("a", "1")])),
+            ("b".to_string(), Err("offline".to_string())),
+        ]);
+        let batches = result.expect("one account answered");
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0][0].email_id, "1");
+        assert_eq!(skipped, vec!["b".to_string()]);
+    }
+
+    #[test]
+    fn search_fails_only_when_every_account_fails() {
+        let (result, skipped) = gather_account_hits(vec![
+            ("a".to_string(), Err("timeout".to_string())),
+            ("b".to_string(), Err("offline".to_string())),
+        ]);
+        assert!(matches!(result, Err(e) if e == "offline"));
+        assert_eq!(skipped.len(), 2);
+    }
+
+    #[test]
+    fn server_hits_merge_newest_first_and_are_marked() {
+        let local = vec![MessageSummary {
+            timestamp: 20,
+            ..sample_summary("a", "1")
+        }];
+        let server = vec![
+            MessageSummary {
+                timestamp: 30,
+                ..sample_summary("a", "2")
+            },
+            MessageSummary {
+                timestamp: 10,
+                ..sample_summary("b", "3")
+            },
+        ];
+        let (merged, new_hits) = merge_server_hits(local, server);
+        let ids: Vec<_> = merged.iter().map(|m| m.email_id.as_str()).collect();
+        assert_eq!(ids, vec!["2", "1", "3"]);
+        assert_eq!(new_hits.len(), 2);
+        assert!(new_hits.contains(&("a".to_string(), "2".to_string())));
+        assert!(!new_hits.contains(&("a".to_string(), "1".to_string())));
+    }
+
+    #[test]
+    fn server_hit_already_cached_keeps_local_row() {
+        let local = vec![MessageSummary {
+            is_read: false,
+            ..sample_summary("a", "1")
+        }];
+        let server = vec![sample_summary("a", "1")];
+        let (merged, new_hits) = merge_server_hits(local, server);
+        assert_eq!(merged.len(), 1);
+        assert!(!merged[0].is_read);
+        assert!(new_hits.is_empty());
+    }
+
+    fn ids(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn folder_scope_searches_selected_mailbox() {
+        let all = ids(&["a", "b"]);
+        assert_eq!(
+            scope_targets(SearchScope::Folder, "a".into(), &all, Some("M1")),
+            (ids(&["a"]), Some("M1".to_string()))
+        );
+    }
+
+    #[test]
+    fn folder_scope_without_folder_widens_to_account() {
+        let all = ids(&["a", "b"]);
+        assert_eq!(
+            scope_targets(SearchScope::Folder, "b".into(), &all, None),
+            (ids(&["b"]), None)
+        );
+        assert_eq!(
+            scope_targets(SearchScope::Account, "b".into(), &all, Some("M1")),
+            (ids(&["b"]), None)
+        );
+    }
+
+    #[test]
+    fn all_accounts_scope_ignores_folder() {
+        let all = ids(&["a", "b"]);
+        assert_eq!(
+            scope_targets(SearchScope::AllAccounts, "a".into(), &all, Some("M1")),
+            (all.clone(), None)
+        );
+    }
+
+    #[test]
+    fn per_account_counts_include_empty_accounts() {
+        let results = vec![
+            sample_summary("a", "1"),
+            sample_summary("a", "2"),
+            sample_summary("c", "3"),
+        ];
+        let accounts = vec![
+            ("a".to_string(), "Work".to_string()),
+            ("b".to_string(), "Home".to_string()),
+        ];
+        assert_eq!(
+            results_per_account(&results, &accounts),
+            vec![("Work".to_string(), 2), ("Home".to_string(), 0)]
+        );
+    }
 }
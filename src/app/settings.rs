@@ -0,0 +1,466 @@
+//! App-level preferences persisted as JSON.
+//!
+//! Account credentials and layout live in neverlight-mail-core's config; GUI-only
+//! state is kept in its own file next to it,
+//! `~/.config/neverlight-mail/app-settings.json`, so neither side can drop the
+//! other's keys. Unknown or missing fields fall back to defaults so older files
+//! keep loading.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use neverlight_mail_core::config::AccountId;
+
+const CONFIG_DIR: &str = "neverlight-mail";
+const SETTINGS_FILE: &str = "app-settings.json";
+
+/// A named cache query shown as a smart folder in the sidebar.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub id: String,
+    pub name: String,
+    pub query: String,
+    /// `None` searches every account.
+    pub account_id: Option<AccountId>,
+}
+
+/// A local mail rule, run on new arrivals and on demand over a folder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MailRule {
+    pub name: String,
+    pub account_id: AccountId,
+    pub enabled: bool,
+    /// All must match; a rule without conditions never runs.
+    pub conditions: Vec<RuleCondition>,
+    pub actions: Vec<RuleAction>,
+}
+
+/// Text conditions are case-insensitive substring matches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum RuleCondition {
+    Sender(String),
+    Recipient(String),
+    Subject(String),
+    ListId(String),
+    HasAttachment,
+}
+
+/// Folders are named by path, which survives mailbox re-creation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "folder", rename_all = "snake_case")]
+pub enum RuleAction {
+    Move(String),
+    /// File into another folder as well, keeping the message where it is.
+    Label(String),
+    MarkRead,
+    Star,
+    /// Desktop notification.
+    Notify,
+}
+
+/// How far the local rules have read an account's inbox.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RulesMark {
+    /// Received time (unix seconds) of the newest message seen.
+    pub received: i64,
+    /// Messages seen that were received in that same second.
+    pub ids: BTreeSet<String>,
+}
+
+/// Where the search bar looks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchScope {
+    /// The selected folder; widens to the account when none is selected.
+    Folder,
+    #[default]
+    Account,
+    AllAccounts,
+}
+
+impl SearchScope {
+    pub const ALL: [SearchScope; 3] = [Self::Folder, Self::Account, Self::AllAccounts];
+    pub const LABELS: &'static [&'static str] = &["This folder", "This account", "All accounts"];
+
+    pub fn index(self) -> usize {
+        match self {
+            Self::Folder => 0,
+            Self::Account => 1,
+            Self::AllAccounts => 2,
+        }
+    }
+}
+
+/// Message list order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListSort {
+    #[default]
+    DateDesc,
+    DateAsc,
+    Sender,
+    Subject,
+    /// Largest first.
+    Size,
+    /// Unread, then newest first.
+    UnreadFirst,
+}
+
+impl ListSort {
+    pub const ALL: [ListSort; 6] = [
+        Self::DateDesc,
+        Self::DateAsc,
+        Self::Sender,
+        Self::Subject,
+        Self::Size,
+        Self::UnreadFirst,
+    ];
+    pub const LABELS: &'static [&'static str] = &[
+        "Newest first",
+        "Oldest first",
+        "Sender",
+        "Subject",
+        "Largest first",
+        "Unread first",
+    ];
+
+    pub fn index(self) -> usize {
+        match self {
+            Self::DateDesc => 0,
+            Self::DateAsc => 1,
+            Self::Sender => 2,
+            Self::Subject => 3,
+            Self::Size => 4,
+            Self::UnreadFirst => 5,
+        }
+    }
+}
+
+/// Message list quick filter toggle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuickFilter {
+    Unread,
+    Starred,
+    HasAttachment,
+    /// Addressed to one of the account's addresses.
+    ToMe,
+}
+
+impl QuickFilter {
+    pub const ALL: [QuickFilter; 4] =
+        [Self::Unread, Self::Starred, Self::HasAttachment, Self::ToMe];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Unread => "Unread",
+            Self::Starred => "Starred",
+            Self::HasAttachment => "Attachment",
+            Self::ToMe => "To me",
+        }
+    }
+}
+
+/// Sort and filter preference for one folder.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FolderView {
+    pub sort: ListSort,
+    pub filters: BTreeSet<QuickFilter>,
+}
+
+impl FolderView {
+    /// Cache order with threads grouped; anything else lists flat rows.
+    pub fn is_default(&self) -> bool {
+        self.sort == ListSort::default() && self.filters.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    pub saved_searches: Vec<SavedSearch>,
+    /// Last scope picked in the search bar.
+    pub search_scope: SearchScope,
+    /// Show body preview lines in the message list.
+    pub show_previews: bool,
+    /// Per-folder list preferences, keyed by account then mailbox id.
+    /// Folders on the default view have no entry.
+    pub folder_views: HashMap<AccountId, HashMap<String, FolderView>>,
+    /// Lowercased sender addresses whose remote images load without asking.
+    pub remote_image_senders: BTreeSet<String>,
+    /// Unwrap redirect wrappers and drop tracking parameters from links
+    /// before opening them.
+    pub clean_links: bool,
+    /// Never prompt about read receipt requests.
+    pub ignore_receipt_requests: bool,
+    pub mail_rules: Vec<MailRule>,
+    /// Per account; inbox mail received after the mark is new to the rules.
+    pub rules_marks: HashMap<AccountId, RulesMark>,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            saved_searches: Vec::new(),
+            search_scope: SearchScope::default(),
+            show_previews: true,
+            folder_views: HashMap::new(),
+            remote_image_senders: BTreeSet::new(),
+            clean_links: true,
+            ignore_receipt_requests: false,
+            mail_rules: Vec::new(),
+            rules_marks: HashMap::new(),
+        }
+    }
+}
+
+impl AppSettings {
+    fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join(CONFIG_DIR).join(SETTINGS_FILE))
+    }
+
+    /// Load settings, falling back to defaults if the file is missing or unreadable.
+    pub fn load() -> Self {
+        Self::path().map_or_else(Self::default, |path| Self::read_from(&path))
+    }
+
+    fn read_from(path: &Path) -> Self {
+        let Ok(data) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&data).unwrap_or_else(|e| {
+            log::warn!(
+                "Ignoring unreadable app settings in {}: {}",
+                path.display(),
+                e
+            );
+            Self::default()
+        })
+    }
+
+    /// Serialized settings, taken on the update loop for a later `write`.
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+
+    /// Replace the settings file with `data`. Blocking; run it off the
+    /// update loop.
+    pub fn write(data: &str) -> Result<(), String> {
+        let path = Self::path().ok_or("No config directory")?;
+        Self::write_to(&path, data)
+    }
+
+    /// Write a temp file beside `path` and rename it over, so a crash
+    /// mid-write leaves the previous settings intact.
+    fn write_to(path: &Path, data: &str) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, data).map_err(|e| e.to_string())?;
+        std::fs::rename(&tmp, path).map_err(|e| e.to_string())
+    }
+
+    pub fn folder_view(&self, account_id: &str, mailbox_id: &str) -> FolderView {
+        self.folder_views
+            .get(account_id)
+            .and_then(|m| m.get(mailbox_id))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn set_folder_view(&mut self, account_id: &str, mailbox_id: &str, view: FolderView) {
+        if view.is_default() {
+            if let Some(views) = self.folder_views.get_mut(account_id) {
+                views.remove(mailbox_id);
+                if views.is_empty() {
+                    self.folder_views.remove(account_id);
+                }
+            }
+        } else {
+            self.folder_views
+                .entry(account_id.to_string())
+                .or_default()
+                .insert(mailbox_id.to_string(), view);
+        }
+    }
+
+    pub fn allows_remote_images(&self, sender: &str) -> bool {
+        self.remote_image_senders.contains(&sender.to_lowercase())
+    }
+
+    pub fn allow_remote_images_from(&mut self, sender: &str) {
+        self.remote_image_senders.insert(sender.to_lowercase());
+    }
+
+    /// Drop everything tied to a removed account.
+    pub fn forget_account(&mut self, account_id: &str) {
+        self.saved_searches
+            .retain(|s| s.account_id.as_deref() != Some(account_id));
+        self.folder_views.remove(account_id);
+        self.mail_rules.retain(|r| r.account_id != account_id);
+        self.rules_marks.remove(account_id);
+    }
+
+    /// Whether any enabled rule belongs to the account.
+    pub fn has_rules(&self, account_id: &str) -> bool {
+        self.mail_rules
+            .iter()
+            .any(|r| r.enabled && r.account_id == account_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_search(id: &str, account_id: Option<&str>) -> SavedSearch {
+        SavedSearch {
+            id: id.to_string(),
+            name: format!("search {id}"),
+            query: "from:alice".to_string(),
+            account_id: account_id.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn settings_round_trip_through_json() {
+        let settings = AppSettings {
+            saved_searches: vec![sample_search("1", Some("acct-a")), sample_search("2", None)],
+            search_scope: SearchScope::AllAccounts,
+            show_previews: false,
+            folder_views: HashMap::from([(
+                "acct-a".to_string(),
+                HashMap::from([(
+                    "M1".to_string(),
+                    FolderView {
+                        sort: ListSort::Size,
+                        filters: BTreeSet::from([QuickFilter::Unread, QuickFilter::ToMe]),
+                    },
+                )]),
+            )]),
+            remote_image_senders: BTreeSet::from(["news@example.com".to_string()]),
+            clean_links: false,
+            ignore_receipt_requests: true,
+            mail_rules: vec![MailRule {
+                name: "Lists".to_string(),
+                account_id: "acct-a".to_string(),
+                enabled: true,
+                conditions: vec![
+                    RuleCondition::ListId("rust-users".to_string()),
+                    RuleCondition::HasAttachment,
+                ],
+                actions: vec![
+                    RuleAction::Move("Lists/Rust".to_string()),
+                    RuleAction::MarkRead,
+                ],
+            }],
+            rules_marks: HashMap::from([(
+                "acct-a".to_string(),
+                RulesMark {
+                    received: 1_760_000_000,
+                    ids: BTreeSet::from(["E1".to_string()]),
+                },
+            )]),
+        };
+        let json = serde_json::to_string(&settings).expect("serialize");
+        let back: AppSettings = serde_json::from_str(&json).expect("deserialize");
+        assert_eq!(back, settings);
+    }
+
+    #[test]
+    fn settings_file_is_replaced_whole() {
+        let dir = std::env::temp_dir().join(format!("nm-settings-test-{}", std::process::id()));
+        let path = dir.join(SETTINGS_FILE);
+        assert_eq!(AppSettings::read_from(&path), AppSettings::default());
+
+        let settings = AppSettings {
+            saved_searches: vec![sample_search("1", None)],
+            ..AppSettings::default()
+        };
+        AppSettings::write_to(&path, &settings.to_json().expect("serialize")).expect("write");
+        assert_eq!(AppSettings::read_from(&path), settings);
+        assert!(!path.with_extension("json.tmp").exists());
+
+        AppSettings::write_to(&path, &AppSettings::default().to_json().expect("serialize"))
+            .expect("rewrite");
+        assert_eq!(AppSettings::read_from(&path), AppSettings::default());
+        std::fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn missing_fields_fall_back_to_defaults() {
+        let back: AppSettings = serde_json::from_str("{}").expect("deserialize");
+        assert_eq!(back, AppSettings::default());
+    }
+
+    #[test]
+    fn previews_default_on() {
+        let back: AppSettings =
+            serde_json::from_str(r#"{"saved_searches": []}"#).expect("deserialize");
+        assert!(back.show_previews);
+    }
+
+    #[test]
+    fn remote_image_senders_match_case_insensitively() {
+        let mut settings = AppSettings::default();
+        assert!(!settings.allows_remote_images("news@example.com"));
+        settings.allow_remote_images_from("News@Example.com");
+        assert!(settings.allows_remote_images("news@example.COM"));
+    }
+
+    #[test]
+    fn forget_account_keeps_global_and_other_accounts() {
+        let mut settings = AppSettings {
+            saved_searches: vec![
+                sample_search("1", Some("acct-a")),
+                sample_search("2", Some("acct-b")),
+                sample_search("3", None),
+            ],
+            ..AppSettings::default()
+        };
+        settings.forget_account("acct-a");
+        let ids: Vec<_> = settings
+            .saved_searches
+            .iter()
+            .map(|s| s.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["2", "3"]);
+    }
+
+    #[test]
+    fn search_scope_labels_line_up() {
+        assert_eq!(SearchScope::LABELS.len(), SearchScope::ALL.len());
+        for scope in SearchScope::ALL {
+            assert_eq!(SearchScope::ALL[scope.index()], scope);
+        }
+    }
+
+    #[test]
+    fn list_sort_labels_line_up() {
+        assert_eq!(ListSort::LABELS.len(), ListSort::ALL.len());
+        for sort in ListSort::ALL {
+            assert_eq!(ListSort::ALL[sort.index()], sort);
+        }
+    }
+
+    #[test]
+    fn default_folder_view_is_not_stored() {
+        let mut settings = AppSettings::default();
+        let view = FolderView {
+            sort: ListSort::Subject,
+            ..FolderView::default()
+        };
+        settings.set_folder_view("acct-a", "M1", view.clone());
+        assert_eq!(settings.folder_view("acct-a", "M1"), view);
+
+        settings.set_folder_view("acct-a", "M1", FolderView::default());
+        assert!(settings.folder_views.is_empty());
+        assert_eq!(settings.folder_view("acct-a", "M1"), FolderView::default());
+    }
+}
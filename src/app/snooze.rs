@@ -0,0 +1,642 @@
+//! Snooze: park a message in the Snoozed mailbox until a chosen time.
+//!
+//! Servers advertising the JMAP snooze extension return the message
+//! themselves. Otherwise the schedule lives in the cache and is processed on
+//! startup and on every periodic sync tick.
+
+use chrono::{
+    Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Weekday,
+};
+use cosmic::app::Task;
+use cosmic::iced::Length;
+use cosmic::widget;
+use cosmic::Element;
+
+use neverlight_mail_core::config::AccountId;
+use neverlight_mail_core::models::Folder;
+use neverlight_mail_core::store::{self, CacheHandle, SnoozedEntry};
+
+use super::{
+    AppModel, FlagIntentKind, IntentOrigin, MailboxIdentity, Message, MessageIdentity, MoveKind,
+    PendingFlagIntent, PendingMoveIntent, SnoozeChoice, SnoozePhase,
+};
+
+const SNOOZED_MAILBOX_NAME: &str = "Snoozed";
+const MORNING_HOUR: u32 = 8;
+const LATER_TODAY_HOURS: i64 = 3;
+
+/// Whether a folder is the account's Snoozed mailbox (by role, then by name).
+pub(crate) fn is_snoozed_folder(folder: &Folder) -> bool {
+    folder.role.as_deref() == Some("snoozed")
+        || folder.path.eq_ignore_ascii_case(SNOOZED_MAILBOX_NAME)
+}
+
+fn find_snoozed_mailbox(folders: &[Folder]) -> Option<String> {
+    neverlight_mail_core::mailbox::find_by_role(folders, "snoozed").or_else(|| {
+        folders
+            .iter()
+            .find(|f| is_snoozed_folder(f))
+            .map(|f| f.mailbox_id.clone())
+    })
+}
+
+fn morning_of(date: NaiveDate) -> NaiveDateTime {
+    date.and_hms_opt(MORNING_HOUR, 0, 0)
+        .unwrap_or_else(|| date.and_time(NaiveTime::MIN))
+}
+
+/// First `target` weekday strictly after `from`.
+fn next_weekday(from: NaiveDate, target: Weekday) -> NaiveDate {
+    let ahead = (7 + target.num_days_from_monday() - from.weekday().num_days_from_monday()) % 7;
+    let ahead = if ahead == 0 { 7 } else { ahead };
+    from + Duration::days(i64::from(ahead))
+}
+
+/// Local wall-clock time for a preset, or `None` for `Custom`.
+fn preset_wall_time(choice: SnoozeChoice, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    match choice {
+        SnoozeChoice::LaterToday => {
+            let later = now + Duration::hours(LATER_TODAY_HOURS);
+            later.with_minute(0)?.with_second(0)?.with_nanosecond(0)
+        }
+        SnoozeChoice::Tomorrow => Some(morning_of(now.date() + Duration::days(1))),
+        SnoozeChoice::Weekend => Some(morning_of(next_weekday(now.date(), Weekday::Sat))),
+        SnoozeChoice::NextWeek => Some(morning_of(next_weekday(now.date(), Weekday::Mon))),
+        SnoozeChoice::Custom => None,
+    }
+}
+
+/// Parse `YYYY-MM-DD HH:MM` or `YYYY-MM-DD` (morning) and require a future time.
+fn parse_custom_time(input: &str, now: NaiveDateTime) -> Result<NaiveDateTime, String> {
+    let input = input.trim();
+    let parsed = NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M")
+        .or_else(|_| NaiveDate::parse_from_str(input, "%Y-%m-%d").map(morning_of))
+        .map_err(|_| "Enter a time as YYYY-MM-DD or YYYY-MM-DD HH:MM".to_string())?;
+    if parsed <= now {
+        return Err("Snooze time must be in the future".into());
+    }
+    Ok(parsed)
+}
+
+fn resolve_wall_time(
+    choice: SnoozeChoice,
+    now: NaiveDateTime,
+    custom: &str,
+) -> Result<NaiveDateTime, String> {
+    match choice {
+        SnoozeChoice::Custom => parse_custom_time(custom, now),
+        preset => preset_wall_time(preset, now).ok_or_else(|| "Invalid snooze time".to_string()),
+    }
+}
+
+fn local_timestamp(wall: NaiveDateTime) -> Option<i64> {
+    Local
+        .from_local_datetime(&wall)
+        .earliest()
+        .map(|t| t.timestamp())
+}
+
+fn is_due(until: i64, now: i64) -> bool {
+    until <= now
+}
+
+/// Short relative label: "today 17:00", "tomorrow 08:00", "Sat 08:00", or a full date.
+fn format_wake_time(until: NaiveDateTime, now: NaiveDateTime) -> String {
+    let clock = until.format("%H:%M");
+    match (until.date() - now.date()).num_days() {
+        0 => format!("today {clock}"),
+        1 => format!("tomorrow {clock}"),
+        2..=6 => format!("{} {clock}", until.format("%a")),
+        _ => until.format("%Y-%m-%d %H:%M").to_string(),
+    }
+}
+
+/// Wake-up label for a stored unix timestamp, in local time.
+pub(crate) fn wake_label(until: i64) -> String {
+    let Some(until) = Local.timestamp_opt(until, 0).single() else {
+        return String::new();
+    };
+    format_wake_time(until.naive_local(), Local::now().naive_local())
+}
+
+/// Drop a schedule entry whose message is back (or never left).
+fn forget_snooze(cache: CacheHandle, entry: SnoozedEntry) -> Task<Message> {
+    cosmic::task::future(async move {
+        let result = cache
+            .remove_snooze(entry.account_id.clone(), entry.email_id.clone())
+            .await;
+        Message::SnoozeWoken {
+            account_id: entry.account_id,
+            email_id: entry.email_id,
+            result,
+        }
+    })
+}
+
+/// Move intent returning a due message from the Snoozed mailbox. Queued
+/// as background work so the user's own moves are never displaced.
+fn wake_intent(entry: SnoozedEntry) -> PendingMoveIntent {
+    PendingMoveIntent {
+        message: MessageIdentity {
+            account_id: entry.account_id.clone(),
+            mailbox_id: entry.snoozed_mailbox_id.clone(),
+            email_id: entry.email_id.clone(),
+        },
+        source: MailboxIdentity {
+            account_id: entry.account_id.clone(),
+            mailbox_id: entry.snoozed_mailbox_id.clone(),
+        },
+        dest: MailboxIdentity {
+            account_id: entry.account_id.clone(),
+            mailbox_id: entry.wake_mailbox_id.clone(),
+        },
+        origin: IntentOrigin::Background { flags: entry.flags },
+        kind: MoveKind::Wake(entry),
+    }
+}
+
+impl AppModel {
+    pub(super) fn handle_snooze(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::SnoozeOpen(index) => {
+                if self.setup_model.is_some() || self.compose_phase.is_open() {
+                    return Task::none();
+                }
+                let Some(msg) = self.messages.get(index) else {
+                    return Task::none();
+                };
+                self.snooze_phase = SnoozePhase::Choosing {
+                    target: MessageIdentity {
+                        account_id: msg.account_id.clone(),
+                        mailbox_id: msg.context_mailbox_id.clone(),
+                        email_id: msg.email_id.clone(),
+                    },
+                    custom: String::new(),
+                    error: None,
+                };
+            }
+            Message::SnoozeCustomChanged(value) => {
+                if let SnoozePhase::Choosing { custom, error, .. } = &mut self.snooze_phase {
+                    *custom = value;
+                    *error = None;
+                }
+            }
+            Message::SnoozeConfirm(choice) => {
+                return self.confirm_snooze(choice);
+            }
+            Message::SnoozeCancel => {
+                self.snooze_phase = SnoozePhase::Closed;
+            }
+            Message::SnoozeMailboxReady { account_id, result } => {
+                return self.handle_snooze_mailbox_ready(account_id, result);
+            }
+            Message::SnoozeWakeCheck => {
+                return self.check_snooze_wakeups();
+            }
+            Message::SnoozeScheduleLoaded {
+                account_id,
+                result: Ok(entries),
+            } => {
+                return self.apply_snooze_schedule(account_id, entries);
+            }
+            Message::SnoozeScheduleLoaded {
+                account_id,
+                result: Err(e),
+            } => {
+                log::warn!("Failed to load snooze schedule for {}: {}", account_id, e);
+            }
+            Message::SnoozeWoken {
+                account_id,
+                email_id,
+                result,
+            } => {
+                self.snooze_waking
+                    .remove(&(account_id.clone(), email_id.clone()));
+                match result {
+                    Ok(()) => {
+                        log::info!("Snoozed message {} woke up", email_id);
+                        if let Some(schedule) = self.snooze_schedule.get_mut(&account_id) {
+                            schedule.remove(&email_id);
+                        }
+                        return self.dispatch(Message::Refresh);
+                    }
+                    Err(e) => {
+                        log::warn!("Failed to wake snoozed message {}: {}", email_id, e);
+                    }
+                }
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+
+    fn confirm_snooze(&mut self, choice: SnoozeChoice) -> Task<Message> {
+        let SnoozePhase::Choosing { target, custom, .. } = &self.snooze_phase else {
+            return Task::none();
+        };
+        let target = target.clone();
+        let until =
+            resolve_wall_time(choice, Local::now().naive_local(), custom).and_then(|wall| {
+                local_timestamp(wall)
+                    .ok_or_else(|| "That time does not exist in your time zone".into())
+            });
+        let until = match until {
+            Ok(until) => until,
+            Err(e) => {
+                if let SnoozePhase::Choosing { error, .. } = &mut self.snooze_phase {
+                    *error = Some(e);
+                }
+                return Task::none();
+            }
+        };
+
+        let Some(acct) = self
+            .account_index(&target.account_id)
+            .and_then(|idx| self.accounts.get(idx))
+        else {
+            self.snooze_phase = SnoozePhase::Closed;
+            return Task::none();
+        };
+        if let Some(snoozed_id) = find_snoozed_mailbox(&acct.folders) {
+            self.snooze_phase = SnoozePhase::Closed;
+            return self.start_snooze(target, until, snoozed_id);
+        }
+
+        let Some(client) = acct.client.clone() else {
+            if let SnoozePhase::Choosing { error, .. } = &mut self.snooze_phase {
+                *error = Some("Account is offline".into());
+            }
+            return Task::none();
+        };
+        let account_id = target.account_id.clone();
+        self.snooze_phase = SnoozePhase::CreatingMailbox { target, until };
+        self.status_message = format!("Creating {} folder...", SNOOZED_MAILBOX_NAME);
+        cosmic::task::future(async move {
+            let result = neverlight_mail_core::mailbox::create(
+                &client,
+                SNOOZED_MAILBOX_NAME,
+                Some("snoozed"),
+            )
+            .await
+            .map_err(|e| e.to_string());
+            Message::SnoozeMailboxReady { account_id, result }
+        })
+    }
+
+    fn handle_snooze_mailbox_ready(
+        &mut self,
+        account_id: AccountId,
+        result: Result<String, String>,
+    ) -> Task<Message> {
+        let SnoozePhase::CreatingMailbox { target, until } =
+            std::mem::replace(&mut self.snooze_phase, SnoozePhase::Closed)
+        else {
+            return Task::none();
+        };
+        if target.account_id != account_id {
+            return Task::none();
+        }
+        match result {
+            Ok(snoozed_id) => {
+                // Pick up the new mailbox in the sidebar.
+                let refresh = self.dispatch(Message::Refresh);
+                let snooze = self.start_snooze(target, until, snoozed_id);
+                cosmic::task::batch(vec![snooze, refresh])
+            }
+            Err(e) => {
+                log::error!("Failed to create Snoozed mailbox: {}", e);
+                self.set_status_error(format!("Could not create Snoozed folder: {e}"));
+                Task::none()
+            }
+        }
+    }
+
+    /// Move the message into the Snoozed mailbox through the regular move
+    /// lane; the schedule is recorded once the move succeeds.
+    fn start_snooze(
+        &mut self,
+        target: MessageIdentity,
+        until: i64,
+        snoozed_id: String,
+    ) -> Task<Message> {
+        let Some(acct) = self
+            .account_index(&target.account_id)
+            .and_then(|idx| self.accounts.get(idx))
+        else {
+            return Task::none();
+        };
+        let wake_mailbox_id = neverlight_mail_core::mailbox::find_by_role(&acct.folders, "inbox")
+            .unwrap_or_else(|| target.mailbox_id.clone());
+        let server_managed = acct
+            .client
+            .as_ref()
+            .is_some_and(|c| c.has_capability(neverlight_mail_core::session::CAP_SNOOZE));
+        // Kept so a client-side wake can restore the star on an unlisted row.
+        let flags = self
+            .messages
+            .iter()
+            .find(|m| m.email_id == target.email_id && m.context_mailbox_id == target.mailbox_id)
+            .map_or(store::flags_to_u8(true, false), |m| {
+                store::flags_to_u8(m.is_read, m.is_starred)
+            });
+        let entry = SnoozedEntry {
+            account_id: target.account_id.clone(),
+            email_id: target.email_id.clone(),
+            snoozed_mailbox_id: snoozed_id.clone(),
+            wake_mailbox_id,
+            until,
+            server_managed,
+            flags,
+        };
+
+        self.status_message = format!("Snoozed until {}", wake_label(until));
+        if target.mailbox_id == snoozed_id {
+            return self.record_snooze(entry);
+        }
+        self.queue_or_start_move(PendingMoveIntent {
+            source: MailboxIdentity {
+                account_id: target.account_id.clone(),
+                mailbox_id: target.mailbox_id.clone(),
+            },
+            dest: MailboxIdentity {
+                account_id: target.account_id.clone(),
+                mailbox_id: snoozed_id,
+            },
+            message: target,
+            kind: MoveKind::Snooze(entry),
+            origin: IntentOrigin::User,
+        })
+    }
+
+    /// Add an entry to the in-memory schedule and persist it.
+    fn record_snooze(&mut self, entry: SnoozedEntry) -> Task<Message> {
+        self.snooze_schedule
+            .entry(entry.account_id.clone())
+            .or_default()
+            .insert(entry.email_id.clone(), entry.until);
+        let Some(cache) = self.cache.clone() else {
+            return Task::none();
+        };
+        cosmic::task::future(async move {
+            if let Err(e) = cache.save_snooze(entry).await {
+                log::warn!("Failed to save snooze schedule: {}", e);
+            }
+            Message::Noop
+        })
+    }
+
+    /// Follow-up once a snooze or wake move settles. A snooze is only
+    /// scheduled after it moved; a woken message is marked unread and its
+    /// entry dropped, or left scheduled for the next check if the move
+    /// failed.
+    pub(super) fn finish_snooze_move(&mut self, kind: MoveKind, moved: bool) -> Task<Message> {
+        match (kind, moved) {
+            (MoveKind::Snooze(entry), true) => self.record_snooze(entry),
+            (MoveKind::Wake(entry), true) => {
+                let unread = self.queue_or_start_flag(PendingFlagIntent {
+                    message: MessageIdentity {
+                        account_id: entry.account_id.clone(),
+                        mailbox_id: entry.wake_mailbox_id.clone(),
+                        email_id: entry.email_id.clone(),
+                    },
+                    kind: FlagIntentKind::MarkUnread,
+                    origin: IntentOrigin::Background { flags: entry.flags },
+                });
+                let Some(cache) = self.cache.clone() else {
+                    return unread;
+                };
+                cosmic::task::batch(vec![unread, forget_snooze(cache, entry)])
+            }
+            (MoveKind::Wake(entry), false) => {
+                self.snooze_waking
+                    .remove(&(entry.account_id.clone(), entry.email_id.clone()));
+                self.snooze_schedule
+                    .entry(entry.account_id)
+                    .or_default()
+                    .insert(entry.email_id, entry.until);
+                Task::none()
+            }
+            _ => Task::none(),
+        }
+    }
+
+    fn check_snooze_wakeups(&mut self) -> Task<Message> {
+        let Some(cache) = &self.cache else {
+            return Task::none();
+        };
+        let tasks: Vec<Task<Message>> = self
+            .accounts
+            .iter()
+            .map(|acct| {
+                let cache = cache.clone();
+                let account_id = acct.config.id.clone();
+                cosmic::task::future(async move {
+                    let result = cache.load_snoozed(account_id.clone()).await;
+                    Message::SnoozeScheduleLoaded { account_id, result }
+                })
+            })
+            .collect();
+        cosmic::task::batch(tasks)
+    }
+
+    fn apply_snooze_schedule(
+        &mut self,
+        account_id: AccountId,
+        entries: Vec<SnoozedEntry>,
+    ) -> Task<Message> {
+        let Some(cache) = self.cache.clone() else {
+            return Task::none();
+        };
+        let online = self.client_for_account(&account_id).is_some();
+        let now = Local::now().timestamp();
+        let mut schedule = std::collections::HashMap::new();
+        let mut tasks: Vec<Task<Message>> = Vec::new();
+
+        for entry in entries {
+            let key = (account_id.clone(), entry.email_id.clone());
+            if !is_due(entry.until, now) || self.snooze_waking.contains(&key) {
+                schedule.insert(entry.email_id.clone(), entry.until);
+                continue;
+            }
+            // The server returns its own snoozes; a client-side wake waits
+            // for a connection.
+            if entry.server_managed {
+                self.snooze_waking.insert(key);
+                tasks.push(forget_snooze(cache.clone(), entry));
+            } else if online {
+                self.snooze_waking.insert(key);
+                tasks.push(self.queue_or_start_move(wake_intent(entry)));
+            } else {
+                schedule.insert(entry.email_id.clone(), entry.until);
+            }
+        }
+
+        self.snooze_schedule.insert(account_id, schedule);
+        cosmic::task::batch(tasks)
+    }
+
+    pub(super) fn snooze_dialog(&self) -> Element<'_, Message> {
+        let now = Local::now().naive_local();
+        let (custom, error, busy) = match &self.snooze_phase {
+            SnoozePhase::Choosing { custom, error, .. } => {
+                (custom.as_str(), error.as_deref(), false)
+            }
+            SnoozePhase::CreatingMailbox { .. } | SnoozePhase::Closed => ("", None, true),
+        };
+
+        let mut controls = widget::column().spacing(8);
+        for (choice, label) in [
+            (SnoozeChoice::LaterToday, "Later today"),
+            (SnoozeChoice::Tomorrow, "Tomorrow"),
+            (SnoozeChoice::Weekend, "This weekend"),
+            (SnoozeChoice::NextWeek, "Next week"),
+        ] {
+            let when = preset_wall_time(choice, now)
+                .map(|t| format_wake_time(t, now))
+                .unwrap_or_default();
+            let mut btn = widget::button::standard(format!("{label} — {when}")).width(Length::Fill);
+            if !busy {
+                btn = btn.on_press(Message::SnoozeConfirm(choice));
+            }
+            controls = controls.push(btn);
+        }
+        controls = controls.push(
+            widget::text_input("YYYY-MM-DD HH:MM", custom)
+                .label("Custom time")
+                .on_input(Message::SnoozeCustomChanged)
+                .on_submit(|_| Message::SnoozeConfirm(SnoozeChoice::Custom)),
+        );
+
+        let mut confirm = widget::button::suggested(if busy { "Snoozing..." } else { "Snooze" });
+        if !busy {
+            confirm = confirm.on_press(Message::SnoozeConfirm(SnoozeChoice::Custom));
+        }
+
+        let mut dialog = widget::dialog()
+            .title("Snooze until")
+            .control(controls)
+            .primary_action(confirm)
+            .secondary_action(widget::button::standard("Cancel").on_press(Message::SnoozeCancel));
+        if let Some(err) = error {
+            dialog = dialog.body(err);
+        }
+        dialog.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_support::sample_folder as folder;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").expect("valid test time")
+    }
+
+    // 2026-10-14 is a Wednesday.
+
+    #[test]
+    fn later_today_is_three_hours_on_the_hour() {
+        let t = preset_wall_time(SnoozeChoice::LaterToday, at("2026-10-14 10:45"));
+        assert_eq!(t, Some(at("2026-10-14 13:00")));
+    }
+
+    #[test]
+    fn tomorrow_is_next_morning() {
+        let t = preset_wall_time(SnoozeChoice::Tomorrow, at("2026-10-14 22:10"));
+        assert_eq!(t, Some(at("2026-10-15 08:00")));
+    }
+
+    #[test]
+    fn weekend_is_next_saturday_morning() {
+        let t = preset_wall_time(SnoozeChoice::Weekend, at("2026-10-14 10:00"));
+        assert_eq!(t, Some(at("2026-10-17 08:00")));
+    }
+
+    #[test]
+    fn weekend_on_saturday_skips_to_following_saturday() {
+        let t = preset_wall_time(SnoozeChoice::Weekend, at("2026-10-17 07:00"));
+        assert_eq!(t, Some(at("2026-10-24 08:00")));
+    }
+
+    #[test]
+    fn next_week_is_next_monday_morning() {
+        let t = preset_wall_time(SnoozeChoice::NextWeek, at("2026-10-14 10:00"));
+        assert_eq!(t, Some(at("2026-10-19 08:00")));
+        let t = preset_wall_time(SnoozeChoice::NextWeek, at("2026-10-19 09:00"));
+        assert_eq!(t, Some(at("2026-10-26 08:00")));
+    }
+
+    #[test]
+    fn custom_time_accepts_datetime_and_date() {
+        let now = at("2026-10-14 10:00");
+        assert_eq!(
+            parse_custom_time("2026-10-20 14:30", now),
+            Ok(at("2026-10-20 14:30"))
+        );
+        assert_eq!(
+            parse_custom_time(" 2026-10-20 ", now),
+            Ok(at("2026-10-20 08:00"))
+        );
+    }
+
+    #[test]
+    fn custom_time_rejects_past_and_garbage() {
+        let now = at("2026-10-14 10:00");
+        assert!(parse_custom_time("2026-10-14 09:59", now).is_err());
+        assert!(parse_custom_time("next tuesday", now).is_err());
+        assert!(parse_custom_time("", now).is_err());
+    }
+
+    #[test]
+    fn resolve_uses_custom_input_only_for_custom_choice() {
+        let now = at("2026-10-14 10:00");
+        assert_eq!(
+            resolve_wall_time(SnoozeChoice::Tomorrow, now, "garbage"),
+            Ok(at("2026-10-15 08:00"))
+        );
+        assert!(resolve_wall_time(SnoozeChoice::Custom, now, "garbage").is_err());
+    }
+
+    #[test]
+    fn wake_time_labels_are_relative() {
+        let now = at("2026-10-14 10:00");
+        assert_eq!(format_wake_time(at("2026-10-14 17:00"), now), "today 17:00");
+        assert_eq!(
+            format_wake_time(at("2026-10-15 08:00"), now),
+            "tomorrow 08:00"
+        );
+        assert_eq!(format_wake_time(at("2026-10-17 08:00"), now), "Sat 08:00");
+        assert_eq!(
+            format_wake_time(at("2026-11-02 08:00"), now),
+            "2026-11-02 08:00"
+        );
+    }
+
+    #[test]
+    fn snoozed_mailbox_prefers_role_over_name() {
+        let folders = vec![
+            folder("M1", "INBOX", Some("inbox")),
+            folder("M2", "Snoozed", None),
+            folder("M3", "Later", Some("snoozed")),
+        ];
+        assert_eq!(find_snoozed_mailbox(&folders).as_deref(), Some("M3"));
+    }
+
+    #[test]
+    fn snoozed_mailbox_falls_back_to_name() {
+        let folders = vec![
+            folder("M1", "INBOX", Some("inbox")),
+            folder("M2", "snoozed", None),
+        ];
+        assert_eq!(find_snoozed_mailbox(&folders).as_deref(), Some("M2"));
+        assert_eq!(find_snoozed_mailbox(&folders[..1]), None);
+    }
+
+    #[test]
+    fn due_includes_exact_wake_time() {
+        assert!(is_due(100, 100));
+        assert!(is_due(99, 100));
+        assert!(!is_due(101, 100));
+    }
+}
@@ -0,0 +1,198 @@
+//! Raw message source and the full header list.
+//!
+//! The RFC 5322 blob is downloaded on demand through the email's `blobId`
+//! and kept for the selected message only. "View source" shows it in a
+//! read-only monospace editor; "All headers" lists the parsed header block
+//! under the regular message header.
+
+use cosmic::app::Task;
+use cosmic::widget::text_editor;
+use futures::future::{AbortHandle, Abortable};
+
+use super::{AppModel, Message, RawSource};
+
+/// Sources beyond this are cut for display; big attachments make the editor
+/// crawl and the tail is base64 anyway.
+const MAX_SOURCE_DISPLAY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Header fields of a raw message, in order, with folded lines joined.
+pub(super) fn parse_headers(raw: &str) -> Vec<(String, String)> {
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in raw.lines() {
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    headers
+}
+
+/// Display text for a raw blob, cut on a character boundary when too large.
+fn display_source(raw: &[u8]) -> String {
+    let text = String::from_utf8_lossy(raw);
+    if text.len() <= MAX_SOURCE_DISPLAY_BYTES {
+        return text.into_owned();
+    }
+    let mut end = MAX_SOURCE_DISPLAY_BYTES;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!(
+        "{}\n\n[… {} more bytes not shown]",
+        &text[..end],
+        text.len() - end
+    )
+}
+
+impl AppModel {
+    pub(super) fn handle_source(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::ToggleSourceView => {
+                self.show_source = !self.show_source;
+                if self.show_source {
+                    return self.ensure_raw_source();
+                }
+            }
+            Message::ToggleAllHeaders => {
+                self.headers_expanded = !self.headers_expanded;
+                if self.headers_expanded {
+                    return self.ensure_raw_source();
+                }
+            }
+            Message::RawSourceLoaded {
+                email_id,
+                epoch,
+                result,
+            } => {
+                let selected = self
+                    .selected_message
+                    .and_then(|i| self.messages.get(i))
+                    .map(|m| m.email_id.as_str());
+                if epoch != self.body_epoch || selected != Some(email_id.as_str()) {
+                    self.stale_apply_drop_count = self.stale_apply_drop_count.saturating_add(1);
+                    return Task::none();
+                }
+                self.source_abort = None;
+                match result {
+                    Ok(raw) => {
+                        let text = display_source(&raw);
+                        self.raw_source = Some(RawSource {
+                            email_id,
+                            headers: parse_headers(&text),
+                            editor: text_editor::Content::with_text(&text),
+                        });
+                        self.status_message = "Ready".into();
+                    }
+                    Err(e) => {
+                        log::warn!("Raw source for {} failed: {}", email_id, e);
+                        self.show_source = false;
+                        self.headers_expanded = false;
+                        self.status_message = format!("Could not load message source: {e}");
+                    }
+                }
+            }
+            Message::SourceAction(action) => {
+                if let Some(raw) = &mut self.raw_source {
+                    if !action.is_edit() {
+                        raw.editor.perform(action);
+                    }
+                }
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+
+    /// Collapse the source view and header panel when another message is opened.
+    pub(super) fn reset_source_view(&mut self) {
+        self.show_source = false;
+        self.headers_expanded = false;
+        if let Some(handle) = self.source_abort.take() {
+            handle.abort();
+        }
+    }
+
+    /// Download the selected message's blob unless it is already loaded.
+    fn ensure_raw_source(&mut self) -> Task<Message> {
+        let Some(msg) = self.selected_message.and_then(|i| self.messages.get(i)) else {
+            return Task::none();
+        };
+        if self
+            .raw_source
+            .as_ref()
+            .is_some_and(|r| r.email_id == msg.email_id)
+        {
+            return Task::none();
+        }
+        let email_id = msg.email_id.clone();
+        let Some(client) = self.client_for_account(&msg.account_id) else {
+            self.show_source = false;
+            self.headers_expanded = false;
+            self.status_message = "Not connected — message source needs the server".into();
+            return Task::none();
+        };
+        if let Some(handle) = self.source_abort.take() {
+            handle.abort();
+        }
+        let (abort_handle, abort_reg) = AbortHandle::new_pair();
+        self.source_abort = Some(abort_handle);
+        let epoch = self.body_epoch;
+        self.status_message = "Loading message source...".into();
+        cosmic::task::future(async move {
+            let load = neverlight_mail_core::email::get_raw(&client, &email_id);
+            match Abortable::new(load, abort_reg).await {
+                Ok(result) => Message::RawSourceLoaded {
+                    email_id,
+                    epoch,
+                    result: result.map_err(|e| e.to_string()),
+                },
+                Err(_) => Message::Noop,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers_unfold_and_stop_at_body() {
+        let raw =
+            "Received: from a\r\n\tby b\r\nSubject: Hi\r\nX-Empty:\r\n\r\nBody: not a header\r\n";
+        assert_eq!(
+            parse_headers(raw),
+            vec![
+                ("Received".to_string(), "from a by b".to_string()),
+                ("Subject".to_string(), "Hi".to_string()),
+                ("X-Empty".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn large_sources_are_cut_on_a_char_boundary() {
+        let mut raw = vec![b'a'; MAX_SOURCE_DISPLAY_BYTES - 1];
+        raw.extend_from_slice("é tail".as_bytes());
+        let text = display_source(&raw);
+        assert!(text.starts_with(&"a".repeat(MAX_SOURCE_DISPLAY_BYTES - 1)));
+        assert!(text.ends_with("[… 7 more bytes not shown]"));
+    }
+
+    #[test]
+    fn small_sources_are_shown_whole() {
+        assert_eq!(
+            display_source(b"Subject: x\r\n\r\nhi"),
+            "Subject: x\r\n\r\nhi"
+        );
+    }
+}
@@ -26,6 +26,13 @@ fn should_apply_cached_messages(
         && current.offset == incoming.offset
 }
 
+/// Rows to reload after a sync: the loaded window, rounded up to a whole
+/// page, so the list keeps its length and scroll position.
+pub(super) fn reload_limit(loaded: usize) -> u32 {
+    let pages = (loaded as u32).div_ceil(DEFAULT_PAGE_SIZE).max(1);
+    pages * DEFAULT_PAGE_SIZE
+}
+
 pub(super) fn mark_refresh_account_complete(
     outstanding: &mut std::collections::HashSet<String>,
     account_id: &str,
@@ -50,7 +57,13 @@ impl AppModel {
     pub(super) fn handle_sync(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::CachedFoldersLoaded { account_id, result: Ok(folders) } => {
-                return self.handle_cached_folders_ok(account_id, folders);
+                let load = self.handle_cached_folders_ok(account_id.clone(), folders);
+                self.mark_saved_searches_changed(&account_id, None);
+                return cosmic::task::batch(vec![
+                    load,
+                    self.refresh_saved_search_counts(),
+                    self.refresh_lists(),
+                ]);
             }
             Message::CachedFoldersLoaded { result: Err(e), .. } => {
                 log::warn!("Failed to load cached folders: {}", e);
@@ -60,6 +73,7 @@ impl AppModel {
                 account_id,
                 ref mailbox_id,
                 offset,
+                limit,
                 epoch,
                 result: Ok(messages),
             } => {
@@ -77,7 +91,7 @@ impl AppModel {
                     epoch: self.folder_epoch,
                     account_id: active_account_id,
                     mailbox_id: active_mailbox_id,
-                    offset: self.messages_offset,
+                    offset: self.pending_messages_offset,
                 };
                 let incoming = CachedMessagesContext {
                     epoch,
@@ -91,31 +105,29 @@ impl AppModel {
                 }
 
                 let count = messages.len();
-                self.has_more_messages = count as u32 == DEFAULT_PAGE_SIZE;
+                self.has_more_messages = count as u32 == limit;
                 self.folder_abort = None;
 
                 let prev_email_id = self.selected_message.and_then(|i| {
                     self.messages.get(i).map(|m| m.email_id.clone())
                 });
 
-                if self.messages_offset == 0 {
-                    self.messages = messages;
-                } else {
-                    self.messages.extend(messages);
-                }
-
-                if self.messages_offset == 0 {
-                    if let Some(ref eid) = prev_email_id {
-                        self.selected_message = self
-                            .messages
-                            .iter()
-                            .position(|m| m.email_id == *eid);
-                    }
+                // Each load replaces the window, keeping `messages` bounded.
+                // Rows above the viewport can change with it, so keep the
+                // same mail at the top.
+                let anchor = self.list_scroll_anchor();
+                self.messages_offset = offset;
+                self.messages = messages;
+                if let Some(ref eid) = prev_email_id {
+                    self.selected_message = self
+                        .messages
+                        .iter()
+                        .position(|m| m.email_id == *eid);
                 }
 
                 self.recompute_visible();
 
-                if self.messages_offset == 0 {
+                if offset == 0 {
                     self.reconcile_folder_unread_count(&account_id, mailbox_id);
                 }
 
@@ -124,6 +136,11 @@ impl AppModel {
                         format!("{} messages", self.messages.len());
                 }
                 self.phase = Phase::Idle;
+                // The viewport may have moved on while the window loaded.
+                return cosmic::task::batch(vec![
+                    self.scroll_to_anchor(anchor),
+                    self.ensure_list_window(),
+                ]);
             }
             Message::CachedMessagesLoaded { epoch, result: Err(e), .. } => {
                 if epoch != self.folder_epoch {
@@ -135,7 +152,10 @@ impl AppModel {
             }
 
             Message::AccountConnected { account_id, result: Ok(client) } => {
-                return self.handle_account_connected_ok(account_id, client);
+                let connected = self.handle_account_connected_ok(account_id.clone(), client);
+                let vacation = self.load_vacation(&account_id);
+                let rules = self.rules_arrival_pass(&account_id);
+                return cosmic::task::batch(vec![connected, vacation, rules]);
             }
             Message::AccountConnected { account_id, result: Err(e) } => {
                 return self.handle_account_connected_err(account_id, e);
@@ -162,7 +182,12 @@ impl AppModel {
                 epoch,
                 result: Ok(()),
             } => {
-                return self.handle_sync_messages_ok(account_id, mailbox_id.clone(), epoch);
+                let reload = self.handle_sync_messages_ok(account_id, mailbox_id.clone(), epoch);
+                return cosmic::task::batch(vec![
+                    reload,
+                    self.refresh_saved_search_counts(),
+                    self.refresh_lists(),
+                ]);
             }
             Message::SyncMessagesComplete { ref account_id, epoch, result: Err(ref e), .. } => {
                 return self.handle_sync_messages_err(account_id, epoch, e);
@@ -172,10 +197,6 @@ impl AppModel {
                 return self.handle_select_folder(acct_idx, folder_idx);
             }
 
-            Message::LoadMoreMessages => {
-                return self.handle_load_more_messages();
-            }
-
             Message::Refresh => {
                 return self.handle_refresh();
             }
@@ -193,10 +214,11 @@ impl AppModel {
 #[cfg(test)]
 mod tests {
     use super::{
-        mark_refresh_account_complete, refresh_has_timed_out, should_apply_cached_messages,
-        CachedMessagesContext,
+        mark_refresh_account_complete, refresh_has_timed_out, reload_limit,
+        should_apply_cached_messages, CachedMessagesContext,
     };
     use crate::app::RefreshPhase;
+    use neverlight_mail_core::store::DEFAULT_PAGE_SIZE;
     use std::collections::HashSet;
     use std::time::{Duration, Instant};
 
@@ -237,6 +259,16 @@ mod tests {
         assert!(!should_apply_cached_messages(&wrong_offset, &base));
     }
 
+    #[test]
+    fn reload_keeps_every_loaded_window() {
+        assert_eq!(reload_limit(0), DEFAULT_PAGE_SIZE);
+        assert_eq!(reload_limit(DEFAULT_PAGE_SIZE as usize), DEFAULT_PAGE_SIZE);
+        assert_eq!(
+            reload_limit(DEFAULT_PAGE_SIZE as usize + 1),
+            2 * DEFAULT_PAGE_SIZE
+        );
+    }
+
     #[test]
     fn refresh_is_queued_when_in_flight() {
         assert!(RefreshPhase::InFlight { pending: false, timeout_reported: false }.is_in_flight());
@@ -7,7 +7,10 @@ use neverlight_mail_core::store::DEFAULT_PAGE_SIZE;
 use std::time::Instant;
 
 use super::{AppModel, ConnectionState, Message, Phase, RefreshPhase};
-use super::sync::{mark_refresh_account_complete, refresh_has_timed_out, REFRESH_STUCK_TIMEOUT};
+use super::sync::{
+    mark_refresh_account_complete, refresh_has_timed_out, reload_limit, REFRESH_STUCK_TIMEOUT,
+};
+use super::unified::changed_mailboxes;
 
 impl AppModel {
     pub(super) fn handle_cached_folders_ok(
@@ -39,6 +42,7 @@ impl AppModel {
             Some(self.accounts[idx].folders[fi].mailbox_id.clone());
         self.selected_folder_evicted = false;
         let mailbox_id = self.accounts[idx].folders[fi].mailbox_id.clone();
+        let query = self.folder_list_query(idx, &mailbox_id);
         let Some(cache) = &self.cache else {
             return Task::none();
         };
@@ -47,6 +51,7 @@ impl AppModel {
         self.folder_epoch = self.folder_epoch.saturating_add(1);
         let epoch = self.folder_epoch;
         self.messages_offset = 0;
+        self.pending_messages_offset = 0;
         if let Some(handle) = self.folder_abort.take() {
             handle.abort();
         }
@@ -54,12 +59,7 @@ impl AppModel {
         self.folder_abort = Some(abort_handle);
         cosmic::task::future(async move {
             match Abortable::new(
-                cache.load_messages(
-                    aid.clone(),
-                    mailbox_id.clone(),
-                    DEFAULT_PAGE_SIZE,
-                    0,
-                ),
+                cache.load_messages(aid.clone(), mailbox_id.clone(), query, DEFAULT_PAGE_SIZE, 0),
                 abort_reg,
             )
             .await
@@ -68,6 +68,7 @@ impl AppModel {
                     account_id: aid,
                     mailbox_id,
                     offset: 0,
+                    limit: DEFAULT_PAGE_SIZE,
                     epoch,
                     result,
                 },
@@ -137,6 +138,9 @@ impl AppModel {
             tasks.push(self.dispatch(Message::ViewBody(index)));
         }
 
+        // Wake anything whose snooze expired while we were offline
+        tasks.push(self.dispatch(Message::SnoozeWakeCheck));
+
         cosmic::task::batch(tasks)
     }
 
@@ -233,8 +237,10 @@ impl AppModel {
             }
             return Task::none();
         };
+        let changed = changed_mailboxes(&self.accounts[idx].folders, &folders);
         self.accounts[idx].folders = folders;
         self.accounts[idx].rebuild_folder_map();
+        self.mark_saved_searches_changed(&account_id, Some(&changed));
         self.accounts[idx].conn_state = ConnectionState::Connected;
         // Activate backfill if not already running
         if !self.accounts[idx].backfill_active && self.accounts[idx].client.is_some() {
@@ -249,7 +255,10 @@ impl AppModel {
         );
 
         // Auto-select INBOX if this is the active account and no folder selected
-        if self.active_account == Some(idx) && self.selected_folder.is_none() {
+        if self.active_account == Some(idx)
+            && self.selected_folder.is_none()
+            && self.virtual_view.is_none()
+        {
             if let Some(fi) = self.accounts[idx].folders.iter().position(|f| f.path == "INBOX") {
                 self.selected_folder = Some(fi);
                 self.selected_mailbox_id =
@@ -259,7 +268,9 @@ impl AppModel {
         }
         if self.active_account.is_none() {
             self.active_account = Some(idx);
-            if let Some(fi) = self.accounts[idx].folders.iter().position(|f| f.path == "INBOX") {
+            if self.virtual_view.is_some() {
+                // Keep the virtual view; the account only becomes the compose default.
+            } else if let Some(fi) = self.accounts[idx].folders.iter().position(|f| f.path == "INBOX") {
                 self.selected_folder = Some(fi);
                 self.selected_mailbox_id =
                     Some(self.accounts[idx].folders[fi].mailbox_id.clone());
@@ -268,6 +279,23 @@ impl AppModel {
         }
         self.revalidate_selected_folder();
 
+        // A virtual view pulls from every account, not just the active one
+        if let Some(view) = self.virtual_view {
+            let mut tasks: Vec<Task<Message>> = Vec::new();
+            // Saved search counts follow the view sync, or go now without one
+            match self.sync_virtual_view_account(view, idx, &changed) {
+                Some(t) => tasks.push(t),
+                None => tasks.push(self.refresh_saved_search_counts()),
+            }
+            if let Some(t) = self.background_sync_sent(&account_id) {
+                tasks.push(t);
+            }
+            if refresh_completed && had_pending {
+                tasks.push(self.dispatch(Message::Refresh));
+            }
+            return cosmic::task::batch(tasks);
+        }
+
         // If this is the active account, sync the selected folder's messages
         let can_fetch = self.active_account == Some(idx)
             && self.selected_folder.is_some()
@@ -280,6 +308,7 @@ impl AppModel {
         if can_fetch {
             let fi = self.selected_folder.expect("checked above");
             let mailbox_id = self.accounts[idx].folders[fi].mailbox_id.clone();
+            let (jmap_filter, jmap_sort) = self.folder_jmap_query(idx, &mailbox_id);
             let client = self.accounts[idx].client.clone().expect("checked above");
             let cache = self.cache.clone();
             let mid = mailbox_id.clone();
@@ -303,7 +332,7 @@ impl AppModel {
                             .map_err(|e| e.to_string())
                         } else {
                             neverlight_mail_core::email::query_and_get(
-                                &client, &mid, DEFAULT_PAGE_SIZE, 0,
+                                &client, &jmap_filter, &jmap_sort, DEFAULT_PAGE_SIZE, 0,
                             )
                             .await
                             .map(|(msgs, _)| msgs)
@@ -350,10 +379,61 @@ impl AppModel {
             return cosmic::task::batch(tasks);
         }
 
+        // No message sync follows to recount saved searches
+        let counts = self.refresh_saved_search_counts();
         if refresh_completed && had_pending {
-            return self.dispatch(Message::Refresh);
+            return cosmic::task::batch(vec![counts, self.dispatch(Message::Refresh)]);
         }
-        Task::none()
+        counts
+    }
+
+    /// Reload the loaded window of the selected folder from the cache.
+    /// Returns None when no folder is selected or there is no cache.
+    pub(super) fn reload_selected_folder(&mut self) -> Option<Task<Message>> {
+        self.load_list_window(self.messages_offset, reload_limit(self.messages.len()))
+    }
+
+    /// Load `limit` cache rows of the selected folder from row `offset`; the
+    /// result replaces `messages`. Superseded loads are aborted.
+    pub(super) fn load_list_window(&mut self, offset: u32, limit: u32) -> Option<Task<Message>> {
+        let acct_idx = self.active_account?;
+        let fi = self.selected_folder?;
+        let mailbox_id = self
+            .accounts
+            .get(acct_idx)?
+            .folders
+            .get(fi)?
+            .mailbox_id
+            .clone();
+        let cache = self.cache.clone()?;
+        let aid = self.accounts[acct_idx].config.id.clone();
+        let query = self.folder_list_query(acct_idx, &mailbox_id);
+        self.pending_messages_offset = offset;
+        self.folder_epoch = self.folder_epoch.saturating_add(1);
+        let folder_epoch = self.folder_epoch;
+        if let Some(handle) = self.folder_abort.take() {
+            handle.abort();
+        }
+        let (abort_handle, abort_reg) = AbortHandle::new_pair();
+        self.folder_abort = Some(abort_handle);
+        Some(cosmic::task::future(async move {
+            match Abortable::new(
+                cache.load_messages(aid.clone(), mailbox_id.clone(), query, limit, offset),
+                abort_reg,
+            )
+            .await
+            {
+                Ok(result) => Message::CachedMessagesLoaded {
+                    account_id: aid,
+                    mailbox_id,
+                    offset,
+                    limit,
+                    epoch: folder_epoch,
+                    result,
+                },
+                Err(_) => Message::Noop,
+            }
+        }))
     }
 
     /// Fire-and-forget sync of the Sent folder into cache.
@@ -480,51 +560,8 @@ impl AppModel {
         self.last_sync_at = Some(Instant::now());
         let mut tasks: Vec<Task<Message>> = Vec::new();
 
-        let has_cache_reload_target = self.active_account.is_some()
-            && self.selected_folder.is_some()
-            && self
-                .active_account
-                .and_then(|ai| self.accounts.get(ai))
-                .and_then(|a| self.selected_folder.and_then(|fi| a.folders.get(fi)))
-                .is_some()
-            && self.cache.is_some();
-
-        if has_cache_reload_target {
-            let acct_idx = self.active_account.expect("checked above");
-            let fi = self.selected_folder.expect("checked above");
-            let mailbox_id = self.accounts[acct_idx].folders[fi].mailbox_id.clone();
-            let cache = self.cache.clone().expect("checked above");
-            let aid = self.active_account_id();
-            self.messages_offset = 0;
-            self.folder_epoch = self.folder_epoch.saturating_add(1);
-            let folder_epoch = self.folder_epoch;
-            if let Some(handle) = self.folder_abort.take() {
-                handle.abort();
-            }
-            let (abort_handle, abort_reg) = AbortHandle::new_pair();
-            self.folder_abort = Some(abort_handle);
-            tasks.push(cosmic::task::future(async move {
-                match Abortable::new(
-                    cache.load_messages(
-                        aid.clone(),
-                        mailbox_id.clone(),
-                        DEFAULT_PAGE_SIZE,
-                        0,
-                    ),
-                    abort_reg,
-                )
-                .await
-                {
-                    Ok(result) => Message::CachedMessagesLoaded {
-                        account_id: aid,
-                        mailbox_id,
-                        offset: 0,
-                        epoch: folder_epoch,
-                        result,
-                    },
-                    Err(_) => Message::Noop,
-                }
-            }));
+        if let Some(task) = self.reload_selected_folder() {
+            tasks.push(task);
         }
 
         if let Some(index) = self.pending_body.take() {
@@ -593,6 +630,8 @@ impl AppModel {
     ) -> Task<Message> {
         self.active_account = Some(acct_idx);
         self.selected_folder = Some(folder_idx);
+        self.virtual_view = None;
+        self.active_saved_search = None;
         self.selected_mailbox_id = self
             .accounts
             .get(acct_idx)
@@ -612,12 +651,14 @@ impl AppModel {
         self.messages.clear();
         self.selected_message = None;
         self.preview_body.clear();
-        self.preview_markdown.clear();
+        self.preview_blocks.clear();
         self.preview_editor = cosmic::widget::text_editor::Content::new();
         self.preview_attachments.clear();
         self.preview_image_handles.clear();
         self.messages_offset = 0;
+        self.pending_messages_offset = 0;
         self.has_more_messages = false;
+        self.list_scroll_y = 0.0;
         self.collapsed_threads.clear();
         self.recompute_visible();
         self.phase = Phase::Loading;
@@ -631,6 +672,8 @@ impl AppModel {
         let mailbox_id = folder.mailbox_id.clone();
         let folder_name = folder.name.clone();
         let aid = acct.config.id.clone();
+        let list_query = self.folder_list_query(acct_idx, &mailbox_id);
+        let (jmap_filter, jmap_sort) = self.folder_jmap_query(acct_idx, &mailbox_id);
         let mut tasks: Vec<Task<Message>> = Vec::new();
 
         if let Some(cache) = &self.cache {
@@ -644,6 +687,7 @@ impl AppModel {
                     cache.load_messages(
                         aid2.clone(),
                         mid.clone(),
+                        list_query,
                         DEFAULT_PAGE_SIZE,
                         0,
                     ),
@@ -655,6 +699,7 @@ impl AppModel {
                         account_id: aid2,
                         mailbox_id: mid,
                         offset: 0,
+                        limit: DEFAULT_PAGE_SIZE,
                         epoch: folder_epoch,
                         result,
                     },
@@ -686,7 +731,7 @@ impl AppModel {
                             .map_err(|e| e.to_string())
                         } else {
                             neverlight_mail_core::email::query_and_get(
-                                &client, &mid, DEFAULT_PAGE_SIZE, 0,
+                                &client, &jmap_filter, &jmap_sort, DEFAULT_PAGE_SIZE, 0,
                             )
                             .await
                             .map(|(msgs, _)| msgs)
@@ -724,56 +769,6 @@ impl AppModel {
         }
     }
 
-    pub(super) fn handle_load_more_messages(&mut self) -> Task<Message> {
-        self.messages_offset += DEFAULT_PAGE_SIZE;
-        let offset = self.messages_offset;
-
-        let Some(acct_idx) = self.active_account else {
-            return Task::none();
-        };
-        let Some(fi) = self.selected_folder else {
-            return Task::none();
-        };
-        let Some(folder) = self.accounts.get(acct_idx).and_then(|a| a.folders.get(fi)) else {
-            return Task::none();
-        };
-        let mailbox_id = folder.mailbox_id.clone();
-        let Some(cache) = &self.cache else {
-            return Task::none();
-        };
-        let cache = cache.clone();
-        let aid = self.active_account_id();
-        let mid = mailbox_id.clone();
-        let epoch = self.folder_epoch;
-        if let Some(handle) = self.folder_abort.take() {
-            handle.abort();
-        }
-        let (abort_handle, abort_reg) = AbortHandle::new_pair();
-        self.folder_abort = Some(abort_handle);
-        cosmic::task::future(async move {
-            match Abortable::new(
-                cache.load_messages(
-                    aid.clone(),
-                    mid.clone(),
-                    DEFAULT_PAGE_SIZE,
-                    offset,
-                ),
-                abort_reg,
-            )
-            .await
-            {
-                Ok(result) => Message::CachedMessagesLoaded {
-                    account_id: aid,
-                    mailbox_id: mid,
-                    offset,
-                    epoch,
-                    result,
-                },
-                Err(_) => Message::Noop,
-            }
-        })
-    }
-
     pub(super) fn handle_refresh(&mut self) -> Task<Message> {
         if self.refresh_phase.is_in_flight() {
             if refresh_has_timed_out(self.refresh_started_at, self.refresh_phase.is_timeout_reported()) {
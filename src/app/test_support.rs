@@ -0,0 +1,47 @@
+//! Shared fixtures for app unit tests.
+
+use neverlight_mail_core::models::{Folder, MessageSummary};
+
+/// Folder whose display name is the last path segment.
+pub(super) fn sample_folder(mailbox_id: &str, path: &str, role: Option<&str>) -> Folder {
+    Folder {
+        mailbox_id: mailbox_id.to_string(),
+        path: path.to_string(),
+        name: path.rsplit('/').next().unwrap_or(path).to_string(),
+        role: role.map(str::to_string),
+        sort_order: 0,
+        unread_count: 0,
+        total_count: 0,
+    }
+}
+
+/// Read, unstarred, unthreaded message in mailbox `M1`; override fields with
+/// struct update syntax.
+pub(super) fn sample_summary(account_id: &str, email_id: &str) -> MessageSummary {
+    MessageSummary {
+        email_id: email_id.to_string(),
+        account_id: account_id.to_string(),
+        context_mailbox_id: "M1".to_string(),
+        subject: format!("subject {email_id}"),
+        from: "alice@example.com".to_string(),
+        to: "bob@example.com".to_string(),
+        date: String::new(),
+        timestamp: 0,
+        reply_to: None,
+        message_id: format!("<{email_id}@example.com>"),
+        in_reply_to: None,
+        thread_id: Some(format!("T-{email_id}")),
+        thread_depth: 0,
+        is_read: true,
+        is_starred: false,
+        preview: String::new(),
+        list_unsubscribe: None,
+        list_unsubscribe_post: None,
+        list_id: None,
+        list_post: None,
+        authentication_results: None,
+        disposition_notification_to: None,
+        mdn_sent: false,
+        has_attachment: false,
+    }
+}
@@ -1,9 +1,12 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::time::Instant;
 
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
 use cosmic::app::Core;
+use cosmic::iced::widget::scrollable;
 use cosmic::widget::{image, markdown, pane_grid, text_editor};
 use futures::future::AbortHandle;
 
@@ -11,16 +14,236 @@ use neverlight_mail_core::client::JmapClient;
 use neverlight_mail_core::config::{AccountConfig, AccountId};
 use neverlight_mail_core::models::{AttachmentData, Folder, MessageSummary};
 use neverlight_mail_core::setup::SetupModel;
-use neverlight_mail_core::store::CacheHandle;
+use neverlight_mail_core::sieve::SieveScript;
+use neverlight_mail_core::store::{CacheHandle, ReadReceipt, SnoozedEntry};
+use neverlight_mail_core::vacation::VacationResponse;
 
+use super::settings::{AppSettings, ListSort, QuickFilter, RuleAction, RuleCondition, SearchScope};
 use crate::dnd_models::DraggedFiles;
 use crate::ui::compose_dialog::ComposeMode;
 
+/// A run of the rich message body: markdown text, or an inline `cid:` image.
+#[derive(Debug, Clone)]
+pub enum BodyBlock {
+    Markdown(Vec<markdown::Item>),
+    Image(image::Handle),
+}
+
+/// Raw RFC 5322 source of one message, fetched on request.
+pub struct RawSource {
+    pub email_id: String,
+    pub headers: Vec<(String, String)>,
+    /// Read-only editor over the source text (selectable, monospace).
+    pub editor: text_editor::Content,
+}
+
+/// What an export writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One `.eml` file per message in a directory.
+    Eml,
+    /// A single mboxrd file.
+    Mbox,
+    /// A Maildir (`cur/`, `new/`, `tmp/`) named after the folder.
+    Maildir,
+}
+
+/// The messages an export covers.
+#[derive(Debug, Clone)]
+pub enum ExportSource {
+    /// Known messages, e.g. the open conversation.
+    Messages(Vec<MessageSummary>),
+    /// Every message in a mailbox, by mailbox id.
+    Mailbox(String),
+}
+
+/// A bulk export; its subscription runs while the job is set.
+#[derive(Debug, Clone)]
+pub struct ExportJob {
+    pub id: u64,
+    pub account_id: AccountId,
+    pub source: ExportSource,
+    pub format: ExportFormat,
+    pub destination: PathBuf,
+    /// Messages processed so far, and the total once the listing is done.
+    pub done: usize,
+    pub total: Option<usize>,
+}
+
+/// A running import into one mailbox; its subscription runs while set.
+#[derive(Debug, Clone)]
+pub struct ImportJob {
+    pub id: u64,
+    pub dest: MailboxIdentity,
+    /// `.eml` files and mbox archives, imported in order.
+    pub paths: Vec<PathBuf>,
+    pub files_done: usize,
+    pub imported: usize,
+}
+
+/// How a list offers unsubscribing, picked from `List-Unsubscribe`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnsubscribeMethod {
+    /// RFC 8058 POST to an https URL.
+    OneClick(String),
+    /// A `mailto:` URI; opens a prefilled compose.
+    Mailto(String),
+    /// A web page, opened in the browser.
+    Browser(String),
+}
+
+/// How messages of the list being left are recognised.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListMatch {
+    /// Lowercased `List-Id` identifier.
+    ListId(String),
+    /// Host of the `List-Unsubscribe` URIs (the domain, for `mailto:`),
+    /// when the list sends no `List-Id`.
+    UnsubscribeOrigin(String),
+}
+
+/// An unsubscribe the user is confirming or that is in flight.
+#[derive(Debug, Clone)]
+pub struct UnsubscribeRequest {
+    pub account_id: AccountId,
+    /// Bare sender address, shown in the dialog.
+    pub sender: String,
+    /// Messages of this list can be archived after; `None` when the message
+    /// names neither a list id nor an unsubscribe origin.
+    pub list: Option<ListMatch>,
+    pub method: UnsubscribeMethod,
+    pub archive: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum UnsubscribePhase {
+    Closed,
+    Confirming {
+        request: UnsubscribeRequest,
+        error: Option<String>,
+    },
+    /// One-click request in flight.
+    Sending(UnsubscribeRequest),
+}
+
+impl UnsubscribePhase {
+    pub fn is_open(&self) -> bool {
+        !matches!(self, Self::Closed)
+    }
+}
+
+/// When an invited event starts or ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventTime {
+    /// All-day (`VALUE=DATE`).
+    Date(NaiveDate),
+    /// UTC, or a zone the invite defines.
+    Instant(DateTime<FixedOffset>),
+    /// Floating time, or a `TZID` without a `VTIMEZONE`.
+    Local {
+        time: NaiveDateTime,
+        tzid: Option<String>,
+    },
+}
+
+/// Organizer or attendee of an invite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarAddress {
+    pub email: String,
+    pub name: Option<String>,
+    /// `PARTSTAT`, uppercased: `ACCEPTED`, `NEEDS-ACTION`, …
+    pub partstat: Option<String>,
+}
+
+/// A calendar invitation parsed from a message's `text/calendar` part.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invite {
+    /// iTIP method, uppercased: `REQUEST`, `CANCEL`, …
+    pub method: Option<String>,
+    pub summary: String,
+    pub start: Option<EventTime>,
+    pub end: Option<EventTime>,
+    pub location: Option<String>,
+    pub organizer: Option<CalendarAddress>,
+    pub attendees: Vec<CalendarAddress>,
+    /// Content lines a REPLY echoes back (UID, SEQUENCE, ORGANIZER, …).
+    pub reply_lines: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rsvp {
+    Accepted,
+    Tentative,
+    Declined,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RsvpState {
+    Unsent,
+    Sending(Rsvp),
+    Sent(Rsvp),
+}
+
+#[derive(Debug, Clone)]
+pub struct InviteCard {
+    pub invite: Invite,
+    pub rsvp: RsvpState,
+}
+
+/// Unanswered read-receipt request on the selected message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceiptPrompt {
+    pub email_id: String,
+    /// Where the receipt would go, when that is not the sender.
+    pub notify: Option<String>,
+    /// The account's server supports JMAP MDN.
+    pub can_send: bool,
+}
+
+/// A clicked link held back for confirmation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkCheck {
+    /// What opens on confirmation, cleaned when that setting is on.
+    pub url: String,
+    /// Visible link text, when the body had any.
+    pub text: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+/// How far the sender of a message could be verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthVerdict {
+    /// No `Authentication-Results` to go on.
+    Unknown,
+    Verified(String),
+    Unverified(String),
+    /// The From domain fails DMARC or the display name impersonates us.
+    Danger(String),
+}
+
+/// A mailing list seen in the cache, merged across accounts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailingList {
+    /// Lowercased `List-Id` identifier, e.g. `rust-users.example.org`.
+    pub id: String,
+    pub name: String,
+    pub unread: usize,
+}
+
+/// Remote images fetched on request for one message.
+#[derive(Debug, Clone)]
+pub struct RemoteImages {
+    pub email_id: String,
+    pub handles: Vec<image::Handle>,
+    /// URLs that failed, were not images, or were too large.
+    pub failed: usize,
+}
+
 #[derive(Debug, Clone)]
 pub struct ConversationEntry {
     pub email_id: String,
     pub summary: MessageSummary,
-    pub markdown_items: Vec<markdown::Item>,
+    pub body_blocks: Vec<BodyBlock>,
     pub plain_body: String,
     pub attachments: Vec<AttachmentData>,
     pub image_handles: Vec<Option<image::Handle>>,
@@ -90,6 +313,35 @@ impl SearchPhase {
     }
 }
 
+/// "Save search" dialog lifecycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SavedSearchPhase {
+    Closed,
+    /// Naming the current query; `all_accounts` widens it beyond the active account.
+    Naming {
+        query: String,
+        name: String,
+        all_accounts: bool,
+    },
+}
+
+impl SavedSearchPhase {
+    pub fn is_open(&self) -> bool {
+        !matches!(self, Self::Closed)
+    }
+}
+
+/// Background write of the app settings file: one at a time, with a
+/// follow-up when settings change mid-write.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SettingsWrite {
+    #[default]
+    Idle,
+    InFlight {
+        pending: bool,
+    },
+}
+
 /// Refresh lane coalescing state.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RefreshPhase {
@@ -159,6 +411,11 @@ pub enum ErrorSurface {
 pub enum FlagIntentKind {
     ToggleRead,
     ToggleStar,
+    /// Set-only variants for mail rules; a no-op when already set.
+    MarkRead,
+    Star,
+    /// A snoozed message coming back.
+    MarkUnread,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -178,6 +435,35 @@ pub struct MessageIdentity {
 pub struct PendingFlagIntent {
     pub message: MessageIdentity,
     pub kind: FlagIntentKind,
+    pub origin: IntentOrigin,
+}
+
+/// Who asked for a flag or move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntentOrigin {
+    /// The user. A newer intent replaces one still waiting for its lane.
+    User,
+    /// A mail rule or a snooze wake-up, acting on messages that may not be
+    /// listed; `flags` are the message's flags as last seen (listed
+    /// messages use their own). Queued in order behind the user's intents;
+    /// failures go to the status line rather than the error surface.
+    Background { flags: u8 },
+}
+
+/// How a move is carried out on the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveKind {
+    /// Plain `mailboxIds` patch.
+    Plain,
+    /// Move into the Snoozed mailbox. The entry is saved to the schedule
+    /// once the move succeeds; when `server_managed`, the server's snooze
+    /// extension returns the message itself.
+    Snooze(SnoozedEntry),
+    /// Move a due message from the Snoozed mailbox back to its wake
+    /// mailbox, then drop its schedule entry.
+    Wake(SnoozedEntry),
+    /// Add `dest` to the message's mailboxes, leaving it in the source.
+    Label,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -185,6 +471,227 @@ pub struct PendingMoveIntent {
     pub message: MessageIdentity,
     pub source: MailboxIdentity,
     pub dest: MailboxIdentity,
+    pub kind: MoveKind,
+    pub origin: IntentOrigin,
+}
+
+/// Wake-up time presets offered by the snooze dialog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnoozeChoice {
+    LaterToday,
+    Tomorrow,
+    Weekend,
+    NextWeek,
+    /// Parse the dialog's free-form input.
+    Custom,
+}
+
+/// Snooze dialog lifecycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnoozePhase {
+    Closed,
+    /// Picking a wake-up time for `target`.
+    Choosing {
+        target: MessageIdentity,
+        custom: String,
+        error: Option<String>,
+    },
+    /// The account has no Snoozed mailbox yet; the snooze resumes once it exists.
+    CreatingMailbox {
+        target: MessageIdentity,
+        until: i64,
+    },
+}
+
+impl SnoozePhase {
+    pub fn is_open(&self) -> bool {
+        !matches!(self, Self::Closed)
+    }
+}
+
+/// The account's out-of-office reply, as being edited.
+pub struct VacationForm {
+    pub account_id: AccountId,
+    pub enabled: bool,
+    /// Local `YYYY-MM-DD HH:MM`; empty for no limit.
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub text_body: text_editor::Content,
+    pub html_body: text_editor::Content,
+}
+
+pub enum VacationPhase {
+    Closed,
+    /// Fetching the current response before editing it.
+    Loading(AccountId),
+    Editing {
+        form: VacationForm,
+        error: Option<String>,
+    },
+    Saving(VacationForm),
+}
+
+impl VacationPhase {
+    pub fn is_open(&self) -> bool {
+        !matches!(self, Self::Closed)
+    }
+}
+
+/// What a filter rule matches on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterField {
+    From,
+    Subject,
+    List,
+}
+
+impl FilterField {
+    pub const ALL: [Self; 3] = [Self::From, Self::Subject, Self::List];
+    pub const LABELS: &'static [&'static str] = &["From", "Subject", "List"];
+
+    pub fn index(self) -> usize {
+        match self {
+            Self::From => 0,
+            Self::Subject => 1,
+            Self::List => 2,
+        }
+    }
+}
+
+/// What a filter rule does with a matching message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterAction {
+    FileInto,
+    MarkRead,
+    Star,
+    Discard,
+}
+
+impl FilterAction {
+    pub const ALL: [Self; 4] = [Self::FileInto, Self::MarkRead, Self::Star, Self::Discard];
+    pub const LABELS: &'static [&'static str] = &["File into", "Mark read", "Star", "Discard"];
+
+    pub fn index(self) -> usize {
+        match self {
+            Self::FileInto => 0,
+            Self::MarkRead => 1,
+            Self::Star => 2,
+            Self::Discard => 3,
+        }
+    }
+}
+
+/// "If `field` contains `value`, do `action`" — one rule-builder entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterRule {
+    pub field: FilterField,
+    pub value: String,
+    pub action: FilterAction,
+    /// Target folder path for `FileInto`.
+    pub folder: String,
+}
+
+/// A Sieve script open for editing.
+pub struct FilterEditor {
+    pub account_id: AccountId,
+    /// `None` until a new script is first saved.
+    pub script_id: Option<String>,
+    pub name: String,
+    /// Rules added from the builder this session; their Sieve is in `script`.
+    pub rules: Vec<FilterRule>,
+    pub draft: FilterRule,
+    pub script: text_editor::Content,
+    /// Folder paths offered for "file into".
+    pub folders: Vec<String>,
+    /// Why the server rejected the script.
+    pub invalid: Option<String>,
+    pub error: Option<String>,
+    /// Loading, validating or saving.
+    pub busy: bool,
+}
+
+pub enum FiltersPhase {
+    Closed,
+    Loading(AccountId),
+    List {
+        account_id: AccountId,
+        scripts: Vec<SieveScript>,
+        error: Option<String>,
+        busy: bool,
+    },
+    Editing(FilterEditor),
+}
+
+impl FiltersPhase {
+    pub fn is_open(&self) -> bool {
+        !matches!(self, Self::Closed)
+    }
+}
+
+/// A mail rule being written in the rules dialog.
+pub struct RuleDraft {
+    /// Index into `settings.mail_rules` when changing an existing rule.
+    pub editing: Option<usize>,
+    pub name: String,
+    pub conditions: Vec<RuleCondition>,
+    pub actions: Vec<RuleAction>,
+    /// Dropdown choices for the next condition and action.
+    pub condition_kind: usize,
+    pub condition_value: String,
+    pub action_kind: usize,
+    pub action_folder: Option<usize>,
+}
+
+/// What a dry run would do to one message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RulePreviewRow {
+    pub from: String,
+    pub subject: String,
+    pub actions: String,
+}
+
+pub enum RulesPhase {
+    Closed,
+    Editing {
+        account_id: AccountId,
+        draft: RuleDraft,
+        /// Folder paths offered for move and label.
+        folders: Vec<String>,
+        error: Option<String>,
+    },
+    /// Dry run over the selected folder; `rows` is `None` while loading.
+    Preview {
+        account_id: AccountId,
+        folder: String,
+        rows: Option<Vec<RulePreviewRow>>,
+    },
+}
+
+impl RulesPhase {
+    pub fn is_open(&self) -> bool {
+        !matches!(self, Self::Closed)
+    }
+}
+
+/// Cross-account views shown above the per-account folder trees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VirtualView {
+    AllInboxes,
+    AllUnread,
+    AllStarred,
+}
+
+impl VirtualView {
+    pub const ALL: [VirtualView; 3] = [Self::AllInboxes, Self::AllUnread, Self::AllStarred];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::AllInboxes => "All Inboxes",
+            Self::AllUnread => "All Unread",
+            Self::AllStarred => "All Starred",
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -209,6 +716,8 @@ pub struct AccountState {
     pub backfill_active: bool,
     /// Pause flag: set during head sync to avoid contention.
     pub backfill_pause: Arc<AtomicBool>,
+    /// Server vacation response; `None` until loaded or when unsupported.
+    pub vacation: Option<VacationResponse>,
 }
 
 impl AccountState {
@@ -225,6 +734,7 @@ impl AccountState {
             backfill_progress: HashMap::new(),
             backfill_active: false,
             backfill_pause: Arc::new(AtomicBool::new(false)),
+            vacation: None,
         }
     }
 
@@ -264,18 +774,48 @@ pub struct AppModel {
     pub(super) selected_folder: Option<usize>,
     pub(super) selected_mailbox_id: Option<String>,
     pub(super) selected_folder_evicted: bool,
+    /// Set while a cross-account view replaces the selected folder.
+    pub(super) virtual_view: Option<VirtualView>,
 
     pub(super) messages: Vec<MessageSummary>,
     pub(super) selected_message: Option<usize>,
+    /// Folder row of `messages[0]`; `messages` holds one bounded window.
     pub(super) messages_offset: u32,
+    /// Folder row of the window being loaded; becomes `messages_offset`
+    /// once its rows arrive.
+    pub(super) pending_messages_offset: u32,
+    /// Whether the folder continues past the loaded window.
     pub(super) has_more_messages: bool,
+    /// Message list scroll offset and viewport height, in pixels.
+    pub(super) list_scroll_y: f32,
+    pub(super) list_viewport_height: f32,
 
     pub(super) preview_body: String,
-    pub(super) preview_markdown: Vec<markdown::Item>,
+    pub(super) preview_blocks: Vec<BodyBlock>,
     pub(super) preview_editor: text_editor::Content,
     pub(super) preview_selectable: bool,
     pub(super) preview_attachments: Vec<AttachmentData>,
     pub(super) preview_image_handles: Vec<Option<image::Handle>>,
+    /// Remote images the user asked for; shown only while that message is selected.
+    pub(super) remote_images: Option<RemoteImages>,
+    /// Invites in the loaded message or conversation, by email id.
+    pub(super) invites: HashMap<String, InviteCard>,
+    /// Visible text of links in the loaded message or conversation, by URL.
+    pub(super) link_texts: HashMap<String, String>,
+    /// Suspicious link awaiting confirmation.
+    pub(super) link_check: Option<LinkCheck>,
+    /// Receipts returned for our messages in the loaded view, by email id.
+    pub(super) read_receipts: HashMap<String, Vec<ReadReceipt>>,
+    /// Raw source of the selected message, once fetched for "View source"
+    /// or "All headers".
+    pub(super) raw_source: Option<RawSource>,
+    pub(super) show_source: bool,
+    pub(super) headers_expanded: bool,
+    /// Running bulk export, if any.
+    pub(super) export: Option<ExportJob>,
+    /// Running import, if any.
+    pub(super) import: Option<ImportJob>,
+    pub(super) unsubscribe_phase: UnsubscribePhase,
 
     /// Conversation view: all messages in the selected thread, bodies loading progressively.
     pub(super) conversation: Vec<ConversationEntry>,
@@ -301,6 +841,8 @@ pub struct AppModel {
     pub(super) folder_abort: Option<AbortHandle>,
     pub(super) message_abort: Option<AbortHandle>,
     pub(super) body_abort: Option<AbortHandle>,
+    pub(super) remote_images_abort: Option<AbortHandle>,
+    pub(super) source_abort: Option<AbortHandle>,
 
     pub(super) status_message: String,
     pub(super) error_surface: Option<ErrorSurface>,
@@ -313,6 +855,8 @@ pub struct AppModel {
     pub(super) mutation_epoch: u64,
     pub(super) flag_epoch: u64,
     pub(super) body_epoch: u64,
+    pub(super) export_epoch: u64,
+    pub(super) import_epoch: u64,
     /// Refresh lane coalescing state.
     pub(super) refresh_phase: RefreshPhase,
     pub(super) refresh_accounts_outstanding: HashSet<AccountId>,
@@ -325,6 +869,9 @@ pub struct AppModel {
     pub(super) flag_in_flight_accounts: HashSet<AccountId>,
     pub(super) pending_move_intents: HashMap<AccountId, PendingMoveIntent>,
     pub(super) pending_flag_intents: HashMap<AccountId, PendingFlagIntent>,
+    /// Background intents, run in order once the user's have drained.
+    pub(super) background_move_intents: HashMap<AccountId, VecDeque<PendingMoveIntent>>,
+    pub(super) background_flag_intents: HashMap<AccountId, VecDeque<PendingFlagIntent>>,
     /// Recently notified messages (dedup push events).
     pub(super) notified_messages: HashSet<MessageIdentity>,
     /// Diagnostics counters.
@@ -341,6 +888,38 @@ pub struct AppModel {
     // Search state
     pub(super) search_phase: SearchPhase,
     pub(super) search_query: String,
+    /// Accounts the listed results were searched in.
+    pub(super) search_accounts: Vec<AccountId>,
+    /// Mailbox the listed results were limited to ("this folder" scope).
+    pub(super) search_mailbox: Option<String>,
+    /// Hits per account label, for multi-account searches.
+    pub(super) search_account_counts: Vec<(String, usize)>,
+    /// Result rows fetched by "Search server" that were not in the cache.
+    pub(super) server_hits: HashSet<(AccountId, String)>,
+    /// Query terms highlighted in the preview.
+    pub(super) search_terms: Vec<String>,
+    /// Match snippet per result row.
+    pub(super) search_snippets: HashMap<(AccountId, String), String>,
+    /// Current n/N match in the previewed body.
+    pub(super) search_match: Option<usize>,
+
+    // Saved searches (persisted in `settings`)
+    pub(super) settings: AppSettings,
+    pub(super) settings_write: SettingsWrite,
+    pub(super) saved_search_phase: SavedSearchPhase,
+    /// Unread hits per saved search id, refreshed after syncs.
+    pub(super) saved_search_counts: HashMap<String, usize>,
+    pub(super) saved_search_epoch: u64,
+    /// Saved searches whose counts are stale since a covered mailbox changed.
+    pub(super) saved_search_dirty: HashSet<String>,
+    /// Saved searches the latest count refresh is recounting.
+    pub(super) saved_search_recounting: HashSet<String>,
+    /// Saved search whose results are currently listed.
+    pub(super) active_saved_search: Option<String>,
+
+    // Mailing lists (sidebar "Lists" section)
+    pub(super) mailing_lists: Vec<MailingList>,
+    pub(super) lists_epoch: u64,
 
     // Compose dialog state
     pub(super) compose_phase: ComposePhase,
@@ -353,6 +932,8 @@ pub struct AppModel {
     pub(super) compose_in_reply_to: Option<String>,
     pub(super) compose_references: Option<String>,
     pub(super) compose_attachments: Vec<AttachmentData>,
+    /// Ask recipients for a read receipt.
+    pub(super) compose_request_receipt: bool,
     pub(super) compose_error: Option<String>,
     pub(super) compose_drag_hover: bool,
     // Cached for dialog() lifetime (updated when compose_account changes)
@@ -366,6 +947,22 @@ pub struct AppModel {
     pub(super) oauth_phase: OAuthSetupPhase,
     pub(super) oauth_error: Option<String>,
 
+    // Snooze state
+    pub(super) snooze_phase: SnoozePhase,
+    /// Wake-up times (unix seconds) by account → email_id, for display.
+    pub(super) snooze_schedule: HashMap<AccountId, HashMap<String, i64>>,
+    /// Snoozed messages currently being returned to their wake mailbox.
+    pub(super) snooze_waking: HashSet<(AccountId, String)>,
+
+    // Vacation response dialog
+    pub(super) vacation_phase: VacationPhase,
+
+    // Server-side filters (Sieve) dialog
+    pub(super) filters_phase: FiltersPhase,
+
+    // Local mail rules
+    pub(super) rules_phase: RulesPhase,
+
     // DnD state
     pub(super) folder_drag_target: Option<usize>,
 
@@ -390,6 +987,18 @@ pub enum Message {
     },
 
     SelectFolder(usize, usize), // (account_idx, folder_idx)
+    SelectVirtualView(VirtualView),
+    VirtualViewLoaded {
+        view: VirtualView,
+        epoch: u64,
+        result: Result<Vec<MessageSummary>, String>,
+    },
+    /// An account's source mailboxes for `view` were synced into the cache.
+    VirtualViewSynced {
+        view: VirtualView,
+        account_id: AccountId,
+        result: Result<(), String>,
+    },
 
     ViewBody(usize),
     BodyDeferred {
@@ -401,7 +1010,6 @@ pub enum Message {
         epoch: u64,
         result: Result<(String, String, Vec<AttachmentData>), String>,
     },
-    LinkClicked(markdown::Url),
     CopyBody,
     ToggleSelectableView,
     PreviewBodyAction(text_editor::Action),
@@ -410,6 +1018,207 @@ pub enum Message {
     SaveAttachment(usize),
     SaveAttachmentComplete(Result<String, String>),
 
+    // Raw source / full headers
+    ToggleSourceView,
+    ToggleAllHeaders,
+    RawSourceLoaded {
+        email_id: String,
+        epoch: u64,
+        result: Result<Vec<u8>, String>,
+    },
+    SourceAction(text_editor::Action),
+
+    // Export
+    /// Save the selected message as an `.eml` file.
+    ExportMessage,
+    /// Save every message of the open conversation as `.eml` files.
+    ExportConversation,
+    /// Export the selected folder.
+    ExportFolder(ExportFormat),
+    ExportStart {
+        account_id: AccountId,
+        source: ExportSource,
+        format: ExportFormat,
+        destination: PathBuf,
+    },
+    ExportProgress {
+        job: u64,
+        done: usize,
+        total: usize,
+    },
+    /// Messages written and messages skipped (not cached offline, or failed).
+    ExportFinished {
+        job: u64,
+        result: Result<(usize, usize), String>,
+    },
+    ExportCancel,
+    MessageExported(Result<String, String>),
+    /// A message drag started; write the `.eml` it offers to other apps.
+    DragExportPrepare {
+        account_id: AccountId,
+        email_id: String,
+    },
+    DragExportReady(Result<(), String>),
+
+    // Import
+    /// Pick `.eml` / mbox files to import into the selected folder.
+    ImportFiles,
+    /// Files dropped from a file manager onto a sidebar folder.
+    ImportDropped {
+        dest: MailboxIdentity,
+        files: DraggedFiles,
+    },
+    ImportStart {
+        dest: MailboxIdentity,
+        paths: Vec<PathBuf>,
+    },
+    ImportProgress {
+        job: u64,
+        files_done: usize,
+        imported: usize,
+    },
+    /// Messages imported and messages or files that failed.
+    ImportFinished {
+        job: u64,
+        result: Result<(usize, usize), String>,
+    },
+    ImportCancel,
+
+    // List unsubscribe
+    UnsubscribeOpen,
+    UnsubscribeArchiveToggled(bool),
+    UnsubscribeConfirm,
+    UnsubscribeCancel,
+    UnsubscribeSent(Result<(), String>),
+    /// Messages moved to Archive after unsubscribing.
+    UnsubscribeArchived(Result<usize, String>),
+
+    // Link safety
+    LinkClicked(markdown::Url),
+    LinkOpen,
+    LinkCopy,
+    LinkCancel,
+    /// Strip tracking parameters and unwrap redirects before opening.
+    CleanLinksToggled(bool),
+
+    // Read receipts
+    ReceiptSend(String),
+    ReceiptIgnore(String),
+    /// Stop asking about read receipt requests.
+    ReceiptAlwaysIgnore,
+    ReceiptAnswered {
+        email_id: String,
+        /// A receipt was sent, rather than the request declined.
+        sent: bool,
+        result: Result<(), String>,
+    },
+    ReadReceiptsLoaded(Vec<(String, Vec<ReadReceipt>)>),
+
+    // Vacation response
+    VacationOpen(AccountId),
+    VacationLoaded {
+        account_id: AccountId,
+        result: Result<VacationResponse, String>,
+    },
+    VacationEnabledToggled(bool),
+    VacationFromChanged(String),
+    VacationToChanged(String),
+    VacationSubjectChanged(String),
+    VacationTextAction(text_editor::Action),
+    VacationHtmlAction(text_editor::Action),
+    VacationSave,
+    VacationCancel,
+    VacationSaved {
+        account_id: AccountId,
+        result: Result<VacationResponse, String>,
+    },
+
+    // Server-side filters
+    FiltersOpen(AccountId),
+    FiltersLoaded {
+        account_id: AccountId,
+        result: Result<Vec<SieveScript>, String>,
+    },
+    FiltersClose,
+    /// Make a script the active one; `None` turns filtering off.
+    FilterActivate(Option<String>),
+    FilterActivated(Result<(), String>),
+    FilterNew,
+    FilterEdit(String),
+    FilterContentLoaded {
+        script_id: String,
+        result: Result<String, String>,
+    },
+    FilterNameChanged(String),
+    FilterDraftField(usize),
+    FilterDraftValue(String),
+    FilterDraftAction(usize),
+    FilterDraftFolder(usize),
+    FilterAddRule,
+    FilterRemoveRule(usize),
+    FilterScriptAction(text_editor::Action),
+    FilterValidate,
+    /// Server verdict: `Some(reason)` when the script is invalid.
+    FilterValidated(Result<Option<String>, String>),
+    FilterSave,
+    FilterSaved(Result<String, String>),
+    /// Leave the editor for the script list.
+    FilterBack,
+
+    // Local mail rules
+    RulesOpen,
+    RulesClose,
+    RuleToggled(usize, bool),
+    RuleEdit(usize),
+    RuleDelete(usize),
+    RuleNameChanged(String),
+    RuleConditionKindChanged(usize),
+    RuleConditionValueChanged(String),
+    RuleConditionAdd,
+    RuleConditionRemove(usize),
+    RuleActionKindChanged(usize),
+    RuleActionFolderChanged(usize),
+    RuleActionAdd,
+    RuleActionRemove(usize),
+    RuleSave,
+    /// Dry run over the selected folder.
+    RulesPreview,
+    RulesApplyNow,
+    RulesFolderLoaded {
+        account_id: AccountId,
+        /// Queue the actions rather than preview them.
+        apply: bool,
+        result: Result<Vec<MessageSummary>, String>,
+    },
+    /// Back from the preview to the rule list.
+    RulesBack,
+    /// Inbox mail received since the account's rules mark, after a sync.
+    RulesArrivals {
+        account_id: AccountId,
+        result: Result<Vec<MessageSummary>, String>,
+    },
+
+    // Calendar invites
+    InviteRespond {
+        email_id: String,
+        response: Rsvp,
+    },
+    InviteResponded {
+        email_id: String,
+        response: Rsvp,
+        result: Result<(), String>,
+    },
+
+    // Remote images (opt-in per message / per sender)
+    LoadRemoteImages,
+    /// Add the selected message's sender to the allow-list and load its images.
+    AlwaysLoadRemoteImages,
+    RemoteImagesLoaded {
+        email_id: String,
+        epoch: u64,
+        result: Result<RemoteImages, String>,
+    },
+
     ThreadLoaded {
         thread_id: String,
         email_id: String,
@@ -434,6 +1243,7 @@ pub enum Message {
         account_id: AccountId,
         mailbox_id: String,
         offset: u32,
+        limit: u32,
         epoch: u64,
         result: Result<Vec<MessageSummary>, String>,
     },
@@ -448,7 +1258,8 @@ pub enum Message {
         epoch: u64,
         result: Result<(), String>,
     },
-    LoadMoreMessages,
+    /// Scrolling out of the loaded cache window loads the one in view.
+    MessageListScrolled(scrollable::Viewport),
 
     // Flag/move actions
     ToggleRead(usize),
@@ -460,6 +1271,7 @@ pub enum Message {
     RunMoveIntent(PendingMoveIntent),
     FlagOpComplete {
         message: MessageIdentity,
+        origin: IntentOrigin,
         epoch: u64,
         prev_flags: u8,
         result: Result<u8, String>,
@@ -467,6 +1279,8 @@ pub enum Message {
     MoveOpComplete {
         message: MessageIdentity,
         source: MailboxIdentity,
+        kind: MoveKind,
+        origin: IntentOrigin,
         epoch: u64,
         result: Result<(), String>,
     },
@@ -479,6 +1293,8 @@ pub enum Message {
     // Compose messages
     ComposeNew,
     ComposeReply,
+    /// Reply to the list's `List-Post` address instead of the sender.
+    ComposeReplyList,
     ComposeForward,
     ComposeAccountChanged(usize),
     ComposeFromChanged(usize),
@@ -493,6 +1309,7 @@ pub enum Message {
     ComposeFileTransferResolved(Result<Vec<String>, String>),
     ComposeDragEnter,
     ComposeDragLeave,
+    ComposeRequestReceiptToggled(bool),
     ComposeSend,
     ComposeCancel,
     SendComplete(Result<(), String>),
@@ -507,13 +1324,52 @@ pub enum Message {
     SearchActivate,
     SearchQueryChanged(String),
     SearchExecute,
+    SearchScopeChanged(SearchScope),
     SearchResultsLoaded {
         query: String,
         epoch: u64,
         result: Result<Vec<MessageSummary>, String>,
+        /// Accounts that failed or were offline; the others' hits still list.
+        skipped: Vec<AccountId>,
+    },
+    /// Re-run the current search as JMAP `Email/query` on the server.
+    SearchServer,
+    ServerSearchLoaded {
+        query: String,
+        epoch: u64,
+        result: Result<Vec<MessageSummary>, String>,
+        skipped: Vec<AccountId>,
+    },
+    SearchSnippetsLoaded {
+        query: String,
+        snippets: HashMap<(AccountId, String), String>,
     },
+    /// Jump to the next/previous search match in the preview (n / N).
+    SearchMatchNext,
+    SearchMatchPrev,
     SearchClear,
 
+    // Saved searches
+    SavedSearchSaveOpen,
+    SavedSearchNameChanged(String),
+    SavedSearchAllAccountsToggled(bool),
+    SavedSearchSaveConfirm,
+    SavedSearchSaveCancel,
+    SavedSearchOpen(String),
+    SavedSearchDelete(String),
+    SavedSearchCountsLoaded {
+        epoch: u64,
+        counts: HashMap<String, usize>,
+    },
+
+    // Mailing lists
+    ListsLoaded {
+        epoch: u64,
+        lists: Vec<MailingList>,
+    },
+    /// Show a list's messages across folders and accounts.
+    ListOpen(String),
+
     // Message-to-folder drag
     DragMessageToFolder {
         message: MessageIdentity,
@@ -525,6 +1381,13 @@ pub enum Message {
 
     PaneResized(pane_grid::ResizeEvent),
     ToggleDiagnostics,
+    /// Message list preview lines on/off.
+    ShowPreviewsToggled(bool),
+    /// Background write of the app settings file finished.
+    SettingsSaved(Result<(), String>),
+    /// Sort order / quick filter of the selected folder.
+    ListSortChanged(ListSort),
+    QuickFilterToggled(QuickFilter),
 
     /// Auto-mark-read: fires 5s after a message is displayed
     AutoMarkRead(String),
@@ -543,6 +1406,27 @@ pub enum Message {
         mailbox_id: String,
     },
 
+    // Snooze
+    SnoozeOpen(usize),
+    SnoozeCustomChanged(String),
+    SnoozeConfirm(SnoozeChoice),
+    SnoozeCancel,
+    SnoozeMailboxReady {
+        account_id: AccountId,
+        result: Result<String, String>,
+    },
+    /// Load snooze schedules and wake anything that is due.
+    SnoozeWakeCheck,
+    SnoozeScheduleLoaded {
+        account_id: AccountId,
+        result: Result<Vec<SnoozedEntry>, String>,
+    },
+    SnoozeWoken {
+        account_id: AccountId,
+        email_id: String,
+        result: Result<(), String>,
+    },
+
     ForceReconnect(AccountId),
     Refresh,
     Noop,
@@ -0,0 +1,393 @@
+//! Cross-account virtual views: All Inboxes, All Unread, All Starred.
+//!
+//! A virtual view replaces the selected folder with cached messages merged
+//! from every account. Each row keeps its own `account_id` and
+//! `context_mailbox_id`, so actions route exactly as they do in a folder.
+
+use cosmic::app::Task;
+use futures::future::{AbortHandle, Abortable};
+use std::collections::HashSet;
+
+use neverlight_mail_core::models::{Folder, MessageSummary};
+use neverlight_mail_core::store::{ListQuery, SearchPredicate};
+
+use super::{AppModel, Message, Phase, VirtualView};
+
+/// Per-mailbox cache read for virtual views. The view's filter runs in the
+/// query, so every row counts toward the limit.
+const VIRTUAL_VIEW_SCAN_LIMIT: u32 = 500;
+
+/// Mailboxes of one account that feed `view`.
+fn source_mailboxes(view: VirtualView, folders: &[Folder]) -> Vec<String> {
+    match view {
+        VirtualView::AllInboxes | VirtualView::AllUnread => {
+            neverlight_mail_core::mailbox::find_by_role(folders, "inbox")
+                .or_else(|| {
+                    folders
+                        .iter()
+                        .find(|f| f.path == "INBOX")
+                        .map(|f| f.mailbox_id.clone())
+                })
+                .into_iter()
+                .collect()
+        }
+        VirtualView::AllStarred => folders
+            .iter()
+            .filter(|f| !matches!(f.role.as_deref(), Some("trash") | Some("junk")))
+            .map(|f| f.mailbox_id.clone())
+            .collect(),
+    }
+}
+
+/// Cache query for one source mailbox of `view`, unthreaded.
+fn view_query(view: VirtualView) -> ListQuery {
+    let predicates = match view {
+        VirtualView::AllInboxes => Vec::new(),
+        VirtualView::AllUnread => vec![SearchPredicate::Unread],
+        VirtualView::AllStarred => vec![SearchPredicate::Starred],
+    };
+    ListQuery {
+        predicates,
+        threaded: false,
+        ..ListQuery::default()
+    }
+}
+
+/// Mailboxes that are new in `after` or whose counts moved since `before`;
+/// only these need a fresh sync to keep a virtual view current.
+pub(super) fn changed_mailboxes(before: &[Folder], after: &[Folder]) -> HashSet<String> {
+    after
+        .iter()
+        .filter(|f| {
+            !before.iter().any(|b| {
+                b.mailbox_id == f.mailbox_id
+                    && b.unread_count == f.unread_count
+                    && b.total_count == f.total_count
+            })
+        })
+        .map(|f| f.mailbox_id.clone())
+        .collect()
+}
+
+/// De-duplicate (an email can sit in several mailboxes) and sort newest
+/// first. Rows are flattened: threads never span accounts.
+pub(super) fn merge_newest_first(
+    messages: impl IntoIterator<Item = MessageSummary>,
+) -> Vec<MessageSummary> {
+    let mut seen: HashSet<(String, String)> = HashSet::new();
+    let mut merged: Vec<MessageSummary> = messages
+        .into_iter()
+        .filter(|m| seen.insert((m.account_id.clone(), m.email_id.clone())))
+        .map(|mut m| {
+            m.thread_depth = 0;
+            m
+        })
+        .collect();
+    merged.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    merged
+}
+
+impl AppModel {
+    pub(super) fn handle_unified(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::SelectVirtualView(view) => {
+                return self.handle_select_virtual_view(view);
+            }
+            Message::VirtualViewLoaded {
+                view,
+                epoch,
+                result: Ok(messages),
+            } => {
+                if epoch != self.folder_epoch || self.virtual_view != Some(view) {
+                    self.stale_apply_drop_count = self.stale_apply_drop_count.saturating_add(1);
+                    return Task::none();
+                }
+                self.folder_abort = None;
+                let prev = self.selected_message.and_then(|i| {
+                    self.messages
+                        .get(i)
+                        .map(|m| (m.account_id.clone(), m.email_id.clone()))
+                });
+                self.messages = messages;
+                self.selected_message = prev.and_then(|(aid, eid)| {
+                    self.messages
+                        .iter()
+                        .position(|m| m.account_id == aid && m.email_id == eid)
+                });
+                self.recompute_visible();
+                self.phase = Phase::Idle;
+                self.status_message = format!("{}: {} messages", view.label(), self.messages.len());
+            }
+            Message::VirtualViewLoaded {
+                epoch,
+                result: Err(e),
+                ..
+            } => {
+                if epoch != self.folder_epoch {
+                    self.stale_apply_drop_count = self.stale_apply_drop_count.saturating_add(1);
+                    return Task::none();
+                }
+                self.folder_abort = None;
+                self.phase = Phase::Idle;
+                log::warn!("Failed to load virtual view: {}", e);
+                self.set_status_error(format!("Failed to load messages: {}", e));
+            }
+            Message::VirtualViewSynced {
+                view,
+                account_id,
+                result,
+            } => {
+                if let Err(e) = result {
+                    log::warn!("Virtual view sync failed for {}: {}", account_id, e);
+                }
+                let counts = cosmic::task::batch(vec![
+                    self.refresh_saved_search_counts(),
+                    self.refresh_lists(),
+                ]);
+                if self.virtual_view == Some(view) {
+                    self.last_sync_at = Some(std::time::Instant::now());
+                    return cosmic::task::batch(vec![self.load_virtual_view(view), counts]);
+                }
+                return counts;
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+
+    fn handle_select_virtual_view(&mut self, view: VirtualView) -> Task<Message> {
+        self.virtual_view = Some(view);
+        self.active_saved_search = None;
+        self.selected_folder = None;
+        self.selected_mailbox_id = None;
+        self.selected_folder_evicted = false;
+        if let Some(handle) = self.message_abort.take() {
+            handle.abort();
+        }
+        self.message_epoch = self.message_epoch.saturating_add(1);
+        self.messages.clear();
+        self.selected_message = None;
+        self.preview_body.clear();
+        self.preview_blocks.clear();
+        self.preview_editor = cosmic::widget::text_editor::Content::new();
+        self.preview_attachments.clear();
+        self.preview_image_handles.clear();
+        self.messages_offset = 0;
+        self.pending_messages_offset = 0;
+        self.has_more_messages = false;
+        self.collapsed_threads.clear();
+        self.recompute_visible();
+        self.phase = Phase::Loading;
+        self.status_message = format!("Loading {}...", view.label());
+
+        // Served from the cache; folder syncs keep the sources current.
+        self.load_virtual_view(view)
+    }
+
+    /// Rebuild the merged list for `view` from the cache.
+    pub(super) fn load_virtual_view(&mut self, view: VirtualView) -> Task<Message> {
+        let Some(cache) = self.cache.clone() else {
+            return Task::none();
+        };
+        let sources: Vec<(String, Vec<String>)> = self
+            .accounts
+            .iter()
+            .map(|a| (a.config.id.clone(), source_mailboxes(view, &a.folders)))
+            .collect();
+
+        self.folder_epoch = self.folder_epoch.saturating_add(1);
+        let epoch = self.folder_epoch;
+        if let Some(handle) = self.folder_abort.take() {
+            handle.abort();
+        }
+        let (abort_handle, abort_reg) = AbortHandle::new_pair();
+        self.folder_abort = Some(abort_handle);
+        cosmic::task::future(async move {
+            let load = async {
+                let mut batches = Vec::new();
+                for (aid, mailbox_ids) in sources {
+                    for mid in mailbox_ids {
+                        match cache
+                            .load_messages(
+                                aid.clone(),
+                                mid.clone(),
+                                view_query(view),
+                                VIRTUAL_VIEW_SCAN_LIMIT,
+                                0,
+                            )
+                            .await
+                        {
+                            Ok(batch) => batches.push(batch),
+                            // One unreadable mailbox shouldn't hide every other account.
+                            Err(e) => log::warn!("Virtual view: skipping {}/{}: {}", aid, mid, e),
+                        }
+                    }
+                }
+                merge_newest_first(batches.into_iter().flatten())
+            };
+            match Abortable::new(load, abort_reg).await {
+                Ok(messages) => Message::VirtualViewLoaded {
+                    view,
+                    epoch,
+                    result: Ok(messages),
+                },
+                Err(_) => Message::Noop,
+            }
+        })
+    }
+
+    /// Pull the `changed` source mailboxes of one account into the cache.
+    /// Returns None when the account is offline, there is no cache, or no
+    /// source mailbox changed.
+    pub(super) fn sync_virtual_view_account(
+        &self,
+        view: VirtualView,
+        acct_idx: usize,
+        changed: &HashSet<String>,
+    ) -> Option<Task<Message>> {
+        let acct = self.accounts.get(acct_idx)?;
+        let client = acct.client.clone()?;
+        let cache = self.cache.clone()?;
+        let mailbox_ids: Vec<String> = source_mailboxes(view, &acct.folders)
+            .into_iter()
+            .filter(|mid| changed.contains(mid))
+            .collect();
+        if mailbox_ids.is_empty() {
+            return None;
+        }
+        let account_id = acct.config.id.clone();
+        Some(cosmic::task::future(async move {
+            let mut result = Ok(());
+            for mid in &mailbox_ids {
+                if let Err(e) = neverlight_mail_core::sync::sync_emails(
+                    &client,
+                    &cache,
+                    &account_id,
+                    mid,
+                    neverlight_mail_core::store::DEFAULT_PAGE_SIZE,
+                )
+                .await
+                {
+                    result = Err(e.to_string());
+                }
+            }
+            Message::VirtualViewSynced {
+                view,
+                account_id,
+                result,
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_support::{sample_folder as folder, sample_summary};
+
+    fn msg(
+        account_id: &str,
+        email_id: &str,
+        timestamp: i64,
+        read: bool,
+        starred: bool,
+    ) -> MessageSummary {
+        MessageSummary {
+            timestamp,
+            thread_depth: 1,
+            is_read: read,
+            is_starred: starred,
+            ..sample_summary(account_id, email_id)
+        }
+    }
+
+    #[test]
+    fn inbox_views_use_inbox_role_then_path() {
+        let folders = vec![
+            folder("M1", "Archive", Some("archive")),
+            folder("M2", "INBOX", None),
+        ];
+        assert_eq!(
+            source_mailboxes(VirtualView::AllInboxes, &folders),
+            vec!["M2"]
+        );
+
+        let folders = vec![
+            folder("M1", "Posteingang", Some("inbox")),
+            folder("M2", "INBOX", None),
+        ];
+        assert_eq!(
+            source_mailboxes(VirtualView::AllUnread, &folders),
+            vec!["M1"]
+        );
+    }
+
+    #[test]
+    fn starred_view_skips_trash_and_junk() {
+        let folders = vec![
+            folder("M1", "INBOX", Some("inbox")),
+            folder("M2", "Trash", Some("trash")),
+            folder("M3", "Spam", Some("junk")),
+            folder("M4", "Projects", None),
+        ];
+        assert_eq!(
+            source_mailboxes(VirtualView::AllStarred, &folders),
+            vec!["M1", "M4"]
+        );
+    }
+
+    #[test]
+    fn merge_sorts_newest_first_across_accounts() {
+        let merged = merge_newest_first(vec![
+            msg("a", "1", 10, true, false),
+            msg("a", "2", 30, true, false),
+            msg("b", "3", 20, true, false),
+        ]);
+        let ids: Vec<_> = merged.iter().map(|m| m.email_id.as_str()).collect();
+        assert_eq!(ids, vec!["2", "3", "1"]);
+        assert!(merged.iter().all(|m| m.thread_depth == 0));
+    }
+
+    #[test]
+    fn view_filters_run_in_the_cache_query() {
+        assert!(view_query(VirtualView::AllInboxes).predicates.is_empty());
+        assert_eq!(
+            view_query(VirtualView::AllUnread).predicates,
+            vec![SearchPredicate::Unread]
+        );
+        assert_eq!(
+            view_query(VirtualView::AllStarred).predicates,
+            vec![SearchPredicate::Starred]
+        );
+        assert!(!view_query(VirtualView::AllUnread).threaded);
+    }
+
+    #[test]
+    fn only_new_or_recounted_mailboxes_change() {
+        let counted = |id: &str, unread, total| Folder {
+            unread_count: unread,
+            total_count: total,
+            ..folder(id, id, None)
+        };
+        let before = vec![counted("M1", 1, 10), counted("M2", 0, 5)];
+        let after = vec![
+            counted("M1", 1, 10),
+            counted("M2", 0, 6),
+            counted("M3", 0, 0),
+        ];
+        let changed = changed_mailboxes(&before, &after);
+        assert_eq!(changed.len(), 2);
+        assert!(changed.contains("M2") && changed.contains("M3"));
+        assert!(changed_mailboxes(&after, &after).is_empty());
+    }
+
+    #[test]
+    fn merge_dedupes_per_account_only() {
+        let merged = merge_newest_first(vec![
+            msg("a", "1", 1, true, true),
+            msg("a", "1", 1, true, true),
+            msg("b", "1", 1, true, true),
+        ]);
+        assert_eq!(merged.len(), 2);
+    }
+}
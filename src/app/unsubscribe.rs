@@ -0,0 +1,508 @@
+//! Unsubscribing from list mail (RFC 2369 `List-Unsubscribe`, RFC 8058
+//! one-click).
+//!
+//! A one-click POST is used when the list offers it over https; otherwise
+//! the `mailto:` address gets a prefilled compose, and a web link opens in
+//! the browser after confirmation. Afterwards the list's messages in the
+//! current message list can be archived in one go, matched by `List-Id` or,
+//! failing that, by where `List-Unsubscribe` points.
+
+use std::time::Duration;
+
+use cosmic::app::Task;
+use cosmic::iced::Length;
+use cosmic::widget::{self, text_editor};
+use cosmic::Element;
+
+use neverlight_mail_core::models::MessageSummary;
+
+use super::remote_images::sender_address;
+use super::{
+    parse_list_id, AppModel, ListMatch, Message, UnsubscribeMethod, UnsubscribePhase,
+    UnsubscribeRequest,
+};
+
+const ONE_CLICK_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// URIs of a `List-Unsubscribe` value: angle-bracketed, comma-separated,
+/// possibly folded.
+fn list_unsubscribe_uris(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .filter_map(|part| {
+            let part = part.trim();
+            let uri = part.strip_prefix('<')?.split('>').next()?;
+            let uri: String = uri.split_whitespace().collect();
+            (!uri.is_empty()).then_some(uri)
+        })
+        .collect()
+}
+
+/// Best way to unsubscribe: one-click when the list signals it and has an
+/// https URI, else mail, else a web page.
+pub(super) fn unsubscribe_method(
+    list_unsubscribe: &str,
+    list_unsubscribe_post: Option<&str>,
+) -> Option<UnsubscribeMethod> {
+    let uris = list_unsubscribe_uris(list_unsubscribe);
+    let scheme_is =
+        |uri: &String, scheme: &str| url::Url::parse(uri).is_ok_and(|u| u.scheme() == scheme);
+    let one_click = list_unsubscribe_post
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("List-Unsubscribe=One-Click"));
+    let https = uris.iter().find(|u| scheme_is(u, "https"));
+    if let (true, Some(url)) = (one_click, https) {
+        return Some(UnsubscribeMethod::OneClick(url.clone()));
+    }
+    if let Some(mailto) = uris.iter().find(|u| scheme_is(u, "mailto")) {
+        return Some(UnsubscribeMethod::Mailto(mailto.clone()));
+    }
+    uris.iter()
+        .find(|u| scheme_is(u, "https") || scheme_is(u, "http"))
+        .map(|url| UnsubscribeMethod::Browser(url.clone()))
+}
+
+/// Host of an unsubscribe URI, or the domain of a `mailto:` address.
+fn uri_origin(uri: &str) -> Option<String> {
+    let url = url::Url::parse(uri).ok()?;
+    let host = if url.scheme() == "mailto" {
+        url.path().rsplit_once('@')?.1.to_string()
+    } else {
+        url.host_str()?.to_string()
+    };
+    (!host.is_empty()).then(|| host.to_lowercase())
+}
+
+/// The list a message came from: its `List-Id`, else the origin of its
+/// first `List-Unsubscribe` URI. The sender alone is not enough; lists
+/// share sending addresses with transactional mail.
+pub(super) fn list_match(msg: &MessageSummary) -> Option<ListMatch> {
+    if let Some((id, _)) = msg.list_id.as_deref().and_then(parse_list_id) {
+        return Some(ListMatch::ListId(id));
+    }
+    list_unsubscribe_uris(msg.list_unsubscribe.as_deref()?)
+        .iter()
+        .find_map(|uri| uri_origin(uri))
+        .map(ListMatch::UnsubscribeOrigin)
+}
+
+/// Whether `msg` belongs to `list`. A list id must match exactly; an origin
+/// matches any of the message's unsubscribe URIs.
+fn belongs_to_list(msg: &MessageSummary, list: &ListMatch) -> bool {
+    match list {
+        ListMatch::ListId(id) => msg
+            .list_id
+            .as_deref()
+            .and_then(parse_list_id)
+            .is_some_and(|(other, _)| other == *id),
+        ListMatch::UnsubscribeOrigin(origin) => msg
+            .list_unsubscribe
+            .as_deref()
+            .map(list_unsubscribe_uris)
+            .unwrap_or_default()
+            .iter()
+            .any(|uri| uri_origin(uri).as_deref() == Some(origin.as_str())),
+    }
+}
+
+/// Recipient, subject and body of a `mailto:` URI (RFC 6068).
+pub(super) fn mailto_parts(uri: &str) -> Option<(String, String, String)> {
+    let url = url::Url::parse(uri).ok()?;
+    let to = percent_decode(url.path());
+    if to.is_empty() {
+        return None;
+    }
+    let mut subject = String::from("unsubscribe");
+    let mut body = String::new();
+    for (key, value) in url.query_pairs() {
+        if key.eq_ignore_ascii_case("subject") {
+            subject = value.into_owned();
+        } else if key.eq_ignore_ascii_case("body") {
+            body = value.into_owned();
+        }
+    }
+    Some((to, subject, body))
+}
+
+/// Percent-decode a URI path; `form_urlencoded` is the decoder `url`
+/// exposes, so a literal `+` is escaped first.
+fn percent_decode(s: &str) -> String {
+    url::form_urlencoded::parse(format!("x={}", s.replace('+', "%2B")).as_bytes())
+        .next()
+        .map(|(_, v)| v.into_owned())
+        .unwrap_or_default()
+}
+
+async fn one_click(url: String) -> Result<(), String> {
+    let client = reqwest::Client::builder()
+        .referer(false)
+        .timeout(ONE_CLICK_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client
+        .post(&url)
+        .header(
+            reqwest::header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .body("List-Unsubscribe=One-Click")
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = response.status();
+    if status.is_success() || status.is_redirection() {
+        Ok(())
+    } else {
+        Err(format!("server answered {status}"))
+    }
+}
+
+impl AppModel {
+    pub(super) fn handle_unsubscribe(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::UnsubscribeOpen => {
+                let Some(msg) = self.selected_message.and_then(|i| self.messages.get(i)) else {
+                    return Task::none();
+                };
+                let method = msg.list_unsubscribe.as_deref().and_then(|value| {
+                    unsubscribe_method(value, msg.list_unsubscribe_post.as_deref())
+                });
+                let Some(method) = method else {
+                    self.status_message = "This list has no usable unsubscribe address".into();
+                    return Task::none();
+                };
+                self.unsubscribe_phase = UnsubscribePhase::Confirming {
+                    request: UnsubscribeRequest {
+                        account_id: msg.account_id.clone(),
+                        sender: sender_address(&msg.from).unwrap_or_else(|| msg.from.clone()),
+                        list: list_match(msg),
+                        method,
+                        archive: false,
+                    },
+                    error: None,
+                };
+            }
+            Message::UnsubscribeArchiveToggled(archive) => {
+                if let UnsubscribePhase::Confirming { request, .. } = &mut self.unsubscribe_phase {
+                    request.archive = archive;
+                }
+            }
+            Message::UnsubscribeCancel => {
+                self.unsubscribe_phase = UnsubscribePhase::Closed;
+            }
+            Message::UnsubscribeConfirm => {
+                let UnsubscribePhase::Confirming { request, .. } =
+                    std::mem::replace(&mut self.unsubscribe_phase, UnsubscribePhase::Closed)
+                else {
+                    return Task::none();
+                };
+                match &request.method {
+                    UnsubscribeMethod::OneClick(url) => {
+                        let url = url.clone();
+                        self.unsubscribe_phase = UnsubscribePhase::Sending(request);
+                        return cosmic::task::future(async move {
+                            Message::UnsubscribeSent(one_click(url).await)
+                        });
+                    }
+                    UnsubscribeMethod::Mailto(uri) => {
+                        let Some((to, subject, body)) = mailto_parts(uri) else {
+                            self.status_message = format!("Unusable unsubscribe address {uri}");
+                            return Task::none();
+                        };
+                        let compose = self.dispatch(Message::ComposeNew);
+                        if let Some(idx) = self.account_index(&request.account_id) {
+                            self.compose_account = idx;
+                            self.refresh_compose_cache();
+                        }
+                        self.compose_to = to;
+                        self.compose_subject = subject;
+                        self.compose_body = text_editor::Content::with_text(&body);
+                        self.status_message = "Send the message to finish unsubscribing".into();
+                        return cosmic::task::batch(vec![
+                            compose,
+                            self.archive_after_unsubscribe(&request),
+                        ]);
+                    }
+                    UnsubscribeMethod::Browser(url) => {
+                        neverlight_mail_core::mime::open_link(url);
+                        self.status_message = "Opened the unsubscribe page".into();
+                        return self.archive_after_unsubscribe(&request);
+                    }
+                }
+            }
+            Message::UnsubscribeSent(result) => {
+                let UnsubscribePhase::Sending(request) =
+                    std::mem::replace(&mut self.unsubscribe_phase, UnsubscribePhase::Closed)
+                else {
+                    return Task::none();
+                };
+                match result {
+                    Ok(()) => {
+                        self.status_message = format!("Unsubscribed from {}", request.sender);
+                        return self.archive_after_unsubscribe(&request);
+                    }
+                    Err(e) => {
+                        log::warn!("One-click unsubscribe failed: {}", e);
+                        self.unsubscribe_phase = UnsubscribePhase::Confirming {
+                            request,
+                            error: Some(format!("Unsubscribe request failed: {e}")),
+                        };
+                    }
+                }
+            }
+            Message::UnsubscribeArchived(result) => match result {
+                Ok(count) => {
+                    self.status_message = format!("Archived {count} messages");
+                    return self.dispatch(Message::Refresh);
+                }
+                Err(e) => {
+                    self.status_message = format!("Archiving list mail failed: {e}");
+                    return self.dispatch(Message::Refresh);
+                }
+            },
+            _ => {}
+        }
+        Task::none()
+    }
+
+    /// Messages of the request's list in the current message list, as
+    /// (email id, mailbox id).
+    fn loaded_from_list(&self, request: &UnsubscribeRequest) -> Vec<(String, String)> {
+        let Some(list) = &request.list else {
+            return Vec::new();
+        };
+        self.messages
+            .iter()
+            .filter(|m| m.account_id == request.account_id)
+            .filter(|m| belongs_to_list(m, list))
+            .map(|m| (m.email_id.clone(), m.context_mailbox_id.clone()))
+            .collect()
+    }
+
+    /// Move the list's loaded messages to Archive, when asked for.
+    fn archive_after_unsubscribe(&mut self, request: &UnsubscribeRequest) -> Task<Message> {
+        if !request.archive {
+            return Task::none();
+        }
+        let Some(acct) = self
+            .account_index(&request.account_id)
+            .and_then(|i| self.accounts.get(i))
+        else {
+            return Task::none();
+        };
+        let Some(archive_id) =
+            neverlight_mail_core::mailbox::find_by_role(&acct.folders, "archive")
+        else {
+            self.status_message = "Archive folder not found".into();
+            return Task::none();
+        };
+        let Some(client) = acct.client.clone() else {
+            self.status_message = "Move failed: account is offline".into();
+            return Task::none();
+        };
+        let targets: Vec<_> = self
+            .loaded_from_list(request)
+            .into_iter()
+            .filter(|(_, mailbox_id)| *mailbox_id != archive_id)
+            .collect();
+        cosmic::task::future(async move {
+            let mut moved = 0;
+            for (email_id, mailbox_id) in &targets {
+                if let Err(e) =
+                    neverlight_mail_core::email::move_to(&client, email_id, mailbox_id, &archive_id)
+                        .await
+                {
+                    return Message::UnsubscribeArchived(Err(format!(
+                        "{e} (after {moved} messages)"
+                    )));
+                }
+                moved += 1;
+            }
+            Message::UnsubscribeArchived(Ok(moved))
+        })
+    }
+
+    pub(super) fn unsubscribe_dialog(&self) -> Element<'_, Message> {
+        let (request, error, busy) = match &self.unsubscribe_phase {
+            UnsubscribePhase::Confirming { request, error } => (request, error.as_deref(), false),
+            UnsubscribePhase::Sending(request) => (request, None, true),
+            UnsubscribePhase::Closed => return widget::column().into(),
+        };
+        let what = match &request.method {
+            UnsubscribeMethod::OneClick(url) => {
+                let host = url::Url::parse(url)
+                    .ok()
+                    .and_then(|u| u.host_str().map(str::to_string))
+                    .unwrap_or_default();
+                format!("Sends a one-click unsubscribe request to {host}.")
+            }
+            UnsubscribeMethod::Mailto(uri) => format!(
+                "Opens a new message to {} for you to send.",
+                mailto_parts(uri).map(|(to, _, _)| to).unwrap_or_default()
+            ),
+            UnsubscribeMethod::Browser(url) => {
+                format!("Opens {url} in your browser. The page may ask you to confirm.")
+            }
+        };
+        let count = self.loaded_from_list(request).len();
+        let mut controls = widget::column()
+            .spacing(8)
+            .width(Length::Fill)
+            .push(widget::text::body(what));
+        if count > 0 {
+            controls = controls.push(
+                widget::checkbox(
+                    format!("Also archive {count} messages from this mailing list"),
+                    request.archive,
+                )
+                .on_toggle(Message::UnsubscribeArchiveToggled),
+            );
+        }
+
+        let mut confirm = widget::button::suggested(if busy {
+            "Unsubscribing..."
+        } else {
+            "Unsubscribe"
+        });
+        if !busy {
+            confirm = confirm.on_press(Message::UnsubscribeConfirm);
+        }
+        let mut dialog = widget::dialog()
+            .title(format!("Unsubscribe from {}", request.sender))
+            .control(controls)
+            .primary_action(confirm)
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Message::UnsubscribeCancel),
+            );
+        if let Some(err) = error {
+            dialog = dialog.body(err);
+        }
+        dialog.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::test_support::sample_summary;
+
+    const HEADER: &str = "<mailto:leave-123@lists.example.com?subject=unsubscribe>,\r\n <https://example.com/u?id=1 23>";
+
+    #[test]
+    fn uris_are_unfolded_and_unbracketed() {
+        assert_eq!(
+            list_unsubscribe_uris(HEADER),
+            vec![
+                "mailto:leave-123@lists.example.com?subject=unsubscribe",
+                "https://example.com/u?id=123",
+            ]
+        );
+        assert!(list_unsubscribe_uris("mailto:no-brackets@example.com").is_empty());
+    }
+
+    #[test]
+    fn one_click_needs_the_post_header_and_https() {
+        assert_eq!(
+            unsubscribe_method(HEADER, Some("List-Unsubscribe=One-Click")),
+            Some(UnsubscribeMethod::OneClick(
+                "https://example.com/u?id=123".into()
+            ))
+        );
+        assert_eq!(
+            unsubscribe_method(HEADER, None),
+            Some(UnsubscribeMethod::Mailto(
+                "mailto:leave-123@lists.example.com?subject=unsubscribe".into()
+            ))
+        );
+        assert_eq!(
+            unsubscribe_method("<http://example.com/u>", Some("List-Unsubscribe=One-Click")),
+            Some(UnsubscribeMethod::Browser("http://example.com/u".into()))
+        );
+        assert_eq!(unsubscribe_method("<ftp://example.com/u>", None), None);
+    }
+
+    #[test]
+    fn mailto_fields_are_decoded() {
+        assert_eq!(
+            mailto_parts("mailto:leave%2Bx@example.com?subject=Remove%20me&body=please"),
+            Some((
+                "leave+x@example.com".to_string(),
+                "Remove me".to_string(),
+                "please".to_string()
+            ))
+        );
+        assert_eq!(
+            mailto_parts("mailto:list@example.com"),
+            Some((
+                "list@example.com".to_string(),
+                "unsubscribe".to_string(),
+                String::new()
+            ))
+        );
+        assert_eq!(mailto_parts("mailto:?subject=x"), None);
+    }
+
+    fn list_message(
+        email_id: &str,
+        from: &str,
+        list_id: Option<&str>,
+        unsub: Option<&str>,
+    ) -> MessageSummary {
+        MessageSummary {
+            from: from.to_string(),
+            list_id: list_id.map(str::to_string),
+            list_unsubscribe: unsub.map(str::to_string),
+            ..sample_summary("acct-a", email_id)
+        }
+    }
+
+    #[test]
+    fn list_id_is_preferred_over_unsubscribe_origin() {
+        let msg = list_message(
+            "1",
+            "news@example.com",
+            Some("\"Example News\" <News.Example.COM>"),
+            Some(HEADER),
+        );
+        assert_eq!(
+            list_match(&msg),
+            Some(ListMatch::ListId("news.example.com".into()))
+        );
+        let msg = list_message("2", "news@example.com", None, Some(HEADER));
+        assert_eq!(
+            list_match(&msg),
+            Some(ListMatch::UnsubscribeOrigin("lists.example.com".into()))
+        );
+        assert_eq!(
+            list_match(&list_message("3", "a@example.com", None, None)),
+            None
+        );
+    }
+
+    #[test]
+    fn archive_matches_the_list_not_the_sender() {
+        let list = ListMatch::ListId("news.example.com".into());
+        // Same sender, but a receipt rather than list mail.
+        let receipt = list_message("1", "news@example.com", None, None);
+        assert!(!belongs_to_list(&receipt, &list));
+        // Same list from a different sending address.
+        let relayed = list_message(
+            "2",
+            "bounce@mailer.example.net",
+            Some("<news.example.com>"),
+            None,
+        );
+        assert!(belongs_to_list(&relayed, &list));
+
+        let origin = ListMatch::UnsubscribeOrigin("example.com".into());
+        let other = list_message(
+            "3",
+            "news@example.com",
+            None,
+            Some("<https://example.org/u>"),
+        );
+        assert!(!belongs_to_list(&other, &origin));
+        let same = list_message("4", "x@y.z", None, Some("<https://EXAMPLE.com/u?id=9>"));
+        assert!(belongs_to_list(&same, &origin));
+    }
+}
@@ -0,0 +1,400 @@
+//! Out-of-office replies (JMAP `VacationResponse`, RFC 8621 §8).
+//!
+//! Each account has a single vacation response on the server. It is fetched
+//! when the account connects, so the sidebar can show while it is active,
+//! and again when the dialog opens, since webmail may have changed it.
+//! Dates are entered in local time; either end may be left open.
+
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
+use cosmic::app::Task;
+use cosmic::iced::Length;
+use cosmic::widget::{self, text_editor};
+use cosmic::Element;
+
+use neverlight_mail_core::config::AccountId;
+use neverlight_mail_core::session::CAP_VACATION;
+use neverlight_mail_core::vacation::{self, VacationResponse};
+
+use super::{AppModel, Message, VacationForm, VacationPhase};
+
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// `YYYY-MM-DD HH:MM`, or a bare date meaning the start of that day (or
+/// its end, for the last day). Empty means no limit.
+fn parse_wall(input: &str, day_end: bool) -> Result<Option<NaiveDateTime>, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(input, DATE_FORMAT) {
+        return Ok(Some(time));
+    }
+    let date = NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        .map_err(|_| "Enter dates as YYYY-MM-DD or YYYY-MM-DD HH:MM".to_string())?;
+    let date = if day_end { date.succ_opt() } else { Some(date) };
+    Ok(date.and_then(|d| d.and_hms_opt(0, 0, 0)))
+}
+
+fn parse_local(input: &str, day_end: bool) -> Result<Option<i64>, String> {
+    let Some(wall) = parse_wall(input, day_end)? else {
+        return Ok(None);
+    };
+    Local
+        .from_local_datetime(&wall)
+        .earliest()
+        .map(|t| Some(t.timestamp()))
+        .ok_or_else(|| format!("{input} does not exist in local time"))
+}
+
+fn format_local(timestamp: Option<i64>) -> String {
+    timestamp
+        .and_then(|t| Local.timestamp_opt(t, 0).single())
+        .map(|t| t.format(DATE_FORMAT).to_string())
+        .unwrap_or_default()
+}
+
+/// Whether replies go out at `now`.
+fn is_active(response: &VacationResponse, now: i64) -> bool {
+    response.is_enabled
+        && response.from_date.is_none_or(|from| from <= now)
+        && response.to_date.is_none_or(|to| now < to)
+}
+
+/// Sidebar indicator for an account whose vacation response is active.
+pub(crate) fn vacation_label(response: &VacationResponse) -> Option<String> {
+    if !is_active(response, Local::now().timestamp()) {
+        return None;
+    }
+    Some(match response.to_date {
+        Some(_) => format!("Vacation reply on until {}", format_local(response.to_date)),
+        None => "Vacation reply on".to_string(),
+    })
+}
+
+fn form_from(account_id: AccountId, response: &VacationResponse) -> VacationForm {
+    VacationForm {
+        account_id,
+        enabled: response.is_enabled,
+        from: format_local(response.from_date),
+        to: format_local(response.to_date),
+        subject: response.subject.clone().unwrap_or_default(),
+        text_body: text_editor::Content::with_text(response.text_body.as_deref().unwrap_or("")),
+        html_body: text_editor::Content::with_text(response.html_body.as_deref().unwrap_or("")),
+    }
+}
+
+/// Editor text, or `None` when blank.
+fn non_blank(text: String) -> Option<String> {
+    (!text.trim().is_empty()).then_some(text)
+}
+
+fn response_from(form: &VacationForm) -> Result<VacationResponse, String> {
+    let from_date = parse_local(&form.from, false)?;
+    let to_date = parse_local(&form.to, true)?;
+    if let (Some(from), Some(to)) = (from_date, to_date) {
+        if to <= from {
+            return Err("The end must be after the start".into());
+        }
+    }
+    let text_body = non_blank(form.text_body.text());
+    let html_body = non_blank(form.html_body.text());
+    if form.enabled && text_body.is_none() && html_body.is_none() {
+        return Err("Write the reply to send".into());
+    }
+    Ok(VacationResponse {
+        is_enabled: form.enabled,
+        from_date,
+        to_date,
+        subject: non_blank(form.subject.clone()),
+        text_body,
+        html_body,
+    })
+}
+
+impl AppModel {
+    pub(super) fn handle_vacation(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::VacationOpen(account_id) => {
+                let online = self
+                    .client_for_account(&account_id)
+                    .is_some_and(|c| c.has_capability(CAP_VACATION));
+                if !online {
+                    self.status_message = "Vacation response needs the account online".into();
+                    return Task::none();
+                }
+                let task = self.load_vacation(&account_id);
+                self.vacation_phase = VacationPhase::Loading(account_id);
+                return task;
+            }
+            Message::VacationLoaded { account_id, result } => {
+                let opening = matches!(
+                    &self.vacation_phase,
+                    VacationPhase::Loading(id) if *id == account_id
+                );
+                let response = match result {
+                    Ok(response) => response,
+                    Err(e) => {
+                        log::warn!("Vacation response for {} failed: {}", account_id, e);
+                        if opening {
+                            self.vacation_phase = VacationPhase::Closed;
+                            self.status_message = format!("Loading vacation response failed: {e}");
+                        }
+                        return Task::none();
+                    }
+                };
+                if opening {
+                    self.vacation_phase = VacationPhase::Editing {
+                        form: form_from(account_id.clone(), &response),
+                        error: None,
+                    };
+                }
+                if let Some(acct) = self
+                    .account_index(&account_id)
+                    .and_then(|i| self.accounts.get_mut(i))
+                {
+                    acct.vacation = Some(response);
+                }
+            }
+            Message::VacationEnabledToggled(enabled) => {
+                if let VacationPhase::Editing { form, .. } = &mut self.vacation_phase {
+                    form.enabled = enabled;
+                }
+            }
+            Message::VacationFromChanged(value) => {
+                if let VacationPhase::Editing { form, .. } = &mut self.vacation_phase {
+                    form.from = value;
+                }
+            }
+            Message::VacationToChanged(value) => {
+                if let VacationPhase::Editing { form, .. } = &mut self.vacation_phase {
+                    form.to = value;
+                }
+            }
+            Message::VacationSubjectChanged(value) => {
+                if let VacationPhase::Editing { form, .. } = &mut self.vacation_phase {
+                    form.subject = value;
+                }
+            }
+            Message::VacationTextAction(action) => {
+                if let VacationPhase::Editing { form, .. } = &mut self.vacation_phase {
+                    form.text_body.perform(action);
+                }
+            }
+            Message::VacationHtmlAction(action) => {
+                if let VacationPhase::Editing { form, .. } = &mut self.vacation_phase {
+                    form.html_body.perform(action);
+                }
+            }
+            Message::VacationCancel => {
+                self.vacation_phase = VacationPhase::Closed;
+            }
+            Message::VacationSave => {
+                let VacationPhase::Editing { form, .. } = &self.vacation_phase else {
+                    return Task::none();
+                };
+                let checked = response_from(form).and_then(|response| {
+                    self.client_for_account(&form.account_id)
+                        .map(|client| (response, client))
+                        .ok_or_else(|| "Account is offline".to_string())
+                });
+                let (response, client) = match checked {
+                    Ok(checked) => checked,
+                    Err(e) => {
+                        if let VacationPhase::Editing { error, .. } = &mut self.vacation_phase {
+                            *error = Some(e);
+                        }
+                        return Task::none();
+                    }
+                };
+                let VacationPhase::Editing { form, .. } =
+                    std::mem::replace(&mut self.vacation_phase, VacationPhase::Closed)
+                else {
+                    return Task::none();
+                };
+                let account_id = form.account_id.clone();
+                self.vacation_phase = VacationPhase::Saving(form);
+                return cosmic::task::future(async move {
+                    let result = vacation::set(&client, &response)
+                        .await
+                        .map(|()| response)
+                        .map_err(|e| e.to_string());
+                    Message::VacationSaved { account_id, result }
+                });
+            }
+            Message::VacationSaved { account_id, result } => {
+                let VacationPhase::Saving(form) =
+                    std::mem::replace(&mut self.vacation_phase, VacationPhase::Closed)
+                else {
+                    return Task::none();
+                };
+                match result {
+                    Ok(response) => {
+                        self.status_message = if response.is_enabled {
+                            "Vacation response saved".into()
+                        } else {
+                            "Vacation response off".into()
+                        };
+                        if let Some(acct) = self
+                            .account_index(&account_id)
+                            .and_then(|i| self.accounts.get_mut(i))
+                        {
+                            acct.vacation = Some(response);
+                        }
+                    }
+                    Err(e) => {
+                        log::warn!("Saving vacation response failed: {}", e);
+                        self.vacation_phase = VacationPhase::Editing {
+                            form,
+                            error: Some(format!("Saving failed: {e}")),
+                        };
+                    }
+                }
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+
+    /// Fetch the account's vacation response, when its server has one.
+    pub(super) fn load_vacation(&self, account_id: &AccountId) -> Task<Message> {
+        let Some(client) = self
+            .client_for_account(account_id)
+            .filter(|c| c.has_capability(CAP_VACATION))
+        else {
+            return Task::none();
+        };
+        let account_id = account_id.clone();
+        cosmic::task::future(async move {
+            let result = vacation::get(&client).await.map_err(|e| e.to_string());
+            Message::VacationLoaded { account_id, result }
+        })
+    }
+
+    pub(super) fn vacation_dialog(&self) -> Element<'_, Message> {
+        let (form, error, busy) = match &self.vacation_phase {
+            VacationPhase::Editing { form, error } => (form, error.as_deref(), false),
+            VacationPhase::Saving(form) => (form, None, true),
+            VacationPhase::Loading(_) => {
+                return widget::dialog()
+                    .title("Vacation response")
+                    .body("Loading...")
+                    .secondary_action(
+                        widget::button::standard("Cancel").on_press(Message::VacationCancel),
+                    )
+                    .into();
+            }
+            VacationPhase::Closed => return widget::column().into(),
+        };
+        let label = self
+            .account_index(&form.account_id)
+            .and_then(|i| self.accounts.get(i))
+            .map(|a| a.config.label.as_str())
+            .unwrap_or_default();
+
+        let controls = widget::column()
+            .spacing(8)
+            .width(Length::Fill)
+            .push(
+                widget::checkbox("Reply automatically to incoming mail", form.enabled)
+                    .on_toggle(Message::VacationEnabledToggled),
+            )
+            .push(
+                widget::row()
+                    .spacing(8)
+                    .push(
+                        widget::text_input("YYYY-MM-DD HH:MM", &form.from)
+                            .label("From (optional)")
+                            .on_input(Message::VacationFromChanged),
+                    )
+                    .push(
+                        widget::text_input("YYYY-MM-DD HH:MM", &form.to)
+                            .label("Until (optional)")
+                            .on_input(Message::VacationToChanged),
+                    ),
+            )
+            .push(
+                widget::text_input("Out of office", &form.subject)
+                    .label("Subject")
+                    .on_input(Message::VacationSubjectChanged),
+            )
+            .push(widget::text::body("Message"))
+            .push(
+                widget::text_editor(&form.text_body)
+                    .placeholder("I'm away until...")
+                    .on_action(Message::VacationTextAction)
+                    .height(Length::Fixed(160.0)),
+            )
+            .push(widget::text::body("HTML version (optional)"))
+            .push(
+                widget::text_editor(&form.html_body)
+                    .font(cosmic::font::mono())
+                    .on_action(Message::VacationHtmlAction)
+                    .height(Length::Fixed(100.0)),
+            );
+
+        let mut save = widget::button::suggested(if busy { "Saving..." } else { "Save" });
+        if !busy {
+            save = save.on_press(Message::VacationSave);
+        }
+        let mut dialog = widget::dialog()
+            .title(format!("Vacation response — {label}"))
+            .control(controls)
+            .primary_action(save)
+            .secondary_action(widget::button::standard("Cancel").on_press(Message::VacationCancel));
+        if let Some(err) = error {
+            dialog = dialog.body(err);
+        }
+        dialog.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, DATE_FORMAT).expect("valid test time")
+    }
+
+    fn response(from_date: Option<i64>, to_date: Option<i64>) -> VacationResponse {
+        VacationResponse {
+            is_enabled: true,
+            from_date,
+            to_date,
+            subject: None,
+            text_body: Some("Away".into()),
+            html_body: None,
+        }
+    }
+
+    #[test]
+    fn bare_dates_cover_whole_days() {
+        assert_eq!(parse_wall("", false), Ok(None));
+        assert_eq!(
+            parse_wall("2026-12-20", false),
+            Ok(Some(at("2026-12-20 00:00")))
+        );
+        assert_eq!(
+            parse_wall("2026-12-31", true),
+            Ok(Some(at("2027-01-01 00:00")))
+        );
+        assert_eq!(
+            parse_wall(" 2026-12-20 17:30 ", true),
+            Ok(Some(at("2026-12-20 17:30")))
+        );
+        assert!(parse_wall("next week", false).is_err());
+    }
+
+    #[test]
+    fn active_only_inside_the_window() {
+        assert!(is_active(&response(None, None), 100));
+        assert!(is_active(&response(Some(100), Some(200)), 100));
+        assert!(!is_active(&response(Some(100), Some(200)), 99));
+        assert!(!is_active(&response(Some(100), Some(200)), 200));
+        let off = VacationResponse {
+            is_enabled: false,
+            ..response(None, None)
+        };
+        assert!(!is_active(&off, 100));
+    }
+}
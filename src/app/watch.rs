@@ -1,9 +1,10 @@
 use cosmic::app::Task;
 use futures::SinkExt;
 use neverlight_mail_core::client::JmapClient;
+use neverlight_mail_core::models::MessageSummary;
 use neverlight_mail_core::push::{self, EventSourceConfig};
 
-use super::{AppModel, ConnectionState, Message};
+use super::{AppModel, ConnectionState, Message, MessageIdentity};
 
 /// Returns a stream that listens for JMAP EventSource (SSE) push notifications
 /// and maps state changes into app messages.
@@ -42,7 +43,9 @@ impl AppModel {
         match message {
             Message::PushStateChanged(ref account_id) => {
                 log::debug!("Push state change for account {}", account_id);
-                return self.dispatch(Message::Refresh);
+                let refresh = self.dispatch(Message::Refresh);
+                let rules = self.rules_arrival_pass(account_id);
+                return cosmic::task::batch(vec![refresh, rules]);
             }
 
             Message::PushError(ref account_id, ref error) => {
@@ -75,4 +78,38 @@ impl AppModel {
         }
         Task::none()
     }
+
+    /// Desktop notification for newly arrived mail, at most once per message
+    /// until the account reconnects. `None` when it was already shown.
+    pub(super) fn notify_new_mail(&mut self, msg: &MessageSummary) -> Option<Task<Message>> {
+        let identity = MessageIdentity {
+            account_id: msg.account_id.clone(),
+            mailbox_id: msg.context_mailbox_id.clone(),
+            email_id: msg.email_id.clone(),
+        };
+        if !self.notified_messages.insert(identity) {
+            return None;
+        }
+        let summary = msg.from.clone();
+        let body = msg.subject.clone();
+        Some(cosmic::task::future(async move {
+            let shown = tokio::task::spawn_blocking(move || {
+                notify_rust::Notification::new()
+                    .appname("Neverlight Mail")
+                    .icon("mail-unread-symbolic")
+                    .summary(&summary)
+                    .body(&body)
+                    .show()
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            })
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|shown| shown);
+            if let Err(e) = shown {
+                log::warn!("New mail notification failed: {}", e);
+            }
+            Message::Noop
+        }))
+    }
 }
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::path::PathBuf;
 
 use cosmic::iced::clipboard::mime::{AllowedMimeTypes, AsMimeTypes};
 
@@ -27,24 +28,38 @@ pub struct DraggedMessage {
     pub source_account_id: String,
     pub email_id: String,
     pub source_mailbox_id: String,
+    /// `.eml` in the private drag directory, written as the drag starts and
+    /// offered to other applications as `text/uri-list`. Not part of the
+    /// internal format.
+    pub eml_path: Option<PathBuf>,
 }
 
 const NEVERLIGHT_MAIL_MIME: &str = "application/x-neverlight-mail-message";
+const URI_LIST_MIME: &str = "text/uri-list";
 
 impl AsMimeTypes for DraggedMessage {
     fn available(&self) -> Cow<'static, [String]> {
-        Cow::Owned(vec![NEVERLIGHT_MAIL_MIME.to_string()])
+        let mut mimes = vec![NEVERLIGHT_MAIL_MIME.to_string()];
+        if self.eml_path.is_some() {
+            mimes.push(URI_LIST_MIME.to_string());
+        }
+        Cow::Owned(mimes)
     }
 
     fn as_bytes(&self, mime_type: &str) -> Option<Cow<'static, [u8]>> {
-        if mime_type == NEVERLIGHT_MAIL_MIME {
-            let s = format!(
-                "{}\n{}\n{}",
-                self.source_account_id, self.email_id, self.source_mailbox_id
-            );
-            Some(Cow::Owned(s.into_bytes()))
-        } else {
-            None
+        match mime_type {
+            NEVERLIGHT_MAIL_MIME => {
+                let s = format!(
+                    "{}\n{}\n{}",
+                    self.source_account_id, self.email_id, self.source_mailbox_id
+                );
+                Some(Cow::Owned(s.into_bytes()))
+            }
+            URI_LIST_MIME => {
+                let url = url::Url::from_file_path(self.eml_path.as_ref()?).ok()?;
+                Some(Cow::Owned(format!("{url}\r\n").into_bytes()))
+            }
+            _ => None,
         }
     }
 }
@@ -73,10 +88,42 @@ impl TryFrom<(Vec<u8>, String)> for DraggedMessage {
             source_account_id,
             email_id,
             source_mailbox_id,
+            eml_path: None,
         })
     }
 }
 
+/// Anything dropped on a sidebar folder: a message from the list moves
+/// there, files from a file manager import into it. One destination takes
+/// both so each drag sees a single enter/leave pair.
+#[derive(Debug, Clone)]
+pub enum FolderDrop {
+    Message(DraggedMessage),
+    Files(DraggedFiles),
+}
+
+impl AllowedMimeTypes for FolderDrop {
+    /// The internal format comes first: a message drag also offers its
+    /// exported `.eml` as `text/uri-list`, and should still move.
+    fn allowed() -> Cow<'static, [String]> {
+        Cow::Owned(vec![
+            NEVERLIGHT_MAIL_MIME.to_string(),
+            URI_LIST_MIME.to_string(),
+        ])
+    }
+}
+
+impl TryFrom<(Vec<u8>, String)> for FolderDrop {
+    type Error = String;
+    fn try_from((bytes, mime): (Vec<u8>, String)) -> Result<Self, Self::Error> {
+        match mime.as_str() {
+            NEVERLIGHT_MAIL_MIME => DraggedMessage::try_from((bytes, mime)).map(Self::Message),
+            URI_LIST_MIME => DraggedFiles::try_from((bytes, mime)).map(Self::Files),
+            other => Err(format!("unexpected drop type {other}")),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -110,6 +157,7 @@ mod tests {
             source_account_id: "acc-1".to_string(),
             email_id: "M12345".to_string(),
             source_mailbox_id: "mb-inbox".to_string(),
+            eml_path: None,
         };
 
         // Serialize
@@ -132,6 +180,7 @@ mod tests {
             source_account_id: "acc:1:foo".to_string(),
             email_id: "M:12345:bar".to_string(),
             source_mailbox_id: "mb:inbox:baz".to_string(),
+            eml_path: None,
         };
 
         let bytes = msg.as_bytes(NEVERLIGHT_MAIL_MIME).unwrap();
@@ -148,8 +197,29 @@ mod tests {
             source_account_id: "acc-1".to_string(),
             email_id: "M1".to_string(),
             source_mailbox_id: "mb-1".to_string(),
+            eml_path: None,
         };
         assert!(msg.as_bytes("text/plain").is_none());
+        assert!(msg.as_bytes(URI_LIST_MIME).is_none());
+    }
+
+    #[test]
+    fn dragged_message_offers_eml_file_to_other_apps() {
+        let msg = DraggedMessage {
+            source_account_id: "acc-1".to_string(),
+            email_id: "M1".to_string(),
+            source_mailbox_id: "mb-1".to_string(),
+            eml_path: Some(PathBuf::from("/tmp/drag/M1/Re_ plan #2.eml")),
+        };
+        assert_eq!(
+            msg.available().as_ref(),
+            &[NEVERLIGHT_MAIL_MIME, URI_LIST_MIME]
+        );
+        let bytes = msg.as_bytes(URI_LIST_MIME).unwrap();
+        assert_eq!(
+            bytes.as_ref(),
+            b"file:///tmp/drag/M1/Re_%20plan%20%232.eml\r\n"
+        );
     }
 
     #[test]
@@ -163,4 +233,29 @@ mod tests {
         let allowed = DraggedMessage::allowed();
         assert_eq!(allowed.as_ref(), &[NEVERLIGHT_MAIL_MIME]);
     }
+
+    // -- FolderDrop --
+
+    #[test]
+    fn folder_drop_prefers_the_internal_format() {
+        let allowed = FolderDrop::allowed();
+        assert_eq!(allowed.as_ref(), &[NEVERLIGHT_MAIL_MIME, URI_LIST_MIME]);
+    }
+
+    #[test]
+    fn folder_drop_routes_by_mime() {
+        let message =
+            FolderDrop::try_from((b"acc-1\nM1\nmb-1".to_vec(), NEVERLIGHT_MAIL_MIME.into()))
+                .unwrap();
+        assert!(matches!(message, FolderDrop::Message(m) if m.email_id == "M1"));
+
+        let files = FolderDrop::try_from((
+            b"file:///home/user/a.eml\r\n".to_vec(),
+            URI_LIST_MIME.into(),
+        ))
+        .unwrap();
+        assert!(matches!(files, FolderDrop::Files(f) if f.0.contains("a.eml")));
+
+        assert!(FolderDrop::try_from((b"x".to_vec(), "text/plain".into())).is_err());
+    }
 }
@@ -23,6 +23,7 @@ pub struct ComposeViewState<'a> {
     pub subject: &'a str,
     pub body: &'a text_editor::Content,
     pub attachments: &'a [AttachmentData],
+    pub request_receipt: bool,
     pub error: Option<&'a str>,
     pub is_sending: bool,
     pub drag_hover: bool,
@@ -49,6 +50,7 @@ pub fn view<'a>(state: ComposeViewState<'a>) -> Element<'a, Message> {
         subject,
         body,
         attachments,
+        request_receipt,
         error,
         is_sending,
         drag_hover,
@@ -139,6 +141,10 @@ pub fn view<'a>(state: ComposeViewState<'a>) -> Element<'a, Message> {
         }
     }
     controls = controls.push(attach_col);
+    controls = controls.push(
+        widget::checkbox("Request read receipt", request_receipt)
+            .on_toggle(Message::ComposeRequestReceiptToggled),
+    );
 
     let send_label = if is_sending { "Sending..." } else { "Send" };
     let send_btn = if is_sending {
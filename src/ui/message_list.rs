@@ -1,16 +1,25 @@
 use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 
 use cosmic::iced::Length;
 use cosmic::widget;
 use cosmic::Element;
 
-use crate::app::Message;
+use crate::app::{
+    drag_eml_path, parse_list_id, wake_label, ExportFormat, FolderView, ListSort, Message,
+    QuickFilter, SearchScope,
+};
+use neverlight_mail_core::config::AccountId;
 use neverlight_mail_core::models::MessageSummary;
 
 use crate::dnd_models::DraggedMessage;
 
 pub struct MessageListState<'a> {
+    /// One loaded window of the list, starting at folder row `first_row`.
+    /// Each folder row above it takes one list row of spacer; inside it
+    /// only `visible_indices` take rows.
     pub messages: &'a [MessageSummary],
+    pub first_row: usize,
     pub visible_indices: &'a [usize],
     pub selected: Option<usize>,
     pub has_more: bool,
@@ -18,16 +27,101 @@ pub struct MessageListState<'a> {
     pub thread_sizes: &'a HashMap<String, usize>,
     pub search_active: bool,
     pub search_query: &'a str,
+    /// Wake-up times of snoozed messages, keyed by account then email id.
+    pub snoozed_until: &'a HashMap<AccountId, HashMap<String, i64>>,
+    /// Search hits that came from the server rather than the cache.
+    pub server_hits: &'a HashSet<(AccountId, String)>,
+    /// Why each search hit matched, keyed like `server_hits`.
+    pub snippets: &'a HashMap<(AccountId, String), String>,
+    pub search_scope: SearchScope,
+    /// Hits per account label, shown for multi-account searches.
+    pub account_counts: &'a [(String, usize)],
+    pub show_previews: bool,
+    /// Sort and quick filters of the selected folder; `None` while searching
+    /// or in a virtual view, where the list order is fixed.
+    pub list_view: Option<FolderView>,
+    /// Scroll offset and height of the list viewport, in pixels.
+    pub scroll_y: f32,
+    pub viewport_height: f32,
+}
+
+/// Height of a row with subject and sender lines only. Rows are fixed-height
+/// so the list can build just the ones in view.
+const ROW_HEIGHT: f32 = 52.0;
+/// Extra height per optional caption (preview, search snippet); both may wrap
+/// to two lines.
+const CAPTION_HEIGHT: f32 = 34.0;
+/// Rows built above and below the viewport so fast scrolling stays filled.
+pub const OVERSCAN_ROWS: usize = 10;
+/// Viewport height assumed until the scrollable reports its bounds.
+const DEFAULT_VIEWPORT_HEIGHT: f32 = 1080.0;
+
+pub fn row_height(show_previews: bool, with_snippets: bool) -> f32 {
+    let captions = usize::from(show_previews) + usize::from(with_snippets);
+    ROW_HEIGHT + captions as f32 * CAPTION_HEIGHT
+}
+
+/// Range of `visible_indices` positions to build for the given scroll state.
+pub fn visible_window(
+    scroll_y: f32,
+    viewport_height: f32,
+    row_height: f32,
+    total: usize,
+) -> Range<usize> {
+    let viewport_height = if viewport_height > 0.0 {
+        viewport_height
+    } else {
+        DEFAULT_VIEWPORT_HEIGHT
+    };
+    let first = (scroll_y.max(0.0) / row_height) as usize;
+    let shown = (viewport_height / row_height).ceil() as usize;
+    let start = first.saturating_sub(OVERSCAN_ROWS).min(total);
+    let end = (first + shown + OVERSCAN_ROWS).min(total);
+    start..end
+}
+
+/// Roughly two lines of preview at the default list width.
+const PREVIEW_MAX_CHARS: usize = 140;
+
+/// Server-supplied preview reduced to display-safe text: control and
+/// bidi/zero-width format characters are dropped, whitespace collapsed, and
+/// the result capped. Rendered as plain text, so nothing in it is fetched.
+fn preview_text(preview: &str) -> String {
+    let cleaned: String = preview
+        .chars()
+        .map(|c| if c.is_whitespace() { ' ' } else { c })
+        .filter(|c| !c.is_control() && !is_format_char(*c))
+        .collect();
+    let collapsed = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.chars().count() <= PREVIEW_MAX_CHARS {
+        return collapsed;
+    }
+    let mut out: String = collapsed.chars().take(PREVIEW_MAX_CHARS - 1).collect();
+    out.push('…');
+    out
+}
+
+/// Invisible Unicode format characters that can reorder or hide text.
+fn is_format_char(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200B}'..='\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2060}'..='\u{2069}' | '\u{FEFF}'
+    )
 }
 
 pub fn search_input_id() -> widget::Id {
     widget::Id::new("search-input")
 }
 
+pub fn list_scroll_id() -> widget::Id {
+    widget::Id::new("message-list-scroll")
+}
+
 /// Render the message list for the selected folder.
 pub fn view<'a>(state: MessageListState<'a>) -> Element<'a, Message> {
     let MessageListState {
         messages,
+        first_row,
         visible_indices,
         selected,
         has_more,
@@ -35,28 +129,104 @@ pub fn view<'a>(state: MessageListState<'a>) -> Element<'a, Message> {
         thread_sizes,
         search_active,
         search_query,
+        snoozed_until,
+        server_hits,
+        snippets,
+        search_scope,
+        account_counts,
+        show_previews,
+        list_view,
+        scroll_y,
+        viewport_height,
     } = state;
-    let mut col = widget::column().spacing(2).padding(8);
+    let mut col = widget::column().spacing(2).padding([8, 8, 0, 8]);
+
+    let mut header = widget::row()
+        .spacing(4)
+        .align_y(cosmic::iced::Alignment::Center);
+    let in_folder = list_view.is_some();
+    if let Some(view) = list_view {
+        header = header.push(widget::dropdown(
+            ListSort::LABELS,
+            Some(view.sort.index()),
+            |i| Message::ListSortChanged(ListSort::ALL[i]),
+        ));
+        for filter in QuickFilter::ALL {
+            let toggle = if view.filters.contains(&filter) {
+                widget::button::suggested(filter.label())
+            } else {
+                widget::button::standard(filter.label())
+            };
+            header = header.push(toggle.on_press(Message::QuickFilterToggled(filter)));
+        }
+    }
+    header = header.push(widget::horizontal_space());
+    if in_folder {
+        header = header
+            .push(widget::button::text("Rules").on_press(Message::RulesOpen))
+            .push(widget::button::text("Import").on_press(Message::ImportFiles))
+            .push(
+                widget::button::text("Export mbox")
+                    .on_press(Message::ExportFolder(ExportFormat::Mbox)),
+            )
+            .push(
+                widget::button::text("Export Maildir")
+                    .on_press(Message::ExportFolder(ExportFormat::Maildir)),
+            );
+    }
+    col =
+        col.push(header.push(
+            widget::checkbox("Previews", show_previews).on_toggle(Message::ShowPreviewsToggled),
+        ));
 
     if search_active {
         let input = widget::text_input("Search all mail...", search_query)
             .on_input(Message::SearchQueryChanged)
             .on_submit(|_| Message::SearchExecute)
             .id(search_input_id());
+        let scope = widget::dropdown(SearchScope::LABELS, Some(search_scope.index()), |i| {
+            Message::SearchScopeChanged(SearchScope::ALL[i])
+        });
+        let server_btn = widget::button::text("Search server").on_press(Message::SearchServer);
+        let save_btn = widget::button::text("Save").on_press(Message::SavedSearchSaveOpen);
         let clear_btn = widget::button::text("Clear").on_press(Message::SearchClear);
         col = col.push(
             widget::row()
                 .push(widget::container(input).width(Length::Fill))
+                .push(scope)
+                .push(server_btn)
+                .push(save_btn)
                 .push(clear_btn)
                 .spacing(4)
                 .align_y(cosmic::iced::Alignment::Center),
         );
+        if !account_counts.is_empty() {
+            let counts: Vec<String> = account_counts
+                .iter()
+                .map(|(label, count)| format!("{label}: {count}"))
+                .collect();
+            col = col.push(widget::text::caption(counts.join(" · ")));
+        }
     }
 
+    let mut list = widget::column().padding([0, 8, 8, 8]);
     if messages.is_empty() {
-        col = col.push(widget::text::body("No messages"));
+        list = list.push(widget::text::body("No messages"));
     } else {
-        for &real_index in visible_indices {
+        let height = row_height(show_previews, !snippets.is_empty());
+        // List rows, not folder rows: collapsed replies in the window take
+        // none. Rows outside the window are spacer until it moves there.
+        let window = visible_window(
+            scroll_y,
+            viewport_height,
+            height,
+            first_row + visible_indices.len(),
+        );
+        let built = window.start.max(first_row) - first_row..window.end.max(first_row) - first_row;
+        let rows_below = visible_indices.len() - built.end;
+        list =
+            list.push(widget::vertical_space().height((first_row + built.start) as f32 * height));
+        for &real_index in &visible_indices[built] {
             let msg = &messages[real_index];
             let is_selected = selected == Some(real_index);
 
@@ -83,18 +253,50 @@ pub fn view<'a>(state: MessageListState<'a>) -> Element<'a, Message> {
                 String::new()
             };
 
-            let subject_text = format!("{}{}{}{}", unread, star, thread_indicator, msg.subject);
-            let subject = widget::text::body(subject_text);
-            let meta = widget::text::caption(format!("{} — {}", msg.from, msg.date));
+            let from_server =
+                if server_hits.contains(&(msg.account_id.clone(), msg.email_id.clone())) {
+                    "☁ "
+                } else {
+                    ""
+                };
+
+            let subject_text = format!(
+                "{}{}{}{}{}",
+                unread, star, from_server, thread_indicator, msg.subject
+            );
+            let mut subject = widget::row()
+                .spacing(6)
+                .align_y(cosmic::iced::Alignment::Center)
+                .push(widget::text::body(subject_text));
+            if let Some((_, name)) = msg.list_id.as_deref().and_then(parse_list_id) {
+                subject = subject.push(
+                    widget::container(widget::text::caption(name))
+                        .padding([0, 6])
+                        .class(cosmic::theme::Container::Card),
+                );
+            }
+            let snoozed = snoozed_until
+                .get(&msg.account_id)
+                .and_then(|m| m.get(&msg.email_id))
+                .map(|&until| format!(" — ⏰ {}", wake_label(until)))
+                .unwrap_or_default();
+            let meta = widget::text::caption(format!("{} — {}{}", msg.from, msg.date, snoozed));
 
             let depth = msg.thread_depth.min(4);
             let indent = (depth as u16) * 16;
-            let row_content = widget::column().push(subject).push(meta).spacing(2);
+            let mut row_content = widget::column().push(subject).push(meta).spacing(2);
+            if show_previews && !msg.preview.is_empty() {
+                row_content = row_content.push(widget::text::caption(preview_text(&msg.preview)));
+            }
+            if let Some(snippet) = snippets.get(&(msg.account_id.clone(), msg.email_id.clone())) {
+                row_content = row_content.push(widget::text::caption(snippet.as_str()));
+            }
             let padded = widget::container(row_content).padding([0, 0, 0, indent]);
 
             let mut btn = widget::button::custom(padded)
                 .on_press(Message::ViewBody(real_index))
-                .width(Length::Fill);
+                .width(Length::Fill)
+                .height(Length::Fill);
 
             if is_selected {
                 btn = btn.class(cosmic::theme::Button::Suggested);
@@ -103,25 +305,98 @@ pub fn view<'a>(state: MessageListState<'a>) -> Element<'a, Message> {
             let email_id = msg.email_id.clone();
             let mailbox_id = msg.context_mailbox_id.clone();
             let source_account_id = msg.account_id.clone();
+            let subject = msg.subject.clone();
+            // The `.eml` offered to other applications is written as the
+            // drag starts, well before anything can be dropped.
             let source = widget::dnd_source::<Message, DraggedMessage>(btn)
                 .drag_content(move || DraggedMessage {
+                    eml_path: drag_eml_path(&source_account_id, &email_id, &subject),
                     source_account_id: source_account_id.clone(),
                     email_id: email_id.clone(),
                     source_mailbox_id: mailbox_id.clone(),
                 })
+                .on_start(Some(Message::DragExportPrepare {
+                    account_id: msg.account_id.clone(),
+                    email_id: msg.email_id.clone(),
+                }))
                 .drag_threshold(8.0);
 
-            col = col.push(source);
+            list = list.push(
+                widget::container(source)
+                    .height(Length::Fixed(height))
+                    .padding([1, 0])
+                    .clip(true),
+            );
         }
+        list = list.push(widget::vertical_space().height(rows_below as f32 * height));
 
+        // The next cache window is requested once the rows in view map
+        // past the loaded window's last folder row.
         if has_more {
-            let load_more_btn = widget::button::text("Load more messages")
-                .on_press(Message::LoadMoreMessages)
-                .width(Length::Fill);
-            col = col.push(widget::vertical_space().height(4));
-            col = col.push(load_more_btn);
+            list = list.push(widget::text::caption("Loading more messages..."));
         }
     }
 
-    widget::scrollable(col).height(Length::Fill).into()
+    col.push(
+        widget::scrollable(list)
+            .id(list_scroll_id())
+            .on_scroll(Message::MessageListScrolled)
+            .height(Length::Fill),
+    )
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_covers_viewport_plus_overscan() {
+        // Rows 100..110 are on screen at 52px each.
+        let window = visible_window(5200.0, 520.0, ROW_HEIGHT, 10_000);
+        assert_eq!(window, 90..120);
+    }
+
+    #[test]
+    fn window_is_clamped_to_the_list() {
+        assert_eq!(visible_window(0.0, 520.0, ROW_HEIGHT, 5), 0..5);
+        assert_eq!(visible_window(1.0e6, 520.0, ROW_HEIGHT, 30), 30..30);
+    }
+
+    #[test]
+    fn window_assumes_a_screenful_before_first_layout() {
+        let window = visible_window(0.0, 0.0, ROW_HEIGHT, 10_000);
+        assert_eq!(window.start, 0);
+        assert!(window.end > OVERSCAN_ROWS);
+    }
+
+    #[test]
+    fn captions_add_to_row_height() {
+        assert_eq!(row_height(false, false), ROW_HEIGHT);
+        assert_eq!(row_height(true, true), ROW_HEIGHT + 2.0 * CAPTION_HEIGHT);
+    }
+
+    #[test]
+    fn preview_drops_control_and_format_chars() {
+        assert_eq!(
+            preview_text("Hi\u{0007} there\u{202E}gnp.exe\u{200B}!"),
+            "Hi theregnp.exe!"
+        );
+    }
+
+    #[test]
+    fn preview_collapses_whitespace() {
+        assert_eq!(
+            preview_text("  line one\r\n\tline two  "),
+            "line one line two"
+        );
+    }
+
+    #[test]
+    fn preview_is_capped_with_ellipsis() {
+        let long = "word ".repeat(100);
+        let text = preview_text(&long);
+        assert_eq!(text.chars().count(), PREVIEW_MAX_CHARS);
+        assert!(text.ends_with('…'));
+    }
 }
@@ -1,14 +1,71 @@
-use cosmic::iced::{ContentFit, Length};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
+
+use cosmic::iced::advanced::text::highlighter::{Format, Highlighter};
+use cosmic::iced::{Color, ContentFit, Font, Length};
 use cosmic::widget;
 use cosmic::widget::{image, markdown, text_editor};
 use cosmic::Element;
 
-use crate::app::{ConversationEntry, Message};
+use crate::app::{
+    auth_verdict, event_time_label, find_matches, list_post_address, receipt_label, AuthVerdict,
+    BodyBlock, CalendarAddress, ConversationEntry, InviteCard, Message, RawSource, ReceiptPrompt,
+    RemoteImages, Rsvp, RsvpState,
+};
 use neverlight_mail_core::models::{AttachmentData, MessageSummary};
+use neverlight_mail_core::store::ReadReceipt;
+
+/// Remote images fetched for the selected message, if any.
+pub struct RemoteImagesState<'a> {
+    pub images: Option<&'a RemoteImages>,
+    /// The sender is already on the allow-list.
+    pub sender_allowed: bool,
+}
+
+/// Read-receipt request to answer and receipts returned for our messages.
+pub struct ReceiptsState<'a> {
+    pub prompt: Option<ReceiptPrompt>,
+    pub receipts: &'a HashMap<String, Vec<ReadReceipt>>,
+}
+
+/// Raw source of the selected message and how much of it to show.
+pub struct SourceState<'a> {
+    /// Only set when the fetched source belongs to the selected message.
+    pub raw: Option<&'a RawSource>,
+    pub show_source: bool,
+    pub headers_expanded: bool,
+}
+
+/// The "All headers" part of a message header.
+enum AllHeaders<'a> {
+    /// Not the selected message; no toggle.
+    Hidden,
+    Collapsed,
+    Loading,
+    Shown(&'a [(String, String)]),
+}
+
+fn all_headers<'a>(
+    msg: &MessageSummary,
+    selected: bool,
+    source: &SourceState<'a>,
+) -> AllHeaders<'a> {
+    if !selected {
+        return AllHeaders::Hidden;
+    }
+    if !source.headers_expanded {
+        return AllHeaders::Collapsed;
+    }
+    match source.raw {
+        Some(raw) if raw.email_id == msg.email_id => AllHeaders::Shown(&raw.headers),
+        _ => AllHeaders::Loading,
+    }
+}
 
 /// Render the message preview pane with an action toolbar when a message is selected.
 pub fn view<'a>(
-    markdown_items: &'a [markdown::Item],
+    body_blocks: &'a [BodyBlock],
     preview_editor: &'a text_editor::Content,
     selectable: bool,
     selected: Option<(usize, &'a MessageSummary)>,
@@ -17,7 +74,18 @@ pub fn view<'a>(
     conversation: &'a [ConversationEntry],
     conversation_editors: &'a [text_editor::Content],
     active_email_id: Option<&'a str>,
+    search_terms: &'a [String],
+    invites: &'a HashMap<String, InviteCard>,
+    own_addresses: &[String],
+    clean_links: bool,
+    remote: RemoteImagesState<'a>,
+    receipts: ReceiptsState<'a>,
+    source: SourceState<'a>,
 ) -> Element<'a, Message> {
+    if let Some((index, msg)) = selected.filter(|_| source.show_source) {
+        return source_view(index, msg, selectable, clean_links, &source);
+    }
+
     if !conversation.is_empty() {
         return conversation_view(
             conversation,
@@ -25,13 +93,20 @@ pub fn view<'a>(
             selectable,
             active_email_id,
             selected,
+            search_terms,
+            invites,
+            own_addresses,
+            clean_links,
+            remote,
+            receipts,
+            source,
         );
     }
 
     let has_body = if selectable {
         !preview_editor.text().trim().is_empty()
     } else {
-        !markdown_items.is_empty()
+        !body_blocks.is_empty()
     };
 
     if !has_body && attachments.is_empty() {
@@ -45,17 +120,36 @@ pub fn view<'a>(
     let mut col = widget::column().spacing(0);
 
     if let Some((index, msg)) = selected {
-        col = col.push(toolbar(index, msg, selectable));
+        col = col.push(toolbar(index, msg, selectable, clean_links, false, false));
         col = col.push(
-            widget::container(message_header(msg))
-                .padding([4, 16])
-                .width(Length::Fill)
-                .class(cosmic::style::Container::Card),
+            widget::container(message_header(
+                msg,
+                all_headers(msg, true, &source),
+                auth_verdict(msg, own_addresses),
+            ))
+            .padding([4, 16])
+            .width(Length::Fill)
+            .class(cosmic::style::Container::Card),
         );
+        if let Some(card) = invites.get(&msg.email_id) {
+            col = col.push(invite_card(&msg.email_id, card));
+        }
+        if let Some(section) = receipts_section(&receipts, &msg.email_id) {
+            col = col.push(section);
+        }
     }
 
     if has_body {
-        col = col.push(body_widget(markdown_items, preview_editor, selectable));
+        col = col.push(body_widget(
+            body_blocks,
+            preview_editor,
+            selectable,
+            search_terms,
+        ));
+    }
+
+    if let Some(section) = selected.and_then(|(_, msg)| remote_images_section(&remote, msg)) {
+        col = col.push(section);
     }
 
     if !attachments.is_empty() {
@@ -63,34 +157,117 @@ pub fn view<'a>(
     }
 
     widget::scrollable(col)
+        .id(preview_scroll_id())
         .height(Length::Fill)
         .width(Length::Fill)
         .into()
 }
 
+pub fn preview_scroll_id() -> widget::Id {
+    widget::Id::new("preview-scroll")
+}
+
+/// Text editor highlighter that marks search terms in selectable bodies.
+struct SearchTermHighlighter {
+    terms: Vec<String>,
+    line: usize,
+}
+
+impl Highlighter for SearchTermHighlighter {
+    type Settings = Vec<String>;
+    type Highlight = ();
+    type Iterator<'a> = std::vec::IntoIter<(Range<usize>, ())>;
+
+    fn new(settings: &Self::Settings) -> Self {
+        Self {
+            terms: settings.clone(),
+            line: 0,
+        }
+    }
+
+    fn update(&mut self, new_settings: &Self::Settings) {
+        self.terms = new_settings.clone();
+        self.line = 0;
+    }
+
+    fn change_line(&mut self, line: usize) {
+        self.line = line;
+    }
+
+    fn highlight_line(&mut self, line: &str) -> Self::Iterator<'_> {
+        self.line += 1;
+        find_matches(line, &self.terms)
+            .into_iter()
+            .map(|range| (range, ()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn current_line(&self) -> usize {
+        self.line
+    }
+}
+
+fn search_term_format(_: &(), theme: &cosmic::Theme) -> Format<Font> {
+    Format {
+        color: Some(theme.cosmic().accent_color().into()),
+        font: Some(cosmic::font::bold()),
+    }
+}
+
+/// Read-only text editor with search terms highlighted.
+fn highlighted_editor<'a>(
+    editor: &'a text_editor::Content,
+    search_terms: &'a [String],
+    on_action: impl Fn(text_editor::Action) -> Message + 'a,
+) -> Element<'a, Message> {
+    widget::text_editor(editor)
+        .highlight_with::<SearchTermHighlighter>(search_terms.to_vec(), search_term_format)
+        .on_action(on_action)
+        .into()
+}
+
 /// Render a body region — markdown (rich) or text_editor (selectable).
 fn body_widget<'a>(
-    markdown_items: &'a [markdown::Item],
+    body_blocks: &'a [BodyBlock],
     editor: &'a text_editor::Content,
     selectable: bool,
+    search_terms: &'a [String],
 ) -> Element<'a, Message> {
     if selectable {
-        widget::container(
-            widget::text_editor(editor).on_action(Message::PreviewBodyAction),
-        )
+        widget::container(highlighted_editor(
+            editor,
+            search_terms,
+            Message::PreviewBodyAction,
+        ))
         .padding(16)
         .width(Length::Fill)
         .into()
     } else {
-        let md = markdown::view(
-            markdown_items,
-            markdown::Settings::default(),
-            markdown::Style::from_palette(cosmic::iced::Theme::Dark.palette()),
-        )
-        .map(Message::LinkClicked);
+        widget::container(rich_body(body_blocks))
+            .padding(16)
+            .width(Length::Fill)
+            .into()
+    }
+}
 
-        widget::container(md).padding(16).width(Length::Fill).into()
+/// Markdown runs with inline `cid:` images between them.
+fn rich_body<'a>(blocks: &'a [BodyBlock]) -> Element<'a, Message> {
+    let mut col = widget::column().spacing(8);
+    for block in blocks {
+        col = col.push(match block {
+            BodyBlock::Markdown(items) => markdown::view(
+                items,
+                markdown::Settings::default(),
+                markdown::Style::from_palette(cosmic::iced::Theme::Dark.palette()),
+            )
+            .map(Message::LinkClicked),
+            BodyBlock::Image(handle) => widget::Image::new(handle.clone())
+                .content_fit(ContentFit::ScaleDown)
+                .into(),
+        });
     }
+    col.into()
 }
 
 fn conversation_view<'a>(
@@ -99,12 +276,19 @@ fn conversation_view<'a>(
     selectable: bool,
     active_email_id: Option<&'a str>,
     selected: Option<(usize, &'a MessageSummary)>,
+    search_terms: &'a [String],
+    invites: &'a HashMap<String, InviteCard>,
+    own_addresses: &[String],
+    clean_links: bool,
+    remote: RemoteImagesState<'a>,
+    receipts: ReceiptsState<'a>,
+    source: SourceState<'a>,
 ) -> Element<'a, Message> {
     let mut col = widget::column().spacing(0);
 
     // Toolbar for the active message
     if let Some((index, msg)) = selected {
-        col = col.push(toolbar(index, msg, selectable));
+        col = col.push(toolbar(index, msg, selectable, clean_links, false, true));
     }
 
     // Stacked message cards
@@ -114,7 +298,18 @@ fn conversation_view<'a>(
         let mut card_col = widget::column().spacing(4);
 
         // Header
-        card_col = card_col.push(message_header(&entry.summary));
+        let is_selected = selected.is_some_and(|(_, m)| m.email_id == entry.email_id);
+        card_col = card_col.push(message_header(
+            &entry.summary,
+            all_headers(&entry.summary, is_selected, &source),
+            auth_verdict(&entry.summary, own_addresses),
+        ));
+        if let Some(card) = invites.get(&entry.email_id) {
+            card_col = card_col.push(invite_card(&entry.email_id, card));
+        }
+        if let Some(section) = receipts_section(&receipts, &entry.email_id) {
+            card_col = card_col.push(section);
+        }
 
         // Body
         if entry.loaded {
@@ -123,42 +318,38 @@ fn conversation_view<'a>(
                     .get(entry_idx)
                     .map_or(false, |e| !e.text().trim().is_empty())
             } else {
-                !entry.markdown_items.is_empty()
+                !entry.body_blocks.is_empty()
             };
 
             if has_content {
                 if selectable {
                     if let Some(editor) = conversation_editors.get(entry_idx) {
                         card_col = card_col.push(
-                            widget::container(
-                                widget::text_editor(editor).on_action(move |action| {
-                                    Message::ConversationBodyAction {
-                                        index: entry_idx,
-                                        action,
-                                    }
-                                }),
-                            )
+                            widget::container(highlighted_editor(
+                                editor,
+                                search_terms,
+                                move |action| Message::ConversationBodyAction {
+                                    index: entry_idx,
+                                    action,
+                                },
+                            ))
                             .padding([8, 0])
                             .width(Length::Fill),
                         );
                     }
                 } else {
-                    let md = markdown::view(
-                        &entry.markdown_items,
-                        markdown::Settings::default(),
-                        markdown::Style::from_palette(
-                            cosmic::iced::Theme::Dark.palette(),
-                        ),
-                    )
-                    .map(Message::LinkClicked);
                     card_col = card_col.push(
-                        widget::container(md)
+                        widget::container(rich_body(&entry.body_blocks))
                             .padding([8, 0])
                             .width(Length::Fill),
                     );
                 }
             }
 
+            if let Some(section) = remote_images_section(&remote, &entry.summary) {
+                card_col = card_col.push(section);
+            }
+
             if !entry.attachments.is_empty() {
                 card_col = card_col.push(attachments_section(
                     &entry.attachments,
@@ -221,12 +412,47 @@ fn conversation_view<'a>(
     }
 
     widget::scrollable(col)
+        .id(preview_scroll_id())
         .height(Length::Fill)
         .width(Length::Fill)
         .into()
 }
 
-fn toolbar<'a>(index: usize, msg: &MessageSummary, selectable: bool) -> Element<'a, Message> {
+/// Raw RFC 5322 source in a read-only monospace editor.
+fn source_view<'a>(
+    index: usize,
+    msg: &'a MessageSummary,
+    selectable: bool,
+    clean_links: bool,
+    source: &SourceState<'a>,
+) -> Element<'a, Message> {
+    let body: Element<'a, Message> = match source.raw {
+        Some(raw) => widget::text_editor(&raw.editor)
+            .font(cosmic::font::mono())
+            .on_action(Message::SourceAction)
+            .into(),
+        None => widget::text::body("Loading source...").into(),
+    };
+    let col = widget::column()
+        .spacing(0)
+        .push(toolbar(index, msg, selectable, clean_links, true, false))
+        .push(widget::container(body).padding(16).width(Length::Fill));
+
+    widget::scrollable(col)
+        .id(preview_scroll_id())
+        .height(Length::Fill)
+        .width(Length::Fill)
+        .into()
+}
+
+fn toolbar<'a>(
+    index: usize,
+    msg: &MessageSummary,
+    selectable: bool,
+    clean_links: bool,
+    showing_source: bool,
+    in_conversation: bool,
+) -> Element<'a, Message> {
     let star_label = if msg.is_starred {
         "\u{2605}"
     } else {
@@ -238,18 +464,43 @@ fn toolbar<'a>(index: usize, msg: &MessageSummary, selectable: bool) -> Element<
         "Mark read"
     };
     let select_label = if selectable { "Rich text" } else { "Select text" };
+    let source_label = if showing_source {
+        "View message"
+    } else {
+        "View source"
+    };
 
-    let toolbar = widget::row()
-        .spacing(8)
-        .push(widget::button::text("Reply").on_press(Message::ComposeReply))
+    let list_address = msg.list_post.as_deref().and_then(list_post_address);
+
+    let mut toolbar = widget::row().spacing(8);
+    if list_address.is_some() {
+        toolbar = toolbar
+            .push(widget::button::text("Reply to sender").on_press(Message::ComposeReply))
+            .push(widget::button::text("Reply to list").on_press(Message::ComposeReplyList));
+    } else {
+        toolbar = toolbar.push(widget::button::text("Reply").on_press(Message::ComposeReply));
+    }
+    toolbar = toolbar
         .push(widget::button::text("Forward").on_press(Message::ComposeForward))
         .push(widget::button::text(star_label).on_press(Message::ToggleStar(index)))
         .push(widget::button::text(read_label).on_press(Message::ToggleRead(index)))
         .push(widget::button::text("Archive").on_press(Message::Archive(index)))
+        .push(widget::button::text("Snooze").on_press(Message::SnoozeOpen(index)))
         .push(widget::button::text("Copy").on_press(Message::CopyBody))
-        .push(
-            widget::button::text(select_label).on_press(Message::ToggleSelectableView),
-        )
+        .push(widget::button::text("Load images").on_press(Message::LoadRemoteImages))
+        .push(widget::button::text(source_label).on_press(Message::ToggleSourceView))
+        .push(widget::button::text("Export .eml").on_press(Message::ExportMessage));
+    if in_conversation {
+        toolbar = toolbar
+            .push(widget::button::text("Export thread").on_press(Message::ExportConversation));
+    }
+    if msg.list_unsubscribe.is_some() {
+        toolbar =
+            toolbar.push(widget::button::text("Unsubscribe").on_press(Message::UnsubscribeOpen));
+    }
+    toolbar = toolbar
+        .push(widget::button::text(select_label).on_press(Message::ToggleSelectableView))
+        .push(widget::checkbox("Clean links", clean_links).on_toggle(Message::CleanLinksToggled))
         .push(widget::button::destructive("Trash").on_press(Message::Delete(index)));
 
     widget::container(toolbar)
@@ -258,7 +509,7 @@ fn toolbar<'a>(index: usize, msg: &MessageSummary, selectable: bool) -> Element<
         .into()
 }
 
-fn header_row<'a>(label: &'a str, value: &'a str) -> Element<'a, Message> {
+fn header_row<'a>(label: &'a str, value: impl Into<Cow<'a, str>>) -> Element<'a, Message> {
     widget::row()
         .spacing(8)
         .push(
@@ -273,9 +524,50 @@ fn header_row<'a>(label: &'a str, value: &'a str) -> Element<'a, Message> {
         .into()
 }
 
-fn message_header<'a>(msg: &'a MessageSummary) -> Element<'a, Message> {
+/// Sender verification under the From row; failures get a red frame.
+fn auth_badge<'a>(verdict: AuthVerdict) -> Option<Element<'a, Message>> {
+    match verdict {
+        AuthVerdict::Unknown => None,
+        AuthVerdict::Verified(detail) => {
+            Some(widget::text::caption(format!("\u{2714} Verified: {detail}")).into())
+        }
+        AuthVerdict::Unverified(detail) => {
+            Some(widget::text::caption(format!("\u{26A0} Unverified: {detail}")).into())
+        }
+        AuthVerdict::Danger(detail) => {
+            let warning = widget::text::body(format!("\u{26A0} Possible forgery: {detail}"));
+            Some(
+                widget::container(warning)
+                    .padding([4, 8])
+                    .width(Length::Fill)
+                    .style(|theme: &cosmic::Theme| {
+                        let destructive: Color = theme.cosmic().destructive_color().into();
+                        cosmic::iced_widget::container::Style {
+                            text_color: Some(destructive),
+                            border: cosmic::iced::Border {
+                                color: destructive,
+                                width: 2.0,
+                                radius: 4.0.into(),
+                            },
+                            ..Default::default()
+                        }
+                    })
+                    .into(),
+            )
+        }
+    }
+}
+
+fn message_header<'a>(
+    msg: &'a MessageSummary,
+    all: AllHeaders<'a>,
+    verdict: AuthVerdict,
+) -> Element<'a, Message> {
     let mut col = widget::column().spacing(4);
     col = col.push(header_row("From:", &msg.from));
+    if let Some(badge) = auth_badge(verdict) {
+        col = col.push(badge);
+    }
     if !msg.to.is_empty() {
         col = col.push(header_row("To:", &msg.to));
     }
@@ -284,9 +576,226 @@ fn message_header<'a>(msg: &'a MessageSummary) -> Element<'a, Message> {
     if let Some(ref reply_to) = msg.reply_to {
         col = col.push(header_row("Reply-To:", reply_to));
     }
+
+    let toggle =
+        |label: &'static str| widget::button::text(label).on_press(Message::ToggleAllHeaders);
+    match all {
+        AllHeaders::Hidden => {}
+        AllHeaders::Collapsed => col = col.push(toggle("All headers ▸")),
+        AllHeaders::Loading => {
+            col = col
+                .push(toggle("All headers ▾"))
+                .push(widget::text::caption("Loading headers..."));
+        }
+        AllHeaders::Shown(headers) => {
+            let mut list = widget::column().spacing(2);
+            for (name, value) in headers {
+                list = list.push(
+                    widget::text::caption(format!("{name}: {value}")).font(cosmic::font::mono()),
+                );
+            }
+            col = col.push(toggle("All headers ▾")).push(list);
+        }
+    }
     col.into()
 }
 
+fn address_label(address: &CalendarAddress) -> String {
+    address
+        .name
+        .clone()
+        .unwrap_or_else(|| address.email.clone())
+}
+
+fn partstat_label(partstat: Option<&str>) -> &'static str {
+    match partstat {
+        Some("ACCEPTED") => "accepted",
+        Some("TENTATIVE") => "tentative",
+        Some("DECLINED") => "declined",
+        Some("DELEGATED") => "delegated",
+        _ => "no reply",
+    }
+}
+
+/// Invite details with RSVP buttons when the invite asks for an answer.
+fn invite_card<'a>(email_id: &str, card: &'a InviteCard) -> Element<'a, Message> {
+    let invite = &card.invite;
+    let title = if invite.summary.is_empty() {
+        "(untitled event)"
+    } else {
+        invite.summary.as_str()
+    };
+    let mut col = widget::column()
+        .spacing(4)
+        .push(widget::text::heading(format!("\u{1F4C5} {title}")));
+    if invite.method.as_deref() == Some("CANCEL") {
+        col = col.push(widget::text::body("This event has been cancelled."));
+    }
+    if let Some(start) = &invite.start {
+        col = col.push(header_row(
+            "When:",
+            event_time_label(start, invite.end.as_ref()),
+        ));
+    }
+    if let Some(location) = &invite.location {
+        col = col.push(header_row("Where:", location.as_str()));
+    }
+    if let Some(organizer) = &invite.organizer {
+        col = col.push(header_row("Organizer:", address_label(organizer)));
+    }
+    if !invite.attendees.is_empty() {
+        let attendees: Vec<String> = invite
+            .attendees
+            .iter()
+            .map(|a| {
+                format!(
+                    "{} ({})",
+                    address_label(a),
+                    partstat_label(a.partstat.as_deref())
+                )
+            })
+            .collect();
+        col = col.push(header_row("Attendees:", attendees.join(", ")));
+    }
+
+    if invite.method.as_deref() == Some("REQUEST") {
+        let sending = matches!(card.rsvp, RsvpState::Sending(_));
+        let mut buttons = widget::row()
+            .spacing(8)
+            .align_y(cosmic::iced::Alignment::Center);
+        for (label, response) in [
+            ("Accept", Rsvp::Accepted),
+            ("Tentative", Rsvp::Tentative),
+            ("Decline", Rsvp::Declined),
+        ] {
+            let mut btn = if card.rsvp == RsvpState::Sent(response) {
+                widget::button::suggested(label)
+            } else {
+                widget::button::standard(label)
+            };
+            if !sending {
+                btn = btn.on_press(Message::InviteRespond {
+                    email_id: email_id.to_string(),
+                    response,
+                });
+            }
+            buttons = buttons.push(btn);
+        }
+        match card.rsvp {
+            RsvpState::Unsent => {}
+            RsvpState::Sending(_) => buttons = buttons.push(widget::text::caption("Sending...")),
+            RsvpState::Sent(_) => buttons = buttons.push(widget::text::caption("Response sent")),
+        }
+        col = col.push(buttons);
+    }
+
+    widget::container(col)
+        .padding([8, 16])
+        .width(Length::Fill)
+        .class(cosmic::style::Container::Card)
+        .into()
+}
+
+/// Remote images loaded for `msg`, with the allow-list action when its sender
+/// is not on the list yet.
+/// Read-receipt prompt and returned receipts for one message.
+fn receipts_section<'a>(
+    receipts: &ReceiptsState<'a>,
+    email_id: &str,
+) -> Option<Element<'a, Message>> {
+    let returned = receipts
+        .receipts
+        .get(email_id)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let prompt = receipts.prompt.as_ref().filter(|p| p.email_id == email_id);
+    if returned.is_empty() && prompt.is_none() {
+        return None;
+    }
+
+    let mut col = widget::column().spacing(4);
+    if let Some(prompt) = prompt {
+        let label = match &prompt.notify {
+            Some(notify) => format!("Sender requested a read receipt (to {notify})"),
+            None => "Sender requested a read receipt".to_string(),
+        };
+        let mut send = widget::button::text("Send");
+        if prompt.can_send {
+            send = send.on_press(Message::ReceiptSend(prompt.email_id.clone()));
+        }
+        col = col.push(
+            widget::row()
+                .spacing(8)
+                .align_y(cosmic::iced::Alignment::Center)
+                .push(widget::text::body(label).width(Length::Fill))
+                .push(send)
+                .push(
+                    widget::button::text("Ignore")
+                        .on_press(Message::ReceiptIgnore(prompt.email_id.clone())),
+                )
+                .push(widget::button::text("Always ignore").on_press(Message::ReceiptAlwaysIgnore)),
+        );
+        if !prompt.can_send {
+            col = col.push(widget::text::caption(
+                "Your mail server cannot send read receipts.",
+            ));
+        }
+    }
+    for receipt in returned {
+        col = col.push(widget::text::caption(format!(
+            "\u{2714} {}",
+            receipt_label(receipt)
+        )));
+    }
+
+    Some(
+        widget::container(col)
+            .padding([4, 16])
+            .width(Length::Fill)
+            .into(),
+    )
+}
+
+fn remote_images_section<'a>(
+    remote: &RemoteImagesState<'a>,
+    msg: &MessageSummary,
+) -> Option<Element<'a, Message>> {
+    let images = remote.images.filter(|r| r.email_id == msg.email_id)?;
+    if images.handles.is_empty() {
+        return None;
+    }
+
+    let mut heading = widget::row()
+        .spacing(8)
+        .align_y(cosmic::iced::Alignment::Center)
+        .push(
+            widget::text::heading(format!("Remote images ({})", images.handles.len()))
+                .width(Length::Fill),
+        );
+    if !remote.sender_allowed {
+        heading = heading.push(
+            widget::button::text("Always for this sender")
+                .on_press(Message::AlwaysLoadRemoteImages),
+        );
+    }
+
+    let mut col = widget::column().spacing(8).push(heading);
+    for handle in &images.handles {
+        col = col.push(
+            widget::Image::new(handle.clone())
+                .content_fit(ContentFit::Contain)
+                .width(Length::Fill),
+        );
+    }
+
+    Some(
+        widget::container(col)
+            .padding([8, 16])
+            .width(Length::Fill)
+            .into(),
+    )
+}
+
 /// Render attachments. If `conversation_email_id` is Some, use SaveConversationAttachment.
 fn attachments_section<'a>(
     attachments: &[AttachmentData],
@@ -1,11 +1,16 @@
+use std::collections::HashMap;
+
 use cosmic::iced::Length;
 use cosmic::widget;
 use cosmic::Element;
 
 use crate::app::{
-    AccountState, ConnectionState, ErrorSurface, MailboxIdentity, Message, MessageIdentity, Phase,
+    is_snoozed_folder, vacation_label, wake_label, AccountState, ConnectionState, ErrorSurface,
+    MailboxIdentity, MailingList, Message, MessageIdentity, Phase, SavedSearch, VirtualView,
 };
-use crate::dnd_models::DraggedMessage;
+use crate::dnd_models::FolderDrop;
+use neverlight_mail_core::config::AccountId;
+use neverlight_mail_core::session::CAP_SIEVE;
 
 pub struct DiagnosticsState<'a> {
     pub collapsed: bool,
@@ -24,12 +29,74 @@ pub struct DiagnosticsState<'a> {
     pub refresh_in_flight: bool,
 }
 
+pub struct SavedSearchState<'a> {
+    pub searches: &'a [SavedSearch],
+    pub unread_counts: &'a HashMap<String, usize>,
+    pub active: Option<&'a str>,
+}
+
+pub struct ListsState<'a> {
+    pub lists: &'a [MailingList],
+    /// List whose messages are currently shown.
+    pub active: Option<&'a str>,
+}
+
+/// One smart-folder row: open on click, delete via the trailing icon.
+fn saved_search_row<'a>(
+    search: &SavedSearch,
+    saved: &SavedSearchState<'a>,
+) -> Element<'a, Message> {
+    let unread = saved.unread_counts.get(&search.id).copied().unwrap_or(0);
+    let label = if unread > 0 {
+        format!("  \u{1F50D} {} ({})", search.name, unread)
+    } else {
+        format!("  \u{1F50D} {}", search.name)
+    };
+    let mut btn = widget::button::text(label)
+        .on_press(Message::SavedSearchOpen(search.id.clone()))
+        .width(Length::Fill);
+    if saved.active == Some(search.id.as_str()) {
+        btn = btn.class(cosmic::theme::Button::Suggested);
+    }
+    widget::row()
+        .spacing(2)
+        .align_y(cosmic::iced::Alignment::Center)
+        .push(btn)
+        .push(
+            widget::button::icon(widget::icon::from_name("edit-delete-symbolic"))
+                .on_press(Message::SavedSearchDelete(search.id.clone()))
+                .padding(4)
+                .class(cosmic::theme::Button::Text),
+        )
+        .into()
+}
+
+/// One mailing-list row in the "Lists" section.
+fn list_row<'a>(list: &MailingList, active: Option<&str>) -> Element<'a, Message> {
+    let label = if list.unread > 0 {
+        format!("  \u{2709} {} ({})", list.name, list.unread)
+    } else {
+        format!("  \u{2709} {}", list.name)
+    };
+    let mut btn = widget::button::text(label)
+        .on_press(Message::ListOpen(list.id.clone()))
+        .width(Length::Fill);
+    if active == Some(list.id.as_str()) {
+        btn = btn.class(cosmic::theme::Button::Suggested);
+    }
+    btn.into()
+}
+
 /// Render the folder sidebar with multi-account sections.
 pub fn view<'a>(
     accounts: &'a [AccountState],
     active_account: Option<usize>,
     selected_folder: Option<usize>,
+    virtual_view: Option<VirtualView>,
     drag_target: Option<usize>,
+    snooze_schedule: &'a HashMap<AccountId, HashMap<String, i64>>,
+    saved: SavedSearchState<'a>,
+    lists: ListsState<'a>,
     diagnostics: DiagnosticsState<'a>,
 ) -> Element<'a, Message> {
     let mut col = widget::column().spacing(4).padding(8);
@@ -49,6 +116,28 @@ pub fn view<'a>(
                 .width(Length::Fill),
         );
     } else {
+        // Cross-account views
+        for view in VirtualView::ALL {
+            let mut btn = widget::button::text(view.label())
+                .on_press(Message::SelectVirtualView(view))
+                .width(Length::Fill);
+            if virtual_view == Some(view) {
+                btn = btn.class(cosmic::theme::Button::Suggested);
+            }
+            col = col.push(btn);
+        }
+        for search in saved.searches.iter().filter(|s| s.account_id.is_none()) {
+            col = col.push(saved_search_row(search, &saved));
+        }
+        if !lists.lists.is_empty() {
+            col = col.push(widget::vertical_space().height(8));
+            col = col.push(widget::text::caption("Lists"));
+            for list in lists.lists {
+                col = col.push(list_row(list, lists.active));
+            }
+        }
+        col = col.push(widget::vertical_space().height(8));
+
         // Track a global folder index offset for drag targets
         let mut global_folder_offset: usize = 0;
 
@@ -69,14 +158,36 @@ pub fn view<'a>(
             let aid_edit = acct.config.id.clone();
             let aid_remove = acct.config.id.clone();
 
-            let header_row = widget::row()
+            let mut header_row = widget::row()
                 .spacing(2)
                 .align_y(cosmic::iced::Alignment::Center)
                 .push(
                     widget::button::text(header_label)
                         .on_press(Message::ToggleAccountCollapse(acct_idx))
                         .width(Length::Fill),
-                )
+                );
+            // Only servers with JMAP vacation support load a response.
+            if acct.vacation.is_some() {
+                header_row = header_row.push(
+                    widget::button::icon(widget::icon::from_name("mail-reply-sender-symbolic"))
+                        .on_press(Message::VacationOpen(acct.config.id.clone()))
+                        .padding(4)
+                        .class(cosmic::theme::Button::Text),
+                );
+            }
+            let has_sieve = acct
+                .client
+                .as_ref()
+                .is_some_and(|c| c.has_capability(CAP_SIEVE));
+            if has_sieve {
+                header_row = header_row.push(
+                    widget::button::icon(widget::icon::from_name("view-filter-symbolic"))
+                        .on_press(Message::FiltersOpen(acct.config.id.clone()))
+                        .padding(4)
+                        .class(cosmic::theme::Button::Text),
+                );
+            }
+            let header_row = header_row
                 .push(
                     widget::button::icon(widget::icon::from_name("document-properties-symbolic"))
                         .on_press(Message::AccountEdit(aid_edit))
@@ -92,6 +203,18 @@ pub fn view<'a>(
 
             col = col.push(header_row);
 
+            if let Some(label) = acct.vacation.as_ref().and_then(vacation_label) {
+                col = col.push(
+                    widget::button::custom(
+                        widget::container(widget::text::caption(format!("  ✈ {label}")))
+                            .padding([2, 8]),
+                    )
+                    .on_press(Message::VacationOpen(acct.config.id.clone()))
+                    .class(cosmic::theme::Button::Text)
+                    .width(Length::Fill),
+                );
+            }
+
             // Show connection error inline if present
             if let ConnectionState::Error(ref e) = acct.conn_state {
                 let short_err = truncate(e, 40);
@@ -109,6 +232,14 @@ pub fn view<'a>(
 
             // Folder list (when not collapsed)
             if !acct.collapsed {
+                for search in saved
+                    .searches
+                    .iter()
+                    .filter(|s| s.account_id.as_deref() == Some(acct.config.id.as_str()))
+                {
+                    col = col.push(saved_search_row(search, &saved));
+                }
+
                 if acct.folders.is_empty() {
                     match &acct.conn_state {
                         ConnectionState::Connecting | ConnectionState::Syncing => {
@@ -127,7 +258,9 @@ pub fn view<'a>(
                             format!("  {}", folder.name)
                         };
 
-                        let is_selected = is_active_account && selected_folder == Some(folder_idx);
+                        let is_selected = virtual_view.is_none()
+                            && is_active_account
+                            && selected_folder == Some(folder_idx);
                         let is_drag_target = drag_target == Some(global_idx);
 
                         let ai = acct_idx;
@@ -142,34 +275,36 @@ pub fn view<'a>(
                             btn = btn.class(cosmic::theme::Button::Suggested);
                         }
 
-                        let mailbox_id = folder.mailbox_id.clone();
-                        let dest_account_id = acct.config.id.clone();
-                        let dest = widget::dnd_destination::dnd_destination_for_data::<
-                            DraggedMessage,
-                            _,
-                        >(btn, move |data, _action| match data {
-                            Some(msg) => Message::DragMessageToFolder {
-                                message: MessageIdentity {
-                                    account_id: msg.source_account_id.clone(),
-                                    mailbox_id: msg.source_mailbox_id.clone(),
-                                    email_id: msg.email_id.clone(),
-                                },
-                                source: MailboxIdentity {
-                                    account_id: msg.source_account_id,
-                                    mailbox_id: msg.source_mailbox_id,
-                                },
-                                dest: MailboxIdentity {
-                                    account_id: dest_account_id.clone(),
-                                    mailbox_id: mailbox_id.clone(),
+                        let folder_id = MailboxIdentity {
+                            account_id: acct.config.id.clone(),
+                            mailbox_id: folder.mailbox_id.clone(),
+                        };
+                        let dest =
+                            widget::dnd_destination::dnd_destination_for_data::<FolderDrop, _>(
+                                btn,
+                                move |data, _action| match data {
+                                    Some(drop) => folder_drop_message(drop, folder_id.clone()),
+                                    None => Message::Noop,
                                 },
-                            },
-                            None => Message::Noop,
-                        })
-                        .on_enter(move |_x, _y, _mimes| Message::FolderDragEnter(global_idx))
-                        .on_leave(|| Message::FolderDragLeave);
+                            )
+                            .on_enter(move |_x, _y, _mimes| Message::FolderDragEnter(global_idx))
+                            .on_leave(|| Message::FolderDragLeave);
 
                         folder_col = folder_col.push(dest);
 
+                        // Next wake-up under the Snoozed folder
+                        if is_snoozed_folder(folder) {
+                            let next_wake = snooze_schedule
+                                .get(&acct.config.id)
+                                .and_then(|m| m.values().min().copied());
+                            if let Some(until) = next_wake {
+                                folder_col = folder_col.push(widget::text::caption(format!(
+                                    "    \u{23F0} next wake {}",
+                                    wake_label(until)
+                                )));
+                            }
+                        }
+
                         // Backfill progress indicator or sync trigger
                         if let Some(&(position, total)) =
                             acct.backfill_progress.get(&folder.mailbox_id)
@@ -397,6 +532,25 @@ fn diagnostics_view<'a>(state: DiagnosticsState<'a>) -> Element<'a, Message> {
         .into()
 }
 
+/// A message dropped on a folder moves there; files import into it.
+fn folder_drop_message(drop: FolderDrop, dest: MailboxIdentity) -> Message {
+    match drop {
+        FolderDrop::Message(msg) => Message::DragMessageToFolder {
+            message: MessageIdentity {
+                account_id: msg.source_account_id.clone(),
+                mailbox_id: msg.source_mailbox_id.clone(),
+                email_id: msg.email_id,
+            },
+            source: MailboxIdentity {
+                account_id: msg.source_account_id,
+                mailbox_id: msg.source_mailbox_id,
+            },
+            dest,
+        },
+        FolderDrop::Files(files) => Message::ImportDropped { dest, files },
+    }
+}
+
 fn format_count(n: u32) -> String {
     if n >= 1_000 {
         format!("{},{:03}", n / 1_000, n % 1_000)
@@ -419,7 +573,48 @@ fn truncate(s: &str, max_len: usize) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::truncate;
+    use super::{folder_drop_message, truncate};
+    use crate::app::{MailboxIdentity, Message};
+    use crate::dnd_models::{DraggedFiles, DraggedMessage, FolderDrop};
+
+    fn inbox() -> MailboxIdentity {
+        MailboxIdentity {
+            account_id: "acc-2".to_string(),
+            mailbox_id: "mb-inbox".to_string(),
+        }
+    }
+
+    #[test]
+    fn message_dropped_on_folder_still_moves() {
+        let drop = FolderDrop::Message(DraggedMessage {
+            source_account_id: "acc-1".to_string(),
+            email_id: "M1".to_string(),
+            source_mailbox_id: "mb-archive".to_string(),
+            eml_path: None,
+        });
+        let Message::DragMessageToFolder {
+            message,
+            source,
+            dest,
+        } = folder_drop_message(drop, inbox())
+        else {
+            panic!("expected a move");
+        };
+        assert_eq!(message.email_id, "M1");
+        assert_eq!(message.mailbox_id, "mb-archive");
+        assert_eq!(source.account_id, "acc-1");
+        assert_eq!(dest, inbox());
+    }
+
+    #[test]
+    fn files_dropped_on_folder_import() {
+        let drop = FolderDrop::Files(DraggedFiles("file:///tmp/a.eml\r\n".to_string()));
+        let Message::ImportDropped { dest, files } = folder_drop_message(drop, inbox()) else {
+            panic!("expected an import");
+        };
+        assert_eq!(dest, inbox());
+        assert!(files.0.contains("a.eml"));
+    }
 
     #[test]
     fn truncate_short_string_unchanged() {